@@ -0,0 +1,201 @@
+use std::hint::black_box;
+
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use lichao::{LiChaoTree, LiChaoTreeBuilder, Line, NodeLayout};
+use rand::Rng;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+/// Domains are `[0, size - 1]`.
+const DOMAIN_SIZES: [i64; 3] = [1_000, 1_000_000, 10_000_000];
+/// Operations per benchmark iteration.
+const OPS: usize = 10_000;
+/// Lines in the trees that query benchmarks run against.
+const QUERY_LINES: usize = 1_000;
+
+/// Lines touching a downward parabola at random points, each with a small random lift. Most of them are on the
+/// envelope somewhere, so insertions walk deep into the tree instead of being discarded near the root.
+fn random_lines(rng: &mut StdRng, size: i64, count: usize) -> Vec<Line> {
+    (0..count)
+        .map(|_| {
+            let x = rng.random_range(0..size);
+            Line::new(-2 * x, x * x + rng.random_range(0..size))
+        })
+        .collect()
+}
+
+fn random_x_coords(rng: &mut StdRng, size: i64, count: usize) -> Vec<i64> {
+    (0..count).map(|_| rng.random_range(0..size)).collect()
+}
+
+/// Baseline that stores every line and scans them all on each query.
+#[derive(Default)]
+struct BruteForce {
+    lines: Vec<Line>,
+}
+
+impl BruteForce {
+    fn add_line(&mut self, line: Line) {
+        self.lines.push(line);
+    }
+
+    fn query(&self, x_coord: i64) -> Option<i64> {
+        self.lines.iter().map(|line| line.eval(x_coord)).min()
+    }
+}
+
+fn tree(size: i64, layout: NodeLayout) -> LiChaoTree {
+    LiChaoTreeBuilder::new(0, size - 1)
+        .layout(layout)
+        .build()
+        .unwrap()
+}
+
+fn bench_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert");
+    group.throughput(Throughput::Elements(OPS as u64));
+    let mut rng = StdRng::seed_from_u64(307);
+    for size in DOMAIN_SIZES {
+        let lines = random_lines(&mut rng, size, OPS);
+        let segments: Vec<(i64, i64)> = (0..OPS)
+            .map(|_| {
+                let x_l = rng.random_range(0..size);
+                (x_l, rng.random_range(x_l..size))
+            })
+            .collect();
+
+        for layout in [NodeLayout::Heap, NodeLayout::Blocked] {
+            group.bench_with_input(
+                BenchmarkId::new(format!("add_line/{layout:?}"), size),
+                &lines,
+                |b, lines| {
+                    b.iter_batched_ref(
+                        || tree(size, layout),
+                        |tree| {
+                            for &line in lines {
+                                tree.add_line(line);
+                            }
+                        },
+                        BatchSize::LargeInput,
+                    )
+                },
+            );
+        }
+        group.bench_with_input(BenchmarkId::new("add_segment", size), &lines, |b, lines| {
+            b.iter_batched_ref(
+                || tree(size, NodeLayout::Heap),
+                |tree| {
+                    for (&line, &(x_l, x_r)) in lines.iter().zip(&segments) {
+                        tree.add_segment(line, x_l, x_r);
+                    }
+                },
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("brute_force", size), &lines, |b, lines| {
+            b.iter_batched_ref(
+                BruteForce::default,
+                |brute| {
+                    for &line in lines {
+                        brute.add_line(line);
+                    }
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_query(c: &mut Criterion) {
+    let mut group = c.benchmark_group("query");
+    group.throughput(Throughput::Elements(OPS as u64));
+    let mut rng = StdRng::seed_from_u64(307);
+    for size in DOMAIN_SIZES {
+        let lines = random_lines(&mut rng, size, QUERY_LINES);
+        let x_coords = random_x_coords(&mut rng, size, OPS);
+
+        for layout in [NodeLayout::Heap, NodeLayout::Blocked] {
+            let mut tree = tree(size, layout);
+            for &line in &lines {
+                tree.add_line(line);
+            }
+            group.bench_with_input(
+                BenchmarkId::new(format!("query/{layout:?}"), size),
+                &x_coords,
+                |b, x_coords| {
+                    b.iter(|| {
+                        for &x_coord in x_coords {
+                            black_box(tree.query(x_coord));
+                        }
+                    })
+                },
+            );
+            group.bench_with_input(
+                BenchmarkId::new(format!("query_many/{layout:?}"), size),
+                &x_coords,
+                |b, x_coords| b.iter(|| tree.query_many(x_coords)),
+            );
+        }
+
+        let brute = BruteForce { lines };
+        group.bench_with_input(
+            BenchmarkId::new("brute_force", size),
+            &x_coords,
+            |b, x_coords| {
+                b.iter(|| {
+                    for &x_coord in x_coords {
+                        black_box(brute.query(x_coord));
+                    }
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_mixed(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mixed");
+    group.throughput(Throughput::Elements(OPS as u64));
+    let mut rng = StdRng::seed_from_u64(307);
+    for size in DOMAIN_SIZES {
+        // Alternates insertions and queries, as in dynamic programming with the convex hull trick
+        let lines = random_lines(&mut rng, size, OPS / 2);
+        let x_coords = random_x_coords(&mut rng, size, OPS / 2);
+
+        for layout in [NodeLayout::Heap, NodeLayout::Blocked] {
+            group.bench_with_input(
+                BenchmarkId::new(format!("tree/{layout:?}"), size),
+                &lines,
+                |b, lines| {
+                    b.iter_batched_ref(
+                        || tree(size, layout),
+                        |tree| {
+                            for (&line, &x_coord) in lines.iter().zip(&x_coords) {
+                                tree.add_line(line);
+                                black_box(tree.query(x_coord));
+                            }
+                        },
+                        BatchSize::LargeInput,
+                    )
+                },
+            );
+        }
+        group.bench_with_input(BenchmarkId::new("brute_force", size), &lines, |b, lines| {
+            b.iter_batched_ref(
+                BruteForce::default,
+                |brute| {
+                    for (&line, &x_coord) in lines.iter().zip(&x_coords) {
+                        brute.add_line(line);
+                        black_box(brute.query(x_coord));
+                    }
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_insert, bench_query, bench_mixed);
+criterion_main!(benches);
@@ -0,0 +1,74 @@
+#ifndef LICHAO_H
+#define LICHAO_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * A Li-Chao Tree for finding the minimum envelope of a set of lines.
+ *
+ * In the default configuration, with [`NodeStorage::Inline`] and no line log, memory is bounded at construction:
+ * the node array holds `2 * domain_size.next_power_of_two() - 1` lines, one per node of a complete binary tree over
+ * the domain, no matter how many lines are inserted, since each node keeps only the line that wins at its midpoint.
+ * Nodes are allocated in chunks the first time a line reaches them, so creating a tree takes O(1) time and parts of
+ * the domain that no line reaches cost no memory. Queries stay exact, so such a tree is suitable for unbounded
+ * streams of insertions.
+ *
+ * Other configurations grow with the insertions: the line log of `set_keep_lines` by one entry per line passed to
+ * the tree, and the line table of [`NodeStorage::Indexed`] by 16 bytes per line added, until `clear`. Insertions
+ * made with `add_line_rollbackable` also record O(log(domain_size)) cells each, until another mutation commits them.
+ */
+typedef struct LiChaoTree LiChaoTree;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates a tree over the integers in `[x_min_coord, x_max_coord]`, see `LiChaoTree::new`.
+ * Returns a null pointer if the domain is empty or the tree cannot be allocated. The tree must be freed with
+ * `lichao_free`.
+ */
+struct LiChaoTree *lichao_new(int64_t x_min_coord,
+                              int64_t x_max_coord);
+
+/**
+ * Adds the line `y = m * x + c`, and returns whether it was kept, see `LiChaoTree::add_line`.
+ * Returns `false` if the line cannot be stored, see `LiChaoTree::try_add_line`.
+ *
+ * # Safety
+ * `tree` must have been returned by `lichao_new`, not freed yet, and not be in use by another thread.
+ */
+bool lichao_add_line(struct LiChaoTree *tree,
+                     int64_t m,
+                     int64_t c);
+
+/**
+ * Writes the minimum at `x_coord` to `out` and returns `true`, or returns `false` without writing if no line has
+ * been added or `x_coord` is outside the tree's domain.
+ *
+ * # Safety
+ * `tree` must have been returned by `lichao_new` and not freed yet. `out` must be valid for writing an `int64_t`.
+ * Several threads may query the same tree at once, as long as none of them modifies it.
+ */
+bool lichao_query(const struct LiChaoTree *tree,
+                  int64_t x_coord,
+                  int64_t *out);
+
+/**
+ * Frees a tree. Does nothing if `tree` is null.
+ *
+ * # Safety
+ * `tree` must be null, or have been returned by `lichao_new` and not freed yet. It must not be used afterwards.
+ */
+void lichao_free(struct LiChaoTree *tree);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* LICHAO_H */
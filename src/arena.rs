@@ -0,0 +1,171 @@
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::nodes::{Chunk, ChunkTable};
+
+/// A pool of node memory shared by many trees, for workloads that build and drop lots of small trees, e.g. one per
+/// test case or per graph node.
+///
+/// Trees created with [`crate::LiChaoTree::new_in`] take their node chunks and chunk table from the arena, and give
+/// them back when they are dropped, so after the first few trees no more memory is requested from the allocator.
+/// Handles are cheap to clone and refer to the same pool, which can be used from several threads at once. The pooled
+/// memory is freed by [`Arena::release`], or once the arena and every tree created from it are dropped.
+///
+/// ```
+/// # use lichao::{Arena, LiChaoTree, Line};
+/// let arena = Arena::new();
+/// for case in 0..100 {
+///     let mut tree = LiChaoTree::new_in(0, 1000, &arena);
+///     tree.add_line(Line::new(-1, case));
+///     assert_eq!(tree.query(1000), Some(case - 1000));
+/// }
+/// assert!(arena.pooled_bytes() > 0);
+/// ```
+#[derive(Clone, Default)]
+pub struct Arena {
+    pool: Arc<Mutex<Pool>>,
+}
+
+#[derive(Default)]
+struct Pool {
+    // Boxed, since chunks move between node arrays and the pool without being copied
+    #[allow(clippy::vec_box)]
+    chunks: Vec<Box<Chunk>>,
+    tables: Vec<ChunkTable>,
+}
+
+impl Arena {
+    /// Creates an empty arena.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Heap memory held by the arena for reuse, not counting the memory of live trees.
+    pub fn pooled_bytes(&self) -> usize {
+        let pool = self.lock();
+        pool.chunks.len() * std::mem::size_of::<Chunk>()
+            + pool
+                .tables
+                .iter()
+                .map(|table| table.capacity() * std::mem::size_of::<Option<Box<Chunk>>>())
+                .sum::<usize>()
+    }
+
+    /// Frees the memory held for reuse. Trees created from the arena keep working, and give their memory back as usual.
+    pub fn release(&self) {
+        *self.lock() = Pool::default();
+    }
+
+    pub(crate) fn take_chunk(&self) -> Option<Box<Chunk>> {
+        self.lock().chunks.pop()
+    }
+
+    pub(crate) fn take_table(&self) -> Option<ChunkTable> {
+        self.lock().tables.pop()
+    }
+
+    /// Returns the chunks of a dropped node array and its emptied table to the pool.
+    pub(crate) fn give_back(&self, mut table: ChunkTable) {
+        let mut pool = self.lock();
+        pool.chunks.extend(table.drain(..).flatten());
+        if table.capacity() > 0 {
+            pool.tables.push(table);
+        }
+    }
+
+    /// Returns chunks freed by a live node array to the pool.
+    pub(crate) fn give_back_chunks(&self, chunks: impl Iterator<Item = Box<Chunk>>) {
+        self.lock().chunks.extend(chunks);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Pool> {
+        // The pool is only changed by pushes and pops, which leave it consistent even if a thread panicked
+        self.pool.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl fmt::Debug for Arena {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pool = self.lock();
+        f.debug_struct("Arena")
+            .field("pooled_chunks", &pool.chunks.len())
+            .field("pooled_tables", &pool.tables.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes::{NodeArray, NodeLayout, NodeStorage};
+    use crate::{LiChaoTree, Line};
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_trees_reuse_pooled_memory() {
+        let arena = Arena::new();
+        let mut rng = StdRng::seed_from_u64(303);
+        for _ in 0..200 {
+            let size = rng.random_range(1..=300);
+            let mut tree = LiChaoTree::new_in(0, size - 1, &arena);
+            let mut lines = Vec::new();
+            for _ in 0..rng.random_range(1..=20) {
+                let line = Line::new(rng.random_range(-50..=50), rng.random_range(-1000..=1000));
+                tree.add_line(line);
+                lines.push(line);
+            }
+            for x in 0..size {
+                assert_eq!(tree.query(x), lines.iter().map(|line| line.eval(x)).min());
+            }
+            drop(tree);
+            // Never more than the memory of the largest possible tree, which has 511 nodes
+            assert!(
+                arena.pooled_bytes()
+                    <= NodeArray::max_bytes(511, NodeLayout::Heap, NodeStorage::Inline).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_release() {
+        let arena = Arena::new();
+        let mut tree = LiChaoTree::new_in(0, 99, &arena);
+        tree.add_line(Line::new(1, 0));
+        let clone = tree.clone();
+        drop(tree);
+        assert!(arena.pooled_bytes() > 0);
+
+        arena.release();
+        assert_eq!(arena.pooled_bytes(), 0);
+        assert_eq!(clone.query(5), Some(5));
+        drop(clone);
+        assert!(arena.pooled_bytes() > 0);
+
+        // Chunks freed by shrinking a live tree go back to the pool too
+        let mut tree = LiChaoTree::new_in(0, 99, &arena);
+        tree.add_line(Line::new(1, 0));
+        arena.release();
+        tree.clear();
+        tree.shrink_to_fit();
+        assert!(arena.pooled_bytes() > 0);
+
+        let threads: Vec<_> = (0..4)
+            .map(|thread| {
+                let arena = arena.clone();
+                std::thread::spawn(move || {
+                    for case in 0..50 {
+                        let mut tree = LiChaoTree::new_in(0, 99, &arena);
+                        tree.add_line(Line::new(thread, case));
+                        assert_eq!(tree.query(2), Some(2 * thread + case));
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+
+use crate::nodes::CHUNK_LEN;
+use crate::{LiChaoTree, Line};
+
+/// A user-defined monoid aggregated over the lines stored in each subtree.
+/// `combine` must be associative with `identity` as its neutral element; it need not be commutative.
+pub trait Monoid {
+    type Value: Clone;
+
+    fn identity() -> Self::Value;
+
+    fn combine(a: &Self::Value, b: &Self::Value) -> Self::Value;
+
+    /// Maps a line stored in a node to its monoid value.
+    fn from_line(line: &Line) -> Self::Value;
+}
+
+/// A Li-Chao Tree that additionally maintains a monoid `M` over the lines stored in every subtree.
+/// Aggregates are folded in order: left subtree, the node's own line, right subtree.
+/// Like the nodes, aggregates are allocated in chunks the first time a line reaches them, so memory does not grow
+/// with the domain.
+pub struct AugmentedLiChaoTree<M: Monoid> {
+    tree: LiChaoTree,
+    // Aggregates of nodes `CHUNK_LEN * k..CHUNK_LEN * (k + 1)` under key `k`. Missing ones are the identity.
+    aggregates: HashMap<usize, Box<[M::Value]>>,
+}
+
+impl<M: Monoid> AugmentedLiChaoTree<M> {
+    /// Creates a new augmented tree over the inclusive range `[x_min_coord, x_max_coord]`.
+    pub fn new(x_min_coord: i64, x_max_coord: i64) -> Self {
+        AugmentedLiChaoTree {
+            tree: LiChaoTree::new(x_min_coord, x_max_coord),
+            aggregates: HashMap::new(),
+        }
+    }
+
+    /// Returns the underlying tree.
+    pub fn tree(&self) -> &LiChaoTree {
+        &self.tree
+    }
+
+    /// Adds a line and updates the aggregates on the path it touched.
+    /// Time complexity: O(log(domain_size)) monoid operations.
+    /// Returns whether the line was kept, see [`LiChaoTree::add_line`].
+    pub fn add_line(&mut self, line: Line) -> bool {
+        let outcome = self.tree.add_line_tracked(line);
+        let mut node_v_idx = outcome.deepest_node;
+        loop {
+            self.recompute(node_v_idx);
+            if node_v_idx == 0 {
+                break;
+            }
+            node_v_idx = (node_v_idx - 1) / 2;
+        }
+        outcome.stored
+    }
+
+    /// Queries the minimum y-value at `x_coord`, see [`LiChaoTree::query`].
+    pub fn query(&self, x_coord: i64) -> Option<i64> {
+        self.tree.query(x_coord)
+    }
+
+    /// Folds the monoid over every line stored in a node whose range intersects `[x_l, x_r]`.
+    /// Time complexity: O(log(domain_size)) monoid operations.
+    pub fn fold(&self, x_l: i64, x_r: i64) -> M::Value {
+        if x_l > x_r || !self.tree.contains(x_l) || !self.tree.contains(x_r) {
+            panic!("[{}, {}] does not fit inside the tree's bounds", x_l, x_r);
+        }
+        let query_l_idx = self.tree.get_idx_from_x_coord(x_l);
+        let query_r_idx = self.tree.get_idx_from_x_coord(x_r);
+        self.fold_internal(0, 0, self.tree.domain_size - 1, query_l_idx, query_r_idx)
+    }
+
+    fn fold_internal(
+        &self,
+        node_v_idx: usize,
+        range_l_idx: usize,
+        range_r_idx: usize,
+        query_l_idx: usize,
+        query_r_idx: usize,
+    ) -> M::Value {
+        if range_r_idx < query_l_idx || query_r_idx < range_l_idx {
+            return M::identity();
+        }
+        if query_l_idx <= range_l_idx && range_r_idx <= query_r_idx {
+            return self.aggregate(node_v_idx);
+        }
+
+        let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+        let left = self.fold_internal(
+            2 * node_v_idx + 1,
+            range_l_idx,
+            range_m_idx,
+            query_l_idx,
+            query_r_idx,
+        );
+        let right = self.fold_internal(
+            2 * node_v_idx + 2,
+            range_m_idx + 1,
+            range_r_idx,
+            query_l_idx,
+            query_r_idx,
+        );
+        M::combine(&M::combine(&left, &self.node_value(node_v_idx)), &right)
+    }
+
+    #[inline]
+    fn node_value(&self, node_v_idx: usize) -> M::Value {
+        match self.tree.nodes.get(node_v_idx) {
+            Some(line) => M::from_line(&line),
+            None => M::identity(),
+        }
+    }
+
+    /// Aggregate of the subtree of `node_v_idx`, which may lie below the leaves.
+    #[inline]
+    fn aggregate(&self, node_v_idx: usize) -> M::Value {
+        match self.aggregates.get(&(node_v_idx / CHUNK_LEN)) {
+            Some(chunk) => chunk[node_v_idx % CHUNK_LEN].clone(),
+            None => M::identity(),
+        }
+    }
+
+    fn recompute(&mut self, node_v_idx: usize) {
+        let left = self.aggregate(2 * node_v_idx + 1);
+        let right = self.aggregate(2 * node_v_idx + 2);
+        let value = M::combine(&M::combine(&left, &self.node_value(node_v_idx)), &right);
+        let chunk = self
+            .aggregates
+            .entry(node_v_idx / CHUNK_LEN)
+            .or_insert_with(|| vec![M::identity(); CHUNK_LEN].into_boxed_slice());
+        chunk[node_v_idx % CHUNK_LEN] = value;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    struct MaxSlope;
+
+    impl Monoid for MaxSlope {
+        type Value = Option<i64>;
+
+        fn identity() -> Self::Value {
+            None
+        }
+
+        fn combine(a: &Self::Value, b: &Self::Value) -> Self::Value {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(*a.max(b)),
+                _ => a.or(*b),
+            }
+        }
+
+        fn from_line(line: &Line) -> Self::Value {
+            Some(line.m)
+        }
+    }
+
+    struct Count;
+
+    impl Monoid for Count {
+        type Value = usize;
+
+        fn identity() -> usize {
+            0
+        }
+
+        fn combine(a: &usize, b: &usize) -> usize {
+            a + b
+        }
+
+        fn from_line(_: &Line) -> usize {
+            1
+        }
+    }
+
+    fn brute_count(tree: &LiChaoTree, v: usize, lo: usize, hi: usize, l: usize, r: usize) -> usize {
+        if hi < l || r < lo {
+            return 0;
+        }
+        let own = usize::from(tree.nodes.get(v).is_some());
+        if lo == hi {
+            return own;
+        }
+        let mid = lo + (hi - lo) / 2;
+        own + brute_count(tree, 2 * v + 1, lo, mid, l, r)
+            + brute_count(tree, 2 * v + 2, mid + 1, hi, l, r)
+    }
+
+    #[test]
+    fn test_whole_domain_fold() {
+        let mut tree = AugmentedLiChaoTree::<MaxSlope>::new(0, 10);
+        assert_eq!(tree.fold(0, 10), None);
+
+        tree.add_line(Line::new(2, 3));
+        tree.add_line(Line::new(-1, 10));
+        assert_eq!(tree.fold(0, 10), Some(2));
+        assert_eq!(tree.query(10), Some(0));
+    }
+
+    #[test]
+    fn test_fold_matches_stored_lines() {
+        let mut tree = AugmentedLiChaoTree::<Count>::new(-50, 50);
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..200 {
+            tree.add_line(Line::new(
+                rng.random_range(-100..=100),
+                rng.random_range(-1000..=1000),
+            ));
+            let stored = tree.tree().nodes.iter().flatten().count();
+            assert_eq!(tree.fold(-50, 50), stored);
+
+            let l = rng.random_range(-50..=50);
+            let r = rng.random_range(l..=50);
+            let brute = brute_count(tree.tree(), 0, 0, 100, (l + 50) as usize, (r + 50) as usize);
+            assert_eq!(tree.fold(l, r), brute);
+        }
+    }
+
+    #[test]
+    fn test_aggregates_follow_lines() {
+        let mut rng = StdRng::seed_from_u64(208);
+        let mut tree = AugmentedLiChaoTree::<MaxSlope>::new(0, 1 << 20);
+        let mut slopes = Vec::new();
+        for _ in 0..20 {
+            let m = rng.random_range(-100..=100);
+            tree.add_line(Line::new(m, rng.random_range(-1 << 40..=1 << 40)));
+            slopes.push(m);
+        }
+        // Each insertion recomputes one path of at most 21 nodes
+        assert!(tree.aggregates.len() <= 20 * 21);
+        let stored = tree.tree().nodes.iter().flatten().map(|line| line.m).max();
+        assert_eq!(tree.fold(0, 1 << 20), stored);
+        assert!(stored <= slopes.iter().copied().max());
+    }
+}
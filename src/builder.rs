@@ -0,0 +1,638 @@
+use crate::frozen::envelope_pieces;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+use crate::nodes::Entry;
+use crate::{
+    EnvelopeMode, LiChaoError, LiChaoTree, Line, LogEntry, NodeLayout, NodeStorage,
+    OutOfRangePolicy, OverflowPolicy, SortedCoords, TieBreak,
+};
+
+/// Batches at least this large are reduced to their lower envelope before being inserted by `extend`.
+const EXTEND_ENVELOPE_THRESHOLD: usize = 64;
+
+/// Collects lines and query coordinates before any tree exists, then builds a tree whose domain is
+/// exactly what the queries need. Useful when the domain bounds are not known up front.
+#[derive(Clone, Debug, Default)]
+pub struct LiChaoBuilder {
+    lines: Vec<Line>,
+    x_coords: Vec<i64>,
+}
+
+impl LiChaoBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a line to be added when the tree is built.
+    pub fn add_line(&mut self, line: Line) {
+        self.lines.push(line);
+    }
+
+    /// Records an x-coordinate that will be queried on the built tree.
+    pub fn add_query_coord(&mut self, x_coord: i64) {
+        self.x_coords.push(x_coord);
+    }
+
+    /// Builds a tree over `[min, max]` of the recorded query coordinates, with all recorded lines added.
+    /// Panics if no query coordinate was recorded.
+    pub fn build(self) -> LiChaoTree {
+        let (Some(&x_min_coord), Some(&x_max_coord)) =
+            (self.x_coords.iter().min(), self.x_coords.iter().max())
+        else {
+            panic!("LiChaoBuilder::build: no query coordinates were recorded");
+        };
+        LiChaoTree::build(x_min_coord, x_max_coord, &self.lines)
+    }
+
+    /// Builds a tree over exactly the recorded query coordinates, with all recorded lines added.
+    /// Its size depends only on the number of distinct coordinates, not on how far apart they are.
+    /// Panics if no query coordinate was recorded.
+    pub fn build_compressed(self) -> LiChaoTree {
+        if self.x_coords.is_empty() {
+            panic!("LiChaoBuilder::build_compressed: no query coordinates were recorded");
+        }
+        let mut tree = LiChaoTree::with_coordinate_map(SortedCoords::new(self.x_coords));
+        tree.add_lines(&self.lines);
+        tree
+    }
+}
+
+/// Configures a [`LiChaoTree`] before creating it, as an alternative to a constructor followed by setters.
+/// Options that are not set keep the defaults of [`LiChaoTree::new`]. The envelope mode can only be chosen here.
+#[derive(Clone, Debug)]
+pub struct LiChaoTreeBuilder {
+    // `None` for the integers in `[x_min_coord, x_max_coord]`, otherwise the grid points
+    points: Option<Vec<i64>>,
+    x_min_coord: i64,
+    x_max_coord: i64,
+    mode: EnvelopeMode,
+    tie_break: TieBreak,
+    overflow_policy: OverflowPolicy,
+    out_of_range_policy: OutOfRangePolicy,
+    keep_lines: bool,
+    budget_bytes: Option<usize>,
+    layout: NodeLayout,
+    storage: NodeStorage,
+}
+
+impl LiChaoTreeBuilder {
+    /// Starts configuring a tree over the integers in `[x_min_coord, x_max_coord]`, as for `LiChaoTree::new`.
+    pub fn new(x_min_coord: i64, x_max_coord: i64) -> Self {
+        LiChaoTreeBuilder {
+            points: None,
+            x_min_coord,
+            x_max_coord,
+            mode: EnvelopeMode::default(),
+            tie_break: TieBreak::default(),
+            overflow_policy: OverflowPolicy::default(),
+            out_of_range_policy: OutOfRangePolicy::default(),
+            keep_lines: false,
+            budget_bytes: None,
+            layout: NodeLayout::default(),
+            storage: NodeStorage::default(),
+        }
+    }
+
+    /// Starts configuring a tree over exactly the given x-coordinates, as for `LiChaoTree::new_with_points`.
+    /// Panics if `points` is empty.
+    pub fn with_points(points: &[i64]) -> Self {
+        let (Some(&x_min_coord), Some(&x_max_coord)) = (points.iter().min(), points.iter().max())
+        else {
+            panic!("LiChaoTreeBuilder::with_points: at least one point is required");
+        };
+        LiChaoTreeBuilder {
+            points: Some(points.to_vec()),
+            ..Self::new(x_min_coord, x_max_coord)
+        }
+    }
+
+    /// Whether the tree maintains the lower (minimum) or upper (maximum) envelope.
+    pub fn mode(mut self, mode: EnvelopeMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// See [`LiChaoTree::set_tie_break`].
+    pub fn tie_break(mut self, rule: TieBreak) -> Self {
+        self.tie_break = rule;
+        self
+    }
+
+    /// See [`LiChaoTree::set_overflow_policy`].
+    pub fn overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+
+    /// See [`LiChaoTree::set_out_of_range_policy`].
+    pub fn out_of_range_policy(mut self, policy: OutOfRangePolicy) -> Self {
+        self.out_of_range_policy = policy;
+        self
+    }
+
+    /// Whether the tree keeps a log of the lines added to it, see [`LiChaoTree::set_keep_lines`].
+    pub fn keep_lines(mut self, keep: bool) -> Self {
+        self.keep_lines = keep;
+        self
+    }
+
+    /// Limits the node array to `budget_bytes`, see [`LiChaoTree::with_memory_budget`].
+    pub fn memory_budget(mut self, budget_bytes: usize) -> Self {
+        self.budget_bytes = Some(budget_bytes);
+        self
+    }
+
+    /// How the nodes are ordered in memory. `NodeLayout::Blocked` speeds up trees over large domains, see
+    /// [`NodeLayout`].
+    pub fn layout(mut self, layout: NodeLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// How the nodes hold their lines. `NodeStorage::Indexed` shrinks the nodes and enables
+    /// [`LiChaoTree::query_argmin`], see [`NodeStorage`].
+    pub fn storage(mut self, storage: NodeStorage) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    /// Creates the configured tree. Fails in the same cases as `LiChaoTree::try_new` and
+    /// `LiChaoTree::with_memory_budget`.
+    pub fn build(self) -> Result<LiChaoTree, LiChaoError> {
+        let mut tree = match self.points {
+            None => LiChaoTree::allocate(
+                self.x_min_coord,
+                self.x_max_coord,
+                self.budget_bytes,
+                None,
+                self.layout,
+                self.storage,
+            )?,
+            Some(points) => {
+                let coords = SortedCoords::new(points);
+                let num_points = coords.as_slice().len();
+                let nodes = LiChaoTree::allocate_nodes(
+                    num_points as u128,
+                    self.budget_bytes,
+                    None,
+                    self.layout,
+                    self.storage,
+                )?;
+                LiChaoTree::from_parts(nodes, self.x_min_coord, num_points, Some(Arc::new(coords)))
+            }
+        };
+        tree.mode = self.mode;
+        tree.set_tie_break(self.tie_break);
+        tree.set_overflow_policy(self.overflow_policy);
+        tree.set_out_of_range_policy(self.out_of_range_policy);
+        tree.set_keep_lines(self.keep_lines);
+        Ok(tree)
+    }
+}
+
+impl LiChaoTree {
+    /// Creates a tree over `[x_min_coord, x_max_coord]` holding all of `lines`, equivalent to `new` followed by
+    /// `add_lines`. The lower envelope is computed first by sorting the lines by slope, and each of its pieces is
+    /// then written directly into the nodes whose midpoints it covers, instead of inserting lines one by one.
+    /// Panics if the domain is empty.
+    /// Time complexity: O(lines.len() * log(lines.len()) + pieces * log(domain_size)), on top of the allocation.
+    pub fn build(x_min_coord: i64, x_max_coord: i64, lines: &[Line]) -> Self {
+        let pieces = envelope_pieces(x_min_coord, x_max_coord, lines);
+        let mut tree = LiChaoTree::new(x_min_coord, x_max_coord);
+        let entry_of = tree.nodes.push_batch(lines);
+        if !pieces.is_empty() {
+            tree.build_internal(&pieces, &entry_of, 0, 0, tree.domain_size - 1, None);
+        }
+        tree.line_count = lines.len();
+        tree
+    }
+
+    /// Same as `build`, for lines produced by an iterator.
+    pub fn from_lines(
+        x_min_coord: i64,
+        x_max_coord: i64,
+        lines: impl IntoIterator<Item = Line>,
+    ) -> Self {
+        Self::build(
+            x_min_coord,
+            x_max_coord,
+            &lines.into_iter().collect::<Vec<_>>(),
+        )
+    }
+
+    /// Creates a tree over `[x_min_coord, x_max_coord]` holding all of `segments`, equivalent to `new` followed by
+    /// `add_segment` for each `(line, x_l..=x_r)`. The lower envelope of the segments is computed first by divide and
+    /// conquer, and each of its pieces is then inserted as a single segment into nodes that are still empty, instead
+    /// of inserting every segment into every node it covers.
+    /// Panics if the domain is empty, or if a range is reversed or sticks out of the domain.
+    /// Time complexity: O(n log(n) + pieces * log(domain_size)) for n segments whose envelope has `pieces` pieces, up
+    /// to an inverse Ackermann factor in n, on top of the allocation.
+    pub fn from_segments(
+        x_min_coord: i64,
+        x_max_coord: i64,
+        segments: &[(Line, RangeInclusive<i64>)],
+    ) -> Self {
+        let mut tree = LiChaoTree::new(x_min_coord, x_max_coord);
+        let mut clipped = Vec::with_capacity(segments.len());
+        for (line, range) in segments {
+            tree.segment_idx_range(*range.start(), *range.end());
+            clipped.push((*line, *range.start(), *range.end()));
+        }
+        let pieces = segment_envelope(x_min_coord, x_max_coord, &clipped);
+        let lines: Vec<Line> = segments.iter().map(|(line, _)| *line).collect();
+        let entry_of = tree.nodes.push_batch(&lines);
+        for (i, &(start, line)) in pieces.iter().enumerate() {
+            let Some(line) = line else {
+                continue;
+            };
+            let end = pieces.get(i + 1).map_or(x_max_coord, |&(next, _)| next - 1);
+            // Pieces are disjoint, so each node covering one exactly is still empty and takes the line as it is
+            tree.add_line_on_range(
+                entry_of(line),
+                0,
+                0,
+                tree.domain_size - 1,
+                tree.get_idx_from_x_coord(start),
+                tree.get_idx_from_x_coord(end),
+            );
+        }
+        tree.line_count = segments.len();
+        tree.partial_lines = true;
+        tree
+    }
+
+    /// Fills the subtree of `node_v_idx` from the envelope `pieces` that overlap its range.
+    /// Nodes whose envelope line is already stored higher up on the path, in `inherited`, are left empty.
+    fn build_internal(
+        &mut self,
+        pieces: &[(i64, Line)],
+        entry_of: &impl Fn(Line) -> Entry,
+        node_v_idx: usize,
+        range_l_idx: usize,
+        range_r_idx: usize,
+        inherited: Option<Line>,
+    ) {
+        if pieces.len() == 1 && Some(pieces[0].1) == inherited {
+            return;
+        }
+        let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+        let x_at_m = self.get_x_coord_from_idx(range_m_idx);
+        let line = pieces[pieces.partition_point(|&(start, _)| start <= x_at_m) - 1].1;
+        if Some(line) != inherited {
+            self.nodes.set(node_v_idx, Some(entry_of(line)));
+        }
+        if range_l_idx == range_r_idx {
+            return;
+        }
+        // Pieces overlapping each half. The first one may start before the half does.
+        let x_at_split = self.get_x_coord_from_idx(range_m_idx + 1);
+        let split = pieces.partition_point(|&(start, _)| start < x_at_split);
+        let right_first = pieces.partition_point(|&(start, _)| start <= x_at_split) - 1;
+        self.build_internal(
+            &pieces[..split],
+            entry_of,
+            2 * node_v_idx + 1,
+            range_l_idx,
+            range_m_idx,
+            Some(line),
+        );
+        self.build_internal(
+            &pieces[right_first..],
+            entry_of,
+            2 * node_v_idx + 2,
+            range_m_idx + 1,
+            range_r_idx,
+            Some(line),
+        );
+    }
+}
+
+/// Computes the pieces of the lower envelope of the segments `(line, x_l, x_r)` on `[x_min_coord, x_max_coord]`,
+/// as `(first x-coordinate, line)` pairs in increasing order of x. `None` marks the gaps covered by no segment.
+fn segment_envelope(
+    x_min_coord: i64,
+    x_max_coord: i64,
+    segments: &[(Line, i64, i64)],
+) -> Vec<(i64, Option<Line>)> {
+    match segments {
+        [] => vec![(x_min_coord, None)],
+        &[(line, x_l, x_r)] => {
+            let mut pieces = Vec::with_capacity(3);
+            if x_l > x_min_coord {
+                pieces.push((x_min_coord, None));
+            }
+            pieces.push((x_l, Some(line)));
+            if x_r < x_max_coord {
+                pieces.push((x_r + 1, None));
+            }
+            pieces
+        }
+        _ => {
+            let (left, right) = segments.split_at(segments.len() / 2);
+            merge_envelopes(
+                x_max_coord,
+                &segment_envelope(x_min_coord, x_max_coord, left),
+                &segment_envelope(x_min_coord, x_max_coord, right),
+            )
+        }
+    }
+}
+
+/// Pointwise minimum of two envelopes in the form returned by `segment_envelope`, in linear time.
+fn merge_envelopes(
+    x_max_coord: i64,
+    a: &[(i64, Option<Line>)],
+    b: &[(i64, Option<Line>)],
+) -> Vec<(i64, Option<Line>)> {
+    let mut pieces: Vec<(i64, Option<Line>)> = Vec::with_capacity(a.len() + b.len());
+    let mut push = |start: i64, line: Option<Line>| {
+        if pieces.last().is_none_or(|&(_, last)| last != line) {
+            pieces.push((start, line));
+        }
+    };
+    let (mut i, mut j, mut start) = (0, 0, a[0].0);
+    loop {
+        // Both envelopes keep the same line on `[start, end]`
+        let end_a = a.get(i + 1).map_or(x_max_coord, |&(next, _)| next - 1);
+        let end_b = b.get(j + 1).map_or(x_max_coord, |&(next, _)| next - 1);
+        let end = end_a.min(end_b);
+        match (a[i].1, b[j].1) {
+            (Some(p), Some(q)) => {
+                // `flat` wins from `first_flat` on, and `steep` before
+                let (flat, steep) = if p.m <= q.m { (p, q) } else { (q, p) };
+                let first_flat = if flat.m < steep.m {
+                    flat.first_below(&steep)
+                        .clamp(start as i128, end as i128 + 1)
+                } else if flat.c <= steep.c {
+                    start as i128
+                } else {
+                    end as i128 + 1
+                };
+                if first_flat > start as i128 {
+                    push(start, Some(steep));
+                }
+                if first_flat <= end as i128 {
+                    push(first_flat as i64, Some(flat));
+                }
+            }
+            (line, None) | (None, line) => push(start, line),
+        }
+        if end == x_max_coord {
+            break;
+        }
+        start = end + 1;
+        i += usize::from(end == end_a);
+        j += usize::from(end == end_b);
+    }
+    pieces
+}
+
+/// Adds every line, like repeated `add_line`. Large batches are first reduced to their own lower envelope,
+/// so lines of the batch that are dominated by other lines of the batch are never inserted.
+impl Extend<Line> for LiChaoTree {
+    fn extend<I: IntoIterator<Item = Line>>(&mut self, iter: I) {
+        let lines: Vec<Line> = iter.into_iter().collect();
+        if lines.len() < EXTEND_ENVELOPE_THRESHOLD {
+            for line in lines {
+                self.add_line(line);
+            }
+            return;
+        }
+        let x_min_coord = self.get_x_coord_from_idx(0);
+        let x_max_coord = self.get_x_coord_from_idx(self.domain_size - 1);
+        // Pieces of the envelope of the lines as stored, which in max mode is the lower envelope of their negations
+        let stored: Vec<Line> = lines.iter().map(|&line| self.stored_line(line)).collect();
+        let pieces = envelope_pieces(x_min_coord, x_max_coord, &stored);
+        // The whole batch is logged, including the lines that are never inserted
+        self.forget_rollback();
+        let entry_of = self.nodes.push_batch(&stored);
+        for (&line, &stored) in lines.iter().zip(&stored) {
+            self.log(LogEntry::Line(line), entry_of(stored).id);
+        }
+        self.line_count += lines.len();
+        for (_, line) in pieces {
+            self.add_line_internal(entry_of(line), 0, 0, self.domain_size - 1);
+        }
+    }
+}
+
+/// Adds every `(m, c)` pair as the line `y = mx + c`, see `Extend<Line>`.
+impl Extend<(i64, i64)> for LiChaoTree {
+    fn extend<I: IntoIterator<Item = (i64, i64)>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().map(|(m, c)| Line::new(m, c)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_tree_builder() {
+        let mut tree = LiChaoTreeBuilder::new(-10, 10)
+            .mode(EnvelopeMode::Max)
+            .tie_break(TieBreak::SmallerSlope)
+            .overflow_policy(OverflowPolicy::Checked)
+            .out_of_range_policy(OutOfRangePolicy::Ignore)
+            .keep_lines(true)
+            .build()
+            .unwrap();
+        assert_eq!(tree.mode(), EnvelopeMode::Max);
+        assert_eq!(tree.tie_break(), TieBreak::SmallerSlope);
+        assert_eq!(tree.overflow_policy(), OverflowPolicy::Checked);
+        assert_eq!(tree.out_of_range_policy(), OutOfRangePolicy::Ignore);
+        assert!(tree.keeps_lines());
+
+        tree.add_line(Line::new(1, 0));
+        tree.add_line(Line::new(-1, 0));
+        assert_eq!(tree.query(-4), Some(4));
+        assert_eq!(tree.query(3), Some(3));
+        // Both lines tie at 0, and the smaller slope wins
+        assert_eq!(tree.query_line(0), Some((0, Line::new(-1, 0))));
+        assert_eq!(tree.query(11), None);
+        assert_eq!(tree.lines().count(), 2);
+
+        let tree = LiChaoTreeBuilder::with_points(&[100, -5, 7, 100])
+            .build()
+            .unwrap();
+        assert_eq!(tree.mode(), EnvelopeMode::Min);
+        assert!(!tree.keeps_lines());
+        assert_eq!(tree.try_query(7), Ok(None));
+        assert!(tree.try_query(0).is_err());
+
+        let bytes =
+            crate::nodes::NodeArray::max_bytes(31, NodeLayout::Heap, NodeStorage::Inline).unwrap();
+        assert!(
+            LiChaoTreeBuilder::new(0, 10)
+                .memory_budget(bytes)
+                .build()
+                .is_ok()
+        );
+        assert_eq!(
+            LiChaoTreeBuilder::new(0, 10)
+                .memory_budget(bytes - 1)
+                .build()
+                .err(),
+            Some(LiChaoError::MemoryBudgetExceeded {
+                required_bytes: bytes,
+                budget_bytes: bytes - 1
+            })
+        );
+        assert!(
+            LiChaoTreeBuilder::with_points(&[1, 2, 3])
+                .memory_budget(bytes)
+                .build()
+                .is_ok()
+        );
+        assert_eq!(
+            LiChaoTreeBuilder::new(5, 4).build().err(),
+            Some(LiChaoError::InvalidDomain {
+                x_min_coord: 5,
+                x_max_coord: 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_build() {
+        let mut builder = LiChaoBuilder::new();
+        builder.add_line(Line::new(2, 3));
+        builder.add_line(Line::new(-1, 10));
+        for x in [5, -1_000_000_000, 1_000_000_000] {
+            builder.add_query_coord(x);
+        }
+
+        let compressed = builder.clone().build_compressed();
+        assert_eq!(compressed.query(5), Some(5));
+        assert_eq!(compressed.query(-1_000_000_000), Some(-1_999_999_997));
+        assert_eq!(compressed.query(1_000_000_000), Some(-999_999_990));
+
+        let mut builder = LiChaoBuilder::new();
+        builder.add_line(Line::new(2, 3));
+        builder.add_query_coord(4);
+        builder.add_query_coord(-2);
+        let tree = builder.build();
+        assert_eq!(tree.query(-2), Some(-1));
+        assert_eq!(tree.query(4), Some(11));
+    }
+
+    #[test]
+    fn test_tree_build() {
+        let mut rng = StdRng::seed_from_u64(268);
+        for _ in 0..200 {
+            let (x_min, x_max) = (rng.random_range(-50..=0), rng.random_range(0..=50));
+            let lines: Vec<Line> = (0..rng.random_range(0..40))
+                .map(|_| Line::new(rng.random_range(-20..=20), rng.random_range(-500..=500)))
+                .collect();
+            let mut built = LiChaoTree::build(x_min, x_max, &lines);
+            let from_iter = LiChaoTree::from_lines(x_min, x_max, lines.iter().copied());
+            let mut expected = LiChaoTree::new(x_min, x_max);
+            expected.add_lines(&lines);
+            for x in x_min..=x_max {
+                assert_eq!(built.query(x), expected.query(x));
+                assert_eq!(from_iter.query(x), expected.query(x));
+            }
+            // The built tree keeps working as a regular tree
+            for _ in 0..10 {
+                let line = Line::new(rng.random_range(-20..=20), rng.random_range(-500..=500));
+                built.add_line(line);
+                expected.add_line(line);
+            }
+            for x in x_min..=x_max {
+                assert_eq!(built.query(x), expected.query(x));
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_segments() {
+        let mut rng = StdRng::seed_from_u64(249);
+        for _ in 0..200 {
+            let (x_min, x_max) = (rng.random_range(-50..=0), rng.random_range(0..=50));
+            let segments: Vec<(Line, RangeInclusive<i64>)> = (0..rng.random_range(0..40))
+                .map(|_| {
+                    let line = Line::new(rng.random_range(-20..=20), rng.random_range(-500..=500));
+                    let x_l = rng.random_range(x_min..=x_max);
+                    (line, x_l..=rng.random_range(x_l..=x_max))
+                })
+                .collect();
+            let mut built = LiChaoTree::from_segments(x_min, x_max, &segments);
+            let mut expected = LiChaoTree::new(x_min, x_max);
+            for (line, range) in &segments {
+                expected.add_segment(*line, *range.start(), *range.end());
+            }
+            assert_eq!(built.len(), segments.len());
+            for x in x_min..=x_max {
+                assert_eq!(built.query(x), expected.query(x));
+            }
+            // The built tree keeps working as a regular tree
+            for _ in 0..10 {
+                let line = Line::new(rng.random_range(-20..=20), rng.random_range(-500..=500));
+                let x_l = rng.random_range(x_min..=x_max);
+                built.add_segment(line, x_l, x_max);
+                expected.add_segment(line, x_l, x_max);
+            }
+            for x in x_min..=x_max {
+                assert_eq!(built.query(x), expected.query(x));
+            }
+        }
+
+        // Values far outside the i64 range
+        let segments = [
+            (Line::new(i64::MAX, 0), -1000..=1000),
+            (Line::new(i64::MIN, -1), 0..=1000),
+        ];
+        let tree = LiChaoTree::from_segments(-1000, 1000, &segments);
+        assert_eq!(tree.query(0), Some(-1));
+        assert_eq!(tree.query(1), Some(i64::MIN));
+        assert_eq!(tree.query(-1), Some(-i64::MAX));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_from_segments_out_of_range() {
+        LiChaoTree::from_segments(0, 10, &[(Line::new(0, 0), 5..=11)]);
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut rng = StdRng::seed_from_u64(271);
+        for size in [0, 10, EXTEND_ENVELOPE_THRESHOLD, 500] {
+            let lines: Vec<Line> = (0..size)
+                .map(|_| Line::new(rng.random_range(-20..=20), rng.random_range(-500..=500)))
+                .collect();
+            let mut expected = LiChaoTree::new(-50, 50);
+            expected.add_line(Line::new(0, 0));
+            expected.add_lines(&lines);
+
+            let mut tree = LiChaoTree::new(-50, 50);
+            tree.add_line(Line::new(0, 0));
+            tree.extend(lines.iter().copied());
+            let mut pairs = LiChaoTree::new(-50, 50);
+            pairs.add_line(Line::new(0, 0));
+            pairs.extend(lines.iter().map(|line| (line.m, line.c)));
+            let mut grid = LiChaoTree::with_coordinate_map(SortedCoords::new([-50, -7, 0, 3, 50]));
+            grid.extend(lines.iter().copied());
+            for x in -50..=50 {
+                assert_eq!(tree.query(x), expected.query(x));
+                assert_eq!(pairs.query(x), expected.query(x));
+            }
+            for x in [-50, -7, 0, 3, 50] {
+                assert_eq!(grid.query(x), lines.iter().map(|line| line.eval(x)).min());
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_build_without_queries() {
+        let mut builder = LiChaoBuilder::new();
+        builder.add_line(Line::new(0, 0));
+        builder.build();
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+use std::ops::Bound::{Excluded, Unbounded};
+
+use crate::Line;
+
+/// A fully dynamic lower envelope over the whole `i64` range, with no domain declared up front.
+///
+/// Only the lines that are minimal at some x are kept, ordered by slope in a `BTreeMap`, together with a second
+/// map from the first x-coordinate of each piece to its line. Both insertions and queries take O(log n) time,
+/// amortized for insertions since each line is removed at most once.
+/// Lines are compared exactly and results are clamped to the `i64` range, like in `SparseLiChaoTree`.
+#[derive(Clone, Debug, Default)]
+pub struct LineContainer {
+    // slope -> (intercept, first x-coordinate where the line is minimal)
+    hull: BTreeMap<i64, (i64, i64)>,
+    // first x-coordinate of each piece -> slope
+    starts: BTreeMap<i64, i64>,
+}
+
+impl LineContainer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of lines on the envelope. Lines that are never minimal are not counted.
+    pub fn len(&self) -> usize {
+        self.hull.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hull.is_empty()
+    }
+
+    /// Line with the next larger slope on the hull, which is minimal left of the line with slope `m`.
+    fn prev(&self, m: i64) -> Option<Line> {
+        self.hull
+            .range((Excluded(m), Unbounded))
+            .next()
+            .map(|(&m, &(c, _))| Line::new(m, c))
+    }
+
+    /// Line with the next smaller slope on the hull, which is minimal right of the line with slope `m`.
+    fn next(&self, m: i64) -> Option<Line> {
+        self.hull
+            .range(..m)
+            .next_back()
+            .map(|(&m, &(c, _))| Line::new(m, c))
+    }
+
+    /// Range of x-coordinates on which `line` would be minimal between `prev` and `next`, if any.
+    fn piece(line: &Line, prev: Option<Line>, next: Option<Line>) -> Option<(i64, i64)> {
+        let start = prev.map_or(i64::MIN as i128, |prev| {
+            line.first_below(&prev).max(i64::MIN as i128)
+        });
+        let end = next.map_or(i64::MAX as i128, |next| {
+            (next.first_below(line) - 1).min(i64::MAX as i128)
+        });
+        (start <= end).then_some((start as i64, end as i64))
+    }
+
+    fn remove(&mut self, m: i64) {
+        let (_, start) = self.hull.remove(&m).unwrap();
+        self.starts.remove(&start);
+    }
+
+    /// Inserts `line` into the hull with its piece starting at `start`, or moves the start of its piece.
+    fn set_start(&mut self, line: &Line, start: i64) {
+        if let Some((_, old)) = self.hull.insert(line.m, (line.c, start))
+            // The old key may already have been taken over by a newly inserted line
+            && self.starts.get(&old) == Some(&line.m)
+        {
+            self.starts.remove(&old);
+        }
+        self.starts.insert(start, line.m);
+    }
+
+    /// Adds a line. Returns whether it was kept, which requires it to be minimal at some x.
+    /// Time complexity: amortized O(log n).
+    pub fn add_line(&mut self, line: Line) -> bool {
+        if let Some(&(c, _)) = self.hull.get(&line.m)
+            && c <= line.c
+        {
+            return false;
+        }
+        if Self::piece(&line, self.prev(line.m), self.next(line.m)).is_none() {
+            return false;
+        }
+        if self.hull.contains_key(&line.m) {
+            self.remove(line.m);
+        }
+
+        // Neighbours that are no longer minimal anywhere
+        while let Some(next) = self.next(line.m) {
+            if Self::piece(&next, Some(line), self.next(next.m)).is_some() {
+                break;
+            }
+            self.remove(next.m);
+        }
+        while let Some(prev) = self.prev(line.m) {
+            if Self::piece(&prev, self.prev(prev.m), Some(line)).is_some() {
+                break;
+            }
+            self.remove(prev.m);
+        }
+
+        let (start, _) = Self::piece(&line, self.prev(line.m), self.next(line.m)).unwrap();
+        self.set_start(&line, start);
+        if let Some(next) = self.next(line.m) {
+            let (next_start, _) = Self::piece(&next, Some(line), self.next(next.m)).unwrap();
+            self.set_start(&next, next_start);
+        }
+        true
+    }
+
+    /// Queries the minimum y-value at `x_coord`, or `None` if no line has been added.
+    /// Time complexity: O(log n).
+    pub fn query(&self, x_coord: i64) -> Option<i64> {
+        let (_, &m) = self.starts.range(..=x_coord).next_back()?;
+        let (c, _) = self.hull[&m];
+        Some(
+            Line::new(m, c)
+                .eval_wide(x_coord)
+                .clamp(i64::MIN as i128, i64::MAX as i128) as i64,
+        )
+    }
+}
+
+/// Collects lines into a container. Unlike trees, a container needs no domain, so no bounds have to be given.
+impl FromIterator<Line> for LineContainer {
+    fn from_iter<I: IntoIterator<Item = Line>>(iter: I) -> Self {
+        let mut container = LineContainer::new();
+        for line in iter {
+            container.add_line(line);
+        }
+        container
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn naive(lines: &[Line], x: i64) -> Option<i64> {
+        lines
+            .iter()
+            .map(|line| line.eval_wide(x))
+            .min()
+            .map(|val| val.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+    }
+
+    #[test]
+    fn test_matches_naive() {
+        let mut rng = StdRng::seed_from_u64(262);
+        for _ in 0..100 {
+            let mut container = LineContainer::new();
+            let mut lines = Vec::new();
+            for _ in 0..60 {
+                let line = Line::new(rng.random_range(-20..=20), rng.random_range(-300..=300));
+                let kept = container.add_line(line);
+                // Checked on a range wide enough to contain every crossing of these lines. Ties at a single
+                // point may go either way, so only check that kept lines are minimal somewhere, and that
+                // dropped lines are never strictly below all others.
+                let minimal = |strict: bool| {
+                    (-700..=700).any(|x| {
+                        lines.iter().all(|other: &Line| match strict {
+                            true => line.eval(x) < other.eval(x),
+                            false => line.eval(x) <= other.eval(x),
+                        })
+                    })
+                };
+                assert!(if kept { minimal(false) } else { !minimal(true) });
+                lines.push(line);
+                for x in -100..=100 {
+                    assert_eq!(container.query(x), naive(&lines, x));
+                }
+            }
+            assert!(container.len() <= 41);
+        }
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let container: LineContainer = [Line::new(1, 0), Line::new(-1, 0), Line::new(0, 5)]
+            .into_iter()
+            .collect();
+        assert_eq!(container.len(), 2);
+        assert_eq!(container.query(-1_000_000), Some(-1_000_000));
+        assert_eq!(container.query(0), Some(0));
+        assert_eq!(container.query(7), Some(-7));
+    }
+
+    #[test]
+    fn test_unbounded_coordinates() {
+        let mut rng = StdRng::seed_from_u64(263);
+        let mut container = LineContainer::new();
+        assert_eq!(container.query(0), None);
+        let mut lines = Vec::new();
+        for _ in 0..500 {
+            let line = Line::new(
+                rng.random_range(-1_000_000..=1_000_000),
+                rng.random_range(i64::MIN + 1..=i64::MAX),
+            );
+            container.add_line(line);
+            lines.push(line);
+            for x in [
+                i64::MIN,
+                i64::MIN + 1,
+                -1,
+                0,
+                1,
+                i64::MAX - 1,
+                i64::MAX,
+                rng.random(),
+            ] {
+                assert_eq!(container.query(x), naive(&lines, x));
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_value_is_reported() {
+        let mut container = LineContainer::new();
+        assert!(container.add_line(Line::new(0, i64::MAX)));
+        assert_eq!(container.query(i64::MIN), Some(i64::MAX));
+        assert!(container.add_line(Line::new(1, i64::MAX - 5)));
+        assert_eq!(container.query(0), Some(i64::MAX - 5));
+        assert_eq!(container.query(10), Some(i64::MAX));
+    }
+}
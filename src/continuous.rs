@@ -0,0 +1,349 @@
+use crate::GenericLine;
+
+/// Index of the root, which is never anyone's child, so it doubles as "no child".
+const NO_CHILD: u32 = 0;
+
+#[derive(Clone, Copy, Debug)]
+struct ContinuousNode {
+    line: GenericLine<f64>,
+    children: [u32; 2],
+}
+
+/// A Li-Chao Tree over the real interval `[x_min, x_max]`, for minima at non-integer x.
+///
+/// Ranges are halved at their real midpoint instead of over integer indices, down to a fixed `max_depth`.
+/// Nodes are created on demand as in [`crate::SparseLiChaoTree`], so memory is O(lines) nodes whatever the depth.
+/// Queries are exact except within `(x_max - x_min) / 2^max_depth` of a crossing between two lines, where a line
+/// dropped at the depth limit could have been the minimum.
+/// Operations take O(max_depth) time.
+///
+/// Results are bit-identical across platforms in the same way as for [`crate::GenericLiChaoTree`] over `f64`:
+/// lines are evaluated with two roundings, never fused, ranges are split at `l + (r - l) / 2.0`, comparisons are
+/// strict so that the first of equal values wins, and `integral` adds up its pieces from left to right.
+#[derive(Clone, Debug)]
+pub struct ContinuousLiChaoTree {
+    nodes: Vec<ContinuousNode>,
+    x_min: f64,
+    x_max: f64,
+    max_depth: u32,
+}
+
+impl ContinuousLiChaoTree {
+    /// Creates an empty tree over `[x_min, x_max]` whose leaves are `max_depth` halvings below the root.
+    /// Panics if the bounds are not finite or `x_min > x_max`.
+    pub fn new(x_min: f64, x_max: f64, max_depth: u32) -> Self {
+        if !x_min.is_finite() || !x_max.is_finite() || x_min > x_max {
+            panic!("[{}, {}] is not a valid finite interval", x_min, x_max);
+        }
+        ContinuousLiChaoTree {
+            nodes: Vec::new(),
+            x_min,
+            x_max,
+            max_depth,
+        }
+    }
+
+    /// Number of nodes allocated so far.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn push_node(&mut self, line: GenericLine<f64>) -> u32 {
+        let idx = u32::try_from(self.nodes.len()).expect("ContinuousLiChaoTree: too many nodes");
+        self.nodes.push(ContinuousNode {
+            line,
+            children: [NO_CHILD; 2],
+        });
+        idx
+    }
+
+    /// Adds a line to the tree. Returns whether it was kept, see [`crate::LiChaoTree::add_line`].
+    /// Panics if a coefficient is NaN.
+    /// Time complexity: O(max_depth).
+    pub fn add_line(&mut self, line: GenericLine<f64>) -> bool {
+        if line.m.is_nan() || line.c.is_nan() {
+            panic!("ContinuousLiChaoTree::add_line: coefficients cannot be NaN");
+        }
+        if self.nodes.is_empty() {
+            self.push_node(line);
+            return true;
+        }
+
+        let mut line_to_add = line;
+        let mut stored = false;
+        let (mut node, mut range_l, mut range_r) = (0usize, self.x_min, self.x_max);
+        for depth in 0.. {
+            let range_m = range_l + (range_r - range_l) / 2.0;
+            let current = self.nodes[node].line;
+            if line_to_add.eval(range_m) < current.eval(range_m) {
+                self.nodes[node].line = line_to_add;
+                line_to_add = current;
+                // The first swap stores the new line, later ones only happen after it was stored
+                stored = true;
+            }
+            if depth == self.max_depth {
+                return stored;
+            }
+
+            let current = self.nodes[node].line;
+            let side = if line_to_add.eval(range_l) < current.eval(range_l) {
+                range_r = range_m;
+                0
+            } else if line_to_add.eval(range_r) < current.eval(range_r) {
+                range_l = range_m;
+                1
+            } else {
+                return stored;
+            };
+            match self.nodes[node].children[side] {
+                NO_CHILD => {
+                    let child = self.push_node(line_to_add);
+                    self.nodes[node].children[side] = child;
+                    return true;
+                }
+                child => node = child as usize,
+            }
+        }
+        unreachable!()
+    }
+
+    /// Queries the minimum y-value at `x`, or `None` if no line has been added.
+    /// Panics if `x` is NaN or outside the tree's bounds.
+    /// Time complexity: O(max_depth).
+    pub fn query(&self, x: f64) -> Option<f64> {
+        if !(self.x_min..=self.x_max).contains(&x) {
+            panic!("{} does not fit inside the tree's bounds", x);
+        }
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut best = f64::INFINITY;
+        let (mut node, mut range_l, mut range_r) = (0usize, self.x_min, self.x_max);
+        loop {
+            // Not `f64::min`, which may return either zero when comparing `-0.0` and `0.0`
+            let value = self.nodes[node].line.eval(x);
+            if value < best {
+                best = value;
+            }
+            let range_m = range_l + (range_r - range_l) / 2.0;
+            let side = if x <= range_m {
+                range_r = range_m;
+                0
+            } else {
+                range_l = range_m;
+                1
+            };
+            match self.nodes[node].children[side] {
+                NO_CHILD => return Some(best),
+                child => node = child as usize,
+            }
+        }
+    }
+
+    /// Integrates the minimum over `[x_l, x_r]`, or returns `None` if no line has been added.
+    /// Wherever a node has no child, the minimum is that of the lines on the path to it, so the integral is exact
+    /// up to the approximation described on [`ContinuousLiChaoTree`].
+    /// Panics if the range is reversed, contains NaN or is outside the tree's bounds.
+    /// Time complexity: O(nodes * max_depth * log(max_depth)).
+    pub fn integral(&self, x_l: f64, x_r: f64) -> Option<f64> {
+        if !(x_l <= x_r && self.x_min <= x_l && x_r <= self.x_max) {
+            panic!("[{}, {}] does not fit inside the tree's bounds", x_l, x_r);
+        }
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let mut path = Vec::new();
+        Some(self.integral_internal(0, self.x_min, self.x_max, x_l, x_r, &mut path))
+    }
+
+    fn integral_internal(
+        &self,
+        node: usize,
+        range_l: f64,
+        range_r: f64,
+        x_l: f64,
+        x_r: f64,
+        path: &mut Vec<GenericLine<f64>>,
+    ) -> f64 {
+        path.push(self.nodes[node].line);
+        let range_m = range_l + (range_r - range_l) / 2.0;
+        let mut sum = 0.0;
+        for (side, (child_l, child_r)) in [(range_l, range_m), (range_m, range_r)]
+            .into_iter()
+            .enumerate()
+        {
+            let (part_l, part_r) = (child_l.max(x_l), child_r.min(x_r));
+            if part_l >= part_r {
+                continue;
+            }
+            sum += match self.nodes[node].children[side] {
+                NO_CHILD => integrate_min(path, part_l, part_r),
+                child => self.integral_internal(child as usize, child_l, child_r, x_l, x_r, path),
+            };
+        }
+        path.pop();
+        sum
+    }
+}
+
+/// Integral over `[x_l, x_r]` of the minimum of `lines`, summed piece by piece over their lower envelope.
+fn integrate_min(lines: &[GenericLine<f64>], x_l: f64, x_r: f64) -> f64 {
+    let mut sorted = lines.to_vec();
+    // Towards larger x, the minimum's slope only decreases
+    sorted.sort_by(|a, b| b.m.total_cmp(&a.m).then(a.c.total_cmp(&b.c)));
+    let mut pieces: Vec<(f64, GenericLine<f64>)> = Vec::new();
+    for (i, &line) in sorted.iter().enumerate() {
+        // Among equal slopes only the first, lowest line matters
+        if i > 0 && sorted[i - 1].m == line.m {
+            continue;
+        }
+        let start = loop {
+            let Some(&(top_start, top)) = pieces.last() else {
+                break Some(x_l);
+            };
+            // Where `line` drops below `top`
+            let crossing = (line.c - top.c) / (top.m - line.m);
+            if crossing <= top_start {
+                pieces.pop();
+                continue;
+            }
+            break (crossing < x_r).then_some(crossing);
+        };
+        if let Some(start) = start {
+            pieces.push((start, line));
+        }
+    }
+    pieces
+        .iter()
+        .enumerate()
+        .map(|(i, &(start, line))| {
+            let end = pieces.get(i + 1).map_or(x_r, |&(next, _)| next);
+            (line.eval(start) + line.eval(end)) / 2.0 * (end - start)
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_matches_naive() {
+        let mut rng = StdRng::seed_from_u64(275);
+        for _ in 0..50 {
+            let mut tree = ContinuousLiChaoTree::new(-10.0, 10.0, 60);
+            let mut lines = Vec::new();
+            for _ in 0..50 {
+                let line =
+                    GenericLine::new(rng.random_range(-5.0..5.0), rng.random_range(-20.0..20.0));
+                tree.add_line(line);
+                lines.push(line);
+                for _ in 0..20 {
+                    let x: f64 = rng.random_range(-10.0..=10.0);
+                    let expected = lines
+                        .iter()
+                        .map(|line| line.eval(x))
+                        .fold(f64::INFINITY, f64::min);
+                    assert!((tree.query(x).unwrap() - expected).abs() < 1e-9);
+                }
+            }
+            assert!(tree.node_count() <= 50);
+        }
+    }
+
+    #[test]
+    fn test_bit_identical() {
+        // Both lines are zero at x = 0.0, the first one is met first from the root
+        let mut tree = ContinuousLiChaoTree::new(-1.0, 1.0, 10);
+        tree.add_line(GenericLine::new(-1.0, -0.0));
+        tree.add_line(GenericLine::new(1.0, 0.0));
+        assert_eq!(tree.query(0.0).map(f64::to_bits), Some((-0.0f64).to_bits()));
+
+        // Computed on x86_64: any platform-dependent rounding would change it
+        let mut rng = StdRng::seed_from_u64(232);
+        let mut tree = ContinuousLiChaoTree::new(-1e3, 1e3, 50);
+        for _ in 0..500 {
+            tree.add_line(GenericLine::new(
+                rng.random_range(-1e2..1e2),
+                rng.random_range(-1e5..1e5),
+            ));
+        }
+        let checksum = (0..200).fold(0u64, |acc, _| {
+            let x = rng.random_range(-1e3..=1e3);
+            acc.rotate_left(7) ^ tree.query(x).unwrap().to_bits()
+        });
+        assert_eq!(checksum, 16922598256268951673);
+        assert_eq!(
+            tree.integral(-1e3, 1e3).unwrap().to_bits(),
+            13956879771152722520
+        );
+    }
+
+    #[test]
+    fn test_non_integer_minimum() {
+        let mut tree = ContinuousLiChaoTree::new(0.0, 1.0, 40);
+        assert_eq!(tree.query(0.5), None);
+        tree.add_line(GenericLine::new(1.0, 0.0));
+        tree.add_line(GenericLine::new(-1.0, 0.5));
+        assert_eq!(tree.query(0.25), Some(0.25));
+        assert_eq!(tree.query(0.75), Some(-0.25));
+        assert_eq!(tree.query(0.1), Some(0.1));
+    }
+
+    #[test]
+    fn test_depth_zero() {
+        // A single node keeps only the line that is best at the middle of the interval
+        let mut tree = ContinuousLiChaoTree::new(0.0, 1.0, 0);
+        assert!(tree.add_line(GenericLine::new(1.0, 0.0)));
+        assert!(!tree.add_line(GenericLine::new(-1.0, 1.5)));
+        assert_eq!(tree.query(1.0), Some(1.0));
+    }
+
+    #[test]
+    fn test_integral() {
+        let mut tree = ContinuousLiChaoTree::new(-1.0, 1.0, 40);
+        assert_eq!(tree.integral(-1.0, 1.0), None);
+        tree.add_line(GenericLine::new(1.0, 0.0));
+        tree.add_line(GenericLine::new(-1.0, 0.0));
+        // Integral of -|x|
+        assert!((tree.integral(-1.0, 1.0).unwrap() + 1.0).abs() < 1e-9);
+        assert!((tree.integral(0.25, 0.75).unwrap() + 0.25).abs() < 1e-9);
+        assert_eq!(tree.integral(0.5, 0.5), Some(0.0));
+
+        let mut rng = StdRng::seed_from_u64(298);
+        for _ in 0..20 {
+            let mut tree = ContinuousLiChaoTree::new(-10.0, 10.0, 50);
+            let lines: Vec<_> = (0..30)
+                .map(|_| {
+                    GenericLine::new(rng.random_range(-5.0..5.0), rng.random_range(-20.0..20.0))
+                })
+                .collect();
+            for &line in &lines {
+                tree.add_line(line);
+            }
+            let x_l: f64 = rng.random_range(-10.0..10.0);
+            let x_r: f64 = rng.random_range(x_l..=10.0);
+            // Trapezoidal rule on the exact minimum, precise enough for a piecewise-linear function
+            let steps = 100_000;
+            let step = (x_r - x_l) / steps as f64;
+            let min_at = |x: f64| {
+                lines
+                    .iter()
+                    .map(|line| line.eval(x))
+                    .fold(f64::INFINITY, f64::min)
+            };
+            let expected: f64 = (0..steps)
+                .map(|k| {
+                    let x = x_l + k as f64 * step;
+                    (min_at(x) + min_at(x + step)) / 2.0 * step
+                })
+                .sum();
+            assert!((tree.integral(x_l, x_r).unwrap() - expected).abs() < 1e-4);
+        }
+    }
+}
@@ -0,0 +1,135 @@
+/// A strictly increasing mapping from grid indices `0..num_points()` to x-coordinates.
+///
+/// A tree built with [`crate::LiChaoTree::with_coordinate_map`] only stores the envelope at these x-coordinates,
+/// so a few hundred points can cover an exponentially wide domain with resolution where it is needed.
+/// Queries and ranges still take raw x-coordinates, which must be grid points.
+pub trait CoordinateMap: Send + Sync {
+    /// Number of grid points. Must be at least one.
+    fn num_points(&self) -> usize;
+
+    /// The x-coordinate of grid point `idx`. Must be strictly increasing in `idx`.
+    fn x_at(&self, idx: usize) -> i64;
+
+    /// The grid index of `x_coord`, or `None` if it is not a grid point.
+    /// Defaults to a binary search over `x_at`; override it if the inverse has a closed form.
+    fn idx_of(&self, x_coord: i64) -> Option<usize> {
+        let (mut lo, mut hi) = (0, self.num_points());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.x_at(mid) < x_coord {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        (lo < self.num_points() && self.x_at(lo) == x_coord).then_some(lo)
+    }
+}
+
+/// A grid made of an arbitrary set of x-coordinates, e.g. the coordinates an offline algorithm will query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SortedCoords {
+    x_coords: Vec<i64>,
+}
+
+impl SortedCoords {
+    /// Creates the grid of the given x-coordinates. They may be unsorted and contain duplicates.
+    pub fn new(x_coords: impl IntoIterator<Item = i64>) -> Self {
+        let mut x_coords: Vec<i64> = x_coords.into_iter().collect();
+        x_coords.sort_unstable();
+        x_coords.dedup();
+        SortedCoords { x_coords }
+    }
+
+    /// The grid points in increasing order.
+    pub fn as_slice(&self) -> &[i64] {
+        &self.x_coords
+    }
+}
+
+impl CoordinateMap for SortedCoords {
+    fn num_points(&self) -> usize {
+        self.x_coords.len()
+    }
+
+    fn x_at(&self, idx: usize) -> i64 {
+        self.x_coords[idx]
+    }
+
+    fn idx_of(&self, x_coord: i64) -> Option<usize> {
+        self.x_coords.binary_search(&x_coord).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LiChaoTree, Line};
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    /// Zero and the powers of two up to 2^61.
+    struct PowersOfTwo;
+
+    impl CoordinateMap for PowersOfTwo {
+        fn num_points(&self) -> usize {
+            63
+        }
+
+        fn x_at(&self, idx: usize) -> i64 {
+            if idx == 0 { 0 } else { 1 << (idx - 1) }
+        }
+    }
+
+    #[test]
+    fn test_default_idx_of() {
+        assert_eq!(PowersOfTwo.idx_of(0), Some(0));
+        assert_eq!(PowersOfTwo.idx_of(1), Some(1));
+        assert_eq!(PowersOfTwo.idx_of(1 << 61), Some(62));
+        assert_eq!(PowersOfTwo.idx_of(3), None);
+        assert_eq!(PowersOfTwo.idx_of(-1), None);
+        assert_eq!(PowersOfTwo.idx_of(i64::MAX), None);
+    }
+
+    #[test]
+    fn test_log_scale_tree() {
+        let mut rng = StdRng::seed_from_u64(245);
+        let mut tree = LiChaoTree::with_coordinate_map(PowersOfTwo);
+        let xs: Vec<i64> = (0..63).map(|idx| PowersOfTwo.x_at(idx)).collect();
+        let mut lines = Vec::new();
+        for _ in 0..200 {
+            let line = Line::new(rng.random_range(-3..=3), rng.random_range(-1000..=1000));
+            tree.add_line(line);
+            lines.push(line);
+            for &x in &xs {
+                let expected = lines.iter().map(|line| line.eval(x)).min();
+                assert_eq!(tree.query(x), expected);
+            }
+        }
+
+        tree.assign_range(4, 1 << 20, Line::new(0, 7));
+        assert_eq!(tree.query(8), Some(7));
+        assert_eq!(tree.query(1 << 20), Some(7));
+        assert_eq!(
+            tree.query(1 << 21),
+            lines.iter().map(|line| line.eval(1 << 21)).min()
+        );
+    }
+
+    #[test]
+    fn test_sorted_coords() {
+        let coords = SortedCoords::new([7, -3, 7, 100, 0]);
+        assert_eq!(coords.as_slice(), &[-3, 0, 7, 100]);
+        assert_eq!(coords.idx_of(7), Some(2));
+        assert_eq!(coords.idx_of(8), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_query_between_grid_points() {
+        let tree = LiChaoTree::with_coordinate_map(PowersOfTwo);
+        tree.query(3);
+    }
+}
@@ -0,0 +1,170 @@
+use std::fmt;
+
+use crate::Line;
+
+/// Errors reported by the fallible constructors and operations of the crate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LiChaoError {
+    /// The lower bound of the domain is greater than the upper bound.
+    InvalidDomain { x_min_coord: i64, x_max_coord: i64 },
+    /// The node array for the domain cannot be indexed or sized in `usize`.
+    DomainTooLarge { domain_size: u128 },
+    /// The node array would need more memory than the configured budget.
+    MemoryBudgetExceeded {
+        required_bytes: usize,
+        budget_bytes: usize,
+    },
+    /// The allocator could not provide the node array.
+    AllocationFailed { bytes: usize },
+    /// An x-coordinate lies outside the tree's domain.
+    OutOfRange { x_coord: i64 },
+    /// A range has its lower bound greater than its upper bound.
+    InvalidRange { x_l: i64, x_r: i64 },
+    /// The exact minimum at an x-coordinate does not fit in an `i64`.
+    Overflow { x_coord: i64, value: i128 },
+    /// Two trees that must share their x-coordinates do not.
+    DomainMismatch,
+    /// More insertions were to be rolled back than are recorded.
+    RollbackUnavailable { requested: usize, available: usize },
+    /// A line through two points with the same x-coordinate was requested.
+    VerticalLine { x_coord: i64 },
+    /// A requested line does not have an integer slope and intercept that fit in `i64`, such as the line through two
+    /// points or a ray of `LiChaoTree::add_abs`.
+    UnrepresentableLine,
+    /// A line with an `i64::MIN` coefficient was passed to a tree in max mode, which stores lines negated.
+    NegationOverflow { line: Line },
+    /// Two trees that must maintain the same kind of envelope do not.
+    ModeMismatch,
+    /// A fixed-capacity tree was given a domain whose size differs from its capacity.
+    CapacityMismatch { domain_size: u128, capacity: usize },
+    /// A tree that numbers its lines was to take the lines of a tree that does not, whose insertion order is unknown.
+    StorageMismatch,
+    /// An operation that rebuilds the tree from its line log was called on a tree that keeps none, see
+    /// `LiChaoTree::set_keep_lines`.
+    LinesNotKept,
+    /// No line has the given insertion index, or the tree does not number its lines, see
+    /// `NodeStorage::Indexed`.
+    UnknownLine { id: usize },
+    /// A line was to be updated with an intercept that does not improve it: a higher one in min mode, or a lower one
+    /// in max mode.
+    NotAnImprovement { line: Line, new_c: i64 },
+    /// A line was to be updated in place in a tree holding segments, rays or assigned ranges, which apply on part of
+    /// the domain only.
+    PartialLines,
+    /// A delta of the line log does not start where the log of the tree it is taken from or applied to ends, see
+    /// `LiChaoTree::delta_since`.
+    SnapshotMismatch { snapshot_id: usize, log_len: usize },
+}
+
+impl fmt::Display for LiChaoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LiChaoError::InvalidDomain {
+                x_min_coord,
+                x_max_coord,
+            } => write!(
+                f,
+                "x_min_coord ({}) cannot be greater than x_max_coord ({})",
+                x_min_coord, x_max_coord
+            ),
+            LiChaoError::DomainTooLarge { domain_size } => write!(
+                f,
+                "Domain size {} is too large, the node array would overflow usize.",
+                domain_size
+            ),
+            LiChaoError::MemoryBudgetExceeded {
+                required_bytes,
+                budget_bytes,
+            } => write!(
+                f,
+                "the tree needs {} bytes, exceeding the memory budget of {} bytes",
+                required_bytes, budget_bytes
+            ),
+            LiChaoError::AllocationFailed { bytes } => {
+                write!(f, "failed to allocate {} bytes for the node array", bytes)
+            }
+            LiChaoError::OutOfRange { x_coord } => {
+                write!(f, "{} does not fit inside the tree's bounds", x_coord)
+            }
+            LiChaoError::InvalidRange { x_l, x_r } => {
+                write!(
+                    f,
+                    "range lower bound {} is greater than upper bound {}",
+                    x_l, x_r
+                )
+            }
+            LiChaoError::Overflow { x_coord, value } => {
+                write!(
+                    f,
+                    "the minimum at {} is {}, which overflows i64",
+                    x_coord, value
+                )
+            }
+            LiChaoError::DomainMismatch => {
+                write!(f, "both trees must have the same x-coordinates")
+            }
+            LiChaoError::RollbackUnavailable {
+                requested,
+                available,
+            } => write!(
+                f,
+                "cannot roll back {} insertions, only {} can be rolled back",
+                requested, available
+            ),
+            LiChaoError::VerticalLine { x_coord } => {
+                write!(
+                    f,
+                    "the line through both points is vertical at x = {}",
+                    x_coord
+                )
+            }
+            LiChaoError::UnrepresentableLine => write!(
+                f,
+                "the requested line has no integer slope and intercept in i64"
+            ),
+            LiChaoError::NegationOverflow { line } => write!(
+                f,
+                "{:?} cannot be negated to be stored in a max-mode tree",
+                line
+            ),
+            LiChaoError::ModeMismatch => {
+                write!(f, "both trees must be in the same mode")
+            }
+            LiChaoError::CapacityMismatch {
+                domain_size,
+                capacity,
+            } => write!(
+                f,
+                "the domain has {} x-coordinates, but the tree holds exactly {}",
+                domain_size, capacity
+            ),
+            LiChaoError::StorageMismatch => {
+                write!(f, "the lines of a tree without line ids cannot be numbered")
+            }
+            LiChaoError::LinesNotKept => {
+                write!(f, "the tree keeps no line log to rebuild from")
+            }
+            LiChaoError::UnknownLine { id } => {
+                write!(f, "no line has id {}", id)
+            }
+            LiChaoError::NotAnImprovement { line, new_c } => {
+                write!(f, "an intercept of {} does not improve {:?}", new_c, line)
+            }
+            LiChaoError::PartialLines => write!(
+                f,
+                "lines cannot be updated in a tree holding lines on part of the domain"
+            ),
+            LiChaoError::SnapshotMismatch {
+                snapshot_id,
+                log_len,
+            } => write!(
+                f,
+                "the delta starts at snapshot {}, but the line log has {} entries",
+                snapshot_id, log_len
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LiChaoError {}
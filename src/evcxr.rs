@@ -0,0 +1,98 @@
+use std::fmt::Write;
+
+use crate::LiChaoTree;
+
+const PLOT_WIDTH: f64 = 480.0;
+const PLOT_HEIGHT: f64 = 240.0;
+const MAX_SAMPLES: usize = 256;
+
+impl LiChaoTree {
+    /// Hook picked up by evcxr: evaluating a tree in a Jupyter cell renders the envelope inline.
+    pub fn evcxr_display(&self) {
+        println!(
+            "EVCXR_BEGIN_CONTENT text/html\n{}\nEVCXR_END_CONTENT",
+            self.envelope_svg()
+        );
+    }
+
+    /// Renders the envelope, sampled at up to `MAX_SAMPLES` evenly spaced x-coordinates, as an SVG polyline.
+    fn envelope_svg(&self) -> String {
+        let samples = self.domain_size.min(MAX_SAMPLES);
+        let points: Vec<(i64, i64)> = (0..samples)
+            .map(|k| {
+                let idx = if samples == 1 {
+                    0
+                } else {
+                    (k as u128 * (self.domain_size - 1) as u128 / (samples - 1) as u128) as usize
+                };
+                self.get_x_coord_from_idx(idx)
+            })
+            .filter_map(|x| self.query(x).map(|y| (x, y)))
+            .collect();
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">",
+            PLOT_WIDTH, PLOT_HEIGHT
+        );
+        if points.is_empty() {
+            svg.push_str("<text x=\"10\" y=\"20\">empty envelope</text></svg>");
+            return svg;
+        }
+
+        let (x_lo, x_hi) = (points[0].0 as f64, points[points.len() - 1].0 as f64);
+        let y_lo = points.iter().map(|p| p.1).min().unwrap() as f64;
+        let y_hi = points.iter().map(|p| p.1).max().unwrap() as f64;
+        let scale = |val: f64, lo: f64, hi: f64, len: f64| {
+            if hi > lo {
+                (val - lo) / (hi - lo) * len
+            } else {
+                len / 2.0
+            }
+        };
+
+        svg.push_str("<polyline fill=\"none\" stroke=\"steelblue\" stroke-width=\"2\" points=\"");
+        for (x, y) in &points {
+            let px = scale(*x as f64, x_lo, x_hi, PLOT_WIDTH);
+            let py = PLOT_HEIGHT - scale(*y as f64, y_lo, y_hi, PLOT_HEIGHT);
+            write!(svg, "{:.1},{:.1} ", px, py).unwrap();
+        }
+        write!(
+            svg,
+            "\"/><text x=\"4\" y=\"12\" font-size=\"10\">x ∈ [{}, {}], y ∈ [{}, {}]</text></svg>",
+            x_lo, x_hi, y_lo, y_hi
+        )
+        .unwrap();
+        svg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LiChaoTree, Line};
+
+    fn polyline_len(svg: &str) -> usize {
+        let start = svg.find("points=\"").unwrap() + "points=\"".len();
+        let end = start + svg[start..].find('"').unwrap();
+        svg[start..end].split_whitespace().count()
+    }
+
+    #[test]
+    fn test_envelope_svg() {
+        let mut tree = LiChaoTree::new(0, 10);
+        assert!(tree.envelope_svg().contains("empty envelope"));
+
+        tree.add_line(Line::new(2, 3));
+        tree.add_line(Line::new(-1, 10));
+        let svg = tree.envelope_svg();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.ends_with("</svg>"));
+        assert_eq!(polyline_len(&svg), 11);
+    }
+
+    #[test]
+    fn test_envelope_svg_samples_large_domains() {
+        let mut tree = LiChaoTree::new(0, 1_000_000);
+        tree.add_line(Line::new(1, 0));
+        assert_eq!(polyline_len(&tree.envelope_svg()), super::MAX_SAMPLES);
+    }
+}
@@ -0,0 +1,114 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use crate::{LiChaoTree, Line};
+
+// C interface to `LiChaoTree`, declared in include/lichao.h. Trees are handed out as opaque pointers owned by the
+// caller. Panics must not unwind into C: the fallible methods are called, and every body also runs under
+// `catch_unwind`, reporting a panic like a failure. A tree whose insertion panicked may have lost lines, but stays
+// safe to query and free.
+
+/// Runs `f`, returning `failed` instead if it panics.
+fn guard<T>(failed: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(failed)
+}
+
+/// Creates a tree over the integers in `[x_min_coord, x_max_coord]`, see `LiChaoTree::new`.
+/// Returns a null pointer if the domain is empty or the tree cannot be allocated. The tree must be freed with
+/// `lichao_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn lichao_new(x_min_coord: i64, x_max_coord: i64) -> *mut LiChaoTree {
+    guard(ptr::null_mut(), || {
+        match LiChaoTree::try_new(x_min_coord, x_max_coord) {
+            Ok(tree) => Box::into_raw(Box::new(tree)),
+            Err(_) => ptr::null_mut(),
+        }
+    })
+}
+
+/// Adds the line `y = m * x + c`, and returns whether it was kept, see `LiChaoTree::add_line`.
+/// Returns `false` if the line cannot be stored, see `LiChaoTree::try_add_line`.
+///
+/// # Safety
+/// `tree` must have been returned by `lichao_new`, not freed yet, and not be in use by another thread.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lichao_add_line(tree: *mut LiChaoTree, m: i64, c: i64) -> bool {
+    // SAFETY: guaranteed by the caller
+    let tree = unsafe { &mut *tree };
+    guard(false, || {
+        tree.try_add_line(Line::new(m, c)).unwrap_or(false)
+    })
+}
+
+/// Writes the minimum at `x_coord` to `out` and returns `true`, or returns `false` without writing if no line has
+/// been added or `x_coord` is outside the tree's domain.
+///
+/// # Safety
+/// `tree` must have been returned by `lichao_new` and not freed yet. `out` must be valid for writing an `int64_t`.
+/// Several threads may query the same tree at once, as long as none of them modifies it.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lichao_query(
+    tree: *const LiChaoTree,
+    x_coord: i64,
+    out: *mut i64,
+) -> bool {
+    // SAFETY: guaranteed by the caller
+    let tree = unsafe { &*tree };
+    guard(false, || match tree.try_query(x_coord) {
+        Ok(Some(val)) => {
+            // SAFETY: guaranteed by the caller
+            unsafe { out.write(val) };
+            true
+        }
+        _ => false,
+    })
+}
+
+/// Frees a tree. Does nothing if `tree` is null.
+///
+/// # Safety
+/// `tree` must be null, or have been returned by `lichao_new` and not freed yet. It must not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lichao_free(tree: *mut LiChaoTree) {
+    if !tree.is_null() {
+        // SAFETY: guaranteed by the caller
+        let tree = unsafe { Box::from_raw(tree) };
+        guard((), || drop(tree));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lifecycle() {
+        assert!(lichao_new(10, 0).is_null());
+        // SAFETY: the tree comes from `lichao_new` and is freed last
+        unsafe {
+            let tree = lichao_new(-10, 10);
+            assert!(!tree.is_null());
+            let mut val = 0;
+            assert!(!lichao_query(tree, 0, &mut val));
+            assert!(lichao_add_line(tree, 2, 3));
+            assert!(lichao_add_line(tree, -1, 0));
+            // A copy of a stored line is discarded
+            assert!(!lichao_add_line(tree, 2, 3));
+            assert!(lichao_query(tree, -2, &mut val));
+            assert_eq!(val, -1);
+            assert!(lichao_query(tree, 5, &mut val));
+            assert_eq!(val, -5);
+            // Out of range, which must not panic across the boundary
+            assert!(!lichao_query(tree, 11, &mut val));
+            assert_eq!(val, -5);
+            lichao_free(tree);
+            lichao_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_panics_do_not_unwind() {
+        assert!(!guard(false, || panic!("unwinding into C")));
+        assert!(guard(false, || true));
+    }
+}
@@ -0,0 +1,135 @@
+use crate::LiChaoTree;
+
+/// Tags separating the parts of the canonical form.
+const INTEGER_DOMAIN: u8 = 0;
+const GRID_DOMAIN: u8 = 1;
+const RUN_START: u8 = 2;
+const PIECE: u8 = 3;
+
+/// FNV-1a over 128 bits. Unlike `std::hash`, its output is fixed by its definition, so fingerprints can be compared
+/// across processes, platforms and compiler versions.
+struct Fnv128(u128);
+
+impl Fnv128 {
+    const OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u128).wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn write_i128(&mut self, value: i128) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, value: u128) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_point(&mut self, (x, y): (i64, i128)) {
+        self.write_i128(x as i128);
+        self.write_i128(y);
+    }
+
+    /// Writes a piece of a run by its slope and last point. Runs made of a single point have no pieces.
+    fn write_piece_end(&mut self, point: (i64, i128), slope: Option<Slope>) {
+        if let Some(slope) = slope {
+            self.write(&[PIECE, slope.negative as u8]);
+            self.write_u128(slope.num);
+            self.write_u128(slope.den);
+            self.write_point(point);
+        }
+    }
+}
+
+/// Exact slope between two points of the envelope, as a sign and a reduced fraction, so equal slopes are equal values.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Slope {
+    negative: bool,
+    num: u128,
+    den: u128,
+}
+
+impl Slope {
+    fn between((x_a, y_a): (i64, i128), (x_b, y_b): (i64, i128)) -> Self {
+        let (num, den) = (y_b.abs_diff(y_a), x_b.abs_diff(x_a) as u128);
+        let divisor = gcd(num, den);
+        Slope {
+            negative: y_b < y_a,
+            num: num / divisor,
+            den: den / divisor,
+        }
+    }
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+impl LiChaoTree {
+    /// Returns a stable 128-bit hash of the envelope as a function: trees with the same x-coordinates, and the same
+    /// envelope values wherever it is not empty, have the same fingerprint, whichever lines and settings produced it.
+    /// Different envelopes have different fingerprints with overwhelming probability.
+    ///
+    /// The hash is computed from a canonical piecewise-linear form: the envelope is split into runs of consecutive
+    /// x-coordinates where it is not empty, and each run into maximal pieces of constant exact slope, which does not
+    /// depend on which of several equal lines wins at a point. The hash function is FNV-1a, so the result does not
+    /// change between runs or platforms. The runs are read off the pieces of `to_piecewise`, so only breakpoints are
+    /// visited, except that the grid points of `with_coordinate_map` are all hashed.
+    /// Time complexity: that of `to_piecewise`, plus O(domain_size) on grids.
+    pub fn fingerprint(&self) -> u128 {
+        let mut hasher = Fnv128(Fnv128::OFFSET_BASIS);
+        match self.coord_map {
+            None => {
+                hasher.write(&[INTEGER_DOMAIN]);
+                hasher.write_i128(self.x_min_coord as i128);
+                hasher.write_u128(self.domain_size as u128);
+            }
+            Some(_) => {
+                hasher.write(&[GRID_DOMAIN]);
+                hasher.write_u128(self.domain_size as u128);
+                for idx in 0..self.domain_size {
+                    hasher.write_i128(self.get_x_coord_from_idx(idx) as i128);
+                }
+            }
+        }
+
+        // Last point of the current run, and the slope of the piece ending there unless the run is a single point
+        let mut last: Option<((i64, i128), Option<Slope>)> = None;
+        for piece in self.to_piecewise() {
+            let first = (piece.x_l, piece.line.eval_wide(piece.x_l));
+            let joined = last.is_some_and(|((x, _), _)| {
+                self.get_idx_from_x_coord(x) + 1 == self.get_idx_from_x_coord(piece.x_l)
+            });
+            if !joined {
+                if let Some((point, slope)) = last {
+                    hasher.write_piece_end(point, slope);
+                }
+                hasher.write(&[RUN_START]);
+                hasher.write_point(first);
+                last = Some((first, None));
+            }
+            // Consecutive points of a piece lie on its line, so only the slopes towards its ends need to be checked
+            for point in [first, (piece.x_r, piece.line.eval_wide(piece.x_r))] {
+                let (prev, slope) = last.unwrap();
+                if point.0 == prev.0 {
+                    continue;
+                }
+                let step = Slope::between(prev, point);
+                if let Some(slope) = slope.filter(|&slope| slope != step) {
+                    hasher.write_piece_end(prev, Some(slope));
+                }
+                last = Some((point, Some(step)));
+            }
+        }
+        if let Some((point, slope)) = last {
+            hasher.write_piece_end(point, slope);
+        }
+        hasher.0
+    }
+}
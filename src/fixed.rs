@@ -0,0 +1,466 @@
+use crate::{LiChaoError, Line, NO_LINE};
+
+/// A Li-Chao Tree over the `N` x-coordinates `[x_min_coord, x_min_coord + N - 1]` stored inline, without heap allocation.
+///
+/// Construction with `with_line` and `with_segment` and all queries are `const fn`, so small envelopes known at compile
+/// time can be built into a `const` or `static` and queried with zero startup cost. Nodes use an Euler-tour layout:
+/// the node covering `[l, r]` at index `v` has its left child at `v + 1` and its right child at `v + 2 * (m - l + 1)`,
+/// so exactly `2N - 1` nodes are needed. They are kept in pairs because array lengths like `2 * N` cannot be computed
+/// from a const generic, and whether each node of a pair holds a line is kept in two bits of a byte.
+///
+/// The methods mirror those of [`crate::LiChaoTree`] with the default settings, with the same results, so code can
+/// switch between both with a type alias:
+///
+/// ```
+/// # use lichao::Line;
+/// type Tree = lichao::LiChaoTreeFixed<21>; // or lichao::LiChaoTree
+/// let mut tree = Tree::new(-10, 10);
+/// tree.add_line((2, 3));
+/// tree.add_segment(Line::new(0, -5), 0, 4);
+/// assert_eq!(tree.query(-3), Some(-3));
+/// assert_eq!(tree.query(2), Some(-5));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct LiChaoTreeFixed<const N: usize> {
+    nodes: [[Line; 2]; N],
+    // Bit `i` of `occupied[p]` is set if `nodes[p][i]` holds a line
+    occupied: [u8; N],
+    x_min_coord: i64,
+    // Number of lines added since construction or the last `clear`, see `len`.
+    line_count: usize,
+}
+
+impl<const N: usize> LiChaoTreeFixed<N> {
+    /// Creates an empty tree over the integers in `[x_min_coord, x_max_coord]`, which must be exactly `N` of them.
+    /// Panics otherwise.
+    pub const fn new(x_min_coord: i64, x_max_coord: i64) -> Self {
+        match Self::try_new(x_min_coord, x_max_coord) {
+            Ok(tree) => tree,
+            Err(LiChaoError::InvalidDomain { .. }) => {
+                panic!("LiChaoTreeFixed::new: x_min_coord cannot be greater than x_max_coord")
+            }
+            Err(_) => {
+                panic!("LiChaoTreeFixed::new: the domain must contain exactly N x-coordinates")
+            }
+        }
+    }
+
+    /// Same as `new`, but fails with `LiChaoError::InvalidDomain` if `x_min_coord > x_max_coord`, and with
+    /// `LiChaoError::CapacityMismatch` if the domain does not contain exactly `N` x-coordinates.
+    pub const fn try_new(x_min_coord: i64, x_max_coord: i64) -> Result<Self, LiChaoError> {
+        if x_min_coord > x_max_coord {
+            return Err(LiChaoError::InvalidDomain {
+                x_min_coord,
+                x_max_coord,
+            });
+        }
+        let domain_size = x_max_coord.abs_diff(x_min_coord) as u128 + 1;
+        if domain_size != N as u128 {
+            return Err(LiChaoError::CapacityMismatch {
+                domain_size,
+                capacity: N,
+            });
+        }
+        Ok(LiChaoTreeFixed {
+            nodes: [[NO_LINE; 2]; N],
+            occupied: [0; N],
+            x_min_coord,
+            line_count: 0,
+        })
+    }
+
+    /// Builder-style `add_line`, usable in constant expressions.
+    pub const fn with_line(mut self, line: Line) -> Self {
+        self.add_line_const(line);
+        self
+    }
+
+    /// Builder-style `add_segment`, usable in constant expressions.
+    pub const fn with_segment(mut self, line: Line, x_l: i64, x_r: i64) -> Self {
+        self.add_segment(line, x_l, x_r);
+        self
+    }
+
+    /// Removes every line, keeping the domain.
+    /// Time complexity: O(N).
+    pub const fn clear(&mut self) {
+        self.nodes = [[NO_LINE; 2]; N];
+        self.occupied = [0; N];
+        self.line_count = 0;
+    }
+
+    /// Number of lines added since the tree was created or last cleared, including discarded lines and segments.
+    pub const fn len(&self) -> usize {
+        self.line_count
+    }
+
+    /// Returns whether no line has been added, in which case every query returns `None`.
+    pub const fn is_empty(&self) -> bool {
+        self.line_count == 0
+    }
+
+    /// Line stored in a node, or `None` if it is empty.
+    #[inline]
+    const fn node(&self, node_v_idx: usize) -> Option<Line> {
+        if self.occupied[node_v_idx / 2] >> (node_v_idx % 2) & 1 == 1 {
+            Some(self.nodes[node_v_idx / 2][node_v_idx % 2])
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    const fn set_node(&mut self, node_v_idx: usize, line: Line) {
+        self.nodes[node_v_idx / 2][node_v_idx % 2] = line;
+        self.occupied[node_v_idx / 2] |= 1 << (node_v_idx % 2);
+    }
+
+    #[inline]
+    const fn get_x_coord_from_idx(&self, index: usize) -> i64 {
+        self.x_min_coord + index as i64
+    }
+
+    #[inline]
+    const fn contains(&self, x_coord: i64) -> bool {
+        x_coord >= self.x_min_coord && x_coord.abs_diff(self.x_min_coord) < N as u64
+    }
+
+    /// Checks that `[x_l, x_r]` is a range of the tree's x-coordinates.
+    const fn check_range(&self, x_l: i64, x_r: i64) -> Result<(), LiChaoError> {
+        if !self.contains(x_l) {
+            return Err(LiChaoError::OutOfRange { x_coord: x_l });
+        }
+        if !self.contains(x_r) {
+            return Err(LiChaoError::OutOfRange { x_coord: x_r });
+        }
+        if x_l > x_r {
+            return Err(LiChaoError::InvalidRange { x_l, x_r });
+        }
+        Ok(())
+    }
+
+    /// Adds a line `y = mx + c` to the tree. Returns whether the line was kept, see [`crate::LiChaoTree::add_line`].
+    /// Accepts anything convertible into a line, such as an `(m, c)` tuple.
+    /// Time complexity: O(log N).
+    pub fn add_line(&mut self, line: impl Into<Line>) -> bool {
+        self.add_line_const(line.into())
+    }
+
+    /// Same as `add_line`. It never fails, and is kept so that code written against `LiChaoTree::try_add_line` still
+    /// compiles.
+    pub fn try_add_line(&mut self, line: impl Into<Line>) -> Result<bool, LiChaoError> {
+        Ok(self.add_line_const(line.into()))
+    }
+
+    const fn add_line_const(&mut self, line: Line) -> bool {
+        self.line_count += 1;
+        self.add_line_internal(line, 0, 0, N - 1)
+    }
+
+    /// Pushes `line` down from the node at `node_v_idx` covering `[range_l_idx, range_r_idx]`.
+    /// Returns whether `line` was stored in some node.
+    const fn add_line_internal(
+        &mut self,
+        line: Line,
+        mut node_v_idx: usize,
+        mut range_l_idx: usize,
+        mut range_r_idx: usize,
+    ) -> bool {
+        let mut line_to_add = line;
+        let mut carrying_new_line = true;
+        let mut stored = false;
+        loop {
+            let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+            let x_at_l = self.get_x_coord_from_idx(range_l_idx);
+            let x_at_m = self.get_x_coord_from_idx(range_m_idx);
+            let x_at_r = self.get_x_coord_from_idx(range_r_idx);
+
+            let current = self.node(node_v_idx);
+            let wins = match current {
+                Some(current) => line_to_add.eval_wide(x_at_m) < current.eval_wide(x_at_m),
+                None => true,
+            };
+            if wins {
+                self.set_node(node_v_idx, line_to_add);
+                stored |= carrying_new_line;
+                carrying_new_line = false;
+                match current {
+                    Some(current) => line_to_add = current,
+                    // An empty node takes the line, and nothing is left to carry down
+                    None => return stored,
+                }
+            }
+
+            if range_l_idx == range_r_idx {
+                return stored;
+            }
+
+            // The node holds a line by now
+            let current = self.nodes[node_v_idx / 2][node_v_idx % 2];
+            if line_to_add.eval_wide(x_at_l) < current.eval_wide(x_at_l) {
+                node_v_idx += 1;
+                range_r_idx = range_m_idx;
+            } else if line_to_add.eval_wide(x_at_r) < current.eval_wide(x_at_r) {
+                node_v_idx += 2 * (range_m_idx - range_l_idx + 1);
+                range_l_idx = range_m_idx + 1;
+            } else {
+                return stored;
+            }
+        }
+    }
+
+    /// Adds the segment of `line` over `[x_l, x_r]`, see [`crate::LiChaoTree::add_segment`].
+    /// Panics if the range is reversed or outside the tree's bounds.
+    /// Time complexity: O(log^2 N).
+    pub const fn add_segment(&mut self, line: Line, x_l: i64, x_r: i64) -> bool {
+        match self.check_range(x_l, x_r) {
+            Ok(()) => {}
+            Err(LiChaoError::InvalidRange { .. }) => {
+                panic!(
+                    "LiChaoTreeFixed::add_segment: range lower bound is greater than upper bound"
+                )
+            }
+            Err(_) => panic!("x_coord does not fit inside the tree's bounds"),
+        }
+        self.line_count += 1;
+        let query_l_idx = x_l.abs_diff(self.x_min_coord) as usize;
+        let query_r_idx = x_r.abs_diff(self.x_min_coord) as usize;
+        self.add_line_on_range(line, 0, 0, N - 1, query_l_idx, query_r_idx)
+    }
+
+    /// Same as `add_segment`, but fails with `LiChaoError::OutOfRange` or `LiChaoError::InvalidRange` instead of
+    /// panicking.
+    pub const fn try_add_segment(
+        &mut self,
+        line: Line,
+        x_l: i64,
+        x_r: i64,
+    ) -> Result<bool, LiChaoError> {
+        match self.check_range(x_l, x_r) {
+            Ok(()) => Ok(self.add_segment(line, x_l, x_r)),
+            Err(err) => Err(err),
+        }
+    }
+
+    const fn add_line_on_range(
+        &mut self,
+        line: Line,
+        node_v_idx: usize,
+        range_l_idx: usize,
+        range_r_idx: usize,
+        query_l_idx: usize,
+        query_r_idx: usize,
+    ) -> bool {
+        if range_r_idx < query_l_idx || query_r_idx < range_l_idx {
+            return false;
+        }
+        if query_l_idx <= range_l_idx && range_r_idx <= query_r_idx {
+            return self.add_line_internal(line, node_v_idx, range_l_idx, range_r_idx);
+        }
+
+        let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+        let right_v_idx = node_v_idx + 2 * (range_m_idx - range_l_idx + 1);
+        let stored_l = self.add_line_on_range(
+            line,
+            node_v_idx + 1,
+            range_l_idx,
+            range_m_idx,
+            query_l_idx,
+            query_r_idx,
+        );
+        let stored_r = self.add_line_on_range(
+            line,
+            right_v_idx,
+            range_m_idx + 1,
+            range_r_idx,
+            query_l_idx,
+            query_r_idx,
+        );
+        stored_l || stored_r
+    }
+
+    /// Queries the minimum y-value at `x_coord`, or `None` if no line has been added.
+    /// Panics if `x_coord` is outside the tree's bounds.
+    /// Time complexity: O(log N).
+    pub const fn query(&self, x_coord: i64) -> Option<i64> {
+        match self.query_line(x_coord) {
+            Some((val, _)) => Some(val),
+            None => None,
+        }
+    }
+
+    /// Same as `query`, but also returns the line achieving the minimum. Among lines with the same minimum, the one
+    /// stored closest to the root is returned, as with the default tie-break rule of [`crate::LiChaoTree`].
+    /// Time complexity: O(log N).
+    pub const fn query_line(&self, x_coord: i64) -> Option<(i64, Line)> {
+        if !self.contains(x_coord) {
+            panic!("x_coord does not fit inside the tree's bounds");
+        }
+        let query_idx = x_coord.abs_diff(self.x_min_coord) as usize;
+
+        let mut best: Option<Line> = None;
+        let (mut node_v_idx, mut range_l_idx, mut range_r_idx) = (0, 0, N - 1);
+        loop {
+            if let Some(line) = self.node(node_v_idx) {
+                best = match best {
+                    Some(best) if best.eval_wide(x_coord) <= line.eval_wide(x_coord) => Some(best),
+                    _ => Some(line),
+                };
+            }
+            if range_l_idx == range_r_idx {
+                break;
+            }
+            let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+            if query_idx <= range_m_idx {
+                node_v_idx += 1;
+                range_r_idx = range_m_idx;
+            } else {
+                node_v_idx += 2 * (range_m_idx - range_l_idx + 1);
+                range_l_idx = range_m_idx + 1;
+            }
+        }
+        match best {
+            Some(best) => Some((best.eval_clamped(x_coord), best)),
+            None => None,
+        }
+    }
+
+    /// Same as `query`, but fails with `LiChaoError::OutOfRange` instead of panicking.
+    pub const fn try_query(&self, x_coord: i64) -> Result<Option<i64>, LiChaoError> {
+        if !self.contains(x_coord) {
+            return Err(LiChaoError::OutOfRange { x_coord });
+        }
+        Ok(self.query(x_coord))
+    }
+
+    /// Same as `query_line`, but fails with `LiChaoError::OutOfRange` instead of panicking.
+    pub const fn try_query_line(&self, x_coord: i64) -> Result<Option<(i64, Line)>, LiChaoError> {
+        if !self.contains(x_coord) {
+            return Err(LiChaoError::OutOfRange { x_coord });
+        }
+        Ok(self.query_line(x_coord))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LiChaoTree;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    const TREE: LiChaoTreeFixed<11> = LiChaoTreeFixed::new(0, 10)
+        .with_line(Line::new(2, 3))
+        .with_line(Line::new(-1, 10));
+    const AT_FIVE: Option<i64> = TREE.query(5);
+
+    #[test]
+    fn test_const_tree() {
+        assert_eq!(AT_FIVE, Some(5));
+        assert_eq!(TREE.query(0), Some(3));
+        assert_eq!(TREE.query(10), Some(0));
+        assert_eq!(LiChaoTreeFixed::<4>::new(-2, 1).query(1), None);
+        const SEGMENT: LiChaoTreeFixed<11> = TREE.with_segment(Line::new(0, -1), 4, 6);
+        assert_eq!(SEGMENT.query(5), Some(-1));
+        assert_eq!(SEGMENT.query(7), Some(3));
+        assert_eq!(SEGMENT.len(), 3);
+    }
+
+    #[test]
+    fn test_matches_heap_tree() {
+        let mut rng = StdRng::seed_from_u64(17);
+        let mut fixed = LiChaoTreeFixed::<37>::new(-18, 18);
+        let mut heap = LiChaoTree::new(-18, 18);
+        for _ in 0..300 {
+            let line = Line::new(rng.random_range(-30..=30), rng.random_range(-300..=300));
+            assert_eq!(fixed.add_line(line), heap.add_line(line));
+            for x in -18..=18 {
+                assert_eq!(fixed.query(x), heap.query(x));
+            }
+        }
+    }
+
+    #[test]
+    fn test_mirrors_heap_api() {
+        let mut rng = StdRng::seed_from_u64(292);
+        for _ in 0..50 {
+            let mut fixed = LiChaoTreeFixed::<41>::new(-20, 20);
+            let mut heap = LiChaoTree::new(-20, 20);
+            for _ in 0..40 {
+                // Large coefficients, so that comparisons need to be exact and results are clamped
+                let line = Line::new(rng.random_range(-5..=5) << 59, rng.random_range(-300..=300));
+                let x_l = rng.random_range(-20..=20);
+                let x_r = rng.random_range(x_l..=20);
+                if rng.random_bool(0.5) {
+                    assert_eq!(
+                        fixed.add_line((line.m, line.c)),
+                        heap.add_line((line.m, line.c))
+                    );
+                } else {
+                    assert_eq!(
+                        fixed.add_segment(line, x_l, x_r),
+                        heap.add_segment(line, x_l, x_r)
+                    );
+                }
+                for x in -20..=20 {
+                    assert_eq!(fixed.query_line(x), heap.query_line(x));
+                    assert_eq!(fixed.query(x), heap.query(x));
+                }
+            }
+            assert_eq!(fixed.len(), heap.len());
+            fixed.clear();
+            assert!(fixed.is_empty());
+            assert!((-20..=20).all(|x| fixed.query(x).is_none()));
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            LiChaoTreeFixed::<4>::try_new(3, 0).unwrap_err(),
+            LiChaoError::InvalidDomain {
+                x_min_coord: 3,
+                x_max_coord: 0
+            }
+        );
+        assert_eq!(
+            LiChaoTreeFixed::<4>::try_new(0, 4).unwrap_err(),
+            LiChaoError::CapacityMismatch {
+                domain_size: 5,
+                capacity: 4
+            }
+        );
+        assert!(LiChaoTreeFixed::<0>::try_new(i64::MIN, i64::MAX).is_err());
+
+        let mut tree = TREE;
+        assert_eq!(tree.try_add_line(NO_LINE), Ok(false));
+        assert_eq!(
+            tree.try_add_segment(Line::new(0, 0), 3, 11),
+            Err(LiChaoError::OutOfRange { x_coord: 11 })
+        );
+        assert_eq!(
+            tree.try_add_segment(Line::new(0, 0), 4, 3),
+            Err(LiChaoError::InvalidRange { x_l: 4, x_r: 3 })
+        );
+        assert_eq!(
+            tree.try_query(-1),
+            Err(LiChaoError::OutOfRange { x_coord: -1 })
+        );
+        assert_eq!(tree.try_query_line(5), Ok(Some((5, Line::new(-1, 10)))));
+        assert_eq!(tree.len(), 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_out_of_bounds_query() {
+        TREE.query(11);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_capacity_mismatch() {
+        LiChaoTreeFixed::<11>::new(0, 11);
+    }
+}
@@ -0,0 +1,290 @@
+use std::cmp::Ordering;
+
+use crate::Line;
+
+/// A read-only envelope stored as its pieces: `lines[i]` is the minimum on `[starts[i], starts[i + 1] - 1]`,
+/// and the last line on `[starts[last], x_max_coord]`.
+///
+/// Borrowing plain slices lets the pieces live in `static` data produced at build time by [`FrozenEnvelope::codegen`],
+/// so nothing is computed or allocated at startup. Queries are a binary search over the pieces.
+#[derive(Clone, Copy, Debug)]
+pub struct FrozenEnvelope<'a> {
+    x_min_coord: i64,
+    x_max_coord: i64,
+    starts: &'a [i64],
+    lines: &'a [Line],
+}
+
+impl<'a> FrozenEnvelope<'a> {
+    /// Wraps precomputed pieces. Panics if they do not describe an envelope over `[x_min_coord, x_max_coord]`:
+    /// `starts` and `lines` must have the same length, and `starts` must begin at `x_min_coord` and be strictly
+    /// increasing within the domain. Both slices are empty for an envelope without lines.
+    pub const fn new(
+        x_min_coord: i64,
+        x_max_coord: i64,
+        starts: &'a [i64],
+        lines: &'a [Line],
+    ) -> Self {
+        assert!(
+            x_min_coord <= x_max_coord,
+            "FrozenEnvelope::new: x_min_coord cannot be greater than x_max_coord"
+        );
+        assert!(
+            starts.len() == lines.len(),
+            "FrozenEnvelope::new: every piece needs a start and a line"
+        );
+        assert!(
+            starts.is_empty() || starts[0] == x_min_coord,
+            "FrozenEnvelope::new: the first piece must start at x_min_coord"
+        );
+        let mut i = 1;
+        while i < starts.len() {
+            assert!(
+                starts[i - 1] < starts[i] && starts[i] <= x_max_coord,
+                "FrozenEnvelope::new: piece starts must be strictly increasing and inside the domain"
+            );
+            i += 1;
+        }
+        FrozenEnvelope {
+            x_min_coord,
+            x_max_coord,
+            starts,
+            lines,
+        }
+    }
+
+    /// Queries the minimum y-value at `x_coord`, with the same result as `LiChaoTree::query` on the original lines:
+    /// `None` only if there are no lines, and `i64::MAX` when that is the minimum.
+    /// Panics if `x_coord` is outside the envelope's bounds.
+    /// Time complexity: O(log(pieces)).
+    pub const fn query(&self, x_coord: i64) -> Option<i64> {
+        if x_coord < self.x_min_coord || x_coord > self.x_max_coord {
+            panic!("x_coord does not fit inside the envelope's bounds");
+        }
+        if self.starts.is_empty() {
+            return None;
+        }
+        // Last piece starting at or before x_coord
+        let (mut lo, mut hi) = (0, self.starts.len() - 1);
+        while lo < hi {
+            let mid = hi - (hi - lo) / 2;
+            if self.starts[mid] <= x_coord {
+                lo = mid;
+            } else {
+                hi = mid - 1;
+            }
+        }
+        Some(self.lines[lo].eval_clamped(x_coord))
+    }
+
+    /// Emits Rust source defining `pub static <name>: lichao::FrozenEnvelope<'static>` holding the envelope of `lines`
+    /// over `[x_min_coord, x_max_coord]`. Meant for build scripts: write the result to a file in `OUT_DIR` and
+    /// `include!` it. Only the lines that appear on the envelope are emitted.
+    /// Panics if the domain is empty.
+    pub fn codegen(name: &str, x_min_coord: i64, x_max_coord: i64, lines: &[Line]) -> String {
+        let pieces = envelope_pieces(x_min_coord, x_max_coord, lines);
+        let starts: Vec<String> = pieces.iter().map(|(start, _)| start.to_string()).collect();
+        let lines: Vec<String> = pieces
+            .iter()
+            .map(|(_, line)| format!("lichao::Line::new({}, {})", line.m, line.c))
+            .collect();
+        format!(
+            "// Generated by lichao::FrozenEnvelope::codegen. Do not edit by hand.\n\
+             pub static {}: lichao::FrozenEnvelope<'static> = lichao::FrozenEnvelope::new(\n    \
+             {},\n    {},\n    &[{}],\n    &[{}],\n);\n",
+            name,
+            x_min_coord,
+            x_max_coord,
+            starts.join(", "),
+            lines.join(", ")
+        )
+    }
+}
+
+/// An owned [`FrozenEnvelope`], computed at runtime from a set of lines.
+#[derive(Clone, Debug)]
+pub struct FrozenEnvelopeBuf {
+    x_min_coord: i64,
+    x_max_coord: i64,
+    starts: Vec<i64>,
+    lines: Vec<Line>,
+}
+
+impl FrozenEnvelopeBuf {
+    /// Computes the envelope of `lines` over `[x_min_coord, x_max_coord]`.
+    /// Panics if the domain is empty.
+    /// Time complexity: O(lines.len() * log(lines.len())).
+    pub fn new(x_min_coord: i64, x_max_coord: i64, lines: &[Line]) -> Self {
+        let (starts, lines) = envelope_pieces(x_min_coord, x_max_coord, lines)
+            .into_iter()
+            .unzip();
+        FrozenEnvelopeBuf {
+            x_min_coord,
+            x_max_coord,
+            starts,
+            lines,
+        }
+    }
+
+    /// Borrows the pieces as a [`FrozenEnvelope`].
+    pub fn as_envelope(&self) -> FrozenEnvelope<'_> {
+        // Valid by construction, no need to check the pieces again
+        FrozenEnvelope {
+            x_min_coord: self.x_min_coord,
+            x_max_coord: self.x_max_coord,
+            starts: &self.starts,
+            lines: &self.lines,
+        }
+    }
+
+    /// See [`FrozenEnvelope::query`].
+    pub fn query(&self, x_coord: i64) -> Option<i64> {
+        self.as_envelope().query(x_coord)
+    }
+}
+
+/// Computes the pieces of the lower envelope of `lines` on `[x_min_coord, x_max_coord]`,
+/// as `(first x-coordinate, line)` pairs in increasing order of x.
+pub(crate) fn envelope_pieces(
+    x_min_coord: i64,
+    x_max_coord: i64,
+    lines: &[Line],
+) -> Vec<(i64, Line)> {
+    if x_min_coord > x_max_coord {
+        panic!(
+            "x_min_coord ({}) cannot be greater than x_max_coord ({})",
+            x_min_coord, x_max_coord
+        );
+    }
+    let mut sorted = lines.to_vec();
+    sorted.sort_unstable_by(envelope_order);
+    envelope_pieces_sorted(x_min_coord, x_max_coord, &sorted)
+}
+
+/// Order in which `envelope_pieces_sorted` expects its lines: by decreasing slope, then increasing intercept.
+/// Towards larger x, the winner's slope only decreases.
+pub(crate) fn envelope_order(a: &Line, b: &Line) -> Ordering {
+    b.m.cmp(&a.m).then(a.c.cmp(&b.c))
+}
+
+/// Same as `envelope_pieces` for lines already sorted by `envelope_order`, in linear time.
+pub(crate) fn envelope_pieces_sorted(
+    x_min_coord: i64,
+    x_max_coord: i64,
+    sorted: &[Line],
+) -> Vec<(i64, Line)> {
+    let mut pieces: Vec<(i64, Line)> = Vec::new();
+    for (i, &line) in sorted.iter().enumerate() {
+        // Among equal slopes only the first, lowest line matters
+        if i > 0 && sorted[i - 1].m == line.m {
+            continue;
+        }
+        let start = loop {
+            let Some(&(top_start, top)) = pieces.last() else {
+                break Some(x_min_coord);
+            };
+            // First x at which `line` is strictly below `top`. Slopes differ, and i128 keeps it exact.
+            let d = top.m as i128 - line.m as i128;
+            let first_better = (line.c as i128 - top.c as i128).div_euclid(d) + 1;
+            if first_better <= top_start as i128 {
+                // `top` never wins anymore
+                pieces.pop();
+                continue;
+            }
+            break (first_better <= x_max_coord as i128).then_some(first_better as i64);
+        };
+        if let Some(start) = start {
+            pieces.push((start, line));
+        }
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LiChaoTree, NO_LINE};
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    static ENVELOPE: FrozenEnvelope<'static> =
+        FrozenEnvelope::new(0, 10, &[0, 6], &[Line::new(1, 0), Line::new(-1, 10)]);
+
+    #[test]
+    fn test_static_envelope() {
+        assert_eq!(ENVELOPE.query(0), Some(0));
+        assert_eq!(ENVELOPE.query(5), Some(5));
+        assert_eq!(ENVELOPE.query(6), Some(4));
+        assert_eq!(ENVELOPE.query(10), Some(0));
+        assert_eq!(FrozenEnvelope::new(-3, 3, &[], &[]).query(0), None);
+    }
+
+    #[test]
+    fn test_matches_tree() {
+        let mut rng = StdRng::seed_from_u64(247);
+        for _ in 0..200 {
+            let (x_min, x_max) = (-50, rng.random_range(-50..=50));
+            let lines: Vec<Line> = (0..rng.random_range(0..30))
+                .map(|_| Line::new(rng.random_range(-20..=20), rng.random_range(-500..=500)))
+                .collect();
+            let mut tree = LiChaoTree::new(x_min, x_max);
+            tree.add_lines(&lines);
+
+            let frozen = FrozenEnvelopeBuf::new(x_min, x_max, &lines);
+            // Re-validate the computed pieces through the checked constructor
+            let checked = FrozenEnvelope::new(x_min, x_max, &frozen.starts, &frozen.lines);
+            for x in x_min..=x_max {
+                assert_eq!(frozen.query(x), tree.query(x));
+                assert_eq!(checked.query(x), tree.query(x));
+            }
+        }
+    }
+
+    #[test]
+    fn test_extreme_coordinates() {
+        let lines = [Line::new(1, 0), Line::new(-1, 0), Line::new(0, -5)];
+        let pieces = envelope_pieces(i64::MIN, i64::MAX, &lines);
+        assert_eq!(
+            pieces,
+            vec![
+                (i64::MIN, Line::new(1, 0)),
+                (-4, Line::new(0, -5)),
+                (6, Line::new(-1, 0))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_max_value_matches_tree() {
+        for lines in [[Line::new(1, i64::MAX - 10)], [NO_LINE]] {
+            let mut tree = LiChaoTree::new(0, 10);
+            tree.add_lines(&lines);
+            let frozen = FrozenEnvelopeBuf::new(0, 10, &lines);
+            for x in 0..=10 {
+                assert_eq!(frozen.query(x), tree.query(x));
+            }
+            assert_eq!(frozen.query(10), Some(i64::MAX));
+        }
+        assert!(
+            FrozenEnvelope::codegen("ENVELOPE", 0, 10, &[NO_LINE]).contains(&i64::MAX.to_string())
+        );
+    }
+
+    #[test]
+    fn test_codegen() {
+        let source = FrozenEnvelope::codegen(
+            "ENVELOPE",
+            0,
+            10,
+            &[Line::new(1, 0), Line::new(-1, 10), Line::new(0, 20)],
+        );
+        assert_eq!(
+            source,
+            "// Generated by lichao::FrozenEnvelope::codegen. Do not edit by hand.\n\
+             pub static ENVELOPE: lichao::FrozenEnvelope<'static> = lichao::FrozenEnvelope::new(\n    \
+             0,\n    10,\n    &[0, 6],\n    &[lichao::Line::new(1, 0), lichao::Line::new(-1, 10)],\n);\n"
+        );
+    }
+}
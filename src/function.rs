@@ -0,0 +1,369 @@
+use crate::Line;
+
+/// A function that can be stored in a [`FunctionLiChaoTree`].
+///
+/// For the tree to answer queries correctly, any two functions inserted into the same tree must cross at most once
+/// on the domain: their difference `f(x) - g(x)` must change sign at most once as `x` increases. Lines,
+/// parabolas sharing the same leading coefficient, and `a * sqrt(x) + b` families all have this property.
+/// Debug builds of [`FunctionLiChaoTree`] panic when two functions compared during an insertion are seen to cross
+/// twice, and [`find_crossing_violation`] checks a set of functions up front.
+pub trait ChaoFunction {
+    fn eval(&self, x: i64) -> i64;
+}
+
+impl ChaoFunction for Line {
+    #[inline]
+    fn eval(&self, x: i64) -> i64 {
+        Line::eval(self, x)
+    }
+}
+
+/// Represents a parabola y = ax^2 + bx + c. Evaluation saturates like [`Line::eval`].
+///
+/// Two parabolas only cross at most once if they share the same `a`, so all parabolas added to one tree must
+/// have the same leading coefficient. This covers the usual squared-distance costs `(x - p)^2 + q`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Parabola {
+    pub a: i64,
+    pub b: i64,
+    pub c: i64,
+}
+
+impl Parabola {
+    pub const fn new(a: i64, b: i64, c: i64) -> Self {
+        Parabola { a, b, c }
+    }
+
+    /// `y = a(x - p)^2 + q`, the parabola with vertex `(p, q)`.
+    pub const fn from_vertex(a: i64, p: i64, q: i64) -> Self {
+        Parabola {
+            a,
+            b: a.saturating_mul(p).saturating_mul(-2),
+            c: a.saturating_mul(p).saturating_mul(p).saturating_add(q),
+        }
+    }
+}
+
+impl ChaoFunction for Parabola {
+    #[inline]
+    fn eval(&self, x: i64) -> i64 {
+        self.a
+            .saturating_mul(x)
+            .saturating_add(self.b)
+            .saturating_mul(x)
+            .saturating_add(self.c)
+    }
+}
+
+/// Two functions that cross more than once, found by [`find_crossing_violation`]: their indices in the checked slice,
+/// and three increasing x-coordinates at which `functions[first] - functions[second]` is positive, negative and
+/// positive again, or negative, positive and negative.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CrossingViolation {
+    pub first: usize,
+    pub second: usize,
+    pub x_coords: [i64; 3],
+}
+
+/// Checks that every pair of `functions` crosses at most once on `[x_min_coord, x_max_coord]`, as [`ChaoFunction`]
+/// requires, by comparing them at `samples` evenly spaced x-coordinates including both ends, or at every integer of
+/// the range if it has fewer. Returns the first violating pair found, or `None`. Two crossings that fall between the
+/// same samples are missed, but a reported pair always breaks the requirement. Meant for tests and debugging: run it
+/// on the functions of a tree that gives unexpected answers.
+/// Time complexity: O(functions.len()^2 * samples) comparisons, after O(functions.len() * samples) evaluations.
+pub fn find_crossing_violation<F: ChaoFunction>(
+    functions: &[F],
+    x_min_coord: i64,
+    x_max_coord: i64,
+    samples: usize,
+) -> Option<CrossingViolation> {
+    if x_min_coord > x_max_coord {
+        panic!(
+            "x_min_coord ({}) cannot be greater than x_max_coord ({})",
+            x_min_coord, x_max_coord
+        );
+    }
+    let width = x_max_coord as i128 - x_min_coord as i128;
+    let x_coords: Vec<i64> = if width < samples as i128 {
+        (x_min_coord..=x_max_coord).collect()
+    } else {
+        let steps = samples.max(2) as i128 - 1;
+        (0..=steps)
+            .map(|step| (x_min_coord as i128 + width * step / steps) as i64)
+            .collect()
+    };
+    let values: Vec<Vec<i64>> = functions
+        .iter()
+        .map(|function| x_coords.iter().map(|&x| function.eval(x)).collect())
+        .collect();
+    for first in 0..functions.len() {
+        for second in first + 1..functions.len() {
+            // x-coordinates where the sign of the difference is first seen, and where it flips after that
+            let mut flips: Vec<(i64, std::cmp::Ordering)> = Vec::with_capacity(3);
+            for (idx, &x) in x_coords.iter().enumerate() {
+                let sign = values[first][idx].cmp(&values[second][idx]);
+                if sign.is_ne() && flips.last().is_none_or(|&(_, last)| last != sign) {
+                    flips.push((x, sign));
+                    if let [(x_0, _), (x_1, _), (x_2, _)] = flips[..] {
+                        return Some(CrossingViolation {
+                            first,
+                            second,
+                            x_coords: [x_0, x_1, x_2],
+                        });
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+/// A Li-Chao Tree over the x-coordinates `[x_min_coord, x_max_coord]` storing any [`ChaoFunction`].
+///
+/// `LiChaoTree` remains the implementation for lines: it stores bare lines and tracks empty nodes in occupancy masks,
+/// while this tree stores `Option<F>` nodes.
+#[derive(Clone, Debug)]
+pub struct FunctionLiChaoTree<F> {
+    nodes: Vec<Option<F>>,
+    x_min_coord: i64,
+    domain_size: usize,
+}
+
+impl<F: ChaoFunction> FunctionLiChaoTree<F> {
+    /// Creates an empty tree over the x-coordinates `[x_min_coord, x_max_coord]`.
+    pub fn new(x_min_coord: i64, x_max_coord: i64) -> Self {
+        if x_min_coord > x_max_coord {
+            panic!(
+                "x_min_coord ({}) cannot be greater than x_max_coord ({})",
+                x_min_coord, x_max_coord
+            );
+        }
+        let domain_size = x_max_coord.abs_diff(x_min_coord) as u128 + 1;
+        if domain_size > (usize::MAX / 4) as u128 {
+            panic!(
+                "Domain size {} is too large, 4 * domain_size would overflow usize.",
+                domain_size
+            );
+        }
+        let domain_size = domain_size as usize;
+        FunctionLiChaoTree {
+            nodes: std::iter::repeat_with(|| None)
+                .take(4 * domain_size)
+                .collect(),
+            x_min_coord,
+            domain_size,
+        }
+    }
+
+    #[inline]
+    fn get_x_coord_from_idx(&self, index: usize) -> i64 {
+        self.x_min_coord + index as i64
+    }
+
+    /// Adds a function to the tree. Returns whether it was kept, see [`crate::LiChaoTree::add_line`].
+    /// Time complexity: O(log(domain_size)) evaluations.
+    pub fn add_function(&mut self, function: F) -> bool {
+        self.add_internal(function, 0, 0, self.domain_size - 1)
+    }
+
+    fn add_internal(
+        &mut self,
+        function: F,
+        node_v_idx: usize,
+        range_l_idx: usize,
+        range_r_idx: usize,
+    ) -> bool {
+        let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+        let x_at_l = self.get_x_coord_from_idx(range_l_idx);
+        let x_at_m = self.get_x_coord_from_idx(range_m_idx);
+        let x_at_r = self.get_x_coord_from_idx(range_r_idx);
+
+        let Some(current) = &mut self.nodes[node_v_idx] else {
+            self.nodes[node_v_idx] = Some(function);
+            return true;
+        };
+        let mut to_add = function;
+        // Saturation keeps the order of values, so these signs are those of the exact difference
+        debug_assert!(
+            {
+                let signs = [x_at_l, x_at_m, x_at_r].map(|x| to_add.eval(x).cmp(&current.eval(x)));
+                !(signs[0].is_ne() && signs[1] == signs[0].reverse() && signs[2] == signs[0])
+            },
+            "FunctionLiChaoTree::add_function: the added function and one stored for [{}, {}] cross more than once, \
+             see find_crossing_violation",
+            x_at_l,
+            x_at_r
+        );
+        let stored = to_add.eval(x_at_m) < current.eval(x_at_m);
+        if stored {
+            std::mem::swap(current, &mut to_add);
+        }
+        if range_l_idx == range_r_idx {
+            return stored;
+        }
+
+        let below = if to_add.eval(x_at_l) < current.eval(x_at_l) {
+            self.add_internal(to_add, 2 * node_v_idx + 1, range_l_idx, range_m_idx)
+        } else if to_add.eval(x_at_r) < current.eval(x_at_r) {
+            self.add_internal(to_add, 2 * node_v_idx + 2, range_m_idx + 1, range_r_idx)
+        } else {
+            false
+        };
+        stored || below
+    }
+
+    /// Queries the minimum value at `x_coord` over all functions added to the tree, or `None` if there are none.
+    /// As with `LiChaoTree`, a minimum of `i64::MAX` is reported as such, not as `None`.
+    /// Panics if `x_coord` is outside the tree's bounds.
+    /// Time complexity: O(log(domain_size)) evaluations.
+    pub fn query(&self, x_coord: i64) -> Option<i64> {
+        if x_coord < self.x_min_coord
+            || x_coord.abs_diff(self.x_min_coord) >= self.domain_size as u64
+        {
+            panic!("{} does not fit inside the tree's bounds", x_coord);
+        }
+        let query_idx = (x_coord - self.x_min_coord) as usize;
+
+        let mut best: Option<i64> = None;
+        let (mut node_v_idx, mut range_l_idx, mut range_r_idx) = (0, 0, self.domain_size - 1);
+        loop {
+            if let Some(function) = &self.nodes[node_v_idx] {
+                let val = function.eval(x_coord);
+                best = Some(best.map_or(val, |best| best.min(val)));
+            }
+            if range_l_idx == range_r_idx {
+                break;
+            }
+            let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+            if query_idx <= range_m_idx {
+                node_v_idx = 2 * node_v_idx + 1;
+                range_r_idx = range_m_idx;
+            } else {
+                node_v_idx = 2 * node_v_idx + 2;
+                range_l_idx = range_m_idx + 1;
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LiChaoTree;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    /// `a * floor(sqrt(x)) + b`: the difference of two of them is monotone, so they cross at most once.
+    struct SqrtCost {
+        a: i64,
+        b: i64,
+    }
+
+    impl ChaoFunction for SqrtCost {
+        fn eval(&self, x: i64) -> i64 {
+            self.a * x.isqrt() + self.b
+        }
+    }
+
+    #[test]
+    fn test_lines_match_tree() {
+        let mut rng = StdRng::seed_from_u64(256);
+        let mut functions = FunctionLiChaoTree::new(-20, 20);
+        let mut tree = LiChaoTree::new(-20, 20);
+        for _ in 0..200 {
+            let line = Line::new(rng.random_range(-20..=20), rng.random_range(-200..=200));
+            assert_eq!(functions.add_function(line), tree.add_line(line));
+            for x in -20..=20 {
+                assert_eq!(functions.query(x), tree.query(x));
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_value_is_reported() {
+        let mut tree = FunctionLiChaoTree::new(0, 10);
+        assert_eq!(tree.query(3), None);
+        tree.add_function(Parabola::new(0, 0, i64::MAX));
+        assert_eq!(tree.query(3), Some(i64::MAX));
+    }
+
+    #[test]
+    fn test_parabolas() {
+        let mut rng = StdRng::seed_from_u64(258);
+        let mut tree = FunctionLiChaoTree::new(-1000, 1000);
+        let mut vertices = Vec::new();
+        for _ in 0..300 {
+            let (p, q) = (
+                rng.random_range(-1000..=1000),
+                rng.random_range(-100_000..=100_000),
+            );
+            tree.add_function(Parabola::from_vertex(1, p, q));
+            vertices.push((p, q));
+            let x = rng.random_range(-1000..=1000);
+            let expected = vertices.iter().map(|(p, q)| (x - p) * (x - p) + q).min();
+            assert_eq!(tree.query(x), expected);
+        }
+        assert_eq!(Parabola::from_vertex(3, 2, 5).eval(4), 17);
+        assert_eq!(Parabola::new(1, 0, 0).eval(i64::MAX), i64::MAX);
+    }
+
+    #[test]
+    fn test_sqrt_costs() {
+        let mut rng = StdRng::seed_from_u64(257);
+        let mut tree = FunctionLiChaoTree::new(0, 10_000);
+        let mut costs = Vec::new();
+        for _ in 0..300 {
+            let (a, b) = (rng.random_range(-50..=50), rng.random_range(-5000..=5000));
+            tree.add_function(SqrtCost { a, b });
+            costs.push(SqrtCost { a, b });
+            let x = rng.random_range(0..=10_000);
+            assert_eq!(tree.query(x), costs.iter().map(|cost| cost.eval(x)).min());
+        }
+    }
+
+    #[test]
+    fn test_find_crossing_violation() {
+        let mut rng = StdRng::seed_from_u64(211);
+        let parabolas: Vec<Parabola> = (0..50)
+            .map(|_| {
+                Parabola::from_vertex(
+                    2,
+                    rng.random_range(-100..=100),
+                    rng.random_range(-1000..=1000),
+                )
+            })
+            .collect();
+        assert_eq!(find_crossing_violation(&parabolas, -100, 100, 1000), None);
+        assert_eq!(
+            find_crossing_violation(&parabolas, i64::MIN, i64::MAX, 1000),
+            None
+        );
+
+        // Opposite leading coefficients cross twice, around x = -5 and x = 5
+        let parabolas = [
+            Parabola::from_vertex(1, 30, 10),
+            Parabola::new(1, 0, 0),
+            Parabola::new(-1, 0, 50),
+        ];
+        let violation = find_crossing_violation(&parabolas, -100, 100, 1000).unwrap();
+        assert_eq!((violation.first, violation.second), (1, 2));
+        let [x_0, x_1, x_2] = violation.x_coords;
+        let diff = |x| parabolas[1].eval(x) - parabolas[2].eval(x);
+        assert!(diff(x_0) > 0 && diff(x_1) < 0 && diff(x_2) > 0);
+        assert!(x_0 < x_1 && x_1 < x_2);
+        // Both crossings fall between the same two samples
+        assert_eq!(find_crossing_violation(&parabolas, -100, 100, 2), None);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "cross more than once")]
+    fn test_debug_crossing_check() {
+        let mut tree = FunctionLiChaoTree::new(-10, 10);
+        tree.add_function(Parabola::new(1, 0, 0));
+        tree.add_function(Parabola::new(-1, 0, 50));
+    }
+}
@@ -0,0 +1,270 @@
+use std::cmp::Ordering;
+
+/// Numeric types usable as coordinates and values of [`GenericLiChaoTree`].
+pub trait Scalar: Copy + PartialOrd {
+    /// Computes `m * x + c`. Integer implementations saturate, like [`crate::Line::eval`].
+    fn mul_add(m: Self, x: Self, c: Self) -> Self;
+}
+
+macro_rules! impl_scalar_saturating {
+    ($($t:ty),*) => {
+        $(
+            impl Scalar for $t {
+                #[inline]
+                fn mul_add(m: Self, x: Self, c: Self) -> Self {
+                    m.saturating_mul(x).saturating_add(c)
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar_saturating!(i32, i64, i128);
+
+/// Rounds after the product and again after the sum. Unlike `f64::mul_add`, this is never fused into a single
+/// rounding, since Rust does not contract floating-point expressions, so results do not depend on the CPU having FMA.
+impl Scalar for f64 {
+    #[inline]
+    fn mul_add(m: Self, x: Self, c: Self) -> Self {
+        m * x + c
+    }
+}
+
+/// Represents a line y = mx + c over any [`Scalar`] type.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GenericLine<T> {
+    pub m: T,
+    pub c: T,
+}
+
+impl<T: Scalar> GenericLine<T> {
+    pub const fn new(m: T, c: T) -> Self {
+        GenericLine { m, c }
+    }
+
+    pub fn eval(&self, x: T) -> T {
+        T::mul_add(self.m, x, self.c)
+    }
+}
+
+/// A Li-Chao Tree over a sorted set of x-coordinates of any [`Scalar`] type, e.g. `f64` sample points.
+///
+/// `LiChaoTree` remains the `i64` implementation and should be preferred for integer domains: it stores bare lines,
+/// tracking empty nodes in occupancy masks instead of an `Option`, and computes coordinates instead of storing them.
+///
+/// With `f64`, the same insertions and queries give bit-identical results on every target with IEEE 754 double
+/// arithmetic, which excludes only the x87-based `i586` targets. Each line is evaluated as `m * x + c` rounded to
+/// nearest twice, see [`Scalar`]. A line only replaces a stored one if its value is strictly smaller, and queries
+/// keep the first of equal values met on the way down from the root, so ties, `-0.0` against `0.0` included, are
+/// resolved the same way everywhere. A NaN value never compares smaller.
+#[derive(Clone, Debug)]
+pub struct GenericLiChaoTree<T> {
+    nodes: Vec<Option<GenericLine<T>>>,
+    x_coords: Vec<T>,
+}
+
+impl<T: Scalar> GenericLiChaoTree<T> {
+    /// Creates a tree over the given x-coordinates, which may be unsorted and contain duplicates.
+    /// Panics if there are none, or if two of them are not comparable (e.g. a NaN).
+    pub fn new(x_coords: impl IntoIterator<Item = T>) -> Self {
+        let mut x_coords: Vec<T> = x_coords.into_iter().collect();
+        if x_coords.is_empty() {
+            panic!("GenericLiChaoTree::new: the domain must contain at least one point");
+        }
+        x_coords.sort_by(|a, b| {
+            a.partial_cmp(b)
+                .expect("GenericLiChaoTree::new: x-coordinates must be comparable")
+        });
+        x_coords.dedup_by(|a, b| a == b);
+        GenericLiChaoTree {
+            nodes: vec![None; 4 * x_coords.len()],
+            x_coords,
+        }
+    }
+
+    /// Returns whether `line` is strictly below the line stored in `node` at `x`. Empty nodes lose to any line.
+    fn beats(line: &GenericLine<T>, node: &Option<GenericLine<T>>, x: T) -> bool {
+        node.is_none_or(|current| line.eval(x) < current.eval(x))
+    }
+
+    /// Adds a line to the tree. Returns whether it was kept, see [`crate::LiChaoTree::add_line`].
+    /// Time complexity: O(log(domain_size)).
+    pub fn add_line(&mut self, line: GenericLine<T>) -> bool {
+        self.add_line_internal(line, 0, 0, self.x_coords.len() - 1)
+    }
+
+    fn add_line_internal(
+        &mut self,
+        mut line_to_add: GenericLine<T>,
+        node_v_idx: usize,
+        range_l_idx: usize,
+        range_r_idx: usize,
+    ) -> bool {
+        let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+        let x_at_l = self.x_coords[range_l_idx];
+        let x_at_m = self.x_coords[range_m_idx];
+        let x_at_r = self.x_coords[range_r_idx];
+
+        let stored = Self::beats(&line_to_add, &self.nodes[node_v_idx], x_at_m);
+        if stored {
+            match self.nodes[node_v_idx].replace(line_to_add) {
+                Some(displaced) => line_to_add = displaced,
+                None => return true,
+            }
+        }
+        if range_l_idx == range_r_idx {
+            return stored;
+        }
+
+        let below = if Self::beats(&line_to_add, &self.nodes[node_v_idx], x_at_l) {
+            self.add_line_internal(line_to_add, 2 * node_v_idx + 1, range_l_idx, range_m_idx)
+        } else if Self::beats(&line_to_add, &self.nodes[node_v_idx], x_at_r) {
+            self.add_line_internal(
+                line_to_add,
+                2 * node_v_idx + 2,
+                range_m_idx + 1,
+                range_r_idx,
+            )
+        } else {
+            false
+        };
+        stored || below
+    }
+
+    /// Queries the minimum y-value at `x_coord`, or `None` if no line has been added.
+    /// Panics if `x_coord` is not one of the tree's x-coordinates.
+    /// Time complexity: O(log(domain_size)).
+    pub fn query(&self, x_coord: T) -> Option<T> {
+        let query_idx = self
+            .x_coords
+            .binary_search_by(|x| x.partial_cmp(&x_coord).unwrap_or(Ordering::Less))
+            .unwrap_or_else(|_| panic!("x_coord is not one of the tree's x-coordinates"));
+
+        let mut best: Option<T> = None;
+        let (mut node_v_idx, mut range_l_idx, mut range_r_idx) = (0, 0, self.x_coords.len() - 1);
+        loop {
+            if let Some(line) = &self.nodes[node_v_idx] {
+                let val = line.eval(x_coord);
+                if best.is_none_or(|best| val < best) {
+                    best = Some(val);
+                }
+            }
+            if range_l_idx == range_r_idx {
+                return best;
+            }
+            let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+            if query_idx <= range_m_idx {
+                node_v_idx = 2 * node_v_idx + 1;
+                range_r_idx = range_m_idx;
+            } else {
+                node_v_idx = 2 * node_v_idx + 2;
+                range_l_idx = range_m_idx + 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LiChaoTree, Line};
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_matches_i64_tree() {
+        let mut rng = StdRng::seed_from_u64(253);
+        let mut generic = GenericLiChaoTree::new(-25..=25);
+        let mut tree = LiChaoTree::new(-25, 25);
+        for _ in 0..300 {
+            let (m, c) = (rng.random_range(-30..=30), rng.random_range(-300..=300));
+            assert_eq!(
+                generic.add_line(GenericLine::new(m, c)),
+                tree.add_line(Line::new(m, c))
+            );
+            for x in -25..=25 {
+                assert_eq!(generic.query(x), tree.query(x));
+            }
+        }
+    }
+
+    #[test]
+    fn test_f64() {
+        let mut rng = StdRng::seed_from_u64(254);
+        let x_coords: Vec<f64> = (0..100).map(|_| rng.random_range(-10.0..10.0)).collect();
+        let mut tree = GenericLiChaoTree::new(x_coords.iter().copied());
+        let mut lines = Vec::new();
+        for _ in 0..200 {
+            let line = GenericLine::new(rng.random_range(-5.0..5.0), rng.random_range(-50.0..50.0));
+            tree.add_line(line);
+            lines.push(line);
+        }
+        for &x in &x_coords {
+            let expected = lines
+                .iter()
+                .map(|line| line.eval(x))
+                .fold(f64::INFINITY, f64::min);
+            assert_eq!(tree.query(x), Some(expected));
+        }
+    }
+
+    /// Folds the bits of every query result, so that any difference in rounding changes the checksum.
+    fn checksum(values: impl IntoIterator<Item = Option<f64>>) -> u64 {
+        values.into_iter().fold(0, |acc, value| {
+            acc.rotate_left(7) ^ value.map_or(u64::MAX, f64::to_bits)
+        })
+    }
+
+    #[test]
+    fn test_f64_bit_identical() {
+        // Fusing would give 0.1 * 10.0 - 1.0 = 2^-54 instead of 0.0
+        assert_eq!(
+            GenericLine::new(0.1, -1.0).eval(10.0).to_bits(),
+            0.0f64.to_bits()
+        );
+        assert_ne!(0.1f64.mul_add(10.0, -1.0), 0.0);
+
+        // Both lines are zero at x = 0.0, but the first one stays there, so -0.0 is reported rather than 0.0
+        let mut tree = GenericLiChaoTree::new([-1.0, 0.0, 1.0]);
+        tree.add_line(GenericLine::new(-1.0, -0.0));
+        assert!(tree.add_line(GenericLine::new(1.0, 0.0)));
+        assert_eq!(tree.query(0.0).map(f64::to_bits), Some((-0.0f64).to_bits()));
+
+        // Computed on x86_64: any platform-dependent rounding would change it
+        let mut rng = StdRng::seed_from_u64(232);
+        let x_coords: Vec<f64> = (0..200).map(|_| rng.random_range(-1e3..1e3)).collect();
+        let mut tree = GenericLiChaoTree::new(x_coords.iter().copied());
+        for _ in 0..500 {
+            tree.add_line(GenericLine::new(
+                rng.random_range(-1e2..1e2),
+                rng.random_range(-1e5..1e5),
+            ));
+        }
+        assert_eq!(
+            checksum(x_coords.iter().map(|&x| tree.query(x))),
+            14228422407319714244
+        );
+    }
+
+    #[test]
+    fn test_narrow_and_wide_integers() {
+        let mut narrow = GenericLiChaoTree::<i32>::new([0, 1_000_000]);
+        narrow.add_line(GenericLine::new(3000, 0));
+        assert_eq!(narrow.query(1_000_000), Some(i32::MAX));
+
+        let mut wide = GenericLiChaoTree::<i128>::new([-(1 << 100), 0, 1 << 100]);
+        wide.add_line(GenericLine::new(2, 1));
+        wide.add_line(GenericLine::new(-1, 0));
+        assert_eq!(wide.query(1 << 100), Some(-(1 << 100)));
+        assert_eq!(wide.query(0), Some(0));
+        assert_eq!(wide.query(-(1 << 100)), Some(-(1 << 101) + 1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_query_outside_coords() {
+        GenericLiChaoTree::new([0.5, 1.5]).query(1.0);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{LiChaoTree, Line};
+
+/// A Li-Chao Tree whose domain grows to fit the queries, for when the x-range is only discovered along the way.
+///
+/// A fixed tree forgets lines that are dominated inside its domain, but those lines may win outside of it,
+/// so every added line is kept here and re-inserted whenever the domain changes. A query outside the domain
+/// rebuilds the tree over a range at least twice as wide that includes the queried point, so a sequence of
+/// growing queries only pays for O(log(final domain_size)) rebuilds.
+/// The domain must stay small enough to allocate, as for [`LiChaoTree::new`].
+#[derive(Clone)]
+pub struct GrowableLiChaoTree {
+    tree: LiChaoTree,
+    lines: Vec<Line>,
+    x_min_coord: i64,
+    x_max_coord: i64,
+}
+
+impl GrowableLiChaoTree {
+    /// Creates a new tree over the initial inclusive range `[x_min_coord, x_max_coord]`.
+    pub fn new(x_min_coord: i64, x_max_coord: i64) -> Self {
+        GrowableLiChaoTree {
+            tree: LiChaoTree::new(x_min_coord, x_max_coord),
+            lines: Vec::new(),
+            x_min_coord,
+            x_max_coord,
+        }
+    }
+
+    /// Returns the underlying tree.
+    pub fn tree(&self) -> &LiChaoTree {
+        &self.tree
+    }
+
+    /// Returns every line added so far, in insertion order.
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// Returns the current domain as `(x_min_coord, x_max_coord)`.
+    pub fn bounds(&self) -> (i64, i64) {
+        (self.x_min_coord, self.x_max_coord)
+    }
+
+    /// Adds a line. Returns whether it was kept by the current tree, see [`LiChaoTree::add_line`].
+    /// The line is remembered either way, since it may matter once the domain grows.
+    /// Time complexity: O(log(domain_size)).
+    pub fn add_line(&mut self, line: Line) -> bool {
+        let stored = self.tree.add_line(line);
+        self.lines.push(line);
+        stored
+    }
+
+    /// Replaces the tree with one over `[x_min_coord, x_max_coord]` holding every line added so far.
+    /// The new domain may also be smaller than the current one. The tie-break rule is kept.
+    /// Panics in the same cases as [`LiChaoTree::new`].
+    /// Time complexity: O(domain_size + lines * log(domain_size)).
+    pub fn rebuild_with_domain(&mut self, x_min_coord: i64, x_max_coord: i64) {
+        let mut tree = LiChaoTree::new(x_min_coord, x_max_coord);
+        tree.set_tie_break(self.tree.tie_break());
+        tree.add_lines(&self.lines);
+        self.tree = tree;
+        self.x_min_coord = x_min_coord;
+        self.x_max_coord = x_max_coord;
+    }
+
+    /// Queries the minimum y-value at `x_coord`, first growing the domain if it does not contain `x_coord`.
+    /// Time complexity: O(log(domain_size)), plus a rebuild when the domain grows.
+    pub fn query(&mut self, x_coord: i64) -> Option<i64> {
+        if x_coord < self.x_min_coord || x_coord > self.x_max_coord {
+            // Extend by the current width on the side of x_coord, and further if that is not enough
+            let width = self.x_max_coord as i128 - self.x_min_coord as i128 + 1;
+            let (x_min_coord, x_max_coord) = if x_coord < self.x_min_coord {
+                (
+                    (self.x_min_coord as i128 - width).min(x_coord as i128),
+                    self.x_max_coord as i128,
+                )
+            } else {
+                (
+                    self.x_min_coord as i128,
+                    (self.x_max_coord as i128 + width).max(x_coord as i128),
+                )
+            };
+            self.rebuild_with_domain(
+                x_min_coord.max(i64::MIN as i128) as i64,
+                x_max_coord.min(i64::MAX as i128) as i64,
+            );
+        }
+        self.tree.query(x_coord)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_grows_to_fit_queries() {
+        let mut rng = StdRng::seed_from_u64(273);
+        let mut tree = GrowableLiChaoTree::new(0, 0);
+        let mut lines = Vec::new();
+        for _ in 0..300 {
+            let line = Line::new(rng.random_range(-20..=20), rng.random_range(-500..=500));
+            tree.add_line(line);
+            lines.push(line);
+            let x = rng.random_range(-1000..=1000);
+            assert_eq!(tree.query(x), lines.iter().map(|line| line.eval(x)).min());
+        }
+        let (x_min, x_max) = tree.bounds();
+        // Each growth at least doubles the width, so the domain ends up within a constant factor of the queried range
+        assert!(x_min <= -900 && x_max >= 900 && x_max - x_min < 4 * 2001);
+    }
+
+    #[test]
+    fn test_dominated_lines_survive_growth() {
+        let mut tree = GrowableLiChaoTree::new(0, 10);
+        tree.add_line(Line::new(0, 0));
+        // Dominated everywhere on [0, 10], but the minimum for x > 100
+        assert!(!tree.add_line(Line::new(-1, 100)));
+        assert_eq!(tree.query(10), Some(0));
+        assert_eq!(tree.query(150), Some(-50));
+        assert_eq!(tree.lines().len(), 2);
+
+        tree.rebuild_with_domain(-5, 5);
+        assert_eq!(tree.bounds(), (-5, 5));
+        assert_eq!(tree.query(-5), Some(0));
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{LiChaoTree, LiChaoTreeBuilder, Line, NodeStorage};
+
+/// A Li-Chao Tree that remembers which insertion each stored line came from, so queries can report the optimal
+/// line by index, e.g. to reconstruct the transitions of a DP.
+///
+/// Lines are numbered from 0 in insertion order. This is a [`LiChaoTree`] built with [`NodeStorage::Indexed`], whose
+/// nodes hold line numbers into a table of every line added, see [`LiChaoTree::query_argmin`]. The table grows by 16
+/// bytes per line, dominated lines included, so memory grows with the number of insertions.
+#[derive(Clone)]
+pub struct IndexedLiChaoTree {
+    tree: LiChaoTree,
+}
+
+impl IndexedLiChaoTree {
+    /// Creates a new tree over the inclusive range `[x_min_coord, x_max_coord]`.
+    /// Panics in the same cases as [`LiChaoTree::new`].
+    pub fn new(x_min_coord: i64, x_max_coord: i64) -> Self {
+        match LiChaoTreeBuilder::new(x_min_coord, x_max_coord)
+            .storage(NodeStorage::Indexed)
+            .build()
+        {
+            Ok(tree) => IndexedLiChaoTree { tree },
+            Err(err) => panic!("IndexedLiChaoTree::new: {}", err),
+        }
+    }
+
+    /// Returns the underlying tree.
+    pub fn tree(&self) -> &LiChaoTree {
+        &self.tree
+    }
+
+    /// Number of lines added so far, including dominated ones.
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Adds a line and returns its index, which is the number of lines added before it.
+    /// Time complexity: O(log(domain_size)).
+    pub fn add_line(&mut self, line: Line) -> usize {
+        let idx = self.tree.len();
+        self.tree.add_line(line);
+        idx
+    }
+
+    /// Queries the minimum y-value at `x_coord` together with the index of a line achieving it,
+    /// or `None` if no line has been added. A line equal to a stored one is never stored again, so copies of a
+    /// line report the index of the first one. See [`LiChaoTree::query_argmin`].
+    /// Panics if `x_coord` is outside the tree's bounds.
+    /// Time complexity: O(log(domain_size)).
+    pub fn query_argmin(&self, x_coord: i64) -> Option<(i64, usize)> {
+        self.tree.query_argmin(x_coord)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_argmin() {
+        let mut rng = StdRng::seed_from_u64(266);
+        for _ in 0..100 {
+            let mut tree = IndexedLiChaoTree::new(-20, 20);
+            let mut lines = Vec::new();
+            for _ in 0..rng.random_range(0..30) {
+                let line = Line::new(rng.random_range(-10..=10), rng.random_range(-100..=100));
+                assert_eq!(tree.add_line(line), lines.len());
+                lines.push(line);
+            }
+            for x in -20..=20 {
+                let expected = lines.iter().map(|line| line.eval(x)).min();
+                let result = tree.query_argmin(x);
+                assert_eq!(result.map(|(val, _)| val), expected);
+                if let Some((val, idx)) = result {
+                    assert_eq!(lines[idx].eval(x), val);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_first_index_of_equal_lines() {
+        let mut tree = IndexedLiChaoTree::new(0, 10);
+        tree.add_line(Line::new(0, 5));
+        tree.add_line(Line::new(1, 0));
+        tree.add_line(Line::new(0, 5));
+        assert_eq!(tree.query_argmin(0), Some((0, 1)));
+        assert_eq!(tree.query_argmin(10), Some((5, 0)));
+        assert_eq!(tree.len(), 3);
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+
+use crate::{LiChaoTree, Line};
+
+/// Number of queries won by each line. Equal lines share a counter, since they are the same function.
+/// Queries take `&self`, so the counts live behind a mutex to keep the tree `Sync`.
+#[derive(Debug, Default)]
+pub(crate) struct WinCounts(Mutex<HashMap<Line, u64>>);
+
+impl Clone for WinCounts {
+    fn clone(&self) -> Self {
+        WinCounts(Mutex::new(self.lock().clone()))
+    }
+}
+
+impl WinCounts {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Line, u64>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl LiChaoTree {
+    /// Credits the line achieving the minimum at `query_idx` with a win.
+    pub(crate) fn record_win(&self, query_idx: usize) {
+        if let Some(line) = self.winner(query_idx) {
+            *self.win_counts.lock().entry(line).or_insert(0) += 1;
+        }
+    }
+
+    /// Returns how many queries each line has won, most frequent first.
+    /// Lines that never won a query are not listed. Requires the `instrumentation` feature.
+    pub fn win_report(&self) -> Vec<(Line, u64)> {
+        let mut report: Vec<(Line, u64)> = self
+            .win_counts
+            .lock()
+            .iter()
+            .map(|(line, wins)| (*line, *wins))
+            .collect();
+        report.sort_by(|a, b| {
+            b.1.cmp(&a.1)
+                .then(a.0.m.cmp(&b.0.m))
+                .then(a.0.c.cmp(&b.0.c))
+        });
+        report
+    }
+
+    /// Resets all win counters to zero. Requires the `instrumentation` feature.
+    pub fn reset_win_counts(&self) {
+        self.win_counts.lock().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LiChaoTree, Line, TieBreak};
+
+    #[test]
+    fn test_win_report() {
+        let mut tree = LiChaoTree::new(0, 10);
+        let rising = Line::new(1, 0);
+        let falling = Line::new(-1, 10);
+        tree.add_line(rising);
+        tree.add_line(falling);
+        tree.add_line(Line::new(0, 100));
+        for x in 0..=10 {
+            tree.query(x);
+        }
+        tree.query(0);
+
+        // x = 5 is a tie and is credited to exactly one of the two lines
+        let report = tree.win_report();
+        assert_eq!(report.len(), 2);
+        assert_eq!(report.iter().map(|(_, wins)| wins).sum::<u64>(), 12);
+        assert_eq!(report[0], (rising, 7));
+        assert_eq!(report[1], (falling, 5));
+
+        tree.reset_win_counts();
+        assert!(tree.win_report().is_empty());
+    }
+
+    #[test]
+    fn test_win_report_tie_break() {
+        for (rule, expected) in [
+            (TieBreak::SmallerSlope, Line::new(-1, 10)),
+            (TieBreak::LargerSlope, Line::new(1, 0)),
+        ] {
+            let mut tree = LiChaoTree::new(0, 10);
+            tree.set_tie_break(rule);
+            tree.add_line(Line::new(1, 0));
+            tree.add_line(Line::new(-1, 10));
+            tree.query(5);
+            assert_eq!(tree.win_report(), vec![(expected, 1)]);
+        }
+    }
+}
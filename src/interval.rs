@@ -0,0 +1,146 @@
+use crate::{LiChaoTree, Line};
+
+/// A line `y = mx + c` whose coefficients are only known to lie in `m ∈ [m_lo, m_hi]`, `c ∈ [c_lo, c_hi]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IntervalLine {
+    pub m_lo: i64,
+    pub m_hi: i64,
+    pub c_lo: i64,
+    pub c_hi: i64,
+}
+
+impl IntervalLine {
+    pub fn new(m_lo: i64, m_hi: i64, c_lo: i64, c_hi: i64) -> Self {
+        if m_lo > m_hi || c_lo > c_hi {
+            panic!(
+                "IntervalLine::new: empty coefficient interval m ∈ [{}, {}], c ∈ [{}, {}]",
+                m_lo, m_hi, c_lo, c_hi
+            );
+        }
+        IntervalLine {
+            m_lo,
+            m_hi,
+            c_lo,
+            c_hi,
+        }
+    }
+
+    /// Returns the range of values the line can take at `x`.
+    pub fn eval(&self, x: i64) -> (i64, i64) {
+        let (low_slope, high_slope) = if x >= 0 {
+            (self.m_lo, self.m_hi)
+        } else {
+            (self.m_hi, self.m_lo)
+        };
+        (
+            Line::new(low_slope, self.c_lo).eval(x),
+            Line::new(high_slope, self.c_hi).eval(x),
+        )
+    }
+}
+
+/// A Li-Chao Tree over lines with uncertain coefficients.
+/// Queries return the tightest interval `[lo, hi]` containing the minimum for every possible
+/// choice of coefficients: `lo` is the best case and `hi` the worst case.
+pub struct IntervalLiChaoTree {
+    /// Best case: each line contributes both extreme slopes with its lowest intercept.
+    lower: LiChaoTree,
+    /// Worst case for x < 0, where the smallest slope gives the largest value.
+    upper_neg: Option<LiChaoTree>,
+    /// Worst case for x >= 0, where the largest slope gives the largest value.
+    upper_nonneg: Option<LiChaoTree>,
+}
+
+impl IntervalLiChaoTree {
+    /// Creates a new tree over the inclusive range `[x_min_coord, x_max_coord]`.
+    pub fn new(x_min_coord: i64, x_max_coord: i64) -> Self {
+        let lower = LiChaoTree::new(x_min_coord, x_max_coord);
+        let upper_neg =
+            (x_min_coord < 0).then(|| LiChaoTree::new(x_min_coord, x_max_coord.min(-1)));
+        let upper_nonneg =
+            (x_max_coord >= 0).then(|| LiChaoTree::new(x_min_coord.max(0), x_max_coord));
+        IntervalLiChaoTree {
+            lower,
+            upper_neg,
+            upper_nonneg,
+        }
+    }
+
+    /// Adds an uncertain line. Time complexity: O(log(domain_size)).
+    pub fn add_line(&mut self, line: IntervalLine) {
+        self.lower.add_line(Line::new(line.m_lo, line.c_lo));
+        self.lower.add_line(Line::new(line.m_hi, line.c_lo));
+        if let Some(tree) = &mut self.upper_neg {
+            tree.add_line(Line::new(line.m_lo, line.c_hi));
+        }
+        if let Some(tree) = &mut self.upper_nonneg {
+            tree.add_line(Line::new(line.m_hi, line.c_hi));
+        }
+    }
+
+    /// Returns `(lo, hi)` bounding the minimum at `x_coord` over all coefficient choices,
+    /// or `None` if no lines were added. Panics if `x_coord` is outside the tree's bounds.
+    /// Time complexity: O(log(domain_size)).
+    pub fn query(&self, x_coord: i64) -> Option<(i64, i64)> {
+        let lo = self.lower.query(x_coord)?;
+        let upper = if x_coord < 0 {
+            &self.upper_neg
+        } else {
+            &self.upper_nonneg
+        };
+        let hi = upper
+            .as_ref()
+            .and_then(|tree| tree.query(x_coord))
+            .expect("upper envelope is populated whenever the lower one is");
+        Some((lo, hi))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_exact_coefficients() {
+        let mut tree = IntervalLiChaoTree::new(-10, 10);
+        assert_eq!(tree.query(0), None);
+        tree.add_line(IntervalLine::new(2, 2, 3, 3));
+        tree.add_line(IntervalLine::new(-1, -1, 10, 10));
+        assert_eq!(tree.query(5), Some((5, 5)));
+        assert_eq!(tree.query(-10), Some((-17, -17)));
+    }
+
+    #[test]
+    fn test_bounds_against_brute_force() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut tree = IntervalLiChaoTree::new(-30, 30);
+        let mut lines = Vec::new();
+        for _ in 0..50 {
+            let m_lo = rng.random_range(-20..=20);
+            let c_lo = rng.random_range(-100..=100);
+            let line = IntervalLine::new(
+                m_lo,
+                m_lo + rng.random_range(0..=5),
+                c_lo,
+                c_lo + rng.random_range(0..=50),
+            );
+            lines.push(line);
+            tree.add_line(line);
+            for x in -30..=30 {
+                let lo = lines.iter().map(|l| l.eval(x).0).min().unwrap();
+                let hi = lines.iter().map(|l| l.eval(x).1).min().unwrap();
+                assert_eq!(tree.query(x), Some((lo, hi)));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty_interval() {
+        IntervalLine::new(1, 0, 0, 0);
+    }
+}
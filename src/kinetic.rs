@@ -0,0 +1,290 @@
+use crate::Line;
+
+/// Melting time of nodes whose winner can never change.
+const NEVER: i64 = i64::MAX;
+
+/// A segment tree over positions `0..n`, each holding at most one line, answering range minimum queries of
+/// `m * t + c` at a global time `t` that only moves forward.
+///
+/// Every node caches the position of the line winning on its range at the current time, and the earliest time
+/// at which that can change (its melting time). Advancing the time only recomputes the nodes that melted,
+/// for O(log^2 n) amortized work per update when time is advanced monotonically.
+#[derive(Clone, Debug)]
+pub struct KineticSegmentTree {
+    lines: Vec<Option<Line>>,
+    winners: Vec<Option<usize>>,
+    melts: Vec<i64>,
+    time: i64,
+}
+
+impl KineticSegmentTree {
+    /// Creates a tree over `n` empty positions at time `time`. Panics if `n` is zero.
+    pub fn new(n: usize, time: i64) -> Self {
+        Self::from_lines(vec![None; n], time)
+    }
+
+    /// Creates a tree holding `lines[i]` at position `i`, at time `time`. Panics if `lines` is empty.
+    /// Time complexity: O(n).
+    pub fn from_lines(lines: Vec<Option<Line>>, time: i64) -> Self {
+        if lines.is_empty() {
+            panic!("KineticSegmentTree: there must be at least one position");
+        }
+        let n = lines.len();
+        let mut tree = KineticSegmentTree {
+            lines,
+            winners: vec![None; 4 * n],
+            melts: vec![NEVER; 4 * n],
+            time,
+        };
+        tree.build(0, 0, n - 1);
+        tree
+    }
+
+    /// The current time.
+    pub fn time(&self) -> i64 {
+        self.time
+    }
+
+    /// Number of positions.
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    fn build(&mut self, node_v_idx: usize, range_l_idx: usize, range_r_idx: usize) {
+        if range_l_idx == range_r_idx {
+            self.winners[node_v_idx] = self.lines[range_l_idx].map(|_| range_l_idx);
+            return;
+        }
+        let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+        self.build(2 * node_v_idx + 1, range_l_idx, range_m_idx);
+        self.build(2 * node_v_idx + 2, range_m_idx + 1, range_r_idx);
+        self.pull(node_v_idx);
+    }
+
+    /// Recomputes a node's winner and melting time from its children, at the current time.
+    fn pull(&mut self, node_v_idx: usize) {
+        let (left, right) = (2 * node_v_idx + 1, 2 * node_v_idx + 2);
+        let mut melt = self.melts[left].min(self.melts[right]);
+        let winner = match (self.winners[left], self.winners[right]) {
+            (Some(a), Some(b)) => {
+                let (line_a, line_b) = (self.lines[a].unwrap(), self.lines[b].unwrap());
+                let (val_a, val_b) = (line_a.eval_wide(self.time), line_b.eval_wide(self.time));
+                // On a tie the smaller slope wins, since it stays at least as good from now on
+                let (winner, loser) = if (val_a, line_a.m) <= (val_b, line_b.m) {
+                    ((a, line_a), line_b)
+                } else {
+                    ((b, line_b), line_a)
+                };
+                melt = melt.min(Self::overtake_time(winner.1, loser));
+                Some(winner.0)
+            }
+            (a, b) => a.or(b),
+        };
+        self.winners[node_v_idx] = winner;
+        self.melts[node_v_idx] = melt;
+    }
+
+    /// First time at which `loser` becomes strictly better than `winner`, or `NEVER`.
+    fn overtake_time(winner: Line, loser: Line) -> i64 {
+        if loser.m >= winner.m {
+            return NEVER;
+        }
+        let d = winner.m as i128 - loser.m as i128;
+        let first_better = (loser.c as i128 - winner.c as i128).div_euclid(d) + 1;
+        first_better.min(NEVER as i128) as i64
+    }
+
+    /// Sets the line at `position`, replacing any previous one; `None` empties the position.
+    /// Panics if `position` is out of range.
+    /// Time complexity: O(log n).
+    pub fn set_line(&mut self, position: usize, line: Option<Line>) {
+        if position >= self.lines.len() {
+            panic!(
+                "position {} is out of range for {} positions",
+                position,
+                self.lines.len()
+            );
+        }
+        self.lines[position] = line;
+        self.update(0, 0, self.lines.len() - 1, position);
+    }
+
+    fn update(
+        &mut self,
+        node_v_idx: usize,
+        range_l_idx: usize,
+        range_r_idx: usize,
+        position: usize,
+    ) {
+        if range_l_idx == range_r_idx {
+            self.winners[node_v_idx] = self.lines[position].map(|_| position);
+            return;
+        }
+        let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+        if position <= range_m_idx {
+            self.update(2 * node_v_idx + 1, range_l_idx, range_m_idx, position);
+        } else {
+            self.update(2 * node_v_idx + 2, range_m_idx + 1, range_r_idx, position);
+        }
+        self.pull(node_v_idx);
+    }
+
+    /// Moves the global time forward to `time`. Panics if `time` is earlier than the current time.
+    pub fn advance(&mut self, time: i64) {
+        if time < self.time {
+            panic!("time cannot move backwards from {} to {}", self.time, time);
+        }
+        self.time = time;
+        self.heal(0, 0, self.lines.len() - 1);
+    }
+
+    /// Recomputes every node that melted at or before the current time.
+    fn heal(&mut self, node_v_idx: usize, range_l_idx: usize, range_r_idx: usize) {
+        if self.melts[node_v_idx] > self.time || range_l_idx == range_r_idx {
+            return;
+        }
+        let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+        self.heal(2 * node_v_idx + 1, range_l_idx, range_m_idx);
+        self.heal(2 * node_v_idx + 2, range_m_idx + 1, range_r_idx);
+        self.pull(node_v_idx);
+    }
+
+    /// Queries the minimum of `m * t + c` at the current time over positions `[l, r]`,
+    /// or `None` if they are all empty.
+    /// Values saturate like `Line::eval`. Panics if the range is reversed or out of range.
+    /// Time complexity: O(log n).
+    pub fn query(&self, l: usize, r: usize) -> Option<i64> {
+        if l > r || r >= self.lines.len() {
+            panic!(
+                "[{}, {}] is not a valid range for {} positions",
+                l,
+                r,
+                self.lines.len()
+            );
+        }
+        let winner = self.query_internal(0, 0, self.lines.len() - 1, l, r)?;
+        Some(self.lines[winner].unwrap().eval(self.time))
+    }
+
+    fn query_internal(
+        &self,
+        node_v_idx: usize,
+        range_l_idx: usize,
+        range_r_idx: usize,
+        query_l_idx: usize,
+        query_r_idx: usize,
+    ) -> Option<usize> {
+        if range_r_idx < query_l_idx || query_r_idx < range_l_idx {
+            return None;
+        }
+        if query_l_idx <= range_l_idx && range_r_idx <= query_r_idx {
+            return self.winners[node_v_idx];
+        }
+        let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+        let left = self.query_internal(
+            2 * node_v_idx + 1,
+            range_l_idx,
+            range_m_idx,
+            query_l_idx,
+            query_r_idx,
+        );
+        let right = self.query_internal(
+            2 * node_v_idx + 2,
+            range_m_idx + 1,
+            range_r_idx,
+            query_l_idx,
+            query_r_idx,
+        );
+        match (left, right) {
+            (Some(a), Some(b)) => {
+                let val = |idx: usize| self.lines[idx].unwrap().eval_wide(self.time);
+                Some(if val(a) <= val(b) { a } else { b })
+            }
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_against_naive() {
+        let mut rng = StdRng::seed_from_u64(258);
+        let n = 40;
+        let mut lines: Vec<Option<Line>> = (0..n)
+            .map(|_| {
+                rng.random_bool(0.8).then(|| {
+                    Line::new(
+                        rng.random_range(-100..=100),
+                        rng.random_range(-10_000..=10_000),
+                    )
+                })
+            })
+            .collect();
+        let mut tree = KineticSegmentTree::from_lines(lines.clone(), -200);
+        for _ in 0..2000 {
+            match rng.random_range(0..3) {
+                0 => {
+                    let position = rng.random_range(0..n);
+                    let line = rng.random_bool(0.9).then(|| {
+                        Line::new(
+                            rng.random_range(-100..=100),
+                            rng.random_range(-10_000..=10_000),
+                        )
+                    });
+                    lines[position] = line;
+                    tree.set_line(position, line);
+                }
+                1 => tree.advance(tree.time() + rng.random_range(0..5)),
+                _ => {
+                    let l = rng.random_range(0..n);
+                    let r = rng.random_range(l..n);
+                    let expected = lines[l..=r]
+                        .iter()
+                        .flatten()
+                        .map(|line| line.eval(tree.time()))
+                        .min();
+                    assert_eq!(tree.query(l, r), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_winner_changes_over_time() {
+        let mut tree = KineticSegmentTree::new(3, 0);
+        tree.set_line(0, Some(Line::new(0, 10)));
+        tree.set_line(2, Some(Line::new(-2, 30)));
+        assert_eq!(tree.query(0, 2), Some(10));
+        assert_eq!(tree.query(1, 1), None);
+        tree.advance(10);
+        assert_eq!(tree.query(0, 2), Some(10));
+        tree.advance(11);
+        assert_eq!(tree.query(0, 2), Some(8));
+        assert_eq!(tree.query(0, 1), Some(10));
+    }
+
+    #[test]
+    fn test_advance_to_end_of_time() {
+        let mut tree = KineticSegmentTree::new(2, 0);
+        tree.set_line(1, Some(Line::new(0, 3)));
+        tree.advance(i64::MAX);
+        assert_eq!(tree.query(0, 1), Some(3));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_time_cannot_go_back() {
+        KineticSegmentTree::new(1, 5).advance(4);
+    }
+}
@@ -1,252 +1,2824 @@
+mod arena;
+mod augmented;
+mod builder;
+pub mod bundle;
+mod container;
+mod continuous;
+mod coords;
+mod error;
+#[cfg(feature = "evcxr")]
+mod evcxr;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod fingerprint;
+mod fixed;
+mod frozen;
+mod function;
+mod generic;
+mod growable;
+mod indexed;
+#[cfg(feature = "instrumentation")]
+mod instrumentation;
+mod interval;
+mod kinetic;
+mod logmethod;
+pub mod monge;
+mod monotone;
+mod nodes;
+pub mod offline;
+mod ops;
+pub mod optimize;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod persistent;
+#[cfg(feature = "plot")]
+mod plot;
+#[cfg(feature = "bytemuck")]
+mod pod;
+mod range;
+#[cfg(feature = "rational")]
+mod rational;
+#[cfg(feature = "serde")]
+mod serialize;
+mod service;
+#[cfg(feature = "simd")]
+mod simd;
+#[cfg(feature = "zstd")]
+mod snapshot;
+mod sparse;
+mod union;
+
+pub use arena::Arena;
+pub use augmented::{AugmentedLiChaoTree, Monoid};
+pub use builder::{LiChaoBuilder, LiChaoTreeBuilder};
+pub use container::LineContainer;
+pub use continuous::ContinuousLiChaoTree;
+pub use coords::{CoordinateMap, SortedCoords};
+pub use error::LiChaoError;
+pub use fixed::LiChaoTreeFixed;
+pub use frozen::{FrozenEnvelope, FrozenEnvelopeBuf};
+pub use function::{
+    ChaoFunction, CrossingViolation, FunctionLiChaoTree, Parabola, find_crossing_violation,
+};
+pub use generic::{GenericLiChaoTree, GenericLine, Scalar};
+pub use growable::GrowableLiChaoTree;
+pub use indexed::IndexedLiChaoTree;
+pub use interval::{IntervalLiChaoTree, IntervalLine};
+pub use kinetic::KineticSegmentTree;
+pub use logmethod::{LogMethod, StaticEnvelope};
+pub use monotone::MonotoneCHT;
+pub use nodes::{NodeLayout, NodeStorage};
+pub use ops::{Op, OpResult};
+pub use persistent::{PersistentLiChaoTree, Version};
+pub use range::RangeLiChaoTree;
+#[cfg(feature = "serde")]
+pub use serialize::TreeDelta;
+pub use service::LiChaoService;
+pub use sparse::SparseLiChaoTree;
+pub use union::{EnvelopeQuery, EnvelopeUnion};
+
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+use nodes::{Entry, NodeArray};
+
 /// Represents a line y = mx + c.
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// Laid out as two consecutive `i64`s, `m` then `c`, so slices of lines can be cast from raw buffers
+/// (see the `bytemuck` feature).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Line {
     pub m: i64,
     pub c: i64,
 }
 
 impl Line {
-    pub fn new(m: i64, c: i64) -> Self {
+    pub const fn new(m: i64, c: i64) -> Self {
         Line { m, c }
     }
 
-    pub fn eval(&self, x: i64) -> i64 {
+    /// Line through the points `(x1, y1)` and `(x2, y2)`.
+    /// Fails with `LiChaoError::VerticalLine` if both points have the same x-coordinate, and with
+    /// `LiChaoError::UnrepresentableLine` if the slope or intercept is not an integer that fits in `i64`.
+    pub fn from_points((x1, y1): (i64, i64), (x2, y2): (i64, i64)) -> Result<Self, LiChaoError> {
+        if x1 == x2 {
+            return Err(LiChaoError::VerticalLine { x_coord: x1 });
+        }
+        let (dy, dx) = (y2 as i128 - y1 as i128, x2 as i128 - x1 as i128);
+        if dy % dx != 0 {
+            return Err(LiChaoError::UnrepresentableLine);
+        }
+        let m = dy / dx;
+        let c = m
+            .checked_mul(x1 as i128)
+            .and_then(|mx| (y1 as i128).checked_sub(mx));
+        match (i64::try_from(m), c.map(i64::try_from)) {
+            (Ok(m), Some(Ok(c))) => Ok(Line::new(m, c)),
+            _ => Err(LiChaoError::UnrepresentableLine),
+        }
+    }
+
+    pub const fn eval(&self, x: i64) -> i64 {
         self.m.saturating_mul(x).saturating_add(self.c)
     }
+
+    /// Exact value at `x`, without saturation.
+    #[inline]
+    pub(crate) const fn eval_wide(&self, x: i64) -> i128 {
+        self.m as i128 * x as i128 + self.c as i128
+    }
+
+    /// Exact value at `x`, saturated to the `i64` range. Unlike `eval`, which saturates `m * x` before adding `c`,
+    /// this orders lines correctly wherever their exact values differ inside the `i64` range.
+    #[inline]
+    pub(crate) const fn eval_clamped(&self, x: i64) -> i64 {
+        let val = self.eval_wide(x);
+        if val > i64::MAX as i128 {
+            i64::MAX
+        } else if val < i64::MIN as i128 {
+            i64::MIN
+        } else {
+            val as i64
+        }
+    }
+
+    /// First integer x at which `self` is strictly below `other`. Requires `self.m < other.m`.
+    #[inline]
+    pub(crate) const fn first_below(&self, other: &Line) -> i128 {
+        (self.c as i128 - other.c as i128).div_euclid(other.m as i128 - self.m as i128) + 1
+    }
+}
+
+/// `(m, c)` is the line `y = mx + c`.
+impl From<(i64, i64)> for Line {
+    fn from((m, c): (i64, i64)) -> Self {
+        Line::new(m, c)
+    }
 }
 
 // NPO val since optionals have too much memory overhead in this specific context
 const INF_VAL: i64 = i64::MAX;
+// Contents of empty nodes, which evaluates to INF_VAL everywhere. Whether a node is empty is tracked separately, see
+// `NodeArray`, so this line can still be added to a `LiChaoTree`.
 const NO_LINE: Line = Line { m: 0, c: INF_VAL };
 
+/// Result of [`LiChaoTree::add_lines`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BulkReport {
+    /// Number of input lines that were kept by the tree.
+    pub kept: usize,
+    /// Indices into the input slice of lines that were discarded without touching the envelope.
+    pub dominated: Vec<usize>,
+}
+
+/// Memory and structure of a tree, see [`LiChaoTree::stats`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TreeStats {
+    /// Heap memory held by the tree: the node array and its side buffers, including spare capacity.
+    /// The coordinate map of trees built with `with_coordinate_map` is not included.
+    pub bytes_allocated: usize,
+    /// Number of nodes holding a line that queries can see.
+    pub occupied_nodes: usize,
+    /// Depth of the deepest occupied node, the root being at depth 0. Zero for an empty tree.
+    pub max_depth: usize,
+    /// `occupied_nodes` divided by the number of nodes in the node array, see [`LiChaoTree`].
+    pub occupancy: f64,
+}
+
+/// Interval of x-coordinates on which a single line is the envelope, see [`LiChaoTree::to_piecewise`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EnvelopePiece {
+    /// First x-coordinate of the piece, a breakpoint of the envelope.
+    pub x_l: i64,
+    /// Last x-coordinate of the piece.
+    pub x_r: i64,
+    /// Line achieving the envelope on `[x_l, x_r]`, as it was added.
+    pub line: Line,
+}
+
+/// Position of a point relative to the envelope, see [`LiChaoTree::classify_point`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointPosition {
+    Above,
+    On,
+    Below,
+}
+
+/// Rule deciding which of two lines with equal value at some x is treated as the winner.
+/// The tree keeps no insertion order or line ids, so rules are based on the lines themselves.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TieBreak {
+    /// A line already stored in a node is not displaced by an equal one. Which line wins
+    /// then depends on the order and layout of insertions.
+    #[default]
+    KeepExisting,
+    /// The line with the smaller slope wins.
+    SmallerSlope,
+    /// The line with the larger slope wins.
+    LargerSlope,
+}
+
+/// How query results that overflow `i64` are reported, see [`LiChaoTree::set_overflow_policy`].
+/// Lines are always compared exactly, so the policy never changes which line wins, only the value reported for it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OverflowPolicy {
+    /// Same arithmetic as [`Line::eval`]: `m * x` saturates, then adding `c` saturates again.
+    Saturate,
+    /// No value if `m * x` or the addition of `c` overflows, as with `checked_mul` and `checked_add`.
+    Checked,
+    /// The exact value is computed in `i128` and saturated to the `i64` range once.
+    #[default]
+    WideningI128,
+    /// Panics if `m * x` or the addition of `c` overflows.
+    Panic,
+}
+
+impl OverflowPolicy {
+    /// Evaluates `line` at `x` under this policy.
+    fn eval(self, line: &Line, x: i64) -> Option<i64> {
+        match self {
+            OverflowPolicy::Saturate => Some(line.eval(x)),
+            OverflowPolicy::Checked => line
+                .m
+                .checked_mul(x)
+                .and_then(|val| val.checked_add(line.c)),
+            OverflowPolicy::WideningI128 => Some(line.eval_clamped(x)),
+            OverflowPolicy::Panic => match line
+                .m
+                .checked_mul(x)
+                .and_then(|val| val.checked_add(line.c))
+            {
+                Some(val) => Some(val),
+                None => panic!("evaluating {:?} at {} overflows i64", line, x),
+            },
+        }
+    }
+}
+
+/// Which envelope a tree maintains, see [`crate::LiChaoTreeBuilder::mode`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EnvelopeMode {
+    /// Queries return the minimum over the lines, the lower envelope.
+    #[default]
+    Min,
+    /// Queries return the maximum over the lines, the upper envelope. Lines are stored negated,
+    /// so lines with an `i64::MIN` coefficient cannot be added.
+    Max,
+}
+
+impl EnvelopeMode {
+    /// Converts a line to the form it is stored in, or back, since both are the same operation.
+    /// Returns `None` if negating a coefficient overflows.
+    #[inline]
+    fn orient(self, line: Line) -> Option<Line> {
+        match self {
+            EnvelopeMode::Min => Some(line),
+            EnvelopeMode::Max => Some(Line::new(line.m.checked_neg()?, line.c.checked_neg()?)),
+        }
+    }
+
+    /// Converts an exact value between its stored and its reported form.
+    #[inline]
+    fn orient_value(self, val: i128) -> i128 {
+        match self {
+            EnvelopeMode::Min => val,
+            EnvelopeMode::Max => -val,
+        }
+    }
+}
+
+/// What happens when an x-coordinate outside the domain is passed in, see [`LiChaoTree::set_out_of_range_policy`].
+/// The `try_` methods and `query_checked` report `LiChaoError::OutOfRange` under either policy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutOfRangePolicy {
+    /// Panic, as documented on each method.
+    #[default]
+    Panic,
+    /// Treat the envelope as empty outside the domain: queries there return `None`, and segments and assigned
+    /// ranges are clipped to the domain, or ignored if no x-coordinate of the domain lies inside them.
+    /// Reversed ranges still panic.
+    Ignore,
+}
+
+impl TieBreak {
+    /// Returns whether `a` wins a tie against `b`.
+    #[inline]
+    fn prefers(self, a: &Line, b: &Line) -> bool {
+        match self {
+            TieBreak::KeepExisting => false,
+            TieBreak::SmallerSlope => a.m < b.m,
+            TieBreak::LargerSlope => a.m > b.m,
+        }
+    }
+}
+
+/// Answer of [`LiChaoTree::query_bound`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    /// The exact value of the envelope.
+    Exact(i64),
+    /// An upper bound on the envelope that is at most the requested threshold.
+    AtMost(i64),
+}
+
+/// Side of a point that a ray extends to, see [`LiChaoTree::add_ray`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Towards smaller x-coordinates.
+    Left,
+    /// Towards larger x-coordinates.
+    Right,
+}
+
+/// Summary of a single insertion, used by wrappers that track which nodes changed.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct InsertOutcome {
+    pub(crate) deepest_node: usize,
+    pub(crate) stored: bool,
+}
+
+/// One mutation recorded in a tree's line log, see [`LiChaoTree::set_keep_lines`].
+/// Replaying the entries in order on an empty tree over the same x-coordinates reproduces the envelope.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum LogEntry {
+    Line(Line),
+    Segment { line: Line, x_l: i64, x_r: i64 },
+    Assign { line: Line, x_l: i64, x_r: i64 },
+}
+
+impl LogEntry {
+    fn line(&self) -> &Line {
+        match self {
+            LogEntry::Line(line)
+            | LogEntry::Segment { line, .. }
+            | LogEntry::Assign { line, .. } => line,
+        }
+    }
+
+    fn line_mut(&mut self) -> &mut Line {
+        match self {
+            LogEntry::Line(line)
+            | LogEntry::Segment { line, .. }
+            | LogEntry::Assign { line, .. } => line,
+        }
+    }
+}
+
 /// A Li-Chao Tree for finding the minimum envelope of a set of lines.
+///
+/// In the default configuration, with [`NodeStorage::Inline`] and no line log, memory is bounded at construction:
+/// the node array holds `2 * domain_size.next_power_of_two() - 1` lines, one per node of a complete binary tree over
+/// the domain, no matter how many lines are inserted, since each node keeps only the line that wins at its midpoint.
+/// Nodes are allocated in chunks the first time a line reaches them, so creating a tree takes O(1) time and parts of
+/// the domain that no line reaches cost no memory. Queries stay exact, so such a tree is suitable for unbounded
+/// streams of insertions.
+///
+/// Other configurations grow with the insertions: the line log of `set_keep_lines` by one entry per line passed to
+/// the tree, and the line table of [`NodeStorage::Indexed`] by 16 bytes per line added, until `clear`. Insertions
+/// made with `add_line_rollbackable` also record O(log(domain_size)) cells each, until another mutation commits them.
+#[derive(Clone)]
 pub struct LiChaoTree {
-    nodes: Vec<Line>, // We intentionally do not use Vec<Optional<Line>> since the size of Option<T> must be rounded up to the nearest alignment of T. That kind of memory overhead is not acceptable!
+    nodes: NodeArray, // We intentionally do not use Vec<Optional<Line>> since the size of Option<T> must be rounded up to the nearest alignment of T. That kind of memory overhead is not acceptable!
     x_min_coord: i64,
     domain_size: usize,
+    // Grid of x-coordinates for trees built with `with_coordinate_map`. `None` means the integers from `x_min_coord`.
+    coord_map: Option<Arc<dyn CoordinateMap>>,
+    tie_break: TieBreak,
+    overflow_policy: OverflowPolicy,
+    // Fixed at construction. In `EnvelopeMode::Max`, nodes hold negated lines, see `stored_line`.
+    mode: EnvelopeMode,
+    out_of_range_policy: OutOfRangePolicy,
+    // Set on nodes whose child subtrees were logically emptied by `assign_range`. Allocated on first use.
+    cleared: Vec<bool>,
+    // Undo log of `add_line_rollbackable`: overwritten cells as (node, line, cleared flag), and where each insertion's
+    // entries start. Reset by every other mutation, since those are not logged.
+    journal: Vec<(usize, Option<Entry>, bool)>,
+    journal_marks: Vec<usize>,
+    journaling: bool,
+    // Every line passed to the tree since `set_keep_lines(true)`, with the id it got under `NodeStorage::Indexed`.
+    // `None` while no log is kept.
+    line_log: Option<Vec<(LogEntry, u32)>>,
+    // Number of lines added since construction or the last `clear`, see `len`.
+    line_count: usize,
+    // Whether some line only applies on part of the domain: a segment, an assigned range, or a merged line clipped to
+    // the other tree's domain. `improve_line` only knows how to re-insert full lines.
+    partial_lines: bool,
+    #[cfg(feature = "instrumentation")]
+    win_counts: instrumentation::WinCounts,
 }
 
 impl LiChaoTree {
     /// Creates a new Li-Chao Tree for querying minimum line values.
     /// The tree operates on x-coordinates in the inclusive range `[x_min_coord, x_max_coord]`.
+    /// Time complexity: O(1), see [`LiChaoTree`] for how nodes are allocated.
     pub fn new(x_min_coord: i64, x_max_coord: i64) -> Self {
+        match Self::allocate(
+            x_min_coord,
+            x_max_coord,
+            None,
+            None,
+            NodeLayout::Heap,
+            NodeStorage::Inline,
+        ) {
+            Ok(tree) => tree,
+            Err(err) => panic!("LiChaoTree::new: {}", err),
+        }
+    }
+
+    /// Same as `new`, but fails with `LiChaoError::InvalidDomain` if `x_min_coord > x_max_coord`,
+    /// `LiChaoError::DomainTooLarge` if the node array cannot be sized, and `LiChaoError::AllocationFailed`
+    /// if the allocator cannot provide it, instead of panicking or aborting.
+    pub fn try_new(x_min_coord: i64, x_max_coord: i64) -> Result<Self, LiChaoError> {
+        Self::allocate(
+            x_min_coord,
+            x_max_coord,
+            None,
+            None,
+            NodeLayout::Heap,
+            NodeStorage::Inline,
+        )
+    }
+
+    /// Same as `new`, but fails with `LiChaoError::MemoryBudgetExceeded` instead of allocating
+    /// more than `budget_bytes` for the node array, and with `LiChaoError::AllocationFailed`
+    /// instead of aborting if the allocator cannot provide it.
+    /// The budget covers the node array with every node allocated, and it never grows beyond that, so no later
+    /// operation can exceed the budget, unless a line log is kept with `set_keep_lines`.
+    pub fn with_memory_budget(
+        x_min_coord: i64,
+        x_max_coord: i64,
+        budget_bytes: usize,
+    ) -> Result<Self, LiChaoError> {
+        Self::allocate(
+            x_min_coord,
+            x_max_coord,
+            Some(budget_bytes),
+            None,
+            NodeLayout::Heap,
+            NodeStorage::Inline,
+        )
+    }
+
+    /// Same as `new`, but takes the tree's nodes from `arena` and gives them back when the tree is dropped, see
+    /// [`Arena`]. Clones of the tree use the same arena.
+    pub fn new_in(x_min_coord: i64, x_max_coord: i64, arena: &Arena) -> Self {
+        match Self::allocate(
+            x_min_coord,
+            x_max_coord,
+            None,
+            Some(arena),
+            NodeLayout::Heap,
+            NodeStorage::Inline,
+        ) {
+            Ok(tree) => tree,
+            Err(err) => panic!("LiChaoTree::new_in: {}", err),
+        }
+    }
+
+    /// Same as `new_in`, but fails in the same cases as `try_new` instead of panicking.
+    pub fn try_new_in(
+        x_min_coord: i64,
+        x_max_coord: i64,
+        arena: &Arena,
+    ) -> Result<Self, LiChaoError> {
+        Self::allocate(
+            x_min_coord,
+            x_max_coord,
+            None,
+            Some(arena),
+            NodeLayout::Heap,
+            NodeStorage::Inline,
+        )
+    }
+
+    /// Creates a new Li-Chao Tree over the grid of x-coordinates described by `coord_map`.
+    /// All methods taking x-coordinates then accept exactly the grid points, and panic on any other value.
+    /// Panics if the grid is empty or too large, or if it is not strictly increasing.
+    pub fn with_coordinate_map(coord_map: impl CoordinateMap + 'static) -> Self {
+        let num_points = coord_map.num_points();
+        if num_points == 0 {
+            panic!("LiChaoTree::with_coordinate_map: the grid must contain at least one point");
+        }
+        if (1..num_points).any(|idx| coord_map.x_at(idx - 1) >= coord_map.x_at(idx)) {
+            panic!("LiChaoTree::with_coordinate_map: grid coordinates must be strictly increasing");
+        }
+        match Self::allocate_nodes(
+            num_points as u128,
+            None,
+            None,
+            NodeLayout::Heap,
+            NodeStorage::Inline,
+        ) {
+            Ok(nodes) => LiChaoTree::from_parts(
+                nodes,
+                coord_map.x_at(0),
+                num_points,
+                Some(Arc::new(coord_map)),
+            ),
+            Err(err) => panic!("LiChaoTree::with_coordinate_map: {}", err),
+        }
+    }
+
+    /// Creates a new Li-Chao Tree whose domain is exactly the given x-coordinates, which may be unsorted and contain
+    /// duplicates. Memory is proportional to the number of distinct points, however far apart they are.
+    /// Queries are only allowed at these points. Shorthand for `with_coordinate_map(SortedCoords::new(points))`.
+    /// Panics if `points` is empty.
+    pub fn new_with_points(points: &[i64]) -> Self {
+        if points.is_empty() {
+            panic!("LiChaoTree::new_with_points: at least one point is required");
+        }
+        Self::with_coordinate_map(SortedCoords::new(points.iter().copied()))
+    }
+
+    fn allocate(
+        x_min_coord: i64,
+        x_max_coord: i64,
+        budget_bytes: Option<usize>,
+        arena: Option<&Arena>,
+        layout: NodeLayout,
+        storage: NodeStorage,
+    ) -> Result<Self, LiChaoError> {
         if x_min_coord > x_max_coord {
-            panic!(
-                "LiChaoTree::new: x_min_coord ({}) cannot be greater than x_max_coord ({})",
-                x_min_coord, x_max_coord
-            );
+            return Err(LiChaoError::InvalidDomain {
+                x_min_coord,
+                x_max_coord,
+            });
         }
 
-        let domain_size = (x_max_coord - x_min_coord + 1) as usize;
+        let domain_size = x_max_coord.abs_diff(x_min_coord) as u128 + 1;
+        let nodes = Self::allocate_nodes(domain_size, budget_bytes, arena, layout, storage)?;
+        Ok(LiChaoTree::from_parts(
+            nodes,
+            x_min_coord,
+            domain_size as usize,
+            None,
+        ))
+    }
 
-        let tree_array_size = if domain_size > usize::MAX / 4 {
-            panic!("LiChaoTree::new: Domain size {} is too large, 4 * domain_size would overflow usize.", domain_size);
-        } else {
-            4 * domain_size // Standard segment tree array sizing heuristic
-        };
+    /// Allocates the node array for `domain_size` points, checking it against the size limits and the budget.
+    fn allocate_nodes(
+        domain_size: u128,
+        budget_bytes: Option<usize>,
+        arena: Option<&Arena>,
+        layout: NodeLayout,
+        storage: NodeStorage,
+    ) -> Result<NodeArray, LiChaoError> {
+        let tree_array_size =
+            Self::node_count(domain_size).ok_or(LiChaoError::DomainTooLarge { domain_size })?;
+        let required_bytes = NodeArray::max_bytes(tree_array_size, layout, storage)
+            .ok_or(LiChaoError::DomainTooLarge { domain_size })?;
+        if let Some(budget_bytes) = budget_bytes
+            && required_bytes > budget_bytes
+        {
+            return Err(LiChaoError::MemoryBudgetExceeded {
+                required_bytes,
+                budget_bytes,
+            });
+        }
+
+        NodeArray::try_new(tree_array_size, arena, layout, storage).map_err(|_| {
+            LiChaoError::AllocationFailed {
+                bytes: required_bytes,
+            }
+        })
+    }
+
+    /// Number of nodes of the complete binary tree over `domain_size` points, or `None` if it overflows `usize`.
+    /// Children split their parent's range at its midpoint, so the leaves are at most
+    /// `ceil(log2(domain_size))` levels deep and every index `2v + 1`, `2v + 2` stays below this count.
+    fn node_count(domain_size: u128) -> Option<usize> {
+        let leaves = usize::try_from(domain_size)
+            .ok()?
+            .checked_next_power_of_two()?;
+        Some(leaves.checked_mul(2)? - 1)
+    }
 
+    fn from_parts(
+        nodes: NodeArray,
+        x_min_coord: i64,
+        domain_size: usize,
+        coord_map: Option<Arc<dyn CoordinateMap>>,
+    ) -> Self {
         LiChaoTree {
-            nodes: vec![NO_LINE; tree_array_size],
+            nodes,
             x_min_coord,
             domain_size,
+            coord_map,
+            tie_break: TieBreak::default(),
+            overflow_policy: OverflowPolicy::default(),
+            mode: EnvelopeMode::default(),
+            out_of_range_policy: OutOfRangePolicy::default(),
+            cleared: Vec::new(),
+            journal: Vec::new(),
+            journal_marks: Vec::new(),
+            journaling: false,
+            line_log: None,
+            line_count: 0,
+            partial_lines: false,
+            #[cfg(feature = "instrumentation")]
+            win_counts: Default::default(),
         }
     }
 
-    /// Helper function to get the actual x-coordinate from its index in the domain.
-    #[inline]
-    fn get_x_coord_from_idx(&self, index: usize) -> i64 {
-        self.x_min_coord + index as i64
+    /// Removes every line, keeping the domain and the node array's allocation.
+    /// Time complexity: O(number of nodes ever written), without allocating.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.cleared.clear();
+        self.forget_rollback();
+        self.clear_log();
+        self.line_count = 0;
+        self.partial_lines = false;
     }
 
-    /// Internal recursive function to add a line to the tree.
-    /// `line_to_add`: The new line being inserted. This variable may be swapped.
-    /// `node_v_idx`: Index of the current node in the `nodes` vector.
-    /// `range_l_idx`, `range_r_idx`: The range of *indices* [0...domain_size-1] this node covers.
-    fn add_line_internal(
-        &mut self,
-        mut line_to_add: Line,
-        node_v_idx: usize,
-        range_l_idx: usize,
-        range_r_idx: usize,
-    ) {
-        if node_v_idx >= self.nodes.len() {
-            panic!("Node array was too small");
+    /// Releases memory the tree does not need to answer queries, for trees kept around after their last insertion:
+    /// chunks of the node array holding no line, e.g. after `clear` or `rollback`, and the spare capacity of the
+    /// node array's chunk table and of the side buffers. Results are unchanged, and so is what `rollback` can undo.
+    /// Later insertions still work, but may allocate again. Memory of trees created with `new_in` goes back to the
+    /// arena.
+    /// Time complexity: O(number of nodes ever written).
+    pub fn shrink_to_fit(&mut self) {
+        self.nodes.shrink_to_fit();
+        // The clear flags are only needed while one is set, or while `rollback` may set one again
+        if !self.cleared.contains(&true) && self.journal.iter().all(|&(_, _, cleared)| !cleared) {
+            self.cleared = Vec::new();
         }
+        self.journal.shrink_to_fit();
+        self.journal_marks.shrink_to_fit();
+        if let Some(log) = &mut self.line_log {
+            log.shrink_to_fit();
+        }
+    }
 
-        let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
-
-        // Get actual x-coordinates for evaluation
-        let x_at_l = self.get_x_coord_from_idx(range_l_idx);
-        let x_at_m = self.get_x_coord_from_idx(range_m_idx);
-        let x_at_r = self.get_x_coord_from_idx(range_r_idx);
+    /// Removes every line and moves the tree to the integers in `[x_min_coord, x_max_coord]`, as if it had been
+    /// created with `new`. The node array's allocation is reused, and only grows if the new domain is larger.
+    /// Panics in the same cases as `new`. The tie-break rule is kept.
+    /// Time complexity: O(domain_size).
+    pub fn reset_domain(&mut self, x_min_coord: i64, x_max_coord: i64) {
+        if x_min_coord > x_max_coord {
+            panic!(
+                "LiChaoTree::reset_domain: {}",
+                LiChaoError::InvalidDomain {
+                    x_min_coord,
+                    x_max_coord
+                }
+            );
+        }
+        let domain_size = x_max_coord.abs_diff(x_min_coord) as u128 + 1;
+        let Some(tree_array_size) = Self::node_count(domain_size) else {
+            panic!(
+                "LiChaoTree::reset_domain: {}",
+                LiChaoError::DomainTooLarge { domain_size }
+            );
+        };
+        self.nodes.reset(tree_array_size);
+        self.x_min_coord = x_min_coord;
+        self.domain_size = domain_size as usize;
+        self.coord_map = None;
+        self.cleared.clear();
+        self.forget_rollback();
+        self.clear_log();
+        self.line_count = 0;
+        self.partial_lines = false;
+    }
 
-        let is_new_line_better_at_mid =
-            line_to_add.eval(x_at_m) < self.nodes[node_v_idx].eval(x_at_m);
+    /// Grows the domain to the integers in `[x_min_coord, x_max_coord]`, or to the smallest range of integers
+    /// containing both that range and the current domain. The tree is rebuilt from its line log, see
+    /// `set_keep_lines`, so lines that were dominated on the old domain come back where they win, and lines added
+    /// before the log was started are dropped. Lines added with `add_line` cover the whole new domain, while segments
+    /// and the ranges of `assign_range` keep their bounds.
+    /// A grid of x-coordinates given by a [`CoordinateMap`] is replaced by the integers in the new domain.
+    /// Panics if the range is reversed, if no line log is kept, or if the new domain is too large.
+    /// Time complexity: O(k * log^2(domain_size)) for k logged lines.
+    pub fn extend_domain(&mut self, x_min_coord: i64, x_max_coord: i64) {
+        if let Err(err) = self.try_extend_domain(x_min_coord, x_max_coord) {
+            panic!("LiChaoTree::extend_domain: {}", err);
+        }
+    }
 
-        if is_new_line_better_at_mid {
-            std::mem::swap(&mut self.nodes[node_v_idx], &mut line_to_add);
+    /// Same as `extend_domain`, but fails with `LiChaoError::InvalidDomain`, `LiChaoError::LinesNotKept`,
+    /// `LiChaoError::DomainTooLarge` or `LiChaoError::AllocationFailed` instead of panicking, leaving the tree
+    /// unchanged.
+    pub fn try_extend_domain(
+        &mut self,
+        x_min_coord: i64,
+        x_max_coord: i64,
+    ) -> Result<(), LiChaoError> {
+        if x_min_coord > x_max_coord {
+            return Err(LiChaoError::InvalidDomain {
+                x_min_coord,
+                x_max_coord,
+            });
         }
+        if self.line_log.is_none() {
+            return Err(LiChaoError::LinesNotKept);
+        }
+        let x_min_coord = x_min_coord.min(self.get_x_coord_from_idx(0));
+        let x_max_coord = x_max_coord.max(self.get_x_coord_from_idx(self.domain_size - 1));
+        let domain_size = x_max_coord.abs_diff(x_min_coord) as u128 + 1;
+        let mut nodes = Self::allocate_nodes(
+            domain_size,
+            None,
+            self.nodes.arena(),
+            self.layout(),
+            self.storage(),
+        )?;
+        // Logged ids stay valid, since the line table moves over as is
+        std::mem::swap(nodes.line_table_mut(), self.nodes.line_table_mut());
+        self.nodes = nodes;
+        self.x_min_coord = x_min_coord;
+        self.domain_size = domain_size as usize;
+        self.coord_map = None;
+        self.cleared = Vec::new();
+        self.replay_log(&[(0, 0, self.domain_size - 1)]);
+        Ok(())
+    }
 
-        // If the line that was pushed down (now in `line_to_add`) is effectively NO_LINE,
-        // it cannot be better than any actual line, so we stop propagating it.
-        if line_to_add == NO_LINE {
-            return;
+    /// Replaces every logged line by `f(line)` and rebuilds the envelope from the result, e.g. to scale costs or to
+    /// add a penalty per line. `f` is called once per entry of the line log, in insertion order, see
+    /// `set_keep_lines`: segments and the ranges of `assign_range` keep their bounds, `lines` returns the new lines,
+    /// and lines added before the log was started are dropped. Under [`NodeStorage::Indexed`], lines keep their ids.
+    /// Panics if no line log is kept, or in max mode if `f` returns a line that cannot be negated.
+    /// Time complexity: O(number of nodes ever written + k * log^2(domain_size)) for k logged lines.
+    pub fn map_lines(&mut self, f: impl FnMut(Line) -> Line) {
+        if let Err(err) = self.try_map_lines(f) {
+            panic!("LiChaoTree::map_lines: {}", err);
         }
+    }
 
-        if range_l_idx == range_r_idx {
-            return;
+    /// Same as `map_lines`, but fails with `LiChaoError::LinesNotKept` or `LiChaoError::NegationOverflow` instead of
+    /// panicking, leaving the tree unchanged.
+    pub fn try_map_lines(&mut self, mut f: impl FnMut(Line) -> Line) -> Result<(), LiChaoError> {
+        let Some(log) = &self.line_log else {
+            return Err(LiChaoError::LinesNotKept);
+        };
+        let mapped: Vec<Line> = log.iter().map(|(entry, _)| f(*entry.line())).collect();
+        for &line in &mapped {
+            self.check_line(line)?;
+        }
+        let mut log = self.line_log.take().unwrap();
+        let mut table = std::mem::take(self.nodes.line_table_mut());
+        // An id logged more than once, after `improve_line`, keeps its last line
+        for ((entry, id), line) in log.iter_mut().zip(mapped) {
+            *entry.line_mut() = line;
+            if let Some(slot) = table.get_mut(*id as usize) {
+                *slot = self.stored_line(line);
+            }
         }
+        self.nodes.reset(self.nodes.len());
+        *self.nodes.line_table_mut() = table;
+        self.line_log = Some(log);
+        self.cleared.clear();
+        self.replay_log(&[(0, 0, self.domain_size - 1)]);
+        Ok(())
+    }
 
-        if line_to_add.eval(x_at_l) < self.nodes[node_v_idx].eval(x_at_l) {
-            self.add_line_internal(line_to_add, 2 * node_v_idx + 1, range_l_idx, range_m_idx);
-        } else if line_to_add.eval(x_at_r) < self.nodes[node_v_idx].eval(x_at_r) {
-            self.add_line_internal(
-                line_to_add,
-                2 * node_v_idx + 2,
-                range_m_idx + 1,
-                range_r_idx,
-            );
+    /// Rebuilds the nodes below `[x_l, x_r]` from the line log, see `set_keep_lines`, without touching the rest of the
+    /// tree: the subtrees covering the range exactly are emptied and refilled with the logged lines clipped to the
+    /// range, as a rebuild of the whole tree would fill them, while nodes above them keep their lines. The envelope is
+    /// unchanged, except that lines added before the log was started are dropped from the range unless stored above
+    /// it. Commits earlier insertions made with `add_line_rollbackable`.
+    /// Panics if the range is reversed or not inside the tree's bounds, or if no line log is kept.
+    /// Time complexity: O(k * log^2(domain_size)) for k logged lines, plus the nodes below the range.
+    pub fn rebuild_range(&mut self, x_l: i64, x_r: i64) {
+        if let Err(err) = self.try_rebuild_range(x_l, x_r) {
+            panic!("LiChaoTree::rebuild_range: {}", err);
         }
     }
 
-    /// Adds a line `y = mx + c` to the tree.
-    /// Time complexity: O(log(domain_size)).
-    /// TODO: This will eventually support line segments, not just lines
-    pub fn add_line(&mut self, line: Line) {
-        if line == NO_LINE {
-            // See LiChaoTree struct def
-            panic!("Line added is the internal representation for NO_LINE");
+    /// Same as `rebuild_range`, but fails with `LiChaoError::OutOfRange`, `LiChaoError::InvalidRange` or
+    /// `LiChaoError::LinesNotKept` instead of panicking, leaving the tree unchanged.
+    pub fn try_rebuild_range(&mut self, x_l: i64, x_r: i64) -> Result<(), LiChaoError> {
+        self.check_range(x_l, x_r)?;
+        if self.line_log.is_none() {
+            return Err(LiChaoError::LinesNotKept);
+        }
+        let query_l_idx = self.get_idx_from_x_coord(x_l);
+        let query_r_idx = self.get_idx_from_x_coord(x_r);
+        if self.cleared.is_empty() {
+            self.cleared = vec![false; self.nodes.len()];
         }
-        self.add_line_internal(line, 0, 0, self.domain_size - 1);
+        let mut roots = Vec::new();
+        self.detach_range(
+            &mut roots,
+            0,
+            0,
+            self.domain_size - 1,
+            query_l_idx,
+            query_r_idx,
+        );
+        self.replay_log(&roots);
+        Ok(())
     }
 
-    /// Internal recursive function to query the minimum y-value.
-    /// `node_v_idx`: Index of the current node.
-    /// `range_l_idx`, `range_r_idx`: Range of indices covered by this node.
-    /// `query_idx`: The target index for the query (already mapped from x_coord).
-    fn query_internal(
-        &self,
+    /// Empties the subtrees covering `[query_l_idx, query_r_idx]` exactly and collects them as `(node_v_idx,
+    /// range_l_idx, range_r_idx)`. Pending clears above them are pushed down, but their lines stay.
+    fn detach_range(
+        &mut self,
+        roots: &mut Vec<(usize, usize, usize)>,
         node_v_idx: usize,
         range_l_idx: usize,
         range_r_idx: usize,
-        query_idx: usize,
-    ) -> i64 {
-        if node_v_idx >= self.nodes.len() {
-            // Primary check for array bounds
-            return INF_VAL; // NPO
-        }
-        // query_idx should always be within [range_l_idx, range_r_idx] due to recursive call logic.
-        if query_idx < range_l_idx || query_idx > range_r_idx {
-            panic!(
-                "Recursive logic is bugged: {} \\not\\in [{}, {}]",
-                query_idx, range_l_idx, range_r_idx
-            );
+        query_l_idx: usize,
+        query_r_idx: usize,
+    ) {
+        if range_r_idx < query_l_idx || query_r_idx < range_l_idx {
+            return;
         }
-
-        let query_x_coord = self.get_x_coord_from_idx(query_idx);
-        let min_val_at_query_x = self.nodes[node_v_idx].eval(query_x_coord);
-
-        // ret if leaf node
-        if range_l_idx == range_r_idx {
-            return min_val_at_query_x;
+        if query_l_idx <= range_l_idx && range_r_idx <= query_r_idx {
+            self.nodes.set(node_v_idx, None);
+            self.cleared[node_v_idx] = range_l_idx != range_r_idx;
+            roots.push((node_v_idx, range_l_idx, range_r_idx));
+            return;
         }
 
         let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+        self.push_down_clear(node_v_idx);
+        self.detach_range(
+            roots,
+            2 * node_v_idx + 1,
+            range_l_idx,
+            range_m_idx,
+            query_l_idx,
+            query_r_idx,
+        );
+        self.detach_range(
+            roots,
+            2 * node_v_idx + 2,
+            range_m_idx + 1,
+            range_r_idx,
+            query_l_idx,
+            query_r_idx,
+        );
+    }
 
-        let child_res = if query_idx <= range_m_idx {
-            // Query index falls into the left child's range.
-            self.query_internal(2 * node_v_idx + 1, range_l_idx, range_m_idx, query_idx)
-        } else {
-            // Query index falls into the right child's range.
-            self.query_internal(2 * node_v_idx + 2, range_m_idx + 1, range_r_idx, query_idx)
+    /// Re-inserts every entry of the line log into the subtrees rooted at `roots`, given as `(node_v_idx,
+    /// range_l_idx, range_r_idx)`, each clipped to the subtree's range. The subtrees must be empty, and the line table
+    /// must hold the logged ids. Nodes outside the subtrees are left alone. Commits earlier insertions made with
+    /// `add_line_rollbackable`.
+    fn replay_log(&mut self, roots: &[(usize, usize, usize)]) {
+        self.forget_rollback();
+        let Some(log) = self.line_log.take() else {
+            return;
         };
+        for &(entry, id) in &log {
+            let (query_l_idx, query_r_idx) = match entry {
+                LogEntry::Line(_) => (0, self.domain_size - 1),
+                LogEntry::Segment { x_l, x_r, .. } | LogEntry::Assign { x_l, x_r, .. } => {
+                    match self.idx_range(x_l, x_r) {
+                        Some(idx_range) => idx_range,
+                        None => continue,
+                    }
+                }
+            };
+            // An id's line may have been improved since it was logged
+            let stored = match self.nodes.line_table().get(id as usize) {
+                Some(&line) => Entry { line, id },
+                None => Entry {
+                    line: self.stored_line(*entry.line()),
+                    id,
+                },
+            };
+            for &(node_v_idx, range_l_idx, range_r_idx) in roots {
+                if let LogEntry::Assign { .. } = entry {
+                    if self.cleared.is_empty() {
+                        self.cleared = vec![false; self.nodes.len()];
+                    }
+                    self.assign_internal(
+                        stored,
+                        node_v_idx,
+                        range_l_idx,
+                        range_r_idx,
+                        query_l_idx,
+                        query_r_idx,
+                    );
+                } else {
+                    self.add_line_on_range(
+                        stored,
+                        node_v_idx,
+                        range_l_idx,
+                        range_r_idx,
+                        query_l_idx,
+                        query_r_idx,
+                    );
+                }
+            }
+        }
+        self.line_log = Some(log);
+    }
 
-        min_val_at_query_x.min(child_res)
+    /// Number of lines added since the tree was created or last cleared, including discarded lines, segments and
+    /// the lines of `assign_range`. Merging adds the other tree's count, and `rollback` subtracts the lines it undoes.
+    /// Unlike `lines`, this needs no line log.
+    pub fn len(&self) -> usize {
+        self.line_count
     }
 
-    /// Queries the minimum y-value at a given `x_coord` from all lines added to the tree.
-    /// Returns `i64::MAX` if `x_coord` is outside the tree's defined range,
-    /// or if the tree is empty/uninitialized, or if no lines provide a value better than infinity.
-    /// Time complexity: O(log(domain_size)).
-    pub fn query(&self, x_coord: i64) -> Option<i64> {
-        if x_coord < self.x_min_coord || x_coord >= self.x_min_coord + self.domain_size as i64 {
-            panic!("{} does not fit inside the tree's bounds", x_coord);
+    /// Returns whether no line has been added, in which case every query returns `None`.
+    pub fn is_empty(&self) -> bool {
+        self.line_count == 0
+    }
+
+    /// Reports the memory used by the tree and how much of the node array is occupied.
+    /// Time complexity: O(domain_size).
+    pub fn stats(&self) -> TreeStats {
+        let bytes_allocated = self.nodes.bytes_allocated()
+            + self.cleared.capacity() * std::mem::size_of::<bool>()
+            + self.journal.capacity() * std::mem::size_of::<(usize, Option<Entry>, bool)>()
+            + self.journal_marks.capacity() * std::mem::size_of::<usize>()
+            + self.line_log.as_ref().map_or(0, |log| {
+                log.capacity() * std::mem::size_of::<(LogEntry, u32)>()
+            });
+
+        let (mut occupied_nodes, mut max_depth) = (0, 0);
+        // (node, size of its index range, depth)
+        let mut stack = vec![(0, self.domain_size, 0)];
+        while let Some((node_v_idx, range_len, depth)) = stack.pop() {
+            if self.nodes.get(node_v_idx).is_some() {
+                occupied_nodes += 1;
+                max_depth = max_depth.max(depth);
+            }
+            if range_len > 1 && !self.children_cleared(node_v_idx) {
+                // The left child covers the rounded-up half, as in `add_line_internal`
+                stack.push((2 * node_v_idx + 1, range_len.div_ceil(2), depth + 1));
+                stack.push((2 * node_v_idx + 2, range_len / 2, depth + 1));
+            }
         }
+        TreeStats {
+            bytes_allocated,
+            occupied_nodes,
+            max_depth,
+            occupancy: occupied_nodes as f64 / self.nodes.len() as f64,
+        }
+    }
 
-        let query_idx = (x_coord - self.x_min_coord) as usize;
+    /// Helper function to get the actual x-coordinate from its index in the domain.
+    #[inline]
+    fn get_x_coord_from_idx(&self, index: usize) -> i64 {
+        match &self.coord_map {
+            None => self.x_min_coord + index as i64,
+            Some(coord_map) => coord_map.x_at(index),
+        }
+    }
 
-        let ret = self.query_internal(0, 0, self.domain_size - 1, query_idx);
-        if ret == INF_VAL {
-            None
-        } else {
-            Some(ret)
+    /// Inverse of `get_x_coord_from_idx`. `x_coord` must lie inside the tree's domain.
+    #[inline]
+    pub(crate) fn get_idx_from_x_coord(&self, x_coord: i64) -> usize {
+        match &self.coord_map {
+            None => (x_coord - self.x_min_coord) as usize,
+            Some(coord_map) => coord_map
+                .idx_of(x_coord)
+                .expect("x_coord is not a grid point"),
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Returns whether `x_coord` lies inside the tree's domain.
+    #[inline]
+    pub(crate) fn contains(&self, x_coord: i64) -> bool {
+        match &self.coord_map {
+            None => {
+                x_coord >= self.x_min_coord
+                    && x_coord.abs_diff(self.x_min_coord) < self.domain_size as u64
+            }
+            Some(coord_map) => coord_map.idx_of(x_coord).is_some(),
+        }
+    }
 
-    use rand::rngs::StdRng;
-    use rand::Rng;
-    use rand::SeedableRng;
+    /// Returns the indices of the first and last domain points inside `[x_l, x_r]`, or `None` if there are none.
+    fn idx_range(&self, x_l: i64, x_r: i64) -> Option<(usize, usize)> {
+        let partition_point = |pred: &dyn Fn(i64) -> bool| {
+            let (mut lo, mut hi) = (0, self.domain_size);
+            while lo < hi {
+                let mid = lo + (hi - lo) / 2;
+                if pred(self.get_x_coord_from_idx(mid)) {
+                    lo = mid + 1;
+                } else {
+                    hi = mid;
+                }
+            }
+            lo
+        };
+        let first = partition_point(&|x| x < x_l);
+        let end = partition_point(&|x| x <= x_r);
+        (first < end).then(|| (first, end - 1))
+    }
 
-    #[test]
-    fn test_simple_lines() {
-        let mut tree = LiChaoTree::new(0, 10);
+    /// Returns the indices of the domain points in `[x_l, x_r]` for `add_segment` and `assign_range`, or `None` if
+    /// the range is ignored. Panics if the range is reversed, or sticks out of the domain under
+    /// `OutOfRangePolicy::Panic`.
+    fn segment_idx_range(&self, x_l: i64, x_r: i64) -> Option<(usize, usize)> {
+        if x_l > x_r
+            || (self.out_of_range_policy == OutOfRangePolicy::Panic
+                && !(self.contains(x_l) && self.contains(x_r)))
+        {
+            panic!("[{}, {}] does not fit inside the tree's bounds", x_l, x_r);
+        }
+        self.idx_range(x_l, x_r)
+    }
 
-        tree.add_line(Line::new(2, 3));
-        // 2*0+3 = 3
-        assert_eq!(tree.query(0), Some(3));
-        // 2*5+3 = 13
-        assert_eq!(tree.query(5), Some(13));
-        // 2*10+3 = 23
-        assert_eq!(tree.query(10), Some(23));
+    /// Handles an x-coordinate outside the domain: panics under `OutOfRangePolicy::Panic`, and otherwise returns
+    /// so that the caller treats the envelope as empty there.
+    fn out_of_range(&self, x_coord: i64) {
+        if self.out_of_range_policy == OutOfRangePolicy::Panic {
+            panic!("{} does not fit inside the tree's bounds", x_coord);
+        }
+    }
 
-        tree.add_line(Line::new(-1, 10));
-        // min(3, 10) = 3
-        assert_eq!(tree.query(0), Some(3));
-        // min(13, 5) = 5
-        assert_eq!(tree.query(5), Some(5));
-        // min(23, 0) = 0
-        assert_eq!(tree.query(10), Some(0));
+    /// Returns whether line `a` wins over line `b` at `x`, breaking ties with the configured rule.
+    /// Values are compared exactly: saturated values could tie or even invert near the `i64` limits.
+    #[inline]
+    fn beats(&self, a: &Line, b: &Line, x: i64) -> bool {
+        let (val_a, val_b) = (a.eval_wide(x), b.eval_wide(x));
+        val_a < val_b || (val_a == val_b && self.prefers(a, b))
+    }
 
-        // min(7,8) = 7
-        assert_eq!(tree.query(2), Some(7));
-        // min(9,7) = 7
-        assert_eq!(tree.query(3), Some(7));
+    /// Returns whether stored line `a` wins a tie against stored line `b`. In max mode, the rule applies
+    /// to the lines as they were added, whose slopes have the opposite order.
+    #[inline]
+    fn prefers(&self, a: &Line, b: &Line) -> bool {
+        match self.mode {
+            EnvelopeMode::Min => self.tie_break.prefers(a, b),
+            EnvelopeMode::Max => self.tie_break.prefers(b, a),
+        }
     }
 
-    #[test]
-    fn test_single_point_range() {
-        let mut tree = LiChaoTree::new(5, 5);
-        tree.add_line(Line::new(10, -5));
-        assert_eq!(tree.query(5), Some(45));
+    /// Returns the tie-breaking rule, see [`TieBreak`].
+    pub fn tie_break(&self) -> TieBreak {
+        self.tie_break
+    }
 
-        tree.add_line(Line::new(1, 4));
-        assert_eq!(tree.query(5), Some(9));
+    /// Sets the rule deciding which of two lines with equal value wins, both during insertion and when
+    /// reporting winners. This should be set before adding lines: lines placed earlier keep the
+    /// arrangement chosen under the previous rule.
+    /// Query values are correct under any rule, only the reported winner of a tie depends on it.
+    pub fn set_tie_break(&mut self, rule: TieBreak) {
+        self.tie_break = rule;
     }
 
-    #[test]
-    #[should_panic]
-    fn test_out_of_bounds_query() {
-        let tree = LiChaoTree::new(0, 10);
-        let _q0 = tree.query(-1);
-        let _q1 = tree.query(11);
+    /// Returns the overflow policy, see [`OverflowPolicy`].
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.overflow_policy
     }
 
-    #[test]
-    #[should_panic]
-    fn test_invalid_range_panic() {
-        let _tree = LiChaoTree::new(10, 0);
+    /// Sets how `query`, `query_many` and `query_line` report values that overflow `i64`.
+    /// Unlike the tie-break rule, it can be changed at any time, since it does not affect the stored lines.
+    /// The default, `WideningI128`, is the fastest: the other policies find the winning line first, then evaluate it.
+    pub fn set_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.overflow_policy = policy;
     }
 
-    #[test]
-    fn test_all_same_lines() {
-        let mut tree = LiChaoTree::new(0, 100);
-        let line = Line::new(1, 1);
-        tree.add_line(line);
+    /// Returns whether the tree maintains the lower or the upper envelope. The mode is chosen when building the
+    /// tree with [`crate::LiChaoTreeBuilder`], and defaults to `EnvelopeMode::Min`.
+    pub fn mode(&self) -> EnvelopeMode {
+        self.mode
+    }
+
+    /// Returns how the nodes are ordered in memory, see [`crate::LiChaoTreeBuilder::layout`].
+    pub fn layout(&self) -> NodeLayout {
+        self.nodes.layout()
+    }
+
+    /// Returns how the nodes hold their lines, see [`crate::LiChaoTreeBuilder::storage`].
+    pub fn storage(&self) -> NodeStorage {
+        self.nodes.storage()
+    }
+
+    /// Returns the out-of-range policy, see [`OutOfRangePolicy`].
+    pub fn out_of_range_policy(&self) -> OutOfRangePolicy {
+        self.out_of_range_policy
+    }
+
+    /// Sets what happens when an x-coordinate outside the domain is passed to a query, `add_segment` or
+    /// `assign_range`.
+    pub fn set_out_of_range_policy(&mut self, policy: OutOfRangePolicy) {
+        self.out_of_range_policy = policy;
+    }
+
+    /// Adds a line to the subtree of a node, walking down from it iteratively.
+    /// `line_to_add`: The new line being inserted. This variable may be swapped.
+    /// `node_v_idx`: Index of the node to start from in the `nodes` vector.
+    /// `range_l_idx`, `range_r_idx`: The range of *indices* [0...domain_size-1] this node covers.
+    fn add_line_internal(
+        &mut self,
+        mut line_to_add: Entry,
+        node_v_idx: usize,
+        mut range_l_idx: usize,
+        mut range_r_idx: usize,
+    ) -> InsertOutcome {
+        // Once swapped in, the incoming line stays there, and only displaced lines are carried further down,
+        // so the line was stored if any node on the way took the carried line.
+        let mut stored = false;
+        let mut node = self.nodes.cursor(node_v_idx);
+        loop {
+            let node_v_idx = node.node_v_idx;
+            let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+
+            // Get actual x-coordinates for evaluation
+            let x_at_l = self.get_x_coord_from_idx(range_l_idx);
+            let x_at_m = self.get_x_coord_from_idx(range_m_idx);
+            let x_at_r = self.get_x_coord_from_idx(range_r_idx);
+
+            // Line left in the node, the other one is carried further down
+            let resident = match self.nodes.entry(node) {
+                Some(resident) if !self.beats(&line_to_add.line, &resident.line, x_at_m) => {
+                    resident
+                }
+                _ => {
+                    self.record(node_v_idx);
+                    stored = true;
+                    let Some(displaced) = self.nodes.replace(node, Some(line_to_add)) else {
+                        // An empty node takes the line, and nothing is left to carry down
+                        break;
+                    };
+                    std::mem::replace(&mut line_to_add, displaced)
+                }
+            };
+            if range_l_idx == range_r_idx {
+                break;
+            }
+
+            self.push_down_clear(node_v_idx);
+            if self.beats(&line_to_add.line, &resident.line, x_at_l) {
+                (node, range_r_idx) = (self.nodes.child(node, false), range_m_idx);
+            } else if self.beats(&line_to_add.line, &resident.line, x_at_r) {
+                (node, range_l_idx) = (self.nodes.child(node, true), range_m_idx + 1);
+            } else {
+                break;
+            }
+        }
+
+        InsertOutcome {
+            deepest_node: node.node_v_idx,
+            stored,
+        }
+    }
+
+    /// Adds a line `y = mx + c` to the tree.
+    /// Returns `false` if the line was discarded, in which case it is nowhere below the envelope
+    /// and the envelope is unchanged. Returns `true` if the line was kept in some node; this
+    /// almost always means it improved the envelope, but a kept line may still be dominated
+    /// by lines stored in other nodes.
+    /// Accepts anything convertible into a line, such as an `(m, c)` tuple.
+    /// Time complexity: O(log(domain_size)).
+    pub fn add_line(&mut self, line: impl Into<Line>) -> bool {
+        self.add_line_tracked(line.into()).stored
+    }
+
+    /// Same as `add_line`, but fails with `LiChaoError::NegationOverflow` instead of panicking.
+    pub fn try_add_line(&mut self, line: impl Into<Line>) -> Result<bool, LiChaoError> {
+        let line = line.into();
+        self.check_line(line)?;
+        Ok(self.add_line(line))
+    }
+
+    /// Checks that `line` can be stored: in max mode its coefficients must be negatable.
+    pub(crate) fn check_line(&self, line: Line) -> Result<(), LiChaoError> {
+        match self.mode.orient(line) {
+            None => Err(LiChaoError::NegationOverflow { line }),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Converts `line` to the form it is stored in, which is itself except in max mode. Panics if it cannot be stored.
+    #[inline]
+    fn stored_line(&self, line: Line) -> Line {
+        match self.check_line(line) {
+            Ok(()) => self.mode.orient(line).unwrap(),
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Converts a line stored in a node back to the line that was added.
+    #[inline]
+    fn added_line(&self, line: Line) -> Line {
+        // Stored lines are negations, so negating them again never overflows
+        self.mode.orient(line).unwrap()
+    }
+
+    /// Checks that `[x_l, x_r]` is a range of the tree's x-coordinates.
+    pub(crate) fn check_range(&self, x_l: i64, x_r: i64) -> Result<(), LiChaoError> {
+        for x_coord in [x_l, x_r] {
+            if !self.contains(x_coord) {
+                return Err(LiChaoError::OutOfRange { x_coord });
+            }
+        }
+        if x_l > x_r {
+            return Err(LiChaoError::InvalidRange { x_l, x_r });
+        }
+        Ok(())
+    }
+
+    /// Adds the segment of `line` over `[x_l, x_r]`: queries inside the range see the line, queries outside do not.
+    /// Returns whether the segment was kept, with the same meaning as for `add_line`.
+    /// Panics if the range is reversed or outside the tree's bounds, see [`OutOfRangePolicy`].
+    /// Time complexity: O(log^2(domain_size)).
+    pub fn add_segment(&mut self, line: Line, x_l: i64, x_r: i64) -> bool {
+        let stored = self.stored_line(line);
+        let Some((query_l_idx, query_r_idx)) = self.segment_idx_range(x_l, x_r) else {
+            return false;
+        };
+        self.add_segment_idx(line, stored, query_l_idx, query_r_idx)
+    }
+
+    /// Adds `line`, stored as `stored`, on the index range `[query_l_idx, query_r_idx]`.
+    fn add_segment_idx(
+        &mut self,
+        line: Line,
+        stored: Line,
+        query_l_idx: usize,
+        query_r_idx: usize,
+    ) -> bool {
+        self.forget_rollback();
+        let entry = self.nodes.push_line(stored);
+        self.log(
+            LogEntry::Segment {
+                line,
+                x_l: self.get_x_coord_from_idx(query_l_idx),
+                x_r: self.get_x_coord_from_idx(query_r_idx),
+            },
+            entry.id,
+        );
+        self.line_count += 1;
+        self.partial_lines = true;
+        self.add_line_on_range(entry, 0, 0, self.domain_size - 1, query_l_idx, query_r_idx)
+    }
+
+    /// Same as `add_segment`, but fails with `LiChaoError::NegationOverflow`, `LiChaoError::OutOfRange` or
+    /// `LiChaoError::InvalidRange` instead of panicking.
+    pub fn try_add_segment(&mut self, line: Line, x_l: i64, x_r: i64) -> Result<bool, LiChaoError> {
+        self.check_line(line)?;
+        self.check_range(x_l, x_r)?;
+        Ok(self.add_segment(line, x_l, x_r))
+    }
+
+    /// Adds `line` on the ray from `from_x` towards `direction`, up to the end of the domain: `Direction::Left`
+    /// covers `[x_min, from_x]` and `Direction::Right` covers `[from_x, x_max]`. `from_x` may lie outside the
+    /// domain, in which case the ray covers all of it or none of it. Returns whether the ray was kept, with the
+    /// same meaning as for `add_segment`, and `false` if it misses the domain.
+    /// Panics only if the line cannot be stored, see [`EnvelopeMode::Max`].
+    /// Time complexity: O(log^2(domain_size)).
+    pub fn add_ray(&mut self, line: Line, from_x: i64, direction: Direction) -> bool {
+        let stored = self.stored_line(line);
+        let range = match direction {
+            Direction::Left => self.idx_range(i64::MIN, from_x),
+            Direction::Right => self.idx_range(from_x, i64::MAX),
+        };
+        match range {
+            Some((query_l_idx, query_r_idx)) => {
+                self.add_segment_idx(line, stored, query_l_idx, query_r_idx)
+            }
+            None => false,
+        }
+    }
+
+    /// Same as `add_ray`, but fails with `LiChaoError::NegationOverflow` instead of panicking.
+    pub fn try_add_ray(
+        &mut self,
+        line: Line,
+        from_x: i64,
+        direction: Direction,
+    ) -> Result<bool, LiChaoError> {
+        self.check_line(line)?;
+        Ok(self.add_ray(line, from_x, direction))
+    }
+
+    /// Adds the V-shaped function `y = w * |x - a| + b`, as a ray of slope `-w` left of `a` and a ray of slope `w`
+    /// right of it, see `add_ray`. A negative `w` gives an upside-down V. `a` may lie outside the domain.
+    /// Returns whether either ray was kept. With `NodeStorage::Indexed`, the rays get consecutive line ids.
+    /// Panics if the rays' coefficients do not fit in `i64`, or cannot be stored, see [`EnvelopeMode::Max`].
+    /// Time complexity: O(log^2(domain_size)).
+    pub fn add_abs(&mut self, a: i64, w: i64, b: i64) -> bool {
+        let Some((left, right)) = Self::abs_rays(a, w, b) else {
+            panic!("LiChaoTree::add_abs: {}", LiChaoError::UnrepresentableLine);
+        };
+        let kept_left = self.add_ray(left, a, Direction::Left);
+        let kept_right = self.add_ray(right, a, Direction::Right);
+        kept_left || kept_right
+    }
+
+    /// Same as `add_abs`, but fails with `LiChaoError::UnrepresentableLine` or `LiChaoError::NegationOverflow`
+    /// instead of panicking.
+    pub fn try_add_abs(&mut self, a: i64, w: i64, b: i64) -> Result<bool, LiChaoError> {
+        let (left, right) = Self::abs_rays(a, w, b).ok_or(LiChaoError::UnrepresentableLine)?;
+        self.check_line(left)?;
+        self.check_line(right)?;
+        Ok(self.add_abs(a, w, b))
+    }
+
+    /// Lines of the left and right ray of `y = w * |x - a| + b`, or `None` if a coefficient overflows.
+    fn abs_rays(a: i64, w: i64, b: i64) -> Option<(Line, Line)> {
+        let wa = w.checked_mul(a)?;
+        Some((
+            Line::new(w.checked_neg()?, wa.checked_add(b)?),
+            Line::new(w, b.checked_sub(wa)?),
+        ))
+    }
+
+    /// Adds every line in `lines`, in order, and reports which of them were discarded.
+    /// A line is reported as dominated exactly when `add_line` would have returned `false` for it.
+    /// Time complexity: O(lines.len() * log(domain_size)).
+    pub fn add_lines(&mut self, lines: &[Line]) -> BulkReport {
+        let mut report = BulkReport::default();
+        self.add_lines_into(lines, &mut report);
+        report
+    }
+
+    /// Same as `add_lines`, but writes into a caller-owned report. The report is reset first and
+    /// its `dominated` buffer is reused, so repeated calls do not allocate once it has grown large enough.
+    pub fn add_lines_into(&mut self, lines: &[Line], report: &mut BulkReport) {
+        report.kept = 0;
+        report.dominated.clear();
+        for (idx, line) in lines.iter().enumerate() {
+            if self.add_line(*line) {
+                report.kept += 1;
+            } else {
+                report.dominated.push(idx);
+            }
+        }
+    }
+
+    /// Adds every line of `other`, shifted by `delta` (`m + delta.m`, `c + delta.c`), to this tree.
+    /// Afterwards this tree's envelope is the minimum of its old envelope and `other`'s envelope plus
+    /// `delta.eval(x)`, on the x-coordinates covered by both trees. Elsewhere it is unchanged.
+    /// Shifted coefficients saturate, and a shifted line that saturates into a line that cannot be stored is dropped.
+    /// In max mode, the envelope becomes the maximum instead. Panics if the trees are not in the same mode, or if
+    /// this tree numbers its lines and `other` does not, see [`NodeStorage::Indexed`].
+    /// Time complexity: O(other's domain_size + k * log^2(domain_size)) for k lines stored in `other`.
+    pub fn merge_offset(&mut self, other: &LiChaoTree, delta: Line) {
+        if self.mode != other.mode {
+            panic!("LiChaoTree::merge_offset: {}", LiChaoError::ModeMismatch);
+        }
+        let mode = self.mode;
+        let shift = move |line: Line| {
+            Line::new(
+                line.m.saturating_add(delta.m),
+                line.c.saturating_add(delta.c),
+            )
+        };
+        // Lines that cannot be stored once shifted keep their ids, but no node refers to them
+        let base = self
+            .append_line_table(other, |line| {
+                mode.orient(shift(mode.orient(line).unwrap()))
+                    .unwrap_or(NO_LINE)
+            })
+            .unwrap_or_else(|err| panic!("LiChaoTree::merge_offset: {}", err));
+        self.forget_rollback();
+        self.line_count += other.line_count;
+        self.partial_lines |= other.partial_lines
+            || other.get_x_coord_from_idx(0) > self.get_x_coord_from_idx(0)
+            || other.get_x_coord_from_idx(other.domain_size - 1)
+                < self.get_x_coord_from_idx(self.domain_size - 1);
+        self.merge_offset_internal(other, shift, base, 0, 0, other.domain_size - 1);
+    }
+
+    fn merge_offset_internal(
+        &mut self,
+        other: &LiChaoTree,
+        shift: impl Fn(Line) -> Line + Copy,
+        base: u32,
+        node_v_idx: usize,
+        range_l_idx: usize,
+        range_r_idx: usize,
+    ) {
+        if let Some(entry) = other.nodes.entry(node_v_idx) {
+            let shifted = shift(other.added_line(entry.line));
+            // The line only applies on this node's range, which may stick out of our domain on either side
+            let x_l = other.get_x_coord_from_idx(range_l_idx);
+            let x_r = other.get_x_coord_from_idx(range_r_idx);
+            if self.check_line(shifted).is_ok()
+                && let Some((query_l_idx, query_r_idx)) = self.idx_range(x_l, x_r)
+            {
+                let stored = Entry {
+                    line: self.stored_line(shifted),
+                    id: base + entry.id,
+                };
+                self.log(
+                    LogEntry::Segment {
+                        line: shifted,
+                        x_l: self.get_x_coord_from_idx(query_l_idx),
+                        x_r: self.get_x_coord_from_idx(query_r_idx),
+                    },
+                    stored.id,
+                );
+                self.add_line_on_range(
+                    stored,
+                    0,
+                    0,
+                    self.domain_size - 1,
+                    query_l_idx,
+                    query_r_idx,
+                );
+            }
+        }
+        if range_l_idx == range_r_idx || other.children_cleared(node_v_idx) {
+            return;
+        }
+        let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+        self.merge_offset_internal(
+            other,
+            shift,
+            base,
+            2 * node_v_idx + 1,
+            range_l_idx,
+            range_m_idx,
+        );
+        self.merge_offset_internal(
+            other,
+            shift,
+            base,
+            2 * node_v_idx + 2,
+            range_m_idx + 1,
+            range_r_idx,
+        );
+    }
+
+    /// Appends the line table of `other` to this tree's, each line mapped by `map` from its stored form in `other`,
+    /// so that the lines of `other` keep their order after this tree's. Returns the offset added to their ids, which
+    /// is 0 if this tree does not number its lines. Fails with `LiChaoError::StorageMismatch` if it does but `other`
+    /// does not, since the order of `other`'s lines is unknown.
+    fn append_line_table(
+        &mut self,
+        other: &LiChaoTree,
+        map: impl Fn(Line) -> Line,
+    ) -> Result<u32, LiChaoError> {
+        match (self.storage(), other.storage()) {
+            (NodeStorage::Inline, _) => Ok(0),
+            (NodeStorage::Indexed, NodeStorage::Inline) => Err(LiChaoError::StorageMismatch),
+            (NodeStorage::Indexed, NodeStorage::Indexed) => {
+                let base = self.nodes.line_table().len() as u32;
+                for &line in other.nodes.line_table() {
+                    self.nodes.push_line(map(line));
+                }
+                Ok(base)
+            }
+        }
+    }
+
+    /// Adds every line of `other` to this tree, so that the envelope becomes the minimum of both envelopes.
+    /// Both trees share the same node layout, so each line of `other` is inserted starting from the node it was
+    /// stored in, not from the root: only the subtree below it is walked, which suits small-to-large merging.
+    /// In max mode, the envelope becomes the maximum of both envelopes instead.
+    /// Panics if the trees do not have the same x-coordinates or are not in the same mode, or if this tree numbers
+    /// its lines and `other` does not, see [`NodeStorage::Indexed`].
+    /// Time complexity: O(domain_size + k * log(domain_size)) for k lines stored in `other`.
+    pub fn merge(&mut self, other: LiChaoTree) {
+        if let Err(err) = self.try_merge(other) {
+            panic!("LiChaoTree::merge: {}", err);
+        }
+    }
+
+    /// Same as `merge`, but fails with `LiChaoError::DomainMismatch`, `LiChaoError::ModeMismatch` or
+    /// `LiChaoError::StorageMismatch` instead of panicking.
+    pub fn try_merge(&mut self, other: LiChaoTree) -> Result<(), LiChaoError> {
+        if self.mode != other.mode {
+            return Err(LiChaoError::ModeMismatch);
+        }
+        if self.domain_size != other.domain_size
+            || (0..self.domain_size)
+                .any(|idx| self.get_x_coord_from_idx(idx) != other.get_x_coord_from_idx(idx))
+        {
+            return Err(LiChaoError::DomainMismatch);
+        }
+        let base = self.append_line_table(&other, |line| line)?;
+        self.forget_rollback();
+        self.line_count += other.line_count;
+        self.partial_lines |= other.partial_lines;
+        self.merge_internal(&other, base, 0, 0, self.domain_size - 1);
+        Ok(())
+    }
+
+    fn merge_internal(
+        &mut self,
+        other: &LiChaoTree,
+        base: u32,
+        node_v_idx: usize,
+        range_l_idx: usize,
+        range_r_idx: usize,
+    ) {
+        if let Some(entry) = other.nodes.entry(node_v_idx) {
+            // Inserting at a node is the same as adding the segment over the node's range
+            let entry = Entry {
+                id: base + entry.id,
+                ..entry
+            };
+            self.log(
+                LogEntry::Segment {
+                    line: self.added_line(entry.line),
+                    x_l: self.get_x_coord_from_idx(range_l_idx),
+                    x_r: self.get_x_coord_from_idx(range_r_idx),
+                },
+                entry.id,
+            );
+            self.add_line_internal(entry, node_v_idx, range_l_idx, range_r_idx);
+        }
+        if range_l_idx == range_r_idx || other.children_cleared(node_v_idx) {
+            return;
+        }
+        // Our children must be up to date before lines are inserted into them directly
+        self.push_down_clear(node_v_idx);
+        let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+        self.merge_internal(other, base, 2 * node_v_idx + 1, range_l_idx, range_m_idx);
+        self.merge_internal(
+            other,
+            base,
+            2 * node_v_idx + 2,
+            range_m_idx + 1,
+            range_r_idx,
+        );
+    }
+
+    /// Same as `add_line`, but also reports the deepest node touched by the insertion.
+    /// Only nodes on the path from the root to that node were modified.
+    pub(crate) fn add_line_tracked(&mut self, line: Line) -> InsertOutcome {
+        let stored = self.stored_line(line);
+        self.forget_rollback();
+        let entry = self.nodes.push_line(stored);
+        self.log(LogEntry::Line(line), entry.id);
+        self.line_count += 1;
+        self.add_line_internal(entry, 0, 0, self.domain_size - 1)
+    }
+
+    /// Same as `add_line`, but the insertion can later be undone with `rollback`.
+    /// Only the node cells the insertion overwrites are recorded, O(log(domain_size)) of them.
+    /// Time complexity: O(log(domain_size)).
+    pub fn add_line_rollbackable(&mut self, line: Line) -> bool {
+        let stored = self.stored_line(line);
+        self.journal_marks.push(self.journal.len());
+        let entry = self.nodes.push_line(stored);
+        self.log(LogEntry::Line(line), entry.id);
+        self.line_count += 1;
+        self.journaling = true;
+        let outcome = self.add_line_internal(entry, 0, 0, self.domain_size - 1);
+        self.journaling = false;
+        outcome.stored
+    }
+
+    /// Same as `add_line_rollbackable`, but fails with `LiChaoError::NegationOverflow` instead of panicking.
+    pub fn try_add_line_rollbackable(&mut self, line: Line) -> Result<bool, LiChaoError> {
+        self.check_line(line)?;
+        Ok(self.add_line_rollbackable(line))
+    }
+
+    /// Undoes the last `k` insertions made with `add_line_rollbackable`, most recent first.
+    /// Any other mutation (`add_line`, `add_segment`, `assign_range`, ...) commits all earlier insertions:
+    /// they can no longer be rolled back. Panics if fewer than `k` insertions can be rolled back.
+    /// Time complexity: O(k * log(domain_size)).
+    pub fn rollback(&mut self, k: usize) {
+        if let Err(err) = self.try_rollback(k) {
+            panic!("{}", err);
+        }
+    }
+
+    /// Same as `rollback`, but fails with `LiChaoError::RollbackUnavailable` instead of panicking,
+    /// without rolling anything back.
+    pub fn try_rollback(&mut self, k: usize) -> Result<(), LiChaoError> {
+        if k > self.journal_marks.len() {
+            return Err(LiChaoError::RollbackUnavailable {
+                requested: k,
+                available: self.journal_marks.len(),
+            });
+        }
+        // Rollbackable insertions are always the most recent entries of the line log and of the line table
+        if let Some(log) = &mut self.line_log {
+            log.truncate(log.len() - k);
+        }
+        self.line_count -= k;
+        for _ in 0..k {
+            let mark = self.journal_marks.pop().unwrap();
+            for (node_v_idx, entry, cleared) in self.journal.drain(mark..).rev() {
+                self.nodes.set(node_v_idx, entry);
+                if let Some(flag) = self.cleared.get_mut(node_v_idx) {
+                    *flag = cleared;
+                }
+            }
+        }
+        self.nodes.pop_lines(k);
+        Ok(())
+    }
+
+    /// Number of insertions that `rollback` can currently undo.
+    pub fn rollback_depth(&self) -> usize {
+        self.journal_marks.len()
+    }
+
+    /// Logs the current contents of `node_v_idx` if a rollbackable insertion is running.
+    #[inline]
+    fn record(&mut self, node_v_idx: usize) {
+        if self.journaling {
+            self.journal.push((
+                node_v_idx,
+                self.nodes.entry(node_v_idx),
+                self.children_cleared(node_v_idx),
+            ));
+        }
+    }
+
+    #[inline]
+    fn forget_rollback(&mut self) {
+        self.journal.clear();
+        self.journal_marks.clear();
+    }
+
+    /// Starts or stops keeping a log of every line passed to the tree, which `lines` then returns and from which
+    /// `extend_domain`, `map_lines` and `rebuild_range` rebuild the tree.
+    /// The log is off by default, since it grows with every insertion while the node array does not.
+    /// Starting it while it is already kept does nothing. Otherwise the log starts empty: lines added before
+    /// are not recovered, and earlier insertions made with `add_line_rollbackable` are committed. Stopping it
+    /// frees the log.
+    pub fn set_keep_lines(&mut self, keep: bool) {
+        match (keep, self.line_log.is_some()) {
+            (true, false) => {
+                self.forget_rollback();
+                self.line_log = Some(Vec::new());
+            }
+            (false, true) => self.line_log = None,
+            _ => {}
+        }
+    }
+
+    /// Returns whether a line log is kept, see `set_keep_lines`.
+    pub fn keeps_lines(&self) -> bool {
+        self.line_log.is_some()
+    }
+
+    /// Iterates over the lines passed to the tree since the log was started with `set_keep_lines`, in insertion
+    /// order. This includes lines that were discarded, the lines of segments and of `assign_range`, and for
+    /// `merge` and `merge_offset` the lines stored in the other tree. Lines undone by `rollback` are removed.
+    /// Yields nothing if no log is kept.
+    pub fn lines(&self) -> impl Iterator<Item = &Line> {
+        self.line_log
+            .iter()
+            .flatten()
+            .map(|(entry, _)| entry.line())
+    }
+
+    /// Appends `entry` to the line log, if one is kept, with the id of its line in the line table.
+    #[inline]
+    fn log(&mut self, entry: LogEntry, id: u32) {
+        if let Some(log) = &mut self.line_log {
+            log.push((entry, id));
+        }
+    }
+
+    #[inline]
+    fn clear_log(&mut self) {
+        if let Some(log) = &mut self.line_log {
+            log.clear();
+        }
+    }
+
+    /// Insertions reproducing the envelope: the line log if one is kept, or else the lines stored in the nodes, each
+    /// as a segment over its node's range.
+    #[cfg(any(feature = "plot", feature = "serde"))]
+    fn entries(&self) -> Vec<LogEntry> {
+        match &self.line_log {
+            Some(log) => log.iter().map(|&(entry, _)| entry).collect(),
+            None => {
+                let mut entries = Vec::new();
+                self.stored_entries(&mut entries, 0, 0, self.domain_size - 1);
+                entries
+            }
+        }
+    }
+
+    /// Collects the lines visible in the subtree of `node_v_idx` as segments over their nodes' ranges.
+    #[cfg(any(feature = "plot", feature = "serde"))]
+    fn stored_entries(
+        &self,
+        entries: &mut Vec<LogEntry>,
+        node_v_idx: usize,
+        range_l_idx: usize,
+        range_r_idx: usize,
+    ) {
+        if let Some(line) = self.nodes.get(node_v_idx) {
+            entries.push(LogEntry::Segment {
+                line: self.added_line(line),
+                x_l: self.get_x_coord_from_idx(range_l_idx),
+                x_r: self.get_x_coord_from_idx(range_r_idx),
+            });
+        }
+        if range_l_idx == range_r_idx || self.children_cleared(node_v_idx) {
+            return;
+        }
+        let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+        self.stored_entries(entries, 2 * node_v_idx + 1, range_l_idx, range_m_idx);
+        self.stored_entries(entries, 2 * node_v_idx + 2, range_m_idx + 1, range_r_idx);
+    }
+
+    /// Returns whether the lines stored below `node_v_idx` are stale and must be ignored.
+    #[inline]
+    fn children_cleared(&self, node_v_idx: usize) -> bool {
+        self.cleared.get(node_v_idx).copied().unwrap_or(false)
+    }
+
+    /// Materializes a pending clear of `node_v_idx`'s children one level down. Must only be called on non-leaf nodes.
+    #[inline]
+    fn push_down_clear(&mut self, node_v_idx: usize) {
+        if self.children_cleared(node_v_idx) {
+            self.record(node_v_idx);
+            self.cleared[node_v_idx] = false;
+            for child_v_idx in [2 * node_v_idx + 1, 2 * node_v_idx + 2] {
+                self.record(child_v_idx);
+                self.nodes.set(child_v_idx, None);
+                self.cleared[child_v_idx] = true;
+            }
+        }
+    }
+
+    /// Replaces the envelope on `[x_l, x_r]` with `line`, discarding every line previously added there.
+    /// Outside `[x_l, x_r]` the envelope is unchanged. Later `add_line` calls take the minimum as usual.
+    /// Panics if the range is reversed or outside the tree's bounds, see [`OutOfRangePolicy`].
+    /// Time complexity: O(log^2(domain_size)).
+    pub fn assign_range(&mut self, x_l: i64, x_r: i64, line: Line) {
+        let stored = self.stored_line(line);
+        let Some((query_l_idx, query_r_idx)) = self.segment_idx_range(x_l, x_r) else {
+            return;
+        };
+        self.forget_rollback();
+        let entry = self.nodes.push_line(stored);
+        self.log(
+            LogEntry::Assign {
+                line,
+                x_l: self.get_x_coord_from_idx(query_l_idx),
+                x_r: self.get_x_coord_from_idx(query_r_idx),
+            },
+            entry.id,
+        );
+        self.line_count += 1;
+        self.partial_lines = true;
+        if self.cleared.is_empty() {
+            self.cleared = vec![false; self.nodes.len()];
+        }
+        self.assign_internal(entry, 0, 0, self.domain_size - 1, query_l_idx, query_r_idx);
+    }
+
+    /// Same as `assign_range`, but fails with `LiChaoError::NegationOverflow`, `LiChaoError::OutOfRange` or
+    /// `LiChaoError::InvalidRange` instead of panicking.
+    pub fn try_assign_range(&mut self, x_l: i64, x_r: i64, line: Line) -> Result<(), LiChaoError> {
+        self.check_line(line)?;
+        self.check_range(x_l, x_r)?;
+        self.assign_range(x_l, x_r, line);
+        Ok(())
+    }
+
+    /// Lowers the intercept of the line with insertion index `id` to `new_c`, or raises it in max mode, and re-inserts
+    /// it where it now wins. The line keeps its id and its entry in the line table, so repeated improvements take no
+    /// memory, and `len` is unchanged. Ids are those reported by `query_argmin`. With a line log, the improved line is
+    /// logged again under the same id. Only trees whose lines all span the whole domain can be updated this way.
+    /// Panics if the tree does not number its lines, see [`NodeStorage::Indexed`], if no line has index `id`, if
+    /// `new_c` does not improve the line, or if the tree holds segments, rays or assigned ranges.
+    /// Time complexity: O(log(domain_size)).
+    pub fn improve_line(&mut self, id: usize, new_c: i64) {
+        if let Err(err) = self.try_improve_line(id, new_c) {
+            panic!("LiChaoTree::improve_line: {}", err);
+        }
+    }
+
+    /// Same as `improve_line`, but fails with `LiChaoError::UnknownLine`, `LiChaoError::NotAnImprovement`,
+    /// `LiChaoError::PartialLines` or `LiChaoError::NegationOverflow` instead of panicking. An intercept equal to the
+    /// current one is accepted, and changes nothing.
+    pub fn try_improve_line(&mut self, id: usize, new_c: i64) -> Result<(), LiChaoError> {
+        let Some(&old) = self.nodes.line_table().get(id) else {
+            return Err(LiChaoError::UnknownLine { id });
+        };
+        if self.partial_lines {
+            return Err(LiChaoError::PartialLines);
+        }
+        let line = Line::new(self.added_line(old).m, new_c);
+        self.check_line(line)?;
+        let stored = self.stored_line(line);
+        if stored.c > old.c {
+            return Err(LiChaoError::NotAnImprovement {
+                line: self.added_line(old),
+                new_c,
+            });
+        }
+        self.forget_rollback();
+        // Nodes holding the id see the improved line at once, which can only lower their values
+        self.nodes.line_table_mut()[id] = stored;
+        self.log(LogEntry::Line(line), id as u32);
+        self.add_line_internal(
+            Entry {
+                line: stored,
+                id: id as u32,
+            },
+            0,
+            0,
+            self.domain_size - 1,
+        );
+        Ok(())
+    }
+
+    fn assign_internal(
+        &mut self,
+        line: Entry,
+        node_v_idx: usize,
+        range_l_idx: usize,
+        range_r_idx: usize,
+        query_l_idx: usize,
+        query_r_idx: usize,
+    ) {
+        if range_r_idx < query_l_idx || query_r_idx < range_l_idx {
+            return;
+        }
+        if query_l_idx <= range_l_idx && range_r_idx <= query_r_idx {
+            self.nodes.set(node_v_idx, Some(line));
+            self.cleared[node_v_idx] = range_l_idx != range_r_idx;
+            return;
+        }
+
+        // Partial overlap: this node's line must stop applying inside the range but keep applying
+        // outside it, so hand it down to both children before descending.
+        let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+        self.push_down_clear(node_v_idx);
+        if let Some(old) = self.nodes.replace(node_v_idx, None) {
+            self.add_line_internal(old, 2 * node_v_idx + 1, range_l_idx, range_m_idx);
+            self.add_line_internal(old, 2 * node_v_idx + 2, range_m_idx + 1, range_r_idx);
+        }
+        self.assign_internal(
+            line,
+            2 * node_v_idx + 1,
+            range_l_idx,
+            range_m_idx,
+            query_l_idx,
+            query_r_idx,
+        );
+        self.assign_internal(
+            line,
+            2 * node_v_idx + 2,
+            range_m_idx + 1,
+            range_r_idx,
+            query_l_idx,
+            query_r_idx,
+        );
+    }
+
+    /// Adds `line` on the index range `[query_l_idx, query_r_idx]` only, by inserting it into each node
+    /// that exactly covers part of the range. Returns whether it was kept in any of them.
+    fn add_line_on_range(
+        &mut self,
+        line: Entry,
+        node_v_idx: usize,
+        range_l_idx: usize,
+        range_r_idx: usize,
+        query_l_idx: usize,
+        query_r_idx: usize,
+    ) -> bool {
+        if range_r_idx < query_l_idx || query_r_idx < range_l_idx {
+            return false;
+        }
+        if query_l_idx <= range_l_idx && range_r_idx <= query_r_idx {
+            return self
+                .add_line_internal(line, node_v_idx, range_l_idx, range_r_idx)
+                .stored;
+        }
+
+        let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+        self.push_down_clear(node_v_idx);
+        let stored_l = self.add_line_on_range(
+            line,
+            2 * node_v_idx + 1,
+            range_l_idx,
+            range_m_idx,
+            query_l_idx,
+            query_r_idx,
+        );
+        let stored_r = self.add_line_on_range(
+            line,
+            2 * node_v_idx + 2,
+            range_m_idx + 1,
+            range_r_idx,
+            query_l_idx,
+            query_r_idx,
+        );
+        stored_l || stored_r
+    }
+
+    /// Minimum y-value at an index, over the lines stored on the path from the root to its leaf, or `INF_VAL` if there
+    /// are none. Since empty nodes also evaluate to `INF_VAL`, a result of `INF_VAL` must be checked with `path_is_empty`.
+    /// `query_idx`: The target index for the query (already mapped from x_coord).
+    fn query_internal(&self, query_idx: usize) -> i64 {
+        let query_x_coord = self.get_x_coord_from_idx(query_idx);
+        let (mut node, mut range_l_idx, mut range_r_idx) =
+            (self.nodes.cursor(0), 0, self.domain_size - 1);
+        let mut min_val_at_query_x = INF_VAL;
+        loop {
+            min_val_at_query_x =
+                min_val_at_query_x.min(self.nodes.line_or_empty(node).eval_clamped(query_x_coord));
+
+            // Stop at the leaf, or if everything below was overwritten by `assign_range`
+            if range_l_idx == range_r_idx || self.children_cleared(node.node_v_idx) {
+                return min_val_at_query_x;
+            }
+
+            let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+            if query_idx <= range_m_idx {
+                (node, range_r_idx) = (self.nodes.child(node, false), range_m_idx);
+            } else {
+                (node, range_l_idx) = (self.nodes.child(node, true), range_m_idx + 1);
+            }
+        }
+    }
+
+    /// Queries the minimum y-value at a given `x_coord` from all lines added to the tree.
+    /// Returns `None` if `x_coord` is outside the tree's defined range, see [`OutOfRangePolicy`], or if no line
+    /// covers it.
+    /// Time complexity: O(log(domain_size)).
+    pub fn query(&self, x_coord: i64) -> Option<i64> {
+        if !self.contains(x_coord) {
+            self.out_of_range(x_coord);
+            return None;
+        }
+
+        let query_idx = self.get_idx_from_x_coord(x_coord);
+        if self.overflow_policy != OverflowPolicy::WideningI128 || self.mode != EnvelopeMode::Min {
+            return self
+                .query_line_internal(query_idx, x_coord)
+                .map(|(val, _)| val);
+        }
+
+        let ret = self.query_internal(query_idx);
+        if ret == INF_VAL && self.path_is_empty(query_idx) {
+            None
+        } else {
+            #[cfg(feature = "instrumentation")]
+            self.record_win(query_idx);
+            Some(ret)
+        }
+    }
+
+    /// Same as `query`, but also returns the line achieving the minimum.
+    /// Ties between different lines are resolved by the tie-break rule.
+    /// Time complexity: O(log(domain_size)).
+    pub fn query_line(&self, x_coord: i64) -> Option<(i64, Line)> {
+        if !self.contains(x_coord) {
+            self.out_of_range(x_coord);
+            return None;
+        }
+
+        self.query_line_internal(self.get_idx_from_x_coord(x_coord), x_coord)
+    }
+
+    /// Same as `query`, but also returns the insertion index of the line achieving the minimum, or the maximum in max
+    /// mode: the line added first has index 0, and each line passed to the tree gets the next index, as counted by
+    /// `len`. Ties between different lines are resolved by the tie-break rule, and copies of the same line may report
+    /// the index of any of them. Panics if the tree was not built with [`NodeStorage::Indexed`], which stores lines by
+    /// their index.
+    /// Time complexity: O(log(domain_size)).
+    pub fn query_argmin(&self, x_coord: i64) -> Option<(i64, usize)> {
+        if self.storage() != NodeStorage::Indexed {
+            panic!("LiChaoTree::query_argmin: lines are only numbered with NodeStorage::Indexed");
+        }
+        if !self.contains(x_coord) {
+            self.out_of_range(x_coord);
+            return None;
+        }
+
+        let query_idx = self.get_idx_from_x_coord(x_coord);
+        let entry = self.winning_entry(query_idx)?;
+        let val = self
+            .overflow_policy
+            .eval(&self.added_line(entry.line), x_coord)?;
+        #[cfg(feature = "instrumentation")]
+        self.record_win(query_idx);
+        Some((val, entry.id as usize))
+    }
+
+    /// Same as `query_line`, but fails with `LiChaoError::OutOfRange` instead of panicking.
+    pub fn try_query_line(&self, x_coord: i64) -> Result<Option<(i64, Line)>, LiChaoError> {
+        if !self.contains(x_coord) {
+            return Err(LiChaoError::OutOfRange { x_coord });
+        }
+        Ok(self.query_line(x_coord))
+    }
+
+    /// Returns the slope of the line achieving the envelope at `x_coord`, with ties resolved by the tie-break rule,
+    /// or `None` where the envelope is empty. The slopes on both sides of a breakpoint bound the subgradients of the
+    /// envelope there. Out-of-bounds x-coordinates are handled as in `query`.
+    /// Time complexity: O(log(domain_size)).
+    pub fn slope_at(&self, x_coord: i64) -> Option<i64> {
+        if !self.contains(x_coord) {
+            self.out_of_range(x_coord);
+            return None;
+        }
+        self.winner(self.get_idx_from_x_coord(x_coord))
+            .map(|line| line.m)
+    }
+
+    fn query_line_internal(&self, query_idx: usize, x_coord: i64) -> Option<(i64, Line)> {
+        let line = self.winner(query_idx)?;
+        let val = self.overflow_policy.eval(&line, x_coord)?;
+        #[cfg(feature = "instrumentation")]
+        self.record_win(query_idx);
+        Some((val, line))
+    }
+
+    /// Same as `query`, but fails with `LiChaoError::OutOfRange` instead of panicking on an x-coordinate outside the
+    /// tree's bounds.
+    /// Time complexity: O(log(domain_size)).
+    pub fn try_query(&self, x_coord: i64) -> Result<Option<i64>, LiChaoError> {
+        if !self.contains(x_coord) {
+            return Err(LiChaoError::OutOfRange { x_coord });
+        }
+        Ok(self.query(x_coord))
+    }
+
+    /// Same as `query`, but fails with `LiChaoError::Overflow` instead of saturating when the exact minimum does not
+    /// fit in an `i64`, and with `LiChaoError::OutOfRange` instead of panicking on an x-coordinate outside the tree.
+    /// Since lines are told apart from empty nodes exactly, a minimum of `i64::MAX` is returned as such, not as `None`.
+    /// Time complexity: O(log(domain_size)).
+    pub fn query_checked(&self, x_coord: i64) -> Result<Option<i64>, LiChaoError> {
+        if !self.contains(x_coord) {
+            return Err(LiChaoError::OutOfRange { x_coord });
+        }
+        let query_idx = self.get_idx_from_x_coord(x_coord);
+        match self
+            .query_wide(query_idx)
+            .map(|value| self.mode.orient_value(value))
+        {
+            None => Ok(None),
+            Some(value) => i64::try_from(value)
+                .map(Some)
+                .map_err(|_| LiChaoError::Overflow { x_coord, value }),
+        }
+    }
+
+    /// Queries every x-coordinate in `x_coords`, in order. Out-of-bounds x-coordinates are handled as in `query`.
+    /// Time complexity: O(x_coords.len() * log(domain_size)).
+    pub fn query_many(&self, x_coords: &[i64]) -> Vec<Option<i64>> {
+        let mut out = Vec::with_capacity(x_coords.len());
+        self.query_many_into(x_coords, &mut out);
+        out
+    }
+
+    /// Same as `query_many`, but writes into a caller-owned buffer, which is cleared first.
+    /// With the `simd` feature, several queries are evaluated at once with vector instructions where possible.
+    /// Does not allocate if `out` already has capacity for `x_coords.len()` results.
+    pub fn query_many_into(&self, x_coords: &[i64], out: &mut Vec<Option<i64>>) {
+        out.clear();
+        #[cfg(feature = "simd")]
+        if self.overflow_policy == OverflowPolicy::WideningI128 && self.mode == EnvelopeMode::Min {
+            return self.query_many_simd(x_coords, out);
+        }
+        out.extend(x_coords.iter().map(|&x_coord| self.query(x_coord)));
+    }
+
+    /// Returns the envelope as a function borrowing the tree. Calling it is equivalent to `query`.
+    pub fn as_fn(&self) -> impl Fn(i64) -> Option<i64> + '_ {
+        move |x_coord| self.query(x_coord)
+    }
+
+    /// Consumes the tree and returns the envelope as an owned, boxed function.
+    /// No further lines can be added once the tree has been turned into a function.
+    pub fn into_fn(self) -> Box<dyn Fn(i64) -> Option<i64> + Send + Sync> {
+        Box::new(move |x_coord| self.query(x_coord))
+    }
+
+    /// Node indices on the path from the root to the leaf for `query_idx`.
+    fn path(&self, query_idx: usize) -> impl Iterator<Item = usize> {
+        self.path_ranges(query_idx)
+            .map(|(node_v_idx, _, _)| node_v_idx)
+    }
+
+    /// Same as `path`, with the range of indices covered by each node, as `(node, first index, last index)`.
+    fn path_ranges(&self, query_idx: usize) -> impl Iterator<Item = (usize, usize, usize)> {
+        std::iter::successors(
+            Some((0, 0, self.domain_size - 1)),
+            move |&(node_v_idx, range_l_idx, range_r_idx)| {
+                if range_l_idx == range_r_idx || self.children_cleared(node_v_idx) {
+                    return None;
+                }
+                let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+                Some(if query_idx <= range_m_idx {
+                    (2 * node_v_idx + 1, range_l_idx, range_m_idx)
+                } else {
+                    (2 * node_v_idx + 2, range_m_idx + 1, range_r_idx)
+                })
+            },
+        )
+    }
+
+    /// Returns whether no line is stored on the path from the root to the leaf for `query_idx`.
+    pub(crate) fn path_is_empty(&self, query_idx: usize) -> bool {
+        self.path(query_idx)
+            .all(|node_v_idx| self.nodes.get(node_v_idx).is_none())
+    }
+
+    /// Line achieving the minimum at `query_idx`, or the maximum in max mode, as it was added.
+    /// Ties between different lines are resolved by the tie-break rule. Returns `None` if no line covers the index.
+    pub(crate) fn winner(&self, query_idx: usize) -> Option<Line> {
+        self.winning_entry(query_idx)
+            .map(|entry| self.added_line(entry.line))
+    }
+
+    /// Same as `winner`, with the line as stored and its id.
+    fn winning_entry(&self, query_idx: usize) -> Option<Entry> {
+        let x_coord = self.get_x_coord_from_idx(query_idx);
+        self.path(query_idx)
+            .filter_map(|node_v_idx| self.nodes.entry(node_v_idx))
+            .reduce(|best, entry| {
+                let (line_val, best_val) =
+                    (entry.line.eval_wide(x_coord), best.line.eval_wide(x_coord));
+                if line_val < best_val
+                    || (line_val == best_val && self.prefers(&entry.line, &best.line))
+                {
+                    entry
+                } else {
+                    best
+                }
+            })
+    }
+
+    /// Exact minimum at `query_idx` over the stored lines on the root-to-leaf path, evaluated in i128.
+    /// Returns `None` if no line covers the index.
+    fn query_wide(&self, query_idx: usize) -> Option<i128> {
+        let x = self.get_x_coord_from_idx(query_idx);
+        self.path(query_idx)
+            .filter_map(|node_v_idx| self.nodes.get(node_v_idx))
+            .map(|line| line.eval_wide(x))
+            .min()
+    }
+
+    /// Queries the envelope at `x_coord`, stopping early once the value is known to be at most `threshold`.
+    /// The upper levels of the tree act as a coarse tier: the minimum over a prefix of the root-to-leaf
+    /// path is already an upper bound on the envelope, so the walk ends as soon as that bound is good enough.
+    /// Returns `None` if the envelope is empty at `x_coord`. In max mode no prefix gives an upper bound, so the
+    /// value is always `Exact`.
+    /// Panics if `x_coord` is outside the tree's bounds.
+    /// Time complexity: O(log(domain_size)), often less when `threshold` is loose.
+    pub fn query_bound(&self, x_coord: i64, threshold: i64) -> Option<Bound> {
+        if !self.contains(x_coord) {
+            self.out_of_range(x_coord);
+            return None;
+        }
+        if self.mode == EnvelopeMode::Max {
+            return self.query(x_coord).map(Bound::Exact);
+        }
+        let query_idx = self.get_idx_from_x_coord(x_coord);
+        let mut best = None;
+        let mut path = self.path(query_idx).peekable();
+        while let Some(node_v_idx) = path.next() {
+            if let Some(line) = self.nodes.get(node_v_idx) {
+                let val = line.eval_clamped(x_coord);
+                best = Some(best.map_or(val, |best: i64| best.min(val)));
+            }
+            if let Some(best) = best
+                && best <= threshold
+                && path.peek().is_some()
+            {
+                return Some(Bound::AtMost(best));
+            }
+        }
+        best.map(Bound::Exact)
+    }
+
+    /// Classifies the point `(x_coord, y)` against the envelope at `x_coord`, using exact arithmetic.
+    /// An empty envelope lies at infinity, so every point is `Below` it, or at negative infinity in max mode,
+    /// so every point is `Above` it.
+    /// Panics if `x_coord` is outside the tree's bounds, where the envelope is empty under `OutOfRangePolicy::Ignore`.
+    /// Time complexity: O(log(domain_size)).
+    pub fn classify_point(&self, x_coord: i64, y: i64) -> PointPosition {
+        let env = if self.contains(x_coord) {
+            let query_idx = self.get_idx_from_x_coord(x_coord);
+            self.query_wide(query_idx)
+                .map(|env| self.mode.orient_value(env))
+        } else {
+            self.out_of_range(x_coord);
+            None
+        };
+        match env {
+            Some(env) if (y as i128) > env => PointPosition::Above,
+            Some(env) if (y as i128) == env => PointPosition::On,
+            Some(_) => PointPosition::Below,
+            None if self.mode == EnvelopeMode::Max => PointPosition::Above,
+            None => PointPosition::Below,
+        }
+    }
+
+    /// Finds the smallest x-coordinate in `range` at which the envelope is at most `y`, or at least `y` in max mode.
+    /// X-coordinates where the envelope is empty never match. Ranges sticking out of the domain are handled as in
+    /// `add_segment`, see [`OutOfRangePolicy`], and reversed ranges panic.
+    ///
+    /// Descends the tree from left to right, checking each node's line over its whole range, and skips subtrees that
+    /// hold no line or start at or after the best x-coordinate found so far.
+    /// Time complexity: O((1 + s) * log(domain_size)) for s nodes holding a line over `range`.
+    pub fn first_x_at_most(&self, y: i64, range: RangeInclusive<i64>) -> Option<i64> {
+        let (query_l_idx, query_r_idx) = self.segment_idx_range(*range.start(), *range.end())?;
+        let mut end = query_r_idx + 1;
+        let threshold = (0, self.mode.orient_value(y as i128), None);
+        self.first_at_most(threshold, 0, 0, self.domain_size - 1, query_l_idx, &mut end);
+        (end <= query_r_idx).then(|| self.get_x_coord_from_idx(end))
+    }
+
+    /// Lowers `end` to the smallest index in `[query_l_idx, end)`, within the subtree of `node_v_idx`, at which a
+    /// stored line is at most the threshold line `y = threshold.0 * x + threshold.1`. Nodes storing exactly the line
+    /// `threshold.2` are not checked.
+    fn first_at_most(
+        &self,
+        threshold: (i64, i128, Option<Line>),
+        node_v_idx: usize,
+        range_l_idx: usize,
+        range_r_idx: usize,
+        query_l_idx: usize,
+        end: &mut usize,
+    ) {
+        let query_l_idx = query_l_idx.max(range_l_idx);
+        if query_l_idx >= *end
+            || query_l_idx > range_r_idx
+            || !self.nodes.subtree_may_hold(node_v_idx)
+        {
+            return;
+        }
+        let query_r_idx = range_r_idx.min(*end - 1);
+
+        if let Some(line) = self
+            .nodes
+            .get(node_v_idx)
+            .filter(|&line| Some(line) != threshold.2)
+        {
+            let at_most = |idx: usize| {
+                let x_coord = self.get_x_coord_from_idx(idx);
+                line.eval_wide(x_coord) <= threshold.0 as i128 * x_coord as i128 + threshold.1
+            };
+            // Both are linear in x, so the indices where the line is at most the threshold are a prefix or a suffix
+            if at_most(query_l_idx) {
+                *end = query_l_idx;
+                return;
+            }
+            if line.m < threshold.0 && at_most(query_r_idx) {
+                let (mut lo, mut hi) = (query_l_idx, query_r_idx);
+                while hi - lo > 1 {
+                    let mid = lo + (hi - lo) / 2;
+                    if at_most(mid) {
+                        hi = mid;
+                    } else {
+                        lo = mid;
+                    }
+                }
+                *end = hi;
+            }
+        }
+        if range_l_idx == range_r_idx || self.children_cleared(node_v_idx) {
+            return;
+        }
+
+        let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+        self.first_at_most(
+            threshold,
+            2 * node_v_idx + 1,
+            range_l_idx,
+            range_m_idx,
+            query_l_idx,
+            end,
+        );
+        self.first_at_most(
+            threshold,
+            2 * node_v_idx + 2,
+            range_m_idx + 1,
+            range_r_idx,
+            query_l_idx,
+            end,
+        );
+    }
+
+    /// Computes the exact sum of the envelope over the x-coordinates of the domain in `[x_l, x_r]`, or `None` if the
+    /// envelope is empty at one of them. Ranges sticking out of the domain are clipped to it or panic, as in
+    /// `add_segment`, see [`OutOfRangePolicy`], and reversed ranges panic. Panics if the sum overflows `i128`.
+    ///
+    /// The range is walked piece by piece: the line achieving the envelope at the start of a piece stays the envelope
+    /// until a stored line drops below it, found with the search of `first_x_at_most`, or until its node ends. On the
+    /// integers each piece is summed in closed form, while grids of `with_coordinate_map` sum each point.
+    /// Time complexity: O((pieces * log(domain_size) + s) * log(domain_size)) for s nodes holding a line over the
+    /// range, see `first_x_at_most`.
+    pub fn integral(&self, x_l: i64, x_r: i64) -> Option<i128> {
+        let overflow = || {
+            panic!(
+                "LiChaoTree::integral: the sum over [{}, {}] overflows i128",
+                x_l, x_r
+            )
+        };
+        let Some((query_l_idx, query_r_idx)) = self.segment_idx_range(x_l, x_r) else {
+            return Some(0);
+        };
+        let mut sum: i128 = 0;
+        let mut piece_l_idx = query_l_idx;
+        while piece_l_idx <= query_r_idx {
+            let x_coord = self.get_x_coord_from_idx(piece_l_idx);
+            // Lowest stored line at the start of the piece, and the last index its node covers
+            let (line, node_r_idx) = self
+                .path_ranges(piece_l_idx)
+                .filter_map(|(node_v_idx, _, range_r_idx)| {
+                    Some((self.nodes.get(node_v_idx)?, range_r_idx))
+                })
+                .min_by_key(|(line, _)| line.eval_wide(x_coord))?;
+
+            let mut end = node_r_idx.min(query_r_idx) + 1;
+            let below = (line.m, line.c as i128 - 1, None);
+            self.first_at_most(below, 0, 0, self.domain_size - 1, piece_l_idx + 1, &mut end);
+            let piece_sum = self
+                .sum_line(&line, piece_l_idx, end - 1)
+                .unwrap_or_else(overflow);
+            sum = sum.checked_add(piece_sum).unwrap_or_else(overflow);
+            piece_l_idx = end;
+        }
+        // Stored lines are negated in max mode
+        Some(self.mode.orient_value(sum))
+    }
+
+    /// Exact sum of `line` over the x-coordinates of the indices in `[first_idx, last_idx]`, or `None` on overflow.
+    fn sum_line(&self, line: &Line, first_idx: usize, last_idx: usize) -> Option<i128> {
+        if self.coord_map.is_some() {
+            return (first_idx..=last_idx).try_fold(0i128, |sum, idx| {
+                sum.checked_add(line.eval_wide(self.get_x_coord_from_idx(idx)))
+            });
+        }
+        let x_first = self.get_x_coord_from_idx(first_idx) as i128;
+        let x_last = self.get_x_coord_from_idx(last_idx) as i128;
+        let count = x_last - x_first + 1;
+        // Sum of the x-coordinates, halving whichever factor is even
+        let x_sum = if count % 2 == 0 {
+            (count / 2).checked_mul(x_first + x_last)?
+        } else {
+            count.checked_mul((x_first + x_last) / 2)?
+        };
+        (line.m as i128)
+            .checked_mul(x_sum)?
+            .checked_add((line.c as i128).checked_mul(count)?)
+    }
+
+    /// Returns whether both trees have the same envelope at every x-coordinate in both domains, comparing exact
+    /// values. Which lines are stored where, and which of several equal lines wins, do not matter.
+    /// Trees whose domains do not overlap are trivially equal.
+    /// Time complexity: O(domain_size * log(domain_size)).
+    pub fn envelope_eq(&self, other: &LiChaoTree) -> bool {
+        let other_x_min = other.get_x_coord_from_idx(0);
+        let other_x_max = other.get_x_coord_from_idx(other.domain_size - 1);
+        let Some((l_idx, r_idx)) = self.idx_range(other_x_min, other_x_max) else {
+            return true;
+        };
+        (l_idx..=r_idx).all(|idx| {
+            let x_coord = self.get_x_coord_from_idx(idx);
+            !other.contains(x_coord)
+                || self.query_wide(idx).map(|val| self.mode.orient_value(val))
+                    == other
+                        .query_wide(other.get_idx_from_x_coord(x_coord))
+                        .map(|val| other.mode.orient_value(val))
+        })
+    }
+}
+
+impl LiChaoTree {
+    /// Returns the envelope as an explicit piecewise-linear function: maximal intervals of x-coordinates on which the
+    /// same line achieves the minimum, or the maximum in max mode, in increasing order of x. The `x_l` of each piece
+    /// is a breakpoint of the envelope. X-coordinates where the envelope is empty, which can only happen with
+    /// segments or `assign_range`, are not covered, so a line appearing on both sides of such a gap gives two pieces.
+    /// For trees built with `with_coordinate_map`, pieces only describe the envelope on the grid points.
+    ///
+    /// The domain is walked piece by piece as in `integral`: the winner at the start of a piece stays the winner until
+    /// its node ends or another stored line reaches it, found with the search of `first_x_at_most`. Where that line
+    /// only ties and loses the tie-break, the search resumes after it. Empty stretches are skipped with one search.
+    /// Time complexity: O((pieces * log(domain_size) + s) * log(domain_size)) for s nodes holding a line.
+    pub fn to_piecewise(&self) -> Vec<EnvelopePiece> {
+        let mut pieces: Vec<EnvelopePiece> = Vec::new();
+        // Matches every stored line, to find where the envelope is not empty
+        let any_line = (0, i128::MAX, None);
+        let mut piece_l_idx = 0;
+        loop {
+            let mut next_idx = self.domain_size;
+            self.first_at_most(
+                any_line,
+                0,
+                0,
+                self.domain_size - 1,
+                piece_l_idx,
+                &mut next_idx,
+            );
+            if next_idx == self.domain_size {
+                return pieces;
+            }
+            piece_l_idx = next_idx;
+            let entry = self.winning_entry(piece_l_idx).unwrap();
+            // Last index covered by a node on the path storing the winner
+            let node_r_idx = self
+                .path_ranges(piece_l_idx)
+                .filter(|&(node_v_idx, _, _)| self.nodes.get(node_v_idx) == Some(entry.line))
+                .map(|(_, _, range_r_idx)| range_r_idx)
+                .max()
+                .unwrap();
+
+            let reached = (entry.line.m, entry.line.c as i128, Some(entry.line));
+            let mut end = node_r_idx + 1;
+            let mut search_l_idx = piece_l_idx + 1;
+            while search_l_idx <= node_r_idx {
+                self.first_at_most(reached, 0, 0, self.domain_size - 1, search_l_idx, &mut end);
+                if end > node_r_idx
+                    || self.winning_entry(end).map(|other| other.line) != Some(entry.line)
+                {
+                    break;
+                }
+                (search_l_idx, end) = (end + 1, node_r_idx + 1);
+            }
+
+            let line = self.added_line(entry.line);
+            let (x_l, x_r) = (
+                self.get_x_coord_from_idx(piece_l_idx),
+                self.get_x_coord_from_idx(end - 1),
+            );
+            match pieces.last_mut() {
+                Some(last)
+                    if last.line == line
+                        && self.get_idx_from_x_coord(last.x_r) + 1 == piece_l_idx =>
+                {
+                    last.x_r = x_r
+                }
+                _ => pieces.push(EnvelopePiece { x_l, x_r, line }),
+            }
+            piece_l_idx = end;
+        }
+    }
+
+    /// Returns the x-intervals on which `probe` is strictly better than the envelope, that is below it, or above it in
+    /// max mode, in increasing order of x. These are the x-coordinates where `probe` would become the envelope if it
+    /// were added, so the endpoints are where it crosses the envelope. X-coordinates where the envelope is empty are
+    /// included. Ties do not count, whatever the tie-break rule. The tree is not modified.
+    /// Time complexity: O(domain_size * log(domain_size)).
+    pub fn improving_intervals(&self, probe: Line) -> Vec<RangeInclusive<i64>> {
+        let beats = |line: &Line, idx: usize| {
+            let x_coord = self.get_x_coord_from_idx(idx);
+            match self.mode {
+                EnvelopeMode::Min => probe.eval_wide(x_coord) < line.eval_wide(x_coord),
+                EnvelopeMode::Max => probe.eval_wide(x_coord) > line.eval_wide(x_coord),
+            }
+        };
+        // Maximal runs of indices won by `probe`, as (first, last)
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        let mut push = |first: usize, last: usize| match runs.last_mut() {
+            Some((_, prev_last)) if *prev_last + 1 == first => *prev_last = last,
+            _ => runs.push((first, last)),
+        };
+        let mut next_idx = 0;
+        for piece in self.to_piecewise() {
+            let idx_l = self.get_idx_from_x_coord(piece.x_l);
+            let idx_r = self.get_idx_from_x_coord(piece.x_r);
+            if next_idx < idx_l {
+                push(next_idx, idx_l - 1);
+            }
+            next_idx = idx_r + 1;
+
+            // The difference between `probe` and the piece's line is linear, so `probe` wins on all of the piece,
+            // none of it, or a prefix or suffix whose boundary is found by binary search.
+            let (wins_l, wins_r) = (beats(&piece.line, idx_l), beats(&piece.line, idx_r));
+            if wins_l != wins_r {
+                let (mut lo, mut hi) = (idx_l, idx_r);
+                while hi - lo > 1 {
+                    let mid = lo + (hi - lo) / 2;
+                    if beats(&piece.line, mid) == wins_l {
+                        lo = mid;
+                    } else {
+                        hi = mid;
+                    }
+                }
+                if wins_l {
+                    push(idx_l, lo)
+                } else {
+                    push(hi, idx_r)
+                }
+            } else if wins_l {
+                push(idx_l, idx_r);
+            }
+        }
+        if next_idx < self.domain_size {
+            push(next_idx, self.domain_size - 1);
+        }
+        runs.into_iter()
+            .map(|(first, last)| self.get_x_coord_from_idx(first)..=self.get_x_coord_from_idx(last))
+            .collect()
+    }
+}
+
+/// Prints the domain and the envelope as `(x-range, line)` pieces rather than the raw node array.
+/// Building the pieces queries every x-coordinate, so this is meant for debugging small trees.
+impl fmt::Debug for LiChaoTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let x_max_coord = self.get_x_coord_from_idx(self.domain_size - 1);
+        let pieces: Vec<_> = self
+            .to_piecewise()
+            .into_iter()
+            .map(|piece| (piece.x_l..=piece.x_r, piece.line))
+            .collect();
+        f.debug_struct("LiChaoTree")
+            .field("domain", &(self.x_min_coord..=x_max_coord))
+            .field("envelope", &pieces)
+            .finish()
+    }
+}
+
+/// Prints the envelope one piece per line, as `[x_l, x_r]: y = mx + c`.
+impl fmt::Display for LiChaoTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pieces = self.to_piecewise();
+        if pieces.is_empty() {
+            return write!(f, "empty envelope");
+        }
+        for (i, EnvelopePiece { x_l, x_r, line }) in pieces.into_iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            let sign = if line.c < 0 { '-' } else { '+' };
+            write!(
+                f,
+                "[{}, {}]: y = {}x {} {}",
+                x_l,
+                x_r,
+                line.m,
+                sign,
+                line.c.unsigned_abs()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_simple_lines() {
+        let mut tree = LiChaoTree::new(0, 10);
+
+        tree.add_line(Line::new(2, 3));
+        // 2*0+3 = 3
+        assert_eq!(tree.query(0), Some(3));
+        // 2*5+3 = 13
+        assert_eq!(tree.query(5), Some(13));
+        // 2*10+3 = 23
+        assert_eq!(tree.query(10), Some(23));
+
+        tree.add_line(Line::new(-1, 10));
+        // min(3, 10) = 3
+        assert_eq!(tree.query(0), Some(3));
+        // min(13, 5) = 5
+        assert_eq!(tree.query(5), Some(5));
+        // min(23, 0) = 0
+        assert_eq!(tree.query(10), Some(0));
+
+        // min(7,8) = 7
+        assert_eq!(tree.query(2), Some(7));
+        // min(9,7) = 7
+        assert_eq!(tree.query(3), Some(7));
+    }
+
+    #[test]
+    fn test_single_point_range() {
+        let mut tree = LiChaoTree::new(5, 5);
+        tree.add_line(Line::new(10, -5));
+        assert_eq!(tree.query(5), Some(45));
+
+        tree.add_line(Line::new(1, 4));
+        assert_eq!(tree.query(5), Some(9));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_out_of_bounds_query() {
+        let tree = LiChaoTree::new(0, 10);
+        let _q0 = tree.query(-1);
+        let _q1 = tree.query(11);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_range_panic() {
+        let _tree = LiChaoTree::new(10, 0);
+    }
+
+    #[test]
+    fn test_try_new() {
+        let tree = LiChaoTree::try_new(-3, 3).unwrap();
+        assert!(tree.contains(-3) && tree.contains(3) && !tree.contains(4));
+        assert!(LiChaoTree::try_new(7, 7).is_ok());
+        assert_eq!(
+            LiChaoTree::try_new(3, -3).err(),
+            Some(LiChaoError::InvalidDomain {
+                x_min_coord: 3,
+                x_max_coord: -3
+            })
+        );
+        assert!(matches!(
+            LiChaoTree::try_new(i64::MIN, i64::MAX),
+            Err(LiChaoError::DomainTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn test_memory_budget() {
+        let bytes = NodeArray::max_bytes(31, NodeLayout::Heap, NodeStorage::Inline).unwrap();
+        let mut tree = LiChaoTree::with_memory_budget(0, 10, bytes).unwrap();
+        tree.add_line(Line::new(1, 1));
+        assert_eq!(tree.query(3), Some(4));
+
+        assert_eq!(
+            LiChaoTree::with_memory_budget(0, 10, bytes - 1).err(),
+            Some(LiChaoError::MemoryBudgetExceeded {
+                required_bytes: bytes,
+                budget_bytes: bytes - 1
+            })
+        );
+        assert!(matches!(
+            LiChaoTree::with_memory_budget(10, 0, bytes),
+            Err(LiChaoError::InvalidDomain { .. })
+        ));
+        assert!(matches!(
+            LiChaoTree::with_memory_budget(i64::MIN, i64::MAX, usize::MAX),
+            Err(LiChaoError::DomainTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn test_lazy_node_allocation() {
+        let mut tree = LiChaoTree::new(0, (1 << 24) - 1);
+        let full = tree.nodes.len() * std::mem::size_of::<Line>();
+        assert!(tree.nodes.bytes_allocated() < full / 50);
+
+        let mut rng = StdRng::seed_from_u64(302);
+        let lines: Vec<Line> = (0..20)
+            .map(|_| {
+                Line::new(
+                    rng.random_range(-1000..=1000),
+                    rng.random_range(-1 << 30..=1 << 30),
+                )
+            })
+            .collect();
+        for &line in &lines {
+            tree.add_line(line);
+        }
+        for _ in 0..1000 {
+            let x = rng.random_range(0..1 << 24);
+            assert_eq!(tree.query(x), lines.iter().map(|line| line.eval(x)).min());
+        }
+        // Each insertion writes at most one node per level
+        assert!(tree.nodes.bytes_allocated() < full / 10);
+    }
+
+    #[test]
+    fn test_shrink_to_fit() {
+        let mut rng = StdRng::seed_from_u64(308);
+        let size = 100_000;
+        let mut tree = LiChaoTree::new(0, size - 1);
+        for _ in 0..300 {
+            let line = Line::new(
+                rng.random_range(-100..=100),
+                rng.random_range(-1_000_000..=1_000_000),
+            );
+            let x_l = rng.random_range(0..size);
+            let x_r = rng.random_range(x_l..(x_l + 1000).min(size));
+            match rng.random_range(0..4) {
+                0 => {
+                    tree.add_line(line);
+                }
+                1 => {
+                    tree.add_segment(line, x_l, x_r);
+                }
+                2 => tree.assign_range(x_l, x_r, line),
+                _ => {
+                    tree.add_line_rollbackable(line);
+                }
+            }
+        }
+        let mut expected = tree.clone();
+        let bytes = tree.stats().bytes_allocated;
+        tree.shrink_to_fit();
+        assert!(tree.stats().bytes_allocated < bytes);
+        let x_coords: Vec<i64> = (0..2000).map(|_| rng.random_range(0..size)).collect();
+        assert_eq!(tree.query_many(&x_coords), expected.query_many(&x_coords));
+
+        // Rolled back insertions leave empty nodes, whose chunks can then be freed
+        let depth = tree.rollback_depth();
+        tree.rollback(depth);
+        expected.rollback(depth);
+        let bytes = tree.stats().bytes_allocated;
+        tree.shrink_to_fit();
+        assert!(tree.stats().bytes_allocated <= bytes);
+        assert_eq!(tree.query_many(&x_coords), expected.query_many(&x_coords));
+        for tree in [&mut tree, &mut expected] {
+            tree.assign_range(0, size / 2, Line::new(1, 0));
+            tree.add_line(Line::new(-1, size));
+        }
+        assert_eq!(tree.query_many(&x_coords), expected.query_many(&x_coords));
+
+        tree.clear();
+        tree.shrink_to_fit();
+        assert_eq!(tree.stats().bytes_allocated, 0);
+        tree.add_line(Line::new(2, 1));
+        assert_eq!(tree.query(3), Some(7));
+    }
+
+    #[test]
+    fn test_blocked_layout() {
+        let mut rng = StdRng::seed_from_u64(306);
+        for size in [1, 2, 63, 64, 100, 5000] {
+            let mut heap = LiChaoTree::new(0, size - 1);
+            let mut blocked = LiChaoTreeBuilder::new(0, size - 1)
+                .layout(NodeLayout::Blocked)
+                .build()
+                .unwrap();
+            assert_eq!(blocked.layout(), NodeLayout::Blocked);
+            for _ in 0..200 {
+                let line = Line::new(
+                    rng.random_range(-100..=100),
+                    rng.random_range(-10_000..=10_000),
+                );
+                let x_l = rng.random_range(0..size);
+                let x_r = rng.random_range(x_l..size);
+                match rng.random_range(0..4) {
+                    0 => assert_eq!(heap.add_line(line), blocked.add_line(line)),
+                    1 => assert_eq!(
+                        heap.add_segment(line, x_l, x_r),
+                        blocked.add_segment(line, x_l, x_r)
+                    ),
+                    2 => {
+                        heap.assign_range(x_l, x_r, line);
+                        blocked.assign_range(x_l, x_r, line);
+                    }
+                    _ => {
+                        heap.add_line_rollbackable(line);
+                        blocked.add_line_rollbackable(line);
+                        if rng.random_bool(0.5) {
+                            heap.rollback(1);
+                            blocked.rollback(1);
+                        }
+                    }
+                }
+            }
+            assert!((0..size).all(|x| heap.query(x) == blocked.query(x)));
+            assert_eq!(heap.stats().occupied_nodes, blocked.stats().occupied_nodes);
+
+            blocked.reset_domain(-size, size);
+            blocked.add_line(Line::new(1, 0));
+            assert_eq!(blocked.query(-size), Some(-size));
+        }
+    }
+
+    #[test]
+    fn test_tight_node_array() {
+        let mut rng = StdRng::seed_from_u64(301);
+        for size in 1..=70i64 {
+            let mut tree = LiChaoTree::new(0, size - 1);
+            assert_eq!(
+                tree.nodes.len(),
+                2 * (size as usize).next_power_of_two() - 1
+            );
+            let mut brute = vec![None::<i64>; size as usize];
+            for _ in 0..30 {
+                let line = Line::new(rng.random_range(-10..=10), rng.random_range(-100..=100));
+                let x_l = rng.random_range(0..size);
+                let x_r = rng.random_range(x_l..size);
+                match rng.random_range(0..3) {
+                    0 => {
+                        tree.add_line(line);
+                        for (x, y) in brute.iter_mut().enumerate() {
+                            *y =
+                                Some(y.map_or(line.eval(x as i64), |y| y.min(line.eval(x as i64))));
+                        }
+                    }
+                    1 => {
+                        tree.add_segment(line, x_l, x_r);
+                        for x in x_l..=x_r {
+                            let y = &mut brute[x as usize];
+                            *y = Some(y.map_or(line.eval(x), |y| y.min(line.eval(x))));
+                        }
+                    }
+                    _ => {
+                        tree.assign_range(x_l, x_r, line);
+                        for x in x_l..=x_r {
+                            brute[x as usize] = Some(line.eval(x));
+                        }
+                    }
+                }
+            }
+            for (x, y) in brute.iter().enumerate() {
+                assert_eq!(tree.query(x as i64), *y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_all_same_lines() {
+        let mut tree = LiChaoTree::new(0, 100);
+        let line = Line::new(1, 1);
+        tree.add_line(line);
         tree.add_line(line);
         tree.add_line(line);
         for i in 0..=100 {
@@ -255,61 +2827,1983 @@ mod tests {
     }
 
     #[test]
-    fn test_horizontal_lines() {
+    fn test_horizontal_lines() {
+        let mut tree = LiChaoTree::new(-10, 10);
+        tree.add_line(Line::new(0, 5));
+        assert_eq!(tree.query(0), Some(5));
+        assert_eq!(tree.query(-10), Some(5));
+        assert_eq!(tree.query(10), Some(5));
+
+        tree.add_line(Line::new(0, 2));
+        assert_eq!(tree.query(0), Some(2));
+        assert_eq!(tree.query(5), Some(2));
+
+        tree.add_line(Line::new(0, 10));
+        assert_eq!(tree.query(0), Some(2));
+    }
+
+    #[test]
+    fn test_steeper_lines_crossing_over() {
+        let mut tree = LiChaoTree::new(0, 20);
+        // Line 1: y = -10x + 100
+        // Line 2: y = x
+        // Intersection: -10x + 100 = x => 11x = 100 => x approx 9.09
+        let l1 = Line::new(-10, 100);
+        let l2 = Line::new(1, 0);
+        tree.add_line(l1);
+        tree.add_line(l2);
+
+        assert_eq!(tree.query(0), Some(l2.eval(0)));
+        assert_eq!(tree.query(5), Some(l2.eval(5)));
+        assert_eq!(tree.query(9), Some(l2.eval(9)));
+
+        assert_eq!(tree.query(9), Some(9));
+
+        assert_eq!(tree.query(10), Some(0));
+        assert_eq!(tree.query(15), Some(l1.eval(15)));
+        assert_eq!(tree.query(20), Some(l1.eval(20)));
+    }
+    #[test]
+    fn test_large_coordinates_and_values() {
+        let mut tree = LiChaoTree::new(-1000, 1000);
+
+        // y = 10^6 * x + 5 * 10^11
+        let line1 = Line::new(1_000_000, 500_000_000_000);
+        tree.add_line(line1);
+        assert_eq!(tree.query(0), Some(500_000_000_000));
+        assert_eq!(tree.query(1000), Some(line1.eval(1000))); // 501 * 10^9
+        assert_eq!(tree.query(-1000), Some(line1.eval(-1000))); // 501 * 10^9
+
+        // y = -2*10^6 * x + 6 * 10^11
+        let line2 = Line::new(-2_000_000, 600_000_000_000);
+        tree.add_line(line2);
+
+        assert_eq!(tree.query(0), Some(500_000_000_000));
+        assert_eq!(tree.query(-1000), Some(line1.eval(-1000)));
+        assert_eq!(tree.query(1000), Some(line1.eval(1000)));
+        assert_eq!(tree.query(100), Some(line1.eval(100)));
+    }
+
+    #[test]
+    fn test_add_line_reports_change() {
+        let mut tree = LiChaoTree::new(0, 10);
+        assert!(tree.add_line(Line::new(0, 5)));
+        // Dominated everywhere on the domain
+        assert!(!tree.add_line(Line::new(0, 6)));
+        assert!(!tree.add_line(Line::new(0, 5)));
+        // Better only near x = 10
+        assert!(tree.add_line(Line::new(1, -6)));
+    }
+
+    #[test]
+    fn test_add_lines_report() {
+        let mut tree = LiChaoTree::new(0, 10);
+        let report = tree.add_lines(&[
+            Line::new(0, 5),
+            Line::new(0, 6),
+            Line::new(1, -6),
+            Line::new(0, 5),
+        ]);
+        assert_eq!(report.kept, 2);
+        assert_eq!(report.dominated, vec![1, 3]);
+        assert_eq!(tree.query(0), Some(-6));
+        assert_eq!(tree.query(10), Some(4));
+    }
+
+    #[test]
+    fn test_bulk_ops_reuse_buffers() {
+        let mut tree = LiChaoTree::new(0, 10);
+        let mut report = BulkReport::default();
+        tree.add_lines_into(
+            &[Line::new(0, 5), Line::new(0, 6), Line::new(0, 7)],
+            &mut report,
+        );
+        assert_eq!(report.dominated, vec![1, 2]);
+        let capacity = report.dominated.capacity();
+        tree.add_lines_into(&[Line::new(1, -6), Line::new(0, 8)], &mut report);
+        assert_eq!(report.kept, 1);
+        assert_eq!(report.dominated, vec![1]);
+        assert_eq!(report.dominated.capacity(), capacity);
+
+        let mut out = Vec::with_capacity(3);
+        tree.query_many_into(&[0, 10, 5], &mut out);
+        assert_eq!(out, vec![Some(-6), Some(4), Some(-1)]);
+        assert_eq!(tree.query_many(&[10]), vec![Some(4)]);
+        tree.query_many_into(&[], &mut out);
+        assert!(out.is_empty());
+        assert_eq!(out.capacity(), 3);
+    }
+
+    #[test]
+    fn test_classify_point() {
+        let mut tree = LiChaoTree::new(0, 10);
+        assert_eq!(tree.classify_point(3, i64::MAX), PointPosition::Below);
+
+        tree.add_line(Line::new(2, 3));
+        tree.add_line(Line::new(-1, 10));
+        assert_eq!(tree.classify_point(5, 5), PointPosition::On);
+        assert_eq!(tree.classify_point(5, 6), PointPosition::Above);
+        assert_eq!(tree.classify_point(5, 4), PointPosition::Below);
+    }
+
+    #[test]
+    fn test_classify_point_beyond_saturation() {
+        let mut tree = LiChaoTree::new(i64::MAX - 10, i64::MAX);
+        // Saturates to i64::MIN everywhere, but the true value is far smaller
+        tree.add_line(Line::new(-2, 0));
+        assert_eq!(tree.query(i64::MAX), Some(i64::MIN));
+        assert_eq!(
+            tree.classify_point(i64::MAX, i64::MIN),
+            PointPosition::Above
+        );
+    }
+
+    #[test]
+    fn test_envelope_as_fn() {
+        fn sum_over(f: impl Fn(i64) -> Option<i64>, xs: std::ops::RangeInclusive<i64>) -> i64 {
+            xs.filter_map(f).sum()
+        }
+
+        let mut tree = LiChaoTree::new(0, 10);
+        tree.add_line(Line::new(2, 3));
+        tree.add_line(Line::new(-1, 10));
+        let expected: i64 = (0..=10).map(|x| tree.query(x).unwrap()).sum();
+        assert_eq!(sum_over(tree.as_fn(), 0..=10), expected);
+
+        let owned = tree.into_fn();
+        assert_eq!(owned(5), Some(5));
+        assert_eq!(sum_over(owned, 0..=10), expected);
+    }
+
+    #[test]
+    fn test_query_bound() {
+        let mut tree = LiChaoTree::new(0, 100);
+        assert_eq!(tree.query_bound(50, 0), None);
+
+        tree.add_line(Line::new(0, 10));
+        tree.add_line(Line::new(1, -95));
+        // The root holds the line winning at the midpoint, which already beats the loose threshold
+        assert_eq!(tree.query_bound(50, 100), Some(Bound::AtMost(-45)));
+        assert_eq!(tree.query_bound(100, 100), Some(Bound::AtMost(5)));
+        assert_eq!(tree.query_bound(100, -100), Some(Bound::Exact(5)));
+
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..100 {
+            tree.add_line(Line::new(
+                rng.random_range(-10..=10),
+                rng.random_range(-100..=100),
+            ));
+        }
+        for x in 0..=100 {
+            let exact = tree.query(x).unwrap();
+            let threshold = rng.random_range(-200..=200);
+            match tree.query_bound(x, threshold).unwrap() {
+                Bound::Exact(val) => assert_eq!(val, exact),
+                Bound::AtMost(val) => assert!(exact <= val && val <= threshold),
+            }
+        }
+    }
+
+    #[test]
+    fn test_tie_break_values_unaffected() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let lines: Vec<Line> = (0..300)
+            .map(|_| Line::new(rng.random_range(-5..=5), rng.random_range(-20..=20)))
+            .collect();
+        let mut trees = [
+            TieBreak::KeepExisting,
+            TieBreak::SmallerSlope,
+            TieBreak::LargerSlope,
+        ]
+        .map(|rule| {
+            let mut tree = LiChaoTree::new(-10, 10);
+            tree.set_tie_break(rule);
+            tree
+        });
+        for (idx, line) in lines.iter().enumerate() {
+            for tree in &mut trees {
+                tree.add_line(*line);
+            }
+            for x in -10..=10 {
+                let oracle = lines[..=idx].iter().map(|l| l.eval(x)).min();
+                for tree in &trees {
+                    assert_eq!(tree.query(x), oracle);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_assign_range() {
+        let mut tree = LiChaoTree::new(0, 10);
+        tree.add_line(Line::new(0, 1));
+        tree.assign_range(3, 6, Line::new(0, 5));
+        assert_eq!(tree.query(2), Some(1));
+        assert_eq!(tree.query(3), Some(5));
+        assert_eq!(tree.query(6), Some(5));
+        assert_eq!(tree.query(7), Some(1));
+
+        tree.add_line(Line::new(1, 0));
+        assert_eq!(tree.query(4), Some(4));
+        assert_eq!(tree.query(5), Some(5));
+    }
+
+    #[test]
+    fn test_assign_range_stress() {
+        let mut rng = StdRng::seed_from_u64(31);
+        let mut tree = LiChaoTree::new(-40, 40);
+        let mut oracle: Vec<Option<i64>> = vec![None; 81];
+        for _ in 0..2000 {
+            let line = Line::new(rng.random_range(-20..=20), rng.random_range(-200..=200));
+            if rng.random_bool(0.3) {
+                let l = rng.random_range(-40..=40);
+                let r = rng.random_range(l..=40);
+                tree.assign_range(l, r, line);
+                for x in l..=r {
+                    oracle[(x + 40) as usize] = Some(line.eval(x));
+                }
+            } else {
+                tree.add_line(line);
+                for x in -40..=40 {
+                    let val = &mut oracle[(x + 40) as usize];
+                    *val = Some(val.map_or(line.eval(x), |v| v.min(line.eval(x))));
+                }
+            }
+            let x = rng.random_range(-40..=40);
+            assert_eq!(tree.query(x), oracle[(x + 40) as usize]);
+        }
+        for x in -40..=40 {
+            assert_eq!(tree.query(x), oracle[(x + 40) as usize]);
+        }
+    }
+
+    #[test]
+    fn test_add_segment() {
+        let mut tree = LiChaoTree::new(0, 10);
+        assert!(tree.add_segment(Line::new(1, 0), 2, 5));
+        assert_eq!(tree.query(1), None);
+        assert_eq!(tree.query(2), Some(2));
+        assert_eq!(tree.query(5), Some(5));
+        assert_eq!(tree.query(6), None);
+        assert!(tree.add_line(Line::new(0, 3)));
+        assert_eq!(tree.query(2), Some(2));
+        assert_eq!(tree.query(4), Some(3));
+        assert!(!tree.add_segment(Line::new(0, 4), 0, 10));
+    }
+
+    #[test]
+    fn test_add_segment_stress() {
+        let mut rng = StdRng::seed_from_u64(251);
+        let mut tree = LiChaoTree::new(-40, 40);
+        let mut segments: Vec<(Line, i64, i64)> = Vec::new();
+        for _ in 0..1000 {
+            let line = Line::new(rng.random_range(-20..=20), rng.random_range(-200..=200));
+            let x_l = rng.random_range(-40..=40);
+            let x_r = rng.random_range(x_l..=40);
+            tree.add_segment(line, x_l, x_r);
+            segments.push((line, x_l, x_r));
+            let x = rng.random_range(-40..=40);
+            let expected = segments
+                .iter()
+                .filter(|(_, x_l, x_r)| (*x_l..=*x_r).contains(&x))
+                .map(|(line, _, _)| line.eval(x))
+                .min();
+            assert_eq!(tree.query(x), expected);
+        }
+    }
+
+    #[test]
+    fn test_add_ray() {
+        let mut tree = LiChaoTree::new(-10, 10);
+        assert!(tree.add_ray(Line::new(1, 0), 2, Direction::Right));
+        assert!(tree.add_ray(Line::new(0, 5), -3, Direction::Left));
+        // Starts beyond the domain on either side
+        assert!(!tree.add_ray(Line::new(0, -100), 11, Direction::Right));
+        assert!(!tree.add_ray(Line::new(0, -100), -11, Direction::Left));
+        assert!(tree.add_ray(Line::new(0, 7), i64::MIN, Direction::Right));
+        for x in -10..=10 {
+            let mut expected = 7;
+            if x >= 2 {
+                expected = expected.min(x);
+            }
+            if x <= -3 {
+                expected = expected.min(5);
+            }
+            assert_eq!(tree.query(x), Some(expected));
+        }
+        assert_eq!(tree.len(), 3);
+
+        let mut tree = LiChaoTreeBuilder::new(0, 10)
+            .mode(EnvelopeMode::Max)
+            .build()
+            .unwrap();
+        assert_eq!(
+            tree.try_add_ray(Line::new(i64::MIN, 0), 5, Direction::Left),
+            Err(LiChaoError::NegationOverflow {
+                line: Line::new(i64::MIN, 0)
+            })
+        );
+        tree.add_ray(Line::new(-1, 0), 5, Direction::Left);
+        assert_eq!(tree.query(5), Some(-5));
+        assert_eq!(tree.query(6), None);
+    }
+
+    #[test]
+    fn test_add_abs() {
+        let mut rng = StdRng::seed_from_u64(217);
+        let mut tree = LiChaoTree::new(-50, 50);
+        let mut shapes = Vec::new();
+        for _ in 0..100 {
+            let (a, w, b) = (
+                rng.random_range(-80..=80),
+                rng.random_range(-5..=5),
+                rng.random_range(-100..=100),
+            );
+            tree.add_abs(a, w, b);
+            shapes.push((a, w, b));
+            for x in -50..=50 {
+                assert_eq!(
+                    tree.query(x),
+                    shapes.iter().map(|&(a, w, b)| w * (x - a).abs() + b).min()
+                );
+            }
+        }
+
+        assert_eq!(
+            tree.try_add_abs(i64::MAX, 2, 0),
+            Err(LiChaoError::UnrepresentableLine)
+        );
+        assert_eq!(
+            tree.try_add_abs(0, i64::MIN, 0),
+            Err(LiChaoError::UnrepresentableLine)
+        );
+        let mut tree = LiChaoTreeBuilder::new(0, 10)
+            .mode(EnvelopeMode::Max)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            tree.try_add_abs(0, 1, i64::MIN),
+            Err(LiChaoError::NegationOverflow { .. })
+        ));
+        // Upper envelope of an upside-down V
+        tree.add_abs(4, -2, 10);
+        assert_eq!(tree.query_many(&[0, 4, 10]), [Some(2), Some(10), Some(-2)]);
+    }
+
+    #[test]
+    fn test_rollback() {
+        let mut rng = StdRng::seed_from_u64(259);
+        let mut tree = LiChaoTree::new(-30, 30);
+        tree.add_line(Line::new(0, 100));
+        tree.assign_range(-10, 10, Line::new(1, 50));
+        let mut snapshots = vec![tree.query_many(&(-30..=30).collect::<Vec<_>>())];
+        for _ in 0..500 {
+            if rng.random_bool(0.4) && tree.rollback_depth() > 0 {
+                let k = rng.random_range(1..=tree.rollback_depth());
+                tree.rollback(k);
+                snapshots.truncate(snapshots.len() - k);
+            } else {
+                tree.add_line_rollbackable(Line::new(
+                    rng.random_range(-20..=20),
+                    rng.random_range(-200..=200),
+                ));
+                snapshots.push(tree.query_many(&(-30..=30).collect::<Vec<_>>()));
+            }
+            assert_eq!(
+                Some(&tree.query_many(&(-30..=30).collect::<Vec<_>>())),
+                snapshots.last()
+            );
+        }
+        tree.rollback(tree.rollback_depth());
+        assert_eq!(
+            tree.query_many(&(-30..=30).collect::<Vec<_>>()),
+            snapshots[0]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_rollback_after_commit() {
+        let mut tree = LiChaoTree::new(0, 10);
+        tree.add_line_rollbackable(Line::new(1, 0));
+        tree.add_line(Line::new(0, 3));
+        tree.rollback(1);
+    }
+
+    #[test]
+    fn test_merge_offset() {
+        let mut child = LiChaoTree::new(0, 10);
+        child.add_line(Line::new(-1, 10));
+        child.add_line(Line::new(1, 0));
+        let mut parent = LiChaoTree::new(5, 20);
+        parent.add_line(Line::new(0, 8));
+        parent.merge_offset(&child, Line::new(0, 1));
+        assert_eq!(parent.query(5), Some(6));
+        assert_eq!(parent.query(7), Some(4));
+        assert_eq!(parent.query(10), Some(1));
+        // Outside the child's domain only the parent's own line applies
+        assert_eq!(parent.query(11), Some(8));
+    }
+
+    #[test]
+    fn test_query_line() {
+        let mut rng = StdRng::seed_from_u64(267);
+        for _ in 0..100 {
+            let mut tree = LiChaoTree::new(-20, 20);
+            let mut lines = Vec::new();
+            for _ in 0..rng.random_range(0..30) {
+                let line = Line::new(rng.random_range(-10..=10), rng.random_range(-100..=100));
+                tree.add_line(line);
+                lines.push(line);
+            }
+            for x in -20..=20 {
+                let result = tree.query_line(x);
+                assert_eq!(result.map(|(val, _)| val), tree.query(x));
+                if let Some((val, line)) = result {
+                    assert!(lines.contains(&line));
+                    assert_eq!(line.eval(x), val);
+                }
+            }
+        }
+
+        let mut tree = LiChaoTree::new(0, 10);
+        tree.add_line(Line::new(-1, 10));
+        tree.add_line(Line::new(1, 0));
+        assert_eq!(tree.query_line(5), Some((5, Line::new(-1, 10))));
+        tree.set_tie_break(TieBreak::LargerSlope);
+        assert_eq!(tree.query_line(5), Some((5, Line::new(1, 0))));
+        assert_eq!(tree.query_line(2), Some((2, Line::new(1, 0))));
+    }
+
+    #[test]
+    fn test_clear_and_reset_domain() {
+        let mut tree = LiChaoTree::new(0, 100);
+        tree.add_line(Line::new(1, 0));
+        tree.assign_range(10, 20, Line::new(0, -5));
+        tree.add_line_rollbackable(Line::new(-1, 0));
+        let capacity = tree.nodes.capacity();
+
+        tree.clear();
+        assert_eq!(tree.rollback_depth(), 0);
+        assert!((0..=100).all(|x| tree.query(x).is_none()));
+        tree.add_line(Line::new(2, 1));
+        assert_eq!(tree.query(15), Some(31));
+        assert_eq!(tree.nodes.capacity(), capacity);
+
+        tree.reset_domain(-10, 10);
+        assert_eq!(tree.nodes.capacity(), capacity);
+        assert!(!tree.contains(11) && tree.contains(-10));
+        assert_eq!(tree.query(-10), None);
+        tree.add_line(Line::new(-3, 2));
+        assert_eq!(tree.query(-10), Some(32));
+        assert_eq!(tree.query(10), Some(-28));
+
+        let mut grid = LiChaoTree::with_coordinate_map(SortedCoords::new([1, 5, 1_000_000]));
+        grid.add_line(Line::new(1, 1));
+        grid.reset_domain(0, 3);
+        grid.add_line(Line::new(1, 0));
+        assert_eq!(
+            (0..=3).map(|x| grid.query(x)).collect::<Vec<_>>(),
+            [Some(0), Some(1), Some(2), Some(3)]
+        );
+    }
+
+    #[test]
+    fn test_new_with_points() {
+        let points = [1_000_000_000_000, -7, 3, -7, 0, i64::MIN, 3];
+        let mut tree = LiChaoTree::new_with_points(&points);
+        assert_eq!(tree.nodes.len(), 2 * 8 - 1);
+        tree.add_line(Line::new(2, 1));
+        tree.add_line(Line::new(-1, 0));
+        assert_eq!(tree.query(i64::MIN), Some(i64::MIN));
+        assert_eq!(tree.query(-7), Some(-13));
+        assert_eq!(tree.query(0), Some(0));
+        assert_eq!(tree.query(3), Some(-3));
+        assert_eq!(tree.query(1_000_000_000_000), Some(-1_000_000_000_000));
+        assert!(!tree.contains(1));
+    }
+
+    #[test]
+    fn test_exact_comparisons_near_limits() {
+        let mut tree = LiChaoTree::new_with_points(&[0, 1 << 62, i64::MAX]);
+        // Saturating 3 * i64::MAX before adding the intercept would evaluate this line to 0 at i64::MAX
+        let steep = Line::new(3, -i64::MAX);
+        let shallow = Line::new(1, -1);
+        tree.add_line(shallow);
+        tree.add_line(steep);
+        assert_eq!(tree.query(i64::MAX), Some(i64::MAX - 1));
+        assert_eq!(tree.query_line(i64::MAX), Some((i64::MAX - 1, shallow)));
+        assert_eq!(tree.query(0), Some(-i64::MAX));
+
+        // Both lines saturate to i64::MIN at the left end, but only one is the exact minimum
+        let mut tree = LiChaoTree::new_with_points(&[i64::MIN, 0]);
+        tree.add_line(Line::new(2, 0));
+        tree.add_line(Line::new(3, 0));
+        assert_eq!(tree.query_line(i64::MIN), Some((i64::MIN, Line::new(3, 0))));
+    }
+
+    #[test]
+    fn test_try_query() {
+        let mut tree = LiChaoTree::new(-5, 5);
+        assert_eq!(tree.try_query(0), Ok(None));
+        tree.add_line(Line::new(2, 1));
+        assert_eq!(tree.try_query(-5), Ok(Some(-9)));
+        assert_eq!(
+            tree.try_query(6),
+            Err(LiChaoError::OutOfRange { x_coord: 6 })
+        );
+        assert_eq!(
+            tree.try_query(i64::MIN),
+            Err(LiChaoError::OutOfRange { x_coord: i64::MIN })
+        );
+
+        let grid = LiChaoTree::new_with_points(&[1, 10]);
+        assert_eq!(
+            grid.try_query(5),
+            Err(LiChaoError::OutOfRange { x_coord: 5 })
+        );
+    }
+
+    #[test]
+    fn test_debug_and_display() {
+        let mut tree = LiChaoTree::new(0, 10);
+        assert_eq!(tree.to_string(), "empty envelope");
+        tree.add_line(Line::new(1, 0));
+        tree.add_line(Line::new(-1, 10));
+        tree.add_segment(Line::new(0, -3), 9, 10);
+        assert_eq!(
+            tree.to_string(),
+            "[0, 5]: y = 1x + 0\n[6, 8]: y = -1x + 10\n[9, 10]: y = 0x - 3"
+        );
+        assert_eq!(
+            format!("{:?}", tree),
+            "LiChaoTree { domain: 0..=10, envelope: [(0..=5, Line { m: 1, c: 0 }), \
+             (6..=8, Line { m: -1, c: 10 }), (9..=10, Line { m: 0, c: -3 })] }"
+        );
+
+        // Gaps in the envelope split pieces of the same line
+        let mut grid = LiChaoTree::new_with_points(&[-100, 0, 100]);
+        grid.add_segment(Line::new(2, 1), -100, -100);
+        grid.add_segment(Line::new(2, 1), 100, 100);
+        assert_eq!(
+            grid.to_string(),
+            "[-100, -100]: y = 2x + 1\n[100, 100]: y = 2x + 1"
+        );
+        assert!(format!("{:?}", grid).starts_with("LiChaoTree { domain: -100..=100,"));
+    }
+
+    #[test]
+    fn test_to_piecewise() {
+        let mut tree = LiChaoTree::new(0, 10);
+        assert!(tree.to_piecewise().is_empty());
+        tree.add_line(Line::new(1, 0));
+        tree.add_line(Line::new(-1, 10));
+        assert_eq!(
+            tree.to_piecewise(),
+            vec![
+                EnvelopePiece {
+                    x_l: 0,
+                    x_r: 5,
+                    line: Line::new(1, 0)
+                },
+                EnvelopePiece {
+                    x_l: 6,
+                    x_r: 10,
+                    line: Line::new(-1, 10)
+                },
+            ]
+        );
+
+        // Only the few occupied paths of a huge domain are walked
+        let mut huge = LiChaoTree::new(0, 1 << 26);
+        huge.add_line(Line::new(1, 0));
+        huge.add_segment(Line::new(0, 5), 1 << 25, 1 << 26);
+        assert_eq!(
+            huge.to_piecewise(),
+            vec![
+                EnvelopePiece {
+                    x_l: 0,
+                    x_r: (1 << 25) - 1,
+                    line: Line::new(1, 0)
+                },
+                EnvelopePiece {
+                    x_l: 1 << 25,
+                    x_r: 1 << 26,
+                    line: Line::new(0, 5)
+                },
+            ]
+        );
+
+        let mut rng = StdRng::seed_from_u64(293);
+        for mode in [EnvelopeMode::Min, EnvelopeMode::Max] {
+            for _ in 0..200 {
+                let rule = [
+                    TieBreak::KeepExisting,
+                    TieBreak::SmallerSlope,
+                    TieBreak::LargerSlope,
+                ][rng.random_range(0..3)];
+                let mut tree = LiChaoTreeBuilder::new(-25, 25)
+                    .mode(mode)
+                    .tie_break(rule)
+                    .build()
+                    .unwrap();
+                for _ in 0..rng.random_range(0..20) {
+                    // Small coefficients, so that lines often tie
+                    let line = Line::new(rng.random_range(-3..=3), rng.random_range(-20..=20));
+                    let x_l = rng.random_range(-25..=25);
+                    let x_r = rng.random_range(x_l..=25);
+                    match rng.random_range(0..4) {
+                        0 | 1 => {
+                            tree.add_line(line);
+                        }
+                        2 => {
+                            tree.add_segment(line, x_l, x_r);
+                        }
+                        _ => tree.assign_range(x_l, x_r, line),
+                    }
+                }
+                let pieces = tree.to_piecewise();
+                for pair in pieces.windows(2) {
+                    assert!(pair[0].x_r < pair[1].x_l);
+                    // Adjacent pieces of the same line would have been merged
+                    assert!(pair[0].x_r + 1 < pair[1].x_l || pair[0].line != pair[1].line);
+                }
+                for x in -25..=25 {
+                    let piece = pieces.iter().find(|piece| piece.x_l <= x && x <= piece.x_r);
+                    assert_eq!(
+                        piece.map(|piece| (piece.line.eval(x), piece.line)),
+                        tree.query_line(x)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_improving_intervals() {
+        let mut tree = LiChaoTree::new(0, 10);
+        assert_eq!(tree.improving_intervals(Line::new(5, 5)), vec![0..=10]);
+        tree.add_line(Line::new(1, 0));
+        tree.add_line(Line::new(-1, 10));
+        // Crosses y = x at 2 and y = 10 - x at 8, ties excluded
+        assert_eq!(tree.improving_intervals(Line::new(0, 2)), vec![3..=7]);
+        assert_eq!(tree.improving_intervals(Line::new(-3, 10)), vec![3..=10]);
+        assert!(tree.improving_intervals(Line::new(1, 0)).is_empty());
+        tree.assign_range(5, 5, Line::new(0, 100));
+        assert_eq!(tree.improving_intervals(Line::new(0, 3)), vec![4..=6]);
+        tree.add_segment(Line::new(0, -100), 5, 5);
+        assert_eq!(
+            tree.improving_intervals(Line::new(0, 3)),
+            vec![4..=4, 6..=6]
+        );
+
+        let mut rng = StdRng::seed_from_u64(295);
+        for mode in [EnvelopeMode::Min, EnvelopeMode::Max] {
+            for _ in 0..100 {
+                let points = [-40, -31, -7, -6, 0, 2, 3, 19, 20, 33];
+                let mut tree = LiChaoTreeBuilder::with_points(&points)
+                    .mode(mode)
+                    .build()
+                    .unwrap();
+                for _ in 0..rng.random_range(0..8) {
+                    let line = Line::new(rng.random_range(-10..=10), rng.random_range(-100..=100));
+                    let x_l = rng.random_range(0..10);
+                    let x_r = rng.random_range(x_l..10);
+                    if rng.random_bool(0.5) {
+                        tree.add_line(line);
+                    } else {
+                        tree.add_segment(line, points[x_l], points[x_r]);
+                    }
+                }
+                let probe = Line::new(rng.random_range(-10..=10), rng.random_range(-100..=100));
+                let intervals = tree.improving_intervals(probe);
+                for pair in intervals.windows(2) {
+                    // Maximal runs, so the next one starts after a grid point the probe does not win
+                    assert!(
+                        points
+                            .iter()
+                            .any(|x| pair[0].end() < x && x < pair[1].start())
+                    );
+                }
+                for x in points {
+                    let wins = match (tree.query(x), mode) {
+                        (None, _) => true,
+                        (Some(val), EnvelopeMode::Min) => probe.eval(x) < val,
+                        (Some(val), EnvelopeMode::Max) => probe.eval(x) > val,
+                    };
+                    assert_eq!(intervals.iter().any(|interval| interval.contains(&x)), wins);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_first_x_at_most() {
+        let mut tree = LiChaoTree::new(0, 100);
+        assert_eq!(tree.first_x_at_most(i64::MAX, 0..=100), None);
+        tree.add_line(Line::new(-2, 150));
+        tree.add_line(Line::new(1, 30));
+        // min(150 - 2x, x + 30) is at most 40 from x = 0 to 10, then again from x = 55
+        assert_eq!(tree.first_x_at_most(40, 0..=100), Some(0));
+        assert_eq!(tree.first_x_at_most(40, 11..=100), Some(55));
+        assert_eq!(tree.first_x_at_most(40, 11..=54), None);
+        assert_eq!(tree.first_x_at_most(-50, 0..=100), Some(100));
+
+        // Subtrees without lines are skipped, so a huge domain only costs its few occupied paths
+        let mut huge = LiChaoTree::new(0, 1 << 26);
+        huge.add_line(Line::new(-1, 1 << 26));
+        huge.add_segment(Line::new(0, -5), 1 << 25, (1 << 25) + 10);
+        assert_eq!(huge.first_x_at_most(-10, 0..=1 << 26), None);
+        assert_eq!(huge.first_x_at_most(-5, 0..=1 << 26), Some(1 << 25));
+        assert_eq!(huge.first_x_at_most(-5, (1 << 25) + 11..=1 << 26), None);
+        assert_eq!(
+            huge.first_x_at_most(0, (1 << 25) + 11..=1 << 26),
+            Some(1 << 26)
+        );
+
+        let mut rng = StdRng::seed_from_u64(296);
+        for mode in [EnvelopeMode::Min, EnvelopeMode::Max] {
+            for _ in 0..100 {
+                let mut tree = LiChaoTreeBuilder::new(-30, 30).mode(mode).build().unwrap();
+                for _ in 0..rng.random_range(0..12) {
+                    let line = Line::new(rng.random_range(-10..=10), rng.random_range(-200..=200));
+                    let x_l = rng.random_range(-30..=30);
+                    let x_r = rng.random_range(x_l..=30);
+                    match rng.random_range(0..3) {
+                        0 => {
+                            tree.add_line(line);
+                        }
+                        1 => {
+                            tree.add_segment(line, x_l, x_r);
+                        }
+                        _ => tree.assign_range(x_l, x_r, line),
+                    }
+                }
+                for _ in 0..10 {
+                    let y = rng.random_range(-300..=300);
+                    let x_l = rng.random_range(-30..=30);
+                    let x_r = rng.random_range(x_l..=30);
+                    let expected = (x_l..=x_r).find(|&x| match (tree.query(x), mode) {
+                        (None, _) => false,
+                        (Some(val), EnvelopeMode::Min) => val <= y,
+                        (Some(val), EnvelopeMode::Max) => val >= y,
+                    });
+                    assert_eq!(tree.first_x_at_most(y, x_l..=x_r), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_slope_at() {
         let mut tree = LiChaoTree::new(-10, 10);
-        tree.add_line(Line::new(0, 5));
-        assert_eq!(tree.query(0), Some(5));
-        assert_eq!(tree.query(-10), Some(5));
-        assert_eq!(tree.query(10), Some(5));
+        assert_eq!(tree.slope_at(0), None);
+        tree.add_line(Line::new(1, 0));
+        tree.add_line(Line::new(-1, 0));
+        assert_eq!(tree.slope_at(-5), Some(1));
+        assert_eq!(tree.slope_at(5), Some(-1));
+        // Both lines achieve the minimum at the breakpoint
+        for (rule, slope) in [(TieBreak::SmallerSlope, -1), (TieBreak::LargerSlope, 1)] {
+            let mut tree = LiChaoTreeBuilder::new(-10, 10)
+                .tie_break(rule)
+                .build()
+                .unwrap();
+            tree.add_line(Line::new(1, 0));
+            tree.add_line(Line::new(-1, 0));
+            assert_eq!(tree.slope_at(0), Some(slope));
+        }
 
-        tree.add_line(Line::new(0, 2));
-        assert_eq!(tree.query(0), Some(2));
-        assert_eq!(tree.query(5), Some(2));
+        let mut max_tree = LiChaoTreeBuilder::new(-10, 10)
+            .mode(EnvelopeMode::Max)
+            .build()
+            .unwrap();
+        max_tree.add_segment(Line::new(3, 0), 0, 10);
+        assert_eq!(max_tree.slope_at(-1), None);
+        assert_eq!(max_tree.slope_at(1), Some(3));
 
-        tree.add_line(Line::new(0, 10));
-        assert_eq!(tree.query(0), Some(2));
+        let mut rng = StdRng::seed_from_u64(297);
+        let mut tree = LiChaoTree::new(-50, 50);
+        for _ in 0..100 {
+            tree.add_line(Line::new(
+                rng.random_range(-20..=20),
+                rng.random_range(-500..=500),
+            ));
+            let x = rng.random_range(-50..=50);
+            assert_eq!(tree.slope_at(x), tree.query_line(x).map(|(_, line)| line.m));
+        }
     }
 
     #[test]
-    fn test_steeper_lines_crossing_over() {
-        let mut tree = LiChaoTree::new(0, 20);
-        // Line 1: y = -10x + 100
-        // Line 2: y = x
-        // Intersection: -10x + 100 = x => 11x = 100 => x approx 9.09
-        let l1 = Line::new(-10, 100);
-        let l2 = Line::new(1, 0);
-        tree.add_line(l1);
-        tree.add_line(l2);
+    fn test_integral() {
+        let mut tree = LiChaoTree::new(-10, 10);
+        assert_eq!(tree.integral(-10, 10), None);
+        tree.add_line(Line::new(1, 0));
+        tree.add_line(Line::new(-1, 0));
+        // Sum of -|x|
+        assert_eq!(tree.integral(-10, 10), Some(-110));
+        assert_eq!(tree.integral(3, 5), Some(-12));
 
-        assert_eq!(tree.query(0), Some(l2.eval(0)));
-        assert_eq!(tree.query(5), Some(l2.eval(5)));
-        assert_eq!(tree.query(9), Some(l2.eval(9)));
+        // Sums far beyond i64
+        let mut wide = LiChaoTree::new(1, 1_000_000);
+        wide.add_line(Line::new(1 << 40, -(1 << 62)));
+        let expected = (1_i128 << 40) * 500_000_500_000 - (1_i128 << 62) * 1_000_000;
+        assert_eq!(wide.integral(1, 1_000_000), Some(expected));
 
-        assert_eq!(tree.query(9), Some(9));
+        // Few pieces over a huge domain, without visiting the subtrees that hold no line
+        let mut huge = LiChaoTree::new(0, 1 << 26);
+        huge.add_line(Line::new(1, 0));
+        huge.add_line(Line::new(-1, 1 << 26));
+        huge.add_segment(Line::new(0, 7), 1 << 20, (1 << 20) + 99);
+        let half = 1_i128 << 25;
+        let expected = half * half + (7 * 100 - ((1 << 20) * 100 + 4950));
+        assert_eq!(huge.integral(0, 1 << 26), Some(expected));
+
+        let mut rng = StdRng::seed_from_u64(298);
+        for mode in [EnvelopeMode::Min, EnvelopeMode::Max] {
+            for _ in 0..100 {
+                let builder = match rng.random_bool(0.5) {
+                    true => LiChaoTreeBuilder::new(-30, 30),
+                    false => LiChaoTreeBuilder::with_points(
+                        &(-30..=30).map(|x| x * x * x).collect::<Vec<_>>(),
+                    ),
+                };
+                let mut tree = builder
+                    .mode(mode)
+                    .out_of_range_policy(OutOfRangePolicy::Ignore)
+                    .build()
+                    .unwrap();
+                let points: Vec<i64> = (0..61).map(|idx| tree.get_x_coord_from_idx(idx)).collect();
+                for _ in 0..rng.random_range(1..15) {
+                    let line = Line::new(rng.random_range(-10..=10), rng.random_range(-200..=200));
+                    if rng.random_bool(0.8) {
+                        tree.add_line(line);
+                    } else {
+                        let x_l = rng.random_range(0..61);
+                        tree.add_segment(line, points[x_l], points[rng.random_range(x_l..61)]);
+                    }
+                }
+                for _ in 0..10 {
+                    // Bounds next to grid points, possibly outside of the domain
+                    let idx_l = rng.random_range(0..61);
+                    let x_l = points[idx_l] - rng.random_range(0..2);
+                    let x_r = points[rng.random_range(idx_l..61)] + rng.random_range(0..2);
+                    let expected = points
+                        .iter()
+                        .filter(|&&x| x_l <= x && x <= x_r)
+                        .map(|&x| tree.query(x).map(|val| val as i128))
+                        .sum::<Option<i128>>();
+                    assert_eq!(tree.integral(x_l, x_r), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let mut a = LiChaoTree::new(-10, 10);
+        let mut b = LiChaoTree::new(-10, 10);
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        assert_ne!(a.fingerprint(), LiChaoTree::new(-10, 11).fingerprint());
+        assert_ne!(
+            a.fingerprint(),
+            LiChaoTree::new_with_points(&(-10..=10).collect::<Vec<_>>()).fingerprint()
+        );
+
+        // Same envelope from different lines: y = -|x| as two lines, or segments meeting at 0
+        a.add_line(Line::new(1, 0));
+        a.add_line(Line::new(-1, 0));
+        a.add_line(Line::new(0, 50));
+        b.add_segment(Line::new(1, 0), -10, 0);
+        b.add_segment(Line::new(-1, 0), 0, 10);
+        b.add_segment(Line::new(1, 0), -10, -3);
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        assert!(a.envelope_eq(&b));
+
+        // The same function in max mode, from a single point run of a tie
+        let mut c = LiChaoTreeBuilder::new(-10, 10)
+            .mode(EnvelopeMode::Max)
+            .build()
+            .unwrap();
+        c.add_segment(Line::new(-1, 0), 1, 10);
+        c.add_segment(Line::new(1, 0), -10, -1);
+        c.add_segment(Line::new(5, 0), 0, 0);
+        assert_eq!(a.fingerprint(), c.fingerprint());
+
+        b.add_segment(Line::new(0, -50), 6, 6);
+        assert_ne!(a.fingerprint(), b.fingerprint());
+        // Gaps are part of the envelope
+        let mut d = LiChaoTree::new(-10, 10);
+        d.add_segment(Line::new(1, 0), -10, 4);
+        d.add_segment(Line::new(1, 0), 6, 10);
+        let mut e = LiChaoTree::new(-10, 10);
+        e.add_segment(Line::new(1, 0), -10, 5);
+        e.add_segment(Line::new(1, 0), 7, 10);
+        assert_ne!(d.fingerprint(), e.fingerprint());
+
+        // Fingerprints must not change between versions, since they may be stored or sent to other processes
+        let mut stable = LiChaoTree::new(0, 3);
+        stable.add_line(Line::new(2, -1));
+        assert_eq!(stable.fingerprint(), 0xaf7eb467443eef4db89227a8748a6895);
+
+        // Only breakpoints are visited, so huge domains are cheap
+        let mut f = LiChaoTree::new(-(1 << 25), 1 << 25);
+        f.add_line(Line::new(1, 0));
+        f.add_line(Line::new(-1, 0));
+        let mut g = LiChaoTree::new(-(1 << 25), 1 << 25);
+        g.add_segment(Line::new(-1, 0), 0, 1 << 25);
+        g.add_segment(Line::new(1, 0), -(1 << 25), 0);
+        assert_eq!(f.fingerprint(), g.fingerprint());
+        g.add_segment(Line::new(0, -5), 3, 7);
+        assert_ne!(f.fingerprint(), g.fingerprint());
+
+        let mut rng = StdRng::seed_from_u64(299);
+        for _ in 0..200 {
+            let lines: Vec<Line> = (0..rng.random_range(1..6))
+                .map(|_| Line::new(rng.random_range(-3..=3), rng.random_range(-10..=10)))
+                .collect();
+            let mut forward = LiChaoTree::new(-8, 8);
+            let mut backward = LiChaoTreeBuilder::new(-8, 8)
+                .tie_break(TieBreak::LargerSlope)
+                .build()
+                .unwrap();
+            forward.add_lines(&lines);
+            lines.iter().rev().for_each(|&line| {
+                backward.add_line(line);
+            });
+            assert_eq!(forward.fingerprint(), backward.fingerprint());
+            let mut other = LiChaoTree::new(-8, 8);
+            other.add_line(Line::new(
+                rng.random_range(-3..=3),
+                rng.random_range(-10..=10),
+            ));
+            assert_eq!(
+                forward.fingerprint() == other.fingerprint(),
+                forward.envelope_eq(&other)
+            );
+        }
+    }
+
+    #[test]
+    fn test_len() {
+        let mut tree = LiChaoTree::new(-10, 10);
+        assert!(tree.is_empty());
+        assert_eq!(tree.query(0), None);
+        tree.add_line(Line::new(0, 0));
+        // Discarded lines are counted too
+        assert!(!tree.add_line(Line::new(0, 5)));
+        tree.add_segment(Line::new(1, 0), -3, 3);
+        tree.assign_range(5, 10, Line::new(0, 1));
+        assert_eq!(tree.len(), 4);
+        tree.add_line_rollbackable(Line::new(2, 2));
+        tree.add_line_rollbackable(Line::new(3, 3));
+        tree.rollback(2);
+        assert_eq!(tree.len(), 4);
+
+        let mut other = LiChaoTree::new(-10, 10);
+        other.extend((0..100).map(|i| Line::new(i, -i)));
+        assert_eq!(other.len(), 100);
+        tree.merge_offset(&other, Line::new(0, 1));
+        tree.merge(other);
+        assert_eq!(tree.len(), 204);
+        assert_eq!(
+            LiChaoTree::build(0, 5, &[Line::new(1, 0), Line::new(1, 1)]).len(),
+            2
+        );
+
+        tree.clear();
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut tree = LiChaoTree::new(0, 9);
+        // Only the chunk table is allocated until a line is stored
+        let empty = TreeStats {
+            bytes_allocated: std::mem::size_of::<usize>(),
+            occupied_nodes: 0,
+            max_depth: 0,
+            occupancy: 0.0,
+        };
+        assert_eq!(tree.stats(), empty);
+
+        tree.add_line(Line::new(1, 0));
+        // Wins on [5, 9] only, so it is stored in the root's right child
+        tree.add_line(Line::new(-1, 9));
+        let stats = tree.stats();
+        let bytes = NodeArray::max_bytes(31, NodeLayout::Heap, NodeStorage::Inline).unwrap();
+        assert_eq!(stats.bytes_allocated, bytes);
+        assert_eq!(stats.occupied_nodes, 2);
+        assert_eq!(stats.max_depth, 1);
+        assert_eq!(stats.occupancy, 2.0 / 31.0);
+
+        // Lines hidden below an assigned range are not counted
+        tree.assign_range(0, 9, Line::new(0, 0));
+        let stats = tree.stats();
+        assert_eq!((stats.occupied_nodes, stats.max_depth), (1, 0));
+        assert!(stats.bytes_allocated >= bytes + 31);
+
+        let mut rng = StdRng::seed_from_u64(287);
+        let mut tree = LiChaoTree::new(-500, 500);
+        for _ in 0..2000 {
+            tree.add_line(Line::new(
+                rng.random_range(-100..=100),
+                rng.random_range(-10_000..=10_000),
+            ));
+        }
+        let stats = tree.stats();
+        assert_eq!(stats.occupied_nodes, tree.nodes.iter().flatten().count());
+        // A domain of 1001 points has 11 levels
+        assert!(stats.max_depth <= 10);
+        assert!(stats.occupancy < 0.5);
+    }
+
+    #[test]
+    fn test_envelope_eq() {
+        let lines = [
+            Line::new(2, -5),
+            Line::new(-1, 4),
+            Line::new(0, 1),
+            Line::new(3, 30),
+        ];
+        let mut a = LiChaoTree::new(-10, 10);
+        a.add_lines(&lines);
+        let mut b = LiChaoTree::new(-10, 10);
+        for &line in lines.iter().rev() {
+            b.add_line(line);
+        }
+        // Touches the envelope only at x = 3, so the envelope does not change
+        b.add_line(Line::new(1, -2));
+        assert!(a.envelope_eq(&b) && b.envelope_eq(&a));
+        assert!(a.envelope_eq(&LiChaoTree::build(-10, 10, &lines)));
+
+        b.add_segment(Line::new(0, -100), 9, 10);
+        assert!(!a.envelope_eq(&b));
+        // Only the shared x-coordinates are compared
+        let mut c = LiChaoTree::new(-30, 8);
+        c.add_lines(&lines);
+        assert!(b.envelope_eq(&c) && c.envelope_eq(&b));
+        let mut grid = LiChaoTree::new_with_points(&[-100, -10, 0, 8, 9]);
+        grid.add_lines(&lines);
+        assert!(a.envelope_eq(&grid) && !b.envelope_eq(&grid));
+
+        assert!(LiChaoTree::new(0, 5).envelope_eq(&LiChaoTree::new(-5, 0)));
+        assert!(!LiChaoTree::new(0, 5).envelope_eq(&a));
+        assert!(LiChaoTree::new(20, 30).envelope_eq(&a));
+    }
+
+    #[test]
+    fn test_max_mode() {
+        let max_tree = |x_min, x_max| {
+            LiChaoTreeBuilder::new(x_min, x_max)
+                .mode(EnvelopeMode::Max)
+                .build()
+                .unwrap()
+        };
+        let mut rng = StdRng::seed_from_u64(291);
+        for _ in 0..50 {
+            let mut tree = max_tree(-20, 20);
+            // The same operations on negated lines, in min mode
+            let mut mirror = LiChaoTree::new(-20, 20);
+            for _ in 0..40 {
+                let line = Line::new(rng.random_range(-20..=20), rng.random_range(-300..=300));
+                let negated = Line::new(-line.m, -line.c);
+                let x_l = rng.random_range(-20..=20);
+                let x_r = rng.random_range(x_l..=20);
+                match rng.random_range(0..4) {
+                    0 => assert_eq!(tree.add_line(line), mirror.add_line(negated)),
+                    1 => assert_eq!(
+                        tree.add_segment(line, x_l, x_r),
+                        mirror.add_segment(negated, x_l, x_r)
+                    ),
+                    2 => {
+                        tree.assign_range(x_l, x_r, line);
+                        mirror.assign_range(x_l, x_r, negated);
+                    }
+                    _ => {
+                        let batch: Vec<Line> = (0..100)
+                            .map(|i| Line::new(i % 7, rng.random_range(-500..=0)))
+                            .collect();
+                        tree.extend(batch.iter().copied());
+                        mirror.extend(batch.iter().map(|line| Line::new(-line.m, -line.c)));
+                    }
+                }
+                for x in -20..=20 {
+                    assert_eq!(tree.query(x), mirror.query(x).map(|val| -val));
+                    if let Some((val, line)) = tree.query_line(x) {
+                        assert_eq!(line.eval(x), val);
+                        assert_eq!(tree.classify_point(x, val + 1), PointPosition::Above);
+                        assert_eq!(tree.classify_point(x, val - 1), PointPosition::Below);
+                        assert_eq!(tree.query_bound(x, i64::MAX), Some(Bound::Exact(val)));
+                    }
+                }
+            }
+        }
+
+        let mut tree = max_tree(0, 10);
+        assert_eq!(tree.classify_point(5, i64::MIN), PointPosition::Above);
+        tree.add_line(Line::new(1, 0));
+        tree.add_line(Line::new(-1, 10));
+        assert_eq!(
+            tree.to_string(),
+            "[0, 4]: y = -1x + 10\n[5, 10]: y = 1x + 0"
+        );
+        let mut other = max_tree(0, 10);
+        other.add_line(Line::new(0, 7));
+        tree.merge_offset(&other, Line::new(0, 1));
+        assert_eq!(tree.query(5), Some(8));
+        tree.merge(other);
+        assert_eq!(tree.query(10), Some(10));
+        assert_eq!(
+            tree.try_merge(LiChaoTree::new(0, 10)),
+            Err(LiChaoError::ModeMismatch)
+        );
+
+        // Lines are stored negated
+        assert_eq!(
+            tree.try_add_line(Line::new(i64::MIN, 0)),
+            Err(LiChaoError::NegationOverflow {
+                line: Line::new(i64::MIN, 0)
+            })
+        );
+        assert_eq!(tree.try_add_line(Line::new(0, i64::MAX)), Ok(true));
+        assert_eq!(tree.query(0), Some(i64::MAX));
+        assert_eq!(tree.query_checked(0), Ok(Some(i64::MAX)));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot be negated to be stored in a max-mode tree")]
+    fn test_max_mode_unrepresentable_line() {
+        let mut tree = LiChaoTreeBuilder::new(0, 10)
+            .mode(EnvelopeMode::Max)
+            .build()
+            .unwrap();
+        tree.add_line(Line::new(0, i64::MIN));
+    }
+
+    #[test]
+    fn test_out_of_range_policy() {
+        let mut tree = LiChaoTree::new(0, 10);
+        tree.set_out_of_range_policy(OutOfRangePolicy::Ignore);
+        tree.add_line(Line::new(1, 0));
+        assert_eq!(tree.query(-1), None);
+        assert_eq!(tree.query_line(11), None);
+        assert_eq!(tree.query_bound(11, 0), None);
+        assert_eq!(tree.classify_point(11, 0), PointPosition::Below);
+        assert_eq!(
+            tree.try_query(11),
+            Err(LiChaoError::OutOfRange { x_coord: 11 })
+        );
+
+        // Ranges are clipped to the domain
+        assert!(tree.add_segment(Line::new(0, -1), -5, 2));
+        assert!(!tree.add_segment(Line::new(0, -100), 20, 30));
+        tree.assign_range(9, 100, Line::new(0, 3));
+        assert_eq!(
+            tree.query_many(&[-1, 0, 2, 3, 8, 9, 10]),
+            [None, Some(-1), Some(-1), Some(3), Some(8), Some(3), Some(3)]
+        );
+        assert_eq!(tree.len(), 3);
+
+        let mut grid = LiChaoTree::new_with_points(&[0, 10, 20]);
+        grid.set_out_of_range_policy(OutOfRangePolicy::Ignore);
+        assert!(grid.add_segment(Line::new(0, 5), 1, 15));
+        assert_eq!(
+            grid.query_many(&[0, 5, 10, 20]),
+            [None, None, Some(5), None]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "[5, 4] does not fit inside the tree's bounds")]
+    fn test_out_of_range_policy_reversed_range() {
+        let mut tree = LiChaoTree::new(0, 10);
+        tree.set_out_of_range_policy(OutOfRangePolicy::Ignore);
+        tree.add_segment(Line::new(0, 0), 5, 4);
+    }
+
+    #[test]
+    fn test_lines() {
+        let mut tree = LiChaoTree::new(-20, 20);
+        tree.add_line(Line::new(1, 1));
+        assert!(!tree.keeps_lines());
+        assert_eq!(tree.lines().count(), 0);
+
+        // Lines added before the log is started are not recovered
+        tree.set_keep_lines(true);
+        tree.add_line(Line::new(2, 0));
+        tree.add_segment(Line::new(0, -5), 0, 3);
+        tree.assign_range(10, 20, Line::new(-1, 4));
+        tree.add_line(Line::new(50, 50));
+        tree.add_line_rollbackable(Line::new(3, 3));
+        tree.add_line_rollbackable(Line::new(4, 4));
+        tree.rollback(1);
+        let expected = [
+            Line::new(2, 0),
+            Line::new(0, -5),
+            Line::new(-1, 4),
+            Line::new(50, 50),
+            Line::new(3, 3),
+        ];
+        assert!(tree.lines().eq(&expected));
+        tree.set_keep_lines(true);
+        assert_eq!(tree.lines().count(), 5);
+        tree.clear();
+        assert!(tree.keeps_lines());
+        assert_eq!(tree.lines().count(), 0);
+        tree.set_keep_lines(false);
+        tree.add_line(Line::new(1, 1));
+        assert_eq!(tree.lines().count(), 0);
+    }
+
+    #[test]
+    fn test_line_log_replays_envelope() {
+        let mut rng = StdRng::seed_from_u64(285);
+        let random_line =
+            |rng: &mut StdRng| Line::new(rng.random_range(-20..=20), rng.random_range(-300..=300));
+        for _ in 0..50 {
+            let mut tree = LiChaoTree::new(-20, 20);
+            tree.set_keep_lines(true);
+            for _ in 0..30 {
+                let line = random_line(&mut rng);
+                let x_l = rng.random_range(-20..=20);
+                let x_r = rng.random_range(x_l..=20);
+                match rng.random_range(0..7) {
+                    0 => {
+                        tree.add_line(line);
+                    }
+                    1 => {
+                        tree.add_segment(line, x_l, x_r);
+                    }
+                    2 => tree.assign_range(x_l, x_r, line),
+                    3 => {
+                        tree.add_line_rollbackable(line);
+                        if rng.random_bool(0.5) {
+                            tree.rollback(1);
+                        }
+                    }
+                    4 => {
+                        let mut other = LiChaoTree::new(x_l, x_r + 10);
+                        other.add_line(line);
+                        other.add_line(random_line(&mut rng));
+                        tree.merge_offset(&other, Line::new(1, -3));
+                    }
+                    5 => {
+                        let mut other = LiChaoTree::new(-20, 20);
+                        other.add_segment(line, x_l, x_r);
+                        other.add_line(random_line(&mut rng));
+                        tree.merge(other);
+                    }
+                    _ => tree.extend((0..rng.random_range(0..100)).map(|_| random_line(&mut rng))),
+                }
+            }
+
+            let mut replayed = LiChaoTree::new(-20, 20);
+            for (entry, _) in tree.line_log.as_ref().unwrap() {
+                match *entry {
+                    LogEntry::Line(line) => {
+                        replayed.add_line(line);
+                    }
+                    LogEntry::Segment { line, x_l, x_r } => {
+                        replayed.add_segment(line, x_l, x_r);
+                    }
+                    LogEntry::Assign { line, x_l, x_r } => replayed.assign_range(x_l, x_r, line),
+                }
+            }
+            for x in -20..=20 {
+                assert_eq!(replayed.query(x), tree.query(x));
+            }
+        }
+    }
+
+    /// Envelope at `x` of logged operations `(line, x_l, x_r, assign)`, replayed naively.
+    fn naive_ops(ops: &[(Line, i64, i64, bool)], x: i64) -> Option<i64> {
+        ops.iter().fold(
+            None,
+            |best: Option<i64>, &(line, x_l, x_r, assign)| match (x_l..=x_r).contains(&x) {
+                true if assign => Some(line.eval(x)),
+                true => Some(best.map_or(line.eval(x), |best| best.min(line.eval(x)))),
+                false => best,
+            },
+        )
+    }
+
+    #[test]
+    fn test_extend_domain() {
+        let mut rng = StdRng::seed_from_u64(229);
+        for storage in [NodeStorage::Inline, NodeStorage::Indexed] {
+            let mut tree = LiChaoTreeBuilder::new(-10, 10)
+                .storage(storage)
+                .keep_lines(true)
+                .build()
+                .unwrap();
+            let (mut x_min, mut x_max) = (-10, 10);
+            let mut ops = Vec::new();
+            for _ in 0..6 {
+                for _ in 0..30 {
+                    let line = Line::new(rng.random_range(-20..=20), rng.random_range(-300..=300));
+                    let x_l = rng.random_range(x_min..=x_max);
+                    let x_r = rng.random_range(x_l..=x_max);
+                    match rng.random_range(0..3) {
+                        0 => {
+                            tree.add_line(line);
+                            ops.push((line, i64::MIN, i64::MAX, false));
+                        }
+                        1 => {
+                            tree.add_segment(line, x_l, x_r);
+                            ops.push((line, x_l, x_r, false));
+                        }
+                        _ => {
+                            tree.assign_range(x_l, x_r, line);
+                            ops.push((line, x_l, x_r, true));
+                        }
+                    }
+                }
+                let (new_min, new_max) = (
+                    x_min - rng.random_range(0..=20),
+                    x_max + rng.random_range(-5..=20),
+                );
+                tree.extend_domain(new_min, new_max);
+                (x_min, x_max) = (new_min, new_max.max(x_max));
+                for x in x_min..=x_max {
+                    assert_eq!(tree.query(x), naive_ops(&ops, x));
+                }
+            }
+        }
 
+        let mut tree = LiChaoTree::new(0, 10);
+        assert_eq!(
+            tree.try_extend_domain(-5, 5),
+            Err(LiChaoError::LinesNotKept)
+        );
+        tree.set_keep_lines(true);
+        assert!(matches!(
+            tree.try_extend_domain(5, -5),
+            Err(LiChaoError::InvalidDomain { .. })
+        ));
+        // Dominated on the old domain, but not on the new one
+        tree.add_line(Line::new(0, 0));
+        assert!(!tree.add_line(Line::new(1, 20)));
+        tree.extend_domain(-30, 0);
+        assert_eq!(tree.query(-30), Some(-10));
         assert_eq!(tree.query(10), Some(0));
-        assert_eq!(tree.query(15), Some(l1.eval(15)));
-        assert_eq!(tree.query(20), Some(l1.eval(20)));
     }
+
     #[test]
-    fn test_large_coordinates_and_values() {
-        let mut tree = LiChaoTree::new(-1000, 1000);
+    fn test_improve_line() {
+        let mut rng = StdRng::seed_from_u64(234);
+        for mode in [EnvelopeMode::Min, EnvelopeMode::Max] {
+            let mut tree = LiChaoTreeBuilder::new(-30, 30)
+                .mode(mode)
+                .storage(NodeStorage::Indexed)
+                .build()
+                .unwrap();
+            let mut lines = Vec::new();
+            for _ in 0..300 {
+                if lines.is_empty() || rng.random_bool(0.3) {
+                    let line = Line::new(rng.random_range(-20..=20), rng.random_range(-300..=300));
+                    tree.add_line(line);
+                    lines.push(line);
+                } else {
+                    let id = rng.random_range(0..lines.len());
+                    let step = rng.random_range(0..=100);
+                    let new_c = match mode {
+                        EnvelopeMode::Min => lines[id].c - step,
+                        EnvelopeMode::Max => lines[id].c + step,
+                    };
+                    tree.improve_line(id, new_c);
+                    lines[id].c = new_c;
+                }
+                for x in -30..=30 {
+                    let values = lines.iter().map(|line| line.eval(x));
+                    let expected = match mode {
+                        EnvelopeMode::Min => values.min(),
+                        EnvelopeMode::Max => values.max(),
+                    };
+                    assert_eq!(tree.query(x), expected);
+                    let (value, id) = tree.query_argmin(x).unwrap();
+                    assert_eq!(Some(value), expected);
+                    assert_eq!(lines[id].eval(x), value);
+                }
+            }
+            assert_eq!(tree.len(), tree.nodes.line_table().len());
+        }
 
-        // y = 10^6 * x + 5 * 10^11
-        let line1 = Line::new(1_000_000, 500_000_000_000);
-        tree.add_line(line1);
-        assert_eq!(tree.query(0), Some(500_000_000_000));
-        assert_eq!(tree.query(1000), Some(line1.eval(1000))); // 501 * 10^9
-        assert_eq!(tree.query(-1000), Some(line1.eval(-1000))); // 501 * 10^9
+        let mut tree = LiChaoTreeBuilder::new(0, 10)
+            .storage(NodeStorage::Indexed)
+            .build()
+            .unwrap();
+        tree.add_line(Line::new(1, 5));
+        assert_eq!(
+            tree.try_improve_line(1, 0),
+            Err(LiChaoError::UnknownLine { id: 1 })
+        );
+        assert_eq!(
+            tree.try_improve_line(0, 6),
+            Err(LiChaoError::NotAnImprovement {
+                line: Line::new(1, 5),
+                new_c: 6
+            })
+        );
+        tree.add_segment(Line::new(0, 0), 2, 4);
+        assert_eq!(tree.try_improve_line(0, 0), Err(LiChaoError::PartialLines));
+        // Replaying the log re-inserts the improved line
+        let mut tree = LiChaoTreeBuilder::new(0, 10)
+            .storage(NodeStorage::Indexed)
+            .keep_lines(true)
+            .build()
+            .unwrap();
+        tree.add_line(Line::new(1, 5));
+        tree.improve_line(0, 0);
+        tree.extend_domain(-5, 10);
+        assert_eq!(tree.query_many(&[-5, 10]), [Some(-5), Some(10)]);
+        let mut tree = LiChaoTree::new(0, 10);
+        tree.add_line(Line::new(1, 5));
+        assert_eq!(
+            tree.try_improve_line(0, 0),
+            Err(LiChaoError::UnknownLine { id: 0 })
+        );
+    }
 
-        // y = -2*10^6 * x + 6 * 10^11
-        let line2 = Line::new(-2_000_000, 600_000_000_000);
-        tree.add_line(line2);
+    #[test]
+    fn test_map_lines() {
+        let mut rng = StdRng::seed_from_u64(238);
+        let penalty = |line: Line| Line::new(2 * line.m - 1, line.c + 7 * line.m.abs());
+        for storage in [NodeStorage::Inline, NodeStorage::Indexed] {
+            let mut tree = LiChaoTreeBuilder::new(-20, 20)
+                .storage(storage)
+                .keep_lines(true)
+                .build()
+                .unwrap();
+            let mut ops = Vec::new();
+            for _ in 0..5 {
+                for _ in 0..40 {
+                    let line = Line::new(rng.random_range(-20..=20), rng.random_range(-300..=300));
+                    let x_l = rng.random_range(-20..=20);
+                    let x_r = rng.random_range(x_l..=20);
+                    match rng.random_range(0..3) {
+                        0 => {
+                            tree.add_line(line);
+                            ops.push((line, i64::MIN, i64::MAX, false));
+                        }
+                        1 => {
+                            tree.add_segment(line, x_l, x_r);
+                            ops.push((line, x_l, x_r, false));
+                        }
+                        _ => {
+                            tree.assign_range(x_l, x_r, line);
+                            ops.push((line, x_l, x_r, true));
+                        }
+                    }
+                }
+                tree.map_lines(penalty);
+                for op in &mut ops {
+                    op.0 = penalty(op.0);
+                }
+                assert!(tree.lines().eq(ops.iter().map(|op| &op.0)));
+                for x in -20..=20 {
+                    assert_eq!(tree.query(x), naive_ops(&ops, x));
+                }
+            }
+        }
 
-        assert_eq!(tree.query(0), Some(500_000_000_000));
-        assert_eq!(tree.query(-1000), Some(line1.eval(-1000)));
-        assert_eq!(tree.query(1000), Some(line1.eval(1000)));
-        assert_eq!(tree.query(100), Some(line1.eval(100)));
+        let mut tree = LiChaoTree::new(0, 10);
+        assert_eq!(
+            tree.try_map_lines(|line| line),
+            Err(LiChaoError::LinesNotKept)
+        );
+        let mut tree = LiChaoTreeBuilder::new(0, 10)
+            .mode(EnvelopeMode::Max)
+            .keep_lines(true)
+            .build()
+            .unwrap();
+        tree.add_line(Line::new(1, 0));
+        assert!(matches!(
+            tree.try_map_lines(|line| Line::new(line.m, i64::MIN)),
+            Err(LiChaoError::NegationOverflow { .. })
+        ));
+        assert_eq!(tree.query(10), Some(10));
+    }
+
+    #[test]
+    fn test_rebuild_range() {
+        let mut rng = StdRng::seed_from_u64(228);
+        for storage in [NodeStorage::Inline, NodeStorage::Indexed] {
+            let mut tree = LiChaoTreeBuilder::new(-30, 30)
+                .storage(storage)
+                .keep_lines(true)
+                .build()
+                .unwrap();
+            let mut ops = Vec::new();
+            for _ in 0..400 {
+                let line = Line::new(rng.random_range(-20..=20), rng.random_range(-300..=300));
+                let x_l = rng.random_range(-30..=30);
+                let x_r = rng.random_range(x_l..=30);
+                match rng.random_range(0..4) {
+                    0 => {
+                        tree.add_line(line);
+                        ops.push((line, i64::MIN, i64::MAX, false));
+                    }
+                    1 => {
+                        tree.add_segment(line, x_l, x_r);
+                        ops.push((line, x_l, x_r, false));
+                    }
+                    2 => {
+                        tree.assign_range(x_l, x_r, line);
+                        ops.push((line, x_l, x_r, true));
+                    }
+                    _ => tree.rebuild_range(x_l, x_r),
+                }
+                for x in -30..=30 {
+                    assert_eq!(tree.query(x), naive_ops(&ops, x));
+                }
+            }
+            assert_eq!(tree.len(), ops.len());
+        }
+
+        let mut tree = LiChaoTree::new(0, 10);
+        assert_eq!(tree.try_rebuild_range(2, 4), Err(LiChaoError::LinesNotKept));
+        tree.set_keep_lines(true);
+        assert_eq!(
+            tree.try_rebuild_range(4, 2),
+            Err(LiChaoError::InvalidRange { x_l: 4, x_r: 2 })
+        );
+        assert_eq!(
+            tree.try_rebuild_range(-1, 2),
+            Err(LiChaoError::OutOfRange { x_coord: -1 })
+        );
+    }
+
+    #[test]
+    fn test_line_inputs() {
+        let mut tree = LiChaoTree::new(-10, 10);
+        assert!(tree.add_line((2, 3)));
+        assert_eq!(tree.try_add_line((2, 5)), Ok(false));
+        assert_eq!(tree.query(1), Some(5));
+
+        assert_eq!(Line::from_points((1, 5), (3, 9)), Ok(Line::new(2, 3)));
+        assert_eq!(Line::from_points((3, 9), (1, 5)), Ok(Line::new(2, 3)));
+        assert_eq!(Line::from_points((0, 4), (7, 4)), Ok(Line::new(0, 4)));
+        assert_eq!(
+            Line::from_points((2, 0), (2, 1)),
+            Err(LiChaoError::VerticalLine { x_coord: 2 })
+        );
+        assert_eq!(
+            Line::from_points((0, 0), (2, 1)),
+            Err(LiChaoError::UnrepresentableLine)
+        );
+        assert_eq!(
+            Line::from_points((-1, i64::MIN), (1, i64::MAX)),
+            Err(LiChaoError::UnrepresentableLine)
+        );
+        assert_eq!(
+            Line::from_points((0, i64::MIN), (1, 0)),
+            Err(LiChaoError::UnrepresentableLine)
+        );
+        assert_eq!(
+            Line::from_points((i64::MAX, i64::MAX), (i64::MAX - 1, i64::MAX - 1)),
+            Ok(Line::new(1, 0))
+        );
+    }
+
+    #[test]
+    fn test_try_variants() {
+        let mut tree = LiChaoTree::new(0, 10);
+        assert_eq!(tree.try_add_line(Line::new(1, 0)), Ok(true));
+        assert_eq!(tree.try_add_line(Line::new(1, 5)), Ok(false));
+
+        assert_eq!(
+            tree.try_add_segment(Line::new(0, -1), 3, 11),
+            Err(LiChaoError::OutOfRange { x_coord: 11 })
+        );
+        assert_eq!(
+            tree.try_add_segment(Line::new(0, -1), 5, 3),
+            Err(LiChaoError::InvalidRange { x_l: 5, x_r: 3 })
+        );
+        assert_eq!(tree.try_add_segment(Line::new(0, -1), 3, 5), Ok(true));
+        assert_eq!(
+            tree.try_assign_range(-1, 5, Line::new(0, 0)),
+            Err(LiChaoError::OutOfRange { x_coord: -1 })
+        );
+        assert_eq!(tree.try_assign_range(9, 10, Line::new(0, 2)), Ok(()));
+        assert_eq!(tree.try_query_line(4), Ok(Some((-1, Line::new(0, -1)))));
+        assert_eq!(tree.try_query_line(10), Ok(Some((2, Line::new(0, 2)))));
+        assert_eq!(
+            tree.try_query_line(11),
+            Err(LiChaoError::OutOfRange { x_coord: 11 })
+        );
+
+        assert_eq!(tree.try_add_line_rollbackable(Line::new(0, -7)), Ok(true));
+        assert_eq!(
+            tree.try_rollback(2),
+            Err(LiChaoError::RollbackUnavailable {
+                requested: 2,
+                available: 1
+            })
+        );
+        assert_eq!(tree.query(0), Some(-7));
+        assert_eq!(tree.try_rollback(1), Ok(()));
+        assert_eq!(tree.query(0), Some(0));
+
+        assert_eq!(
+            tree.try_merge(LiChaoTree::new(0, 9)),
+            Err(LiChaoError::DomainMismatch)
+        );
+        let mut other = LiChaoTree::new(0, 10);
+        other.add_line(Line::new(-1, 0));
+        assert_eq!(tree.try_merge(other), Ok(()));
+        assert_eq!(tree.query(8), Some(-8));
+    }
+
+    #[test]
+    fn test_any_line_is_insertable() {
+        // Empty nodes hold NO_LINE, but are told apart from nodes storing it
+        let mut tree = LiChaoTree::new(0, 10);
+        assert_eq!(tree.try_add_line(NO_LINE), Ok(true));
+        assert_eq!(tree.query(3), Some(i64::MAX));
+        assert_eq!(tree.query_line(3), Some((i64::MAX, NO_LINE)));
+        assert_eq!(tree.query_bound(3, 0), Some(Bound::Exact(i64::MAX)));
+        assert_eq!(tree.stats().occupied_nodes, 1);
+        tree.add_line(Line::new(1, 0));
+        assert_eq!(tree.query(3), Some(3));
+
+        let mut tree = LiChaoTree::new(0, 10);
+        assert!(tree.add_segment(NO_LINE, 2, 4));
+        assert_eq!(
+            tree.query_many(&[1, 2, 4, 5]),
+            [None, Some(i64::MAX), Some(i64::MAX), None]
+        );
+        tree.add_line_rollbackable(Line::new(1, i64::MAX - 10));
+        assert_eq!(tree.query(10), Some(i64::MAX));
+        tree.rollback(1);
+        assert_eq!(tree.query(10), None);
+        tree.assign_range(0, 10, NO_LINE);
+        assert!((0..=10).all(|x| tree.query(x) == Some(i64::MAX)));
+        assert_eq!(
+            tree.to_piecewise(),
+            [EnvelopePiece {
+                x_l: 0,
+                x_r: 10,
+                line: NO_LINE
+            }]
+        );
+
+        // In max mode, lines are stored negated
+        let mut tree = LiChaoTreeBuilder::new(0, 10)
+            .mode(EnvelopeMode::Max)
+            .build()
+            .unwrap();
+        assert!(tree.add_line(Line::new(0, -i64::MAX)));
+        assert_eq!(tree.query(3), Some(-i64::MAX));
+    }
+
+    #[test]
+    fn test_query_argmin() {
+        let mut rng = StdRng::seed_from_u64(310);
+        for mode in [EnvelopeMode::Min, EnvelopeMode::Max] {
+            let indexed = || {
+                LiChaoTreeBuilder::new(-30, 30)
+                    .mode(mode)
+                    .storage(NodeStorage::Indexed)
+                    .build()
+                    .unwrap()
+            };
+            let mut tree = indexed();
+            let mut inline = LiChaoTreeBuilder::new(-30, 30).mode(mode).build().unwrap();
+            // Candidate (insertion index, line) pairs at each x-coordinate, with snapshots for rollback
+            let mut candidates: Vec<Vec<(usize, Line)>> = vec![Vec::new(); 61];
+            let mut snapshots = Vec::new();
+            for _ in 0..300 {
+                let line = Line::new(rng.random_range(-5..=5), rng.random_range(-50..=50));
+                let x_l = rng.random_range(-30..=30);
+                let x_r = rng.random_range(x_l..=30);
+                let idx = tree.len();
+                let range = (x_l + 30) as usize..=(x_r + 30) as usize;
+                match rng.random_range(0..5) {
+                    0 => {
+                        tree.add_line(line);
+                        inline.add_line(line);
+                        candidates
+                            .iter_mut()
+                            .for_each(|at_x| at_x.push((idx, line)));
+                    }
+                    1 => {
+                        tree.add_segment(line, x_l, x_r);
+                        inline.add_segment(line, x_l, x_r);
+                        candidates[range]
+                            .iter_mut()
+                            .for_each(|at_x| at_x.push((idx, line)));
+                    }
+                    2 => {
+                        tree.assign_range(x_l, x_r, line);
+                        inline.assign_range(x_l, x_r, line);
+                        candidates[range]
+                            .iter_mut()
+                            .for_each(|at_x| *at_x = vec![(idx, line)]);
+                    }
+                    3 => {
+                        snapshots.push(candidates.clone());
+                        tree.add_line_rollbackable(line);
+                        inline.add_line_rollbackable(line);
+                        candidates
+                            .iter_mut()
+                            .for_each(|at_x| at_x.push((idx, line)));
+                    }
+                    _ => {
+                        let depth = rng.random_range(0..=tree.rollback_depth());
+                        tree.rollback(depth);
+                        inline.rollback(depth);
+                        if depth > 0 {
+                            candidates = snapshots.drain(snapshots.len() - depth..).next().unwrap();
+                        }
+                    }
+                }
+                if tree.rollback_depth() == 0 {
+                    snapshots.clear();
+                }
+
+                for x in -30..=30 {
+                    let result = tree.query_argmin(x);
+                    assert_eq!(result.map(|(val, _)| val), inline.query(x));
+                    if let Some((val, idx)) = result {
+                        assert_eq!(line_at(&candidates, x, idx).eval(x), val);
+                    }
+                }
+            }
+
+            // Lines of a merged tree keep their indices, after those of the tree they are merged into
+            let mut other = indexed();
+            let base = tree.len();
+            for _ in 0..100 {
+                let line = Line::new(rng.random_range(-5..=5), rng.random_range(-50..=50));
+                let idx = base + other.len();
+                other.add_line(line);
+                candidates
+                    .iter_mut()
+                    .for_each(|at_x| at_x.push((idx, line)));
+            }
+            // Large batches are added along their envelope, but each line keeps its own index
+            let batch: Vec<Line> = (0..100)
+                .map(|_| Line::new(rng.random_range(-5..=5), rng.random_range(-50..=50)))
+                .collect();
+            let mut offset = other.clone();
+            tree.merge(other);
+            let base = tree.len();
+            tree.extend(batch.iter().copied());
+            for (i, &line) in batch.iter().enumerate() {
+                candidates
+                    .iter_mut()
+                    .for_each(|at_x| at_x.push((base + i, line)));
+            }
+            for x in -30..=30 {
+                let (val, idx) = tree.query_argmin(x).unwrap();
+                assert_eq!(line_at(&candidates, x, idx).eval(x), val);
+                let best = candidates[(x + 30) as usize]
+                    .iter()
+                    .map(|(_, line)| line.eval(x));
+                assert_eq!(
+                    Some(val),
+                    if mode == EnvelopeMode::Min {
+                        best.min()
+                    } else {
+                        best.max()
+                    }
+                );
+            }
+
+            offset.merge_offset(&tree, Line::new(1, 1));
+            assert_eq!(offset.len(), 100 + tree.len());
+            let (val, idx) = offset.query_argmin(5).unwrap();
+            if idx >= 100 {
+                assert_eq!(line_at(&candidates, 5, idx - 100).eval(5) + 6, val);
+            }
+            assert_eq!(
+                offset.try_merge(inline.clone()),
+                Err(LiChaoError::StorageMismatch)
+            );
+            inline.merge(offset);
+        }
+    }
+
+    /// Line of insertion index `idx` among the candidates at `x`.
+    fn line_at(candidates: &[Vec<(usize, Line)>], x: i64, idx: usize) -> Line {
+        candidates[(x + 30) as usize]
+            .iter()
+            .find(|&&(i, _)| i == idx)
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_query_argmin_needs_indexed_storage() {
+        LiChaoTree::new(0, 10).query_argmin(5);
+    }
+
+    #[test]
+    fn test_query_checked() {
+        let mut tree = LiChaoTree::new(-10, 10);
+        assert_eq!(tree.query_checked(0), Ok(None));
+        assert_eq!(
+            tree.query_checked(11),
+            Err(LiChaoError::OutOfRange { x_coord: 11 })
+        );
+        tree.add_line(Line::new(i64::MAX / 4, i64::MIN / 2));
+        assert_eq!(tree.query_checked(1), Ok(Some(i64::MAX / 4 + i64::MIN / 2)));
+        let value = (i64::MAX / 4) as i128 * 10 + (i64::MIN / 2) as i128;
+        assert_eq!(
+            tree.query_checked(10),
+            Err(LiChaoError::Overflow { x_coord: 10, value })
+        );
+        assert_eq!(tree.query(10), Some(i64::MAX));
+        assert!(
+            tree.query_checked(-10)
+                .unwrap_err()
+                .to_string()
+                .contains("overflows")
+        );
+
+        let mut tree = LiChaoTree::new(0, 1);
+        tree.add_line(Line::new(1, i64::MAX - 1));
+        assert_eq!(tree.query_checked(1), Ok(Some(i64::MAX)));
+        assert_eq!(tree.query(1), Some(i64::MAX));
+    }
+
+    #[test]
+    fn test_overflow_policy() {
+        let x = 3 << 61;
+        let mut tree = LiChaoTree::new_with_points(&[0, x]);
+        tree.add_line(Line::new(1, -2));
+        // 2 * x overflows, but the line's exact value is the minimum
+        let steep = Line::new(2, -x - 5);
+        tree.add_line(steep);
+        assert_eq!(tree.overflow_policy(), OverflowPolicy::WideningI128);
+        assert_eq!(tree.query(x), Some(x - 5));
+        assert_eq!(tree.query(0), Some(-x - 5));
+
+        // The winner is the same under every policy, only its value differs
+        tree.set_overflow_policy(OverflowPolicy::Saturate);
+        assert_eq!(tree.query_line(x), Some((i64::MAX - x - 5, steep)));
+        tree.set_overflow_policy(OverflowPolicy::Checked);
+        assert_eq!(tree.query_line(x), None);
+        assert_eq!(tree.query_many(&[0, x]), [Some(-x - 5), None]);
+    }
+
+    #[test]
+    #[should_panic(expected = "overflows")]
+    fn test_overflow_policy_panic() {
+        let mut tree = LiChaoTree::new(0, 10);
+        tree.add_line(Line::new(i64::MAX / 5, 0));
+        tree.set_overflow_policy(OverflowPolicy::Panic);
+        assert_eq!(tree.query(5), Some(i64::MAX / 5 * 5));
+        tree.query(6);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut rng = StdRng::seed_from_u64(263);
+        for _ in 0..100 {
+            let mut tree = LiChaoTree::new(-20, 20);
+            let mut other = LiChaoTree::new(-20, 20);
+            for target in [&mut tree, &mut other] {
+                for _ in 0..rng.random_range(0..20) {
+                    let line = Line::new(rng.random_range(-10..=10), rng.random_range(-100..=100));
+                    if rng.random_bool(0.2) {
+                        let l = rng.random_range(-20..=20);
+                        target.assign_range(l, rng.random_range(l..=20), line);
+                    } else {
+                        target.add_line(line);
+                    }
+                }
+            }
+            let expected: Vec<_> = (-20..=20)
+                .map(|x| tree.query(x).into_iter().chain(other.query(x)).min())
+                .collect();
+            tree.merge(other);
+            assert_eq!(
+                (-20..=20).map(|x| tree.query(x)).collect::<Vec<_>>(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_merge_different_domains() {
+        let mut tree = LiChaoTree::new(0, 10);
+        tree.merge(LiChaoTree::new(1, 11));
+    }
+
+    #[test]
+    fn test_merge_offset_stress() {
+        let mut rng = StdRng::seed_from_u64(244);
+        for _ in 0..50 {
+            let mut other = LiChaoTree::new(-30, 10);
+            let mut tree = LiChaoTree::new(-10, 30);
+            for _ in 0..rng.random_range(0..20) {
+                let line = Line::new(rng.random_range(-10..=10), rng.random_range(-100..=100));
+                if rng.random_bool(0.2) {
+                    let l = rng.random_range(-30..=10);
+                    other.assign_range(l, rng.random_range(l..=10), line);
+                } else {
+                    other.add_line(line);
+                }
+            }
+            for _ in 0..rng.random_range(0..20) {
+                tree.add_line(Line::new(
+                    rng.random_range(-10..=10),
+                    rng.random_range(-100..=100),
+                ));
+            }
+            let delta = Line::new(rng.random_range(-3..=3), rng.random_range(-50..=50));
+            let expected: Vec<_> = (-10..=30)
+                .map(|x| {
+                    let shifted = if other.contains(x) {
+                        other.query(x).map(|y| y + delta.eval(x))
+                    } else {
+                        None
+                    };
+                    match (tree.query(x), shifted) {
+                        (Some(a), Some(b)) => Some(a.min(b)),
+                        (a, b) => a.or(b),
+                    }
+                })
+                .collect();
+            tree.merge_offset(&other, delta);
+            assert_eq!(tree.query_many(&(-10..=30).collect::<Vec<_>>()), expected);
+        }
+    }
+
+    #[test]
+    fn test_memory_independent_of_insertions() {
+        let mut tree = LiChaoTree::new(0, 1000);
+        let capacity = tree.nodes.capacity();
+        let mut rng = StdRng::seed_from_u64(99);
+        for _ in 0..100_000 {
+            tree.add_line(Line::new(
+                rng.random_range(-1000..=1000),
+                rng.random_range(-1000..=1000),
+            ));
+        }
+        assert_eq!(tree.nodes.capacity(), capacity);
+    }
+
+    #[test]
+    fn test_add_line_discard_means_unchanged() {
+        let mut tree = LiChaoTree::new(-20, 20);
+        let mut rng = StdRng::seed_from_u64(1234);
+        for _ in 0..500 {
+            let line = Line::new(rng.random_range(-50..=50), rng.random_range(-500..=500));
+            let before: Vec<_> = (-20..=20).map(|x| tree.query(x)).collect();
+            let improves = (-20..=20)
+                .zip(&before)
+                .any(|(x, old)| old.is_none_or(|old| line.eval(x) < old));
+            let kept = tree.add_line(line);
+            assert!(
+                kept || !improves,
+                "Discarded a line that improves the envelope: {:?}",
+                line
+            );
+        }
     }
 
     #[test]
@@ -323,10 +4817,10 @@ mod tests {
             let m = rng.random_range(-1_000_000..=1_000_000);
             let c = rng.random_range(-1_000_000..=1_000_000);
             let line = Line::new(m, c);
-            lines.push(line.clone());
+            lines.push(line);
             tree.add_line(line);
             let t = rng.random_range(-1_000_000..=1_000_000);
-            let mut oracle = i64::max_value();
+            let mut oracle = i64::MAX;
             for elem in &lines {
                 oracle = oracle.min(elem.eval(t));
             }
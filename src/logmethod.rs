@@ -0,0 +1,195 @@
+use crate::{FrozenEnvelopeBuf, LiChaoTree, Line};
+
+/// A static envelope structure that [`LogMethod`] rebuilds from scratch instead of updating.
+pub trait StaticEnvelope {
+    /// Builds the envelope of `lines` over `[x_min_coord, x_max_coord]`.
+    fn build(x_min_coord: i64, x_max_coord: i64, lines: &[Line]) -> Self;
+
+    /// Queries the minimum y-value at `x_coord`, or `None` if there are no lines.
+    fn query(&self, x_coord: i64) -> Option<i64>;
+}
+
+impl StaticEnvelope for FrozenEnvelopeBuf {
+    fn build(x_min_coord: i64, x_max_coord: i64, lines: &[Line]) -> Self {
+        FrozenEnvelopeBuf::new(x_min_coord, x_max_coord, lines)
+    }
+
+    fn query(&self, x_coord: i64) -> Option<i64> {
+        FrozenEnvelopeBuf::query(self, x_coord)
+    }
+}
+
+/// Each build allocates a full node array, so this is only sensible for small domains.
+impl StaticEnvelope for LiChaoTree {
+    fn build(x_min_coord: i64, x_max_coord: i64, lines: &[Line]) -> Self {
+        let mut tree = LiChaoTree::new(x_min_coord, x_max_coord);
+        tree.add_lines(lines);
+        tree
+    }
+
+    fn query(&self, x_coord: i64) -> Option<i64> {
+        LiChaoTree::query(self, x_coord)
+    }
+}
+
+/// A block of lines together with the static structure built over them.
+#[derive(Clone, Debug)]
+struct Block<S> {
+    // Sorted by (m, c) so removals can find a line by binary search
+    lines: Vec<Line>,
+    envelope: S,
+}
+
+/// Supports inserting and removing lines on top of a static envelope structure, using the
+/// Bentley–Saxe logarithmic method.
+///
+/// Lines are kept in O(log n) blocks with static envelopes built over each, block `i` holding at most `2^i` lines.
+/// An insertion merges the full blocks below the first free level into it, like incrementing a binary counter,
+/// so every line is part of O(log n) rebuilds over its lifetime. A removal rebuilds only the block holding the line.
+/// Queries take the minimum over all blocks.
+#[derive(Clone, Debug)]
+pub struct LogMethod<S = FrozenEnvelopeBuf> {
+    x_min_coord: i64,
+    x_max_coord: i64,
+    levels: Vec<Option<Block<S>>>,
+    len: usize,
+}
+
+impl<S: StaticEnvelope> LogMethod<S> {
+    /// Creates an empty structure over the x-coordinates `[x_min_coord, x_max_coord]`.
+    pub fn new(x_min_coord: i64, x_max_coord: i64) -> Self {
+        if x_min_coord > x_max_coord {
+            panic!(
+                "x_min_coord ({}) cannot be greater than x_max_coord ({})",
+                x_min_coord, x_max_coord
+            );
+        }
+        LogMethod {
+            x_min_coord,
+            x_max_coord,
+            levels: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Number of lines currently stored, counting duplicates.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn build_block(&self, mut lines: Vec<Line>) -> Block<S> {
+        lines.sort_unstable_by_key(|line| (line.m, line.c));
+        let envelope = S::build(self.x_min_coord, self.x_max_coord, &lines);
+        Block { lines, envelope }
+    }
+
+    /// Adds a line. Every line takes part in O(log n) rebuilds over its lifetime,
+    /// so with `FrozenEnvelopeBuf` blocks an insertion costs amortized O(log^2 n).
+    pub fn insert(&mut self, line: Line) {
+        let mut carry = vec![line];
+        let mut level = 0;
+        while let Some(Some(block)) = self.levels.get_mut(level).map(Option::take) {
+            carry.extend(block.lines);
+            level += 1;
+        }
+        if level == self.levels.len() {
+            self.levels.push(None);
+        }
+        self.levels[level] = Some(self.build_block(carry));
+        self.len += 1;
+    }
+
+    /// Removes one copy of `line` and returns whether it was present.
+    /// Time complexity: one static build over the block that held the line, i.e. O(n) lines in the worst case.
+    pub fn remove(&mut self, line: Line) -> bool {
+        let key = (line.m, line.c);
+        for level in 0..self.levels.len() {
+            let Some(block) = &mut self.levels[level] else {
+                continue;
+            };
+            let Ok(pos) = block
+                .lines
+                .binary_search_by_key(&key, |line| (line.m, line.c))
+            else {
+                continue;
+            };
+            let mut lines = std::mem::take(&mut block.lines);
+            lines.remove(pos);
+            self.levels[level] = (!lines.is_empty()).then(|| self.build_block(lines));
+            self.len -= 1;
+            return true;
+        }
+        false
+    }
+
+    /// Queries the minimum y-value at `x_coord` over all stored lines.
+    /// Panics if `x_coord` is outside the structure's bounds.
+    /// Time complexity: O(log n) static queries.
+    pub fn query(&self, x_coord: i64) -> Option<i64> {
+        if x_coord < self.x_min_coord || x_coord > self.x_max_coord {
+            panic!("{} does not fit inside the tree's bounds", x_coord);
+        }
+        self.levels
+            .iter()
+            .flatten()
+            .filter_map(|block| block.envelope.query(x_coord))
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn check_against_naive<S: StaticEnvelope>(seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut structure = LogMethod::<S>::new(-30, 30);
+        let mut lines: Vec<Line> = Vec::new();
+        for _ in 0..1000 {
+            if !lines.is_empty() && rng.random_bool(0.4) {
+                let line = lines.swap_remove(rng.random_range(0..lines.len()));
+                assert!(structure.remove(line));
+            } else {
+                let line = Line::new(rng.random_range(-5..=5), rng.random_range(-50..=50));
+                structure.insert(line);
+                lines.push(line);
+            }
+            assert_eq!(structure.len(), lines.len());
+            let x = rng.random_range(-30..=30);
+            assert_eq!(
+                structure.query(x),
+                lines.iter().map(|line| line.eval(x)).min()
+            );
+        }
+    }
+
+    #[test]
+    fn test_frozen_blocks() {
+        check_against_naive::<FrozenEnvelopeBuf>(250);
+    }
+
+    #[test]
+    fn test_tree_blocks() {
+        check_against_naive::<LiChaoTree>(251);
+    }
+
+    #[test]
+    fn test_remove_missing() {
+        let mut structure: LogMethod = LogMethod::new(0, 10);
+        structure.insert(Line::new(1, 0));
+        assert!(!structure.remove(Line::new(1, 1)));
+        assert!(structure.remove(Line::new(1, 0)));
+        assert!(structure.is_empty());
+        assert_eq!(structure.query(3), None);
+        structure.insert(Line::new(0, i64::MAX));
+        assert_eq!(structure.query(3), Some(i64::MAX));
+    }
+}
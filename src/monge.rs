@@ -0,0 +1,209 @@
+/// Online row minima of a lower-triangular totally monotone matrix (the LARSCH algorithm).
+///
+/// Row `i` consists of the entries `f(i, j)` for `0 <= j <= i`. Rows are answered one at a time,
+/// and while answering row `i` the algorithm only evaluates entries `f(r, j)` with `j <= i`.
+/// This is what online DP needs: in `dp[i + 1] = min_{j <= i} dp[j] + w(j, i + 1)` the entry
+/// `f(i, j) = dp[j] + w(j, i + 1)` only depends on values that are already known.
+/// The matrix must be totally monotone, which holds whenever `w` satisfies the
+/// quadrangle inequality `w(a, c) + w(b, d) <= w(a, d) + w(b, c)` for `a <= b <= c <= d`.
+///
+/// Time complexity: O(n) evaluations of `f` in total.
+pub struct Larsch {
+    base: ReduceRow,
+}
+
+impl Larsch {
+    /// Creates a solver for a matrix with `n` rows.
+    pub fn new(n: usize) -> Self {
+        Larsch {
+            base: ReduceRow::new(n),
+        }
+    }
+
+    /// Returns the column of a minimum in the next row.
+    /// `f` is passed on every call so that it may borrow state that the caller updates between rows.
+    /// Panics if called more than `n` times.
+    pub fn next_argmin<T: PartialOrd>(&mut self, mut f: impl FnMut(usize, usize) -> T) -> usize {
+        if self.base.cur_row >= self.base.n {
+            panic!(
+                "Larsch::next_argmin: all {} rows have been answered",
+                self.base.n
+            );
+        }
+        self.base.get_argmin(&mut f)
+    }
+}
+
+/// Answers even rows by scanning between the argmins of their neighbours,
+/// and odd rows through the column-reduced subproblem.
+struct ReduceRow {
+    n: usize,
+    cur_row: usize,
+    state: usize,
+    rec: Option<Box<ReduceCol>>,
+}
+
+/// Keeps at most `n` candidate columns, discarding ones that can never win again.
+struct ReduceCol {
+    n: usize,
+    cur_row: usize,
+    cols: Vec<usize>,
+    rec: ReduceRow,
+}
+
+impl ReduceRow {
+    fn new(n: usize) -> Self {
+        let m = n / 2;
+        ReduceRow {
+            n,
+            cur_row: 0,
+            state: 0,
+            rec: (m != 0).then(|| Box::new(ReduceCol::new(m))),
+        }
+    }
+
+    fn get_argmin<T: PartialOrd>(&mut self, f: &mut dyn FnMut(usize, usize) -> T) -> usize {
+        let cur_row = self.cur_row;
+        self.cur_row += 1;
+        if cur_row.is_multiple_of(2) {
+            let prev_argmin = self.state;
+            let next_argmin = if cur_row + 1 == self.n {
+                self.n - 1
+            } else {
+                self.rec
+                    .as_mut()
+                    .expect("ReduceRow: missing subproblem for odd rows")
+                    .get_argmin(&mut |i, j| f(2 * i + 1, j))
+            };
+            self.state = next_argmin;
+            let mut ret = prev_argmin;
+            for j in prev_argmin + 1..=next_argmin {
+                if f(cur_row, ret) > f(cur_row, j) {
+                    ret = j;
+                }
+            }
+            ret
+        } else if f(cur_row, self.state) <= f(cur_row, cur_row) {
+            self.state
+        } else {
+            cur_row
+        }
+    }
+}
+
+impl ReduceCol {
+    fn new(n: usize) -> Self {
+        ReduceCol {
+            n,
+            cur_row: 0,
+            cols: Vec::new(),
+            rec: ReduceRow::new(n),
+        }
+    }
+
+    fn get_argmin<T: PartialOrd>(&mut self, f: &mut dyn FnMut(usize, usize) -> T) -> usize {
+        let cur_row = self.cur_row;
+        self.cur_row += 1;
+        let new_cols = if cur_row == 0 {
+            0..=0
+        } else {
+            2 * cur_row - 1..=2 * cur_row
+        };
+        for j in new_cols {
+            while let Some(&last) = self.cols.last() {
+                let size = self.cols.len();
+                if size == cur_row || f(size - 1, last) <= f(size - 1, j) {
+                    break;
+                }
+                self.cols.pop();
+            }
+            if self.cols.len() != self.n {
+                self.cols.push(j);
+            }
+        }
+        let cols = &self.cols;
+        cols[self.rec.get_argmin(&mut |i, j| f(i, cols[j]))]
+    }
+}
+
+/// Solves `dp[i] = min_{j < i} dp[j] + w(j, i)` for `i` in `1..=n` with `dp[0] = init`,
+/// where `w` satisfies the quadrangle inequality (e.g. concave costs of the gap).
+/// Returns `dp[0..=n]`. Time complexity: O(n) evaluations of `w`.
+pub fn monge_dp(n: usize, init: i64, mut w: impl FnMut(usize, usize) -> i64) -> Vec<i64> {
+    let mut dp = Vec::with_capacity(n + 1);
+    dp.push(init);
+    let mut larsch = Larsch::new(n);
+    for i in 0..n {
+        let j = larsch.next_argmin(|row, col| dp[col].saturating_add(w(col, row + 1)));
+        dp.push(dp[j].saturating_add(w(j, i + 1)));
+    }
+    dp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn brute_dp(n: usize, init: i64, w: impl Fn(usize, usize) -> i64) -> Vec<i64> {
+        let mut dp = vec![init];
+        for i in 1..=n {
+            let best = (0..i).map(|j| dp[j] + w(j, i)).min().unwrap();
+            dp.push(best);
+        }
+        dp
+    }
+
+    #[test]
+    fn test_squared_gaps() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for n in 0..60 {
+            let mut xs: Vec<i64> = (0..=n).map(|_| rng.random_range(0..1000)).collect();
+            xs.sort();
+            let penalty = rng.random_range(0..5000);
+            let w = |j: usize, i: usize| (xs[i] - xs[j]).pow(2) + penalty;
+            assert_eq!(monge_dp(n, 0, w), brute_dp(n, 0, w), "n = {}", n);
+        }
+    }
+
+    #[test]
+    fn test_convex_gap_costs() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..50 {
+            let n = rng.random_range(1..200);
+            // Convex function of the gap: increasing increments
+            let mut g = vec![0i64; n + 1];
+            let mut step = rng.random_range(-100..0);
+            for k in 1..=n {
+                step += rng.random_range(0..20);
+                g[k] = g[k - 1] + step;
+            }
+            let costs: Vec<i64> = (0..=n).map(|_| rng.random_range(-50..50)).collect();
+            let w = |j: usize, i: usize| g[i - j] + costs[i];
+            assert_eq!(monge_dp(n, 3, w), brute_dp(n, 3, w));
+        }
+    }
+
+    #[test]
+    fn test_argmins_are_row_minima() {
+        let n = 100;
+        let f = |i: usize, j: usize| ((i as i64) - 2 * (j as i64)).pow(2);
+        let mut larsch = Larsch::new(n);
+        for i in 0..n {
+            let j = larsch.next_argmin(f);
+            let best = (0..=i).map(|j| f(i, j)).min().unwrap();
+            assert_eq!(f(i, j), best);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_too_many_rows() {
+        let mut larsch = Larsch::new(1);
+        larsch.next_argmin(|_, _| 0);
+        larsch.next_argmin(|_, _| 0);
+    }
+}
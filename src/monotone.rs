@@ -0,0 +1,277 @@
+use std::collections::VecDeque;
+
+use crate::Line;
+
+/// The convex hull trick for lines inserted in slope order, with amortized O(1) operations.
+///
+/// Each new line must have a slope no greater than every line added so far, or no smaller than all of them;
+/// it is then appended to the back or the front of the hull. Lines that can no longer be minimal at any integer x
+/// are dropped on insertion. Queries are O(log n) in general, and amortized O(1) through `query_increasing`
+/// when the x-coordinates are non-decreasing. There is no domain: any `i64` x can be queried.
+/// Lines are compared exactly and results are clamped to the `i64` range, like in `SparseLiChaoTree`.
+#[derive(Clone, Debug, Default)]
+pub struct MonotoneCHT {
+    // Sorted by strictly decreasing slope, i.e. in the order they are minimal as x increases
+    hull: VecDeque<Line>,
+    last_increasing_query: Option<i64>,
+    // Index into `hull` where `query_increasing` resumes. Lines before it are not minimal at any x from the last
+    // increasing query on, but are kept for `query`.
+    cursor: usize,
+}
+
+impl MonotoneCHT {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of lines currently on the hull.
+    pub fn len(&self) -> usize {
+        self.hull.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hull.is_empty()
+    }
+
+    /// Adds a line. Panics if its slope is strictly between the smallest and largest slope added so far.
+    /// Time complexity: amortized O(1).
+    pub fn add_line(&mut self, line: Line) {
+        let (Some(front), Some(back)) = (self.hull.front(), self.hull.back()) else {
+            self.hull.push_back(line);
+            return;
+        };
+        if line.m <= back.m {
+            if line.m == back.m {
+                if line.c >= back.c {
+                    return;
+                }
+                self.hull.pop_back();
+            }
+            // `b` is useless once `line` beats it no later than `b` beats the line before it
+            while self.hull.len() >= 2 {
+                let (b, before) = (
+                    &self.hull[self.hull.len() - 1],
+                    &self.hull[self.hull.len() - 2],
+                );
+                if line.first_below(b) > b.first_below(before) {
+                    break;
+                }
+                self.hull.pop_back();
+            }
+            self.hull.push_back(line);
+            // A line popped at the cursor was beaten by `line` wherever it was minimal
+            self.cursor = self.cursor.min(self.hull.len() - 1);
+        } else if line.m >= front.m {
+            let len = self.hull.len();
+            if line.m == front.m {
+                if line.c >= front.c {
+                    return;
+                }
+                self.hull.pop_front();
+            }
+            while self.hull.len() >= 2 {
+                let (f, after) = (&self.hull[0], &self.hull[1]);
+                if after.first_below(f) > f.first_below(&line) {
+                    break;
+                }
+                self.hull.pop_front();
+            }
+            self.hull.push_front(line);
+            // Restart at `line` if there was no increasing query yet, if it is minimal at the last one or if the
+            // cursor's line was popped, otherwise shift the cursor with the lines in front of it
+            let popped = len + 1 - self.hull.len();
+            let restart = self.last_increasing_query.is_none_or(|x| {
+                self.hull.len() < 2 || line.eval_wide(x) <= self.hull[1].eval_wide(x)
+            });
+            self.cursor = if restart || self.cursor < popped {
+                0
+            } else {
+                self.cursor - popped + 1
+            };
+        } else {
+            panic!(
+                "slope {} is neither <= {} nor >= {}, lines must be added in slope order",
+                line.m, back.m, front.m
+            );
+        }
+    }
+
+    fn clamp(val: i128) -> Option<i64> {
+        Some(val.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+    }
+
+    /// Queries the minimum y-value at `x_coord`, or `None` if no line has been added.
+    /// Time complexity: O(log n).
+    pub fn query(&self, x_coord: i64) -> Option<i64> {
+        // Values along the hull first decrease and then increase, find the turning point
+        let (mut lo, mut hi) = (0, self.hull.len().checked_sub(1)?);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.hull[mid + 1].eval_wide(x_coord) <= self.hull[mid].eval_wide(x_coord) {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        Self::clamp(self.hull[lo].eval_wide(x_coord))
+    }
+
+    /// Same as `query`, for x-coordinates that never decrease between calls. Lines that can only be minimal
+    /// left of `x_coord` are skipped by later calls, so a sequence of calls takes amortized O(1) time each.
+    /// They stay on the hull, so `query` still answers for any x.
+    /// Panics if `x_coord` is smaller than in the previous call.
+    pub fn query_increasing(&mut self, x_coord: i64) -> Option<i64> {
+        if let Some(last) = self.last_increasing_query
+            && x_coord < last
+        {
+            panic!(
+                "query_increasing: {} is smaller than the previous query {}",
+                x_coord, last
+            );
+        }
+        self.last_increasing_query = Some(x_coord);
+        while self.cursor + 1 < self.hull.len()
+            && self.hull[self.cursor + 1].eval_wide(x_coord)
+                <= self.hull[self.cursor].eval_wide(x_coord)
+        {
+            self.cursor += 1;
+        }
+        Self::clamp(self.hull.get(self.cursor)?.eval_wide(x_coord))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_both_insertion_orders() {
+        let mut rng = StdRng::seed_from_u64(261);
+        for _ in 0..100 {
+            let mut cht = MonotoneCHT::new();
+            let mut lines: Vec<Line> = Vec::new();
+            let (mut lo_slope, mut hi_slope) = (0, 0);
+            for _ in 0..50 {
+                let line = if lines.is_empty() || rng.random_bool(0.5) {
+                    lo_slope -= rng.random_range(0..3);
+                    Line::new(lo_slope, rng.random_range(-1000..=1000))
+                } else {
+                    hi_slope += rng.random_range(0..3);
+                    Line::new(hi_slope, rng.random_range(-1000..=1000))
+                };
+                cht.add_line(line);
+                lines.push(line);
+                for x in -100..=100 {
+                    assert_eq!(cht.query(x), lines.iter().map(|line| line.eval(x)).min());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_query_increasing() {
+        let mut rng = StdRng::seed_from_u64(262);
+        let mut cht = MonotoneCHT::new();
+        let mut lines = Vec::new();
+        let (mut slope, mut x) = (1000, -100_000);
+        for _ in 0..1000 {
+            if rng.random_bool(0.5) {
+                slope -= rng.random_range(0..5);
+                let line = Line::new(slope, rng.random_range(-1_000_000..=1_000_000));
+                cht.add_line(line);
+                lines.push(line);
+            }
+            x += rng.random_range(0..500);
+            assert_eq!(
+                cht.query_increasing(x),
+                lines.iter().map(|line| line.eval(x)).min()
+            );
+        }
+    }
+
+    #[test]
+    fn test_query_after_query_increasing() {
+        let mut cht = MonotoneCHT::new();
+        cht.add_line(Line::new(1, 0));
+        cht.add_line(Line::new(-1, 50));
+        assert_eq!(cht.query_increasing(100), Some(-50));
+        assert_eq!(cht.query(0), Some(0));
+        assert_eq!(cht.len(), 2);
+
+        let mut rng = StdRng::seed_from_u64(261);
+        for _ in 0..100 {
+            let mut cht = MonotoneCHT::new();
+            let mut lines: Vec<Line> = Vec::new();
+            let (mut lo_slope, mut hi_slope, mut x) = (0, 0, -200);
+            for _ in 0..50 {
+                let line = if lines.is_empty() || rng.random_bool(0.5) {
+                    lo_slope -= rng.random_range(0..3);
+                    Line::new(lo_slope, rng.random_range(-1000..=1000))
+                } else {
+                    hi_slope += rng.random_range(0..3);
+                    Line::new(hi_slope, rng.random_range(-1000..=1000))
+                };
+                cht.add_line(line);
+                lines.push(line);
+                x += rng.random_range(0..10);
+                let min_at = |x: i64| lines.iter().map(|line| line.eval(x)).min();
+                assert_eq!(cht.query_increasing(x), min_at(x));
+                let y = rng.random_range(-300..=300);
+                assert_eq!(cht.query(y), min_at(y));
+            }
+        }
+    }
+
+    #[test]
+    fn test_front_insertions_before_query_increasing() {
+        let mut cht = MonotoneCHT::new();
+        cht.add_line(Line::new(0, -15));
+        cht.add_line(Line::new(2, -13));
+        assert_eq!(cht.query_increasing(-20), Some(-53));
+
+        // Queries are sparse, so several lines go in on either side between them, including before the first
+        let mut rng = StdRng::seed_from_u64(261);
+        for _ in 0..500 {
+            let mut cht = MonotoneCHT::new();
+            let mut lines: Vec<Line> = Vec::new();
+            let (mut lo_slope, mut hi_slope, mut x) = (0, 0, -200);
+            for _ in 0..30 {
+                let line = if lines.is_empty() || rng.random_bool(0.5) {
+                    lo_slope -= rng.random_range(0..3);
+                    Line::new(lo_slope, rng.random_range(-100..=100))
+                } else {
+                    hi_slope += rng.random_range(0..3);
+                    Line::new(hi_slope, rng.random_range(-100..=100))
+                };
+                cht.add_line(line);
+                lines.push(line);
+                if rng.random_bool(0.3) {
+                    x += rng.random_range(0..20);
+                    let min = lines.iter().map(|line| line.eval(x)).min();
+                    assert_eq!(cht.query_increasing(x), min);
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_unsorted_slope() {
+        let mut cht = MonotoneCHT::new();
+        cht.add_line(Line::new(3, 0));
+        cht.add_line(Line::new(-3, 0));
+        cht.add_line(Line::new(0, 0));
+    }
+
+    #[test]
+    fn test_max_value_is_reported() {
+        let mut cht = MonotoneCHT::new();
+        cht.add_line(Line::new(0, i64::MAX));
+        assert_eq!(cht.query(-7), Some(i64::MAX));
+        assert_eq!(cht.query_increasing(-7), Some(i64::MAX));
+    }
+}
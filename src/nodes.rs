@@ -0,0 +1,843 @@
+use std::collections::{HashMap, TryReserveError};
+
+use crate::{Arena, Line, NO_LINE};
+
+/// Number of nodes allocated together on first write: 1 KiB of lines, and one bit of each chunk's occupancy mask.
+pub(crate) const CHUNK_LEN: usize = 64;
+
+/// Id held by empty nodes of `NodeStorage::Indexed` arrays. Lines get smaller ids, so it is never a table index.
+pub(crate) const NO_ID: u32 = u32::MAX;
+
+/// Levels of the subtrees stored together by `NodeLayout::Blocked`. Their 63 nodes fill a chunk.
+const BLOCK_LEVELS: u32 = 6;
+const BLOCK_NODES: usize = (1 << BLOCK_LEVELS) - 1;
+
+/// What a node holds: its line, or the id of its line in the line table.
+pub(crate) trait Slot: Copy {
+    /// Contents of empty nodes.
+    const EMPTY: Self;
+}
+
+impl Slot for Line {
+    const EMPTY: Line = NO_LINE;
+}
+
+impl Slot for u32 {
+    const EMPTY: u32 = NO_ID;
+}
+
+/// Nodes allocated together. Bit `i` of `occupied` is set if `slots[i]` holds a node's contents, and empty nodes
+/// hold `Slot::EMPTY`. Bit `i` of `subtree` is set if the subtree of the node in `slots[i]` may hold a line, and is
+/// clear if it holds none.
+#[derive(Clone)]
+pub(crate) struct Chunk<T = Line> {
+    slots: [T; CHUNK_LEN],
+    occupied: u64,
+    subtree: u64,
+}
+
+impl<T: Slot> Chunk<T> {
+    const EMPTY: Chunk<T> = Chunk {
+        slots: [T::EMPTY; CHUNK_LEN],
+        occupied: 0,
+        subtree: 0,
+    };
+}
+
+pub(crate) type ChunkTable<T = Line> = Vec<Option<Box<Chunk<T>>>>;
+
+/// A stored line, with its id in the line table of `NodeStorage::Indexed` arrays, or `NO_ID` in other arrays.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Entry {
+    pub(crate) line: Line,
+    pub(crate) id: u32,
+}
+
+/// A node of a [`NodeArray`], given by its index or by a cursor that already located it.
+pub(crate) trait NodeRef: Copy {
+    fn locate(self, nodes: &NodeArray) -> NodeCursor;
+}
+
+impl NodeRef for usize {
+    #[inline]
+    fn locate(self, nodes: &NodeArray) -> NodeCursor {
+        nodes.cursor(self)
+    }
+}
+
+impl NodeRef for NodeCursor {
+    #[inline]
+    fn locate(self, _: &NodeArray) -> NodeCursor {
+        self
+    }
+}
+
+/// A node located in the node array, as found by [`NodeArray::cursor`] or by walking down with [`NodeArray::child`].
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct NodeCursor {
+    pub(crate) node_v_idx: usize,
+    slot: usize,
+    // Blocked layout only: slot of the root of the node's block, the node's depth in it, the block's level, and
+    // the index of the block among those of its level
+    block_slot: usize,
+    depth_in_block: u32,
+    block_level: u32,
+    block: usize,
+}
+
+/// How the nodes of a tree are ordered in memory, see [`crate::LiChaoTreeBuilder::layout`].
+/// Only affects speed and memory, never results.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NodeLayout {
+    /// Breadth-first order. Cheapest to index, but below the first few levels each step of a root-to-leaf walk lands
+    /// on a different cache line, and on large domains on a different page.
+    #[default]
+    Heap,
+    /// Subtrees of six levels are stored together in 1 KiB blocks, so a root-to-leaf walk touches one block every six
+    /// levels, and allocates as many chunks on first write. On a domain of 2^24 points a walk touches 5 chunks
+    /// instead of 20, and about 20 cache lines instead of 24, but each step costs a few more instructions. Query
+    /// paths do not depend on the stored lines, so out-of-order CPUs overlap the misses of the heap layout well, and
+    /// which layout is faster depends on the machine and workload: benchmark before switching.
+    Blocked,
+}
+
+/// How the nodes of a tree hold their lines, see [`crate::LiChaoTreeBuilder::storage`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NodeStorage {
+    /// Each node holds its line, 16 bytes.
+    #[default]
+    Inline,
+    /// Each node holds a 4-byte id into a table of every line passed to the tree, in order, so that the line of a
+    /// node is known by its insertion index, see [`crate::LiChaoTree::query_argmin`]. Nodes take a quarter of the
+    /// memory, but the table grows by 16 bytes per line added, so this saves memory as long as the tree is given
+    /// fewer lines than about three quarters of its allocated nodes. Every node visited costs one more load from
+    /// the table. Memory budgets do not cover the table. At most `u32::MAX` lines can be added.
+    Indexed,
+}
+
+/// The node array of a [`crate::LiChaoTree`], allocated in chunks of `CHUNK_LEN` nodes on first write.
+///
+/// Nodes that were never written are empty, so creating the array only reserves its chunk table, and subtrees that no
+/// line reaches cost nothing. The table itself only grows, within its reservation, up to the last chunk written to,
+/// until `shrink_to_fit` gives the reservation up. Arrays created from an [`Arena`] take their chunks and table from
+/// it, and give them back when dropped.
+///
+/// Whether a node is empty is tracked by the occupancy masks of the chunks, not by its line, so every line can be
+/// stored. Empty nodes still hold `NO_LINE`, which evaluates to `i64::MAX` everywhere, so that hot loops can evaluate
+/// `line_or_empty` without checking the masks.
+///
+/// A second mask per chunk marks the nodes whose subtree may hold a line, so that searches can skip empty subtrees,
+/// see `subtree_may_hold`. Storing a line marks the node and its ancestors, allocating their chunks, which insertions
+/// from the root have written already. Emptying a node leaves the marks, until `shrink_to_fit` recomputes them.
+///
+/// Nodes are indexed in breadth-first order whatever the layout, which only changes the slot each node is stored in.
+/// With `NodeStorage::Indexed`, slots hold ids into the line table instead of lines, and chunks of ids are not taken
+/// from the arena.
+#[derive(Clone)]
+pub(crate) struct NodeArray {
+    chunks: ChunkTable,
+    // `NodeStorage::Indexed` only: the chunks of ids, and the line table they index
+    id_chunks: ChunkTable<u32>,
+    lines: Vec<Line>,
+    len: usize,
+    arena: Option<Arena>,
+    layout: NodeLayout,
+    storage: NodeStorage,
+    // Number of levels of blocks, and nodes per block in the last one, see `cursor`
+    block_levels: u32,
+    last_block_len: usize,
+}
+
+impl NodeArray {
+    /// Creates an array of `len` empty nodes, reserving its chunk table up front so that writes never fail later on.
+    /// Time complexity: O(1).
+    pub(crate) fn try_new(
+        len: usize,
+        arena: Option<&Arena>,
+        layout: NodeLayout,
+        storage: NodeStorage,
+    ) -> Result<Self, TryReserveError> {
+        let mut nodes = NodeArray {
+            chunks: arena.and_then(Arena::take_table).unwrap_or_default(),
+            id_chunks: Vec::new(),
+            lines: Vec::new(),
+            len: 0,
+            arena: arena.cloned(),
+            layout,
+            storage,
+            block_levels: 0,
+            last_block_len: 0,
+        };
+        nodes.set_len(len);
+        let chunk_count = Self::slots(len, layout).unwrap().div_ceil(CHUNK_LEN);
+        match storage {
+            NodeStorage::Inline => nodes.chunks.try_reserve_exact(chunk_count)?,
+            NodeStorage::Indexed => nodes.id_chunks.try_reserve_exact(chunk_count)?,
+        }
+        Ok(nodes)
+    }
+
+    /// Heap memory held by an array of `len` nodes once every chunk is allocated, or `None` if it overflows `usize`.
+    /// The line table of `NodeStorage::Indexed` is not counted, since it grows with the lines added.
+    pub(crate) fn max_bytes(len: usize, layout: NodeLayout, storage: NodeStorage) -> Option<usize> {
+        let chunk_bytes = match storage {
+            NodeStorage::Inline => std::mem::size_of::<Chunk>(),
+            NodeStorage::Indexed => std::mem::size_of::<Chunk<u32>>(),
+        } + std::mem::size_of::<Option<Box<Chunk>>>();
+        Self::slots(len, layout)?
+            .div_ceil(CHUNK_LEN)
+            .checked_mul(chunk_bytes)
+    }
+
+    /// Number of slots needed to store `len` nodes, or `None` if it overflows `usize`. The blocked layout stores
+    /// complete trees, and pads blocks above the last level to a chunk.
+    fn slots(len: usize, layout: NodeLayout) -> Option<usize> {
+        match layout {
+            NodeLayout::Heap => Some(len),
+            NodeLayout::Blocked => {
+                let (block_levels, last_block_len) = Self::blocks(len);
+                let first_last_block = Self::first_block(block_levels.saturating_sub(1));
+                let last_blocks =
+                    1usize.checked_shl(BLOCK_LEVELS * block_levels.saturating_sub(1))?;
+                (first_last_block * CHUNK_LEN).checked_add(last_blocks.checked_mul(last_block_len)?)
+            }
+        }
+    }
+
+    /// Number of levels of blocks of a complete tree with at least `len` nodes, and the number of nodes of the blocks
+    /// of the last level, which may have fewer than `BLOCK_LEVELS` levels.
+    fn blocks(len: usize) -> (u32, usize) {
+        let levels = usize::BITS - len.leading_zeros();
+        let block_levels = levels.div_ceil(BLOCK_LEVELS);
+        let last_levels = levels - BLOCK_LEVELS * block_levels.saturating_sub(1);
+        (block_levels, (1 << last_levels) - 1)
+    }
+
+    /// Number of blocks above level `block_level`, which is also the index of its first block.
+    fn first_block(block_level: u32) -> usize {
+        ((1 << (BLOCK_LEVELS * block_level)) - 1) / BLOCK_NODES
+    }
+
+    fn set_len(&mut self, len: usize) {
+        self.len = len;
+        (self.block_levels, self.last_block_len) = Self::blocks(len);
+    }
+
+    /// Locates node `node_v_idx`. In the blocked layout, blocks are stored level by level, each one in breadth-first
+    /// order, and each block but those of the last level fills a chunk.
+    #[inline]
+    pub(crate) fn cursor(&self, node_v_idx: usize) -> NodeCursor {
+        assert!(
+            node_v_idx < self.len,
+            "node index {} out of bounds for {} nodes",
+            node_v_idx,
+            self.len
+        );
+        if self.layout == NodeLayout::Heap {
+            return NodeCursor {
+                node_v_idx,
+                slot: node_v_idx,
+                ..NodeCursor::default()
+            };
+        }
+        let depth = (node_v_idx + 1).ilog2();
+        let (block_level, depth_in_block) = (depth / BLOCK_LEVELS, depth % BLOCK_LEVELS);
+        // Position of the node among those of its depth, and of its block among those of its block level
+        let pos = node_v_idx + 1 - (1 << depth);
+        let block = pos >> depth_in_block;
+        let block_slot = self.block_slot(block_level, block);
+        NodeCursor {
+            node_v_idx,
+            slot: block_slot + (1 << depth_in_block) - 1 + (pos & ((1 << depth_in_block) - 1)),
+            block_slot,
+            depth_in_block,
+            block_level,
+            block,
+        }
+    }
+
+    /// Moves a cursor to the left or right child of its node, which must not be a leaf.
+    /// Cheaper than `cursor`, since slots only need to be recomputed when the walk enters a new block.
+    #[inline]
+    pub(crate) fn child(&self, cursor: NodeCursor, right: bool) -> NodeCursor {
+        let node_v_idx = 2 * cursor.node_v_idx + 1 + right as usize;
+        debug_assert!(node_v_idx < self.len);
+        if self.layout == NodeLayout::Heap {
+            return NodeCursor {
+                node_v_idx,
+                slot: node_v_idx,
+                ..cursor
+            };
+        }
+        let pos_in_block = cursor.slot - cursor.block_slot;
+        if cursor.depth_in_block + 1 < BLOCK_LEVELS {
+            return NodeCursor {
+                node_v_idx,
+                slot: cursor.slot + pos_in_block + 1 + right as usize,
+                depth_in_block: cursor.depth_in_block + 1,
+                ..cursor
+            };
+        }
+        // Each of the nodes of the last level of a full block has two child blocks
+        let block =
+            (cursor.block << BLOCK_LEVELS) + 2 * (pos_in_block - BLOCK_NODES / 2) + right as usize;
+        let block_slot = self.block_slot(cursor.block_level + 1, block);
+        NodeCursor {
+            node_v_idx,
+            slot: block_slot,
+            block_slot,
+            depth_in_block: 0,
+            block_level: cursor.block_level + 1,
+            block,
+        }
+    }
+
+    /// Slot of the root of a block in the blocked layout, from its block level and its index in that level.
+    #[inline]
+    fn block_slot(&self, block_level: u32, block: usize) -> usize {
+        let first_block = Self::first_block(block_level);
+        if block_level + 1 < self.block_levels {
+            (first_block + block) * CHUNK_LEN
+        } else {
+            first_block * CHUNK_LEN + block * self.last_block_len
+        }
+    }
+
+    pub(crate) fn layout(&self) -> NodeLayout {
+        self.layout
+    }
+
+    pub(crate) fn storage(&self) -> NodeStorage {
+        self.storage
+    }
+
+    /// Number of nodes, written or not.
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Number of nodes the array can hold without growing its chunk table.
+    #[cfg(test)]
+    pub(crate) fn capacity(&self) -> usize {
+        (self.chunks.capacity() + self.id_chunks.capacity()) * CHUNK_LEN
+    }
+
+    /// Heap memory held by the chunk tables, the chunks allocated so far and the line table.
+    pub(crate) fn bytes_allocated(&self) -> usize {
+        table_bytes(&self.chunks)
+            + table_bytes(&self.id_chunks)
+            + self.lines.capacity() * std::mem::size_of::<Line>()
+    }
+
+    /// Empties every node and the line table, keeping the chunks allocated so far for reuse.
+    /// Time complexity: O(number of allocated chunks).
+    pub(crate) fn clear(&mut self) {
+        clear_table(&mut self.chunks);
+        clear_table(&mut self.id_chunks);
+        self.lines.clear();
+    }
+
+    /// Line stored in a node, or `None` if it is empty.
+    #[inline]
+    pub(crate) fn get(&self, node: impl NodeRef) -> Option<Line> {
+        let slot = node.locate(self).slot;
+        match self.storage {
+            NodeStorage::Inline => read(&self.chunks, slot),
+            NodeStorage::Indexed => read(&self.id_chunks, slot).map(|id| self.lines[id as usize]),
+        }
+    }
+
+    /// Same as `get`, with the id of the line.
+    #[inline]
+    pub(crate) fn entry(&self, node: impl NodeRef) -> Option<Entry> {
+        let slot = node.locate(self).slot;
+        match self.storage {
+            NodeStorage::Inline => read(&self.chunks, slot).map(|line| Entry { line, id: NO_ID }),
+            NodeStorage::Indexed => read(&self.id_chunks, slot).map(|id| Entry {
+                line: self.lines[id as usize],
+                id,
+            }),
+        }
+    }
+
+    /// Line stored in a node, or `NO_LINE` if it is empty.
+    #[inline]
+    pub(crate) fn line_or_empty(&self, node: impl NodeRef) -> Line {
+        let slot = node.locate(self).slot;
+        match self.storage {
+            NodeStorage::Inline => read_or_empty(&self.chunks, slot),
+            // `NO_ID` is past the end of the table
+            NodeStorage::Indexed => {
+                let id = read_or_empty(&self.id_chunks, slot);
+                self.lines.get(id as usize).copied().unwrap_or(NO_LINE)
+            }
+        }
+    }
+
+    /// Stores `entry` in a node, or empties it if `entry` is `None`, and returns what the node held before.
+    /// Allocates the node's chunk if a line is stored in it for the first time.
+    #[inline]
+    pub(crate) fn replace(&mut self, node: impl NodeRef, entry: Option<Entry>) -> Option<Entry> {
+        let cursor = node.locate(self);
+        let slot = cursor.slot;
+        let old = match self.storage {
+            NodeStorage::Inline => {
+                let arena = self.arena.as_ref();
+                let old = write(
+                    &mut self.chunks,
+                    slot,
+                    entry.map(|entry| entry.line),
+                    || new_chunk(arena),
+                );
+                old.map(|line| Entry { line, id: NO_ID })
+            }
+            NodeStorage::Indexed => {
+                debug_assert!(
+                    entry.is_none_or(|entry| self.lines[entry.id as usize] == entry.line)
+                );
+                let new_chunk = || Box::new(Chunk::EMPTY);
+                let old = write(
+                    &mut self.id_chunks,
+                    slot,
+                    entry.map(|entry| entry.id),
+                    new_chunk,
+                );
+                old.map(|id| Entry {
+                    line: self.lines[id as usize],
+                    id,
+                })
+            }
+        };
+        if entry.is_some() {
+            self.mark_subtree(cursor);
+        }
+        old
+    }
+
+    /// Marks the subtrees of a node and of its ancestors as holding a line. Marks are only ever set together with
+    /// those of all ancestors, so the walk stops at the first node already marked.
+    #[inline]
+    fn mark_subtree(&mut self, cursor: NodeCursor) {
+        let (mut node_v_idx, mut slot) = (cursor.node_v_idx, cursor.slot);
+        loop {
+            let marked = match self.storage {
+                NodeStorage::Inline => {
+                    let arena = self.arena.as_ref();
+                    mark(&mut self.chunks, slot, || new_chunk(arena))
+                }
+                NodeStorage::Indexed => mark(&mut self.id_chunks, slot, || Box::new(Chunk::EMPTY)),
+            };
+            if marked || node_v_idx == 0 {
+                return;
+            }
+            node_v_idx = (node_v_idx - 1) / 2;
+            slot = self.cursor(node_v_idx).slot;
+        }
+    }
+
+    /// Returns whether the subtree of a node may hold a line. If not, all its nodes are empty.
+    #[inline]
+    pub(crate) fn subtree_may_hold(&self, node: impl NodeRef) -> bool {
+        let slot = node.locate(self).slot;
+        match self.storage {
+            NodeStorage::Inline => subtree_bit(&self.chunks, slot),
+            NodeStorage::Indexed => subtree_bit(&self.id_chunks, slot),
+        }
+    }
+
+    /// Recomputes the subtree marks below `cursor` exactly, visiting only marked nodes, and returns whether its
+    /// subtree holds a line.
+    fn unmark_empty_subtrees(&mut self, cursor: NodeCursor) -> bool {
+        if !self.subtree_may_hold(cursor) {
+            return false;
+        }
+        let mut holds = self.get(cursor).is_some();
+        if 2 * cursor.node_v_idx + 1 < self.len {
+            holds |= self.unmark_empty_subtrees(self.child(cursor, false));
+            holds |= self.unmark_empty_subtrees(self.child(cursor, true));
+        }
+        if !holds {
+            match self.storage {
+                NodeStorage::Inline => unmark(&mut self.chunks, cursor.slot),
+                NodeStorage::Indexed => unmark(&mut self.id_chunks, cursor.slot),
+            }
+        }
+        holds
+    }
+
+    /// Same as `replace`, without returning the previous contents.
+    #[inline]
+    pub(crate) fn set(&mut self, node: impl NodeRef, entry: Option<Entry>) {
+        self.replace(node, entry);
+    }
+
+    /// Appends a line to the line table and returns its entry, to be stored in nodes. Arrays without a line table
+    /// return the line with `NO_ID`. Panics if the table already holds `u32::MAX` lines.
+    #[inline]
+    pub(crate) fn push_line(&mut self, line: Line) -> Entry {
+        if self.storage == NodeStorage::Inline {
+            return Entry { line, id: NO_ID };
+        }
+        let id = u32::try_from(self.lines.len())
+            .ok()
+            .filter(|&id| id != NO_ID)
+            .expect("more than u32::MAX lines added to a tree with NodeStorage::Indexed");
+        self.lines.push(line);
+        Entry { line, id }
+    }
+
+    /// Appends a batch of lines to the line table, and returns a lookup from each of them to the entry of its first
+    /// occurrence in the batch, for lines of the batch that are stored without being added one by one.
+    pub(crate) fn push_batch(&mut self, lines: &[Line]) -> impl Fn(Line) -> Entry + use<> {
+        let first_ids: HashMap<Line, u32> = match self.storage {
+            NodeStorage::Inline => HashMap::new(),
+            NodeStorage::Indexed => {
+                let ids: Vec<u32> = lines.iter().map(|&line| self.push_line(line).id).collect();
+                lines.iter().copied().zip(ids).rev().collect()
+            }
+        };
+        move |line| Entry {
+            line,
+            id: first_ids.get(&line).copied().unwrap_or(NO_ID),
+        }
+    }
+
+    /// Lines in the line table, in the order they were pushed. Empty without `NodeStorage::Indexed`.
+    pub(crate) fn line_table(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// Line table of `NodeStorage::Indexed` arrays, to move it to another array or to rewrite lines in place. Nodes
+    /// holding a rewritten id must be cleared or refilled with the new line.
+    pub(crate) fn line_table_mut(&mut self) -> &mut Vec<Line> {
+        &mut self.lines
+    }
+
+    /// Arena the nodes are taken from, if any.
+    pub(crate) fn arena(&self) -> Option<&Arena> {
+        self.arena.as_ref()
+    }
+
+    /// Removes the last `count` lines of the line table, which no node may hold anymore.
+    pub(crate) fn pop_lines(&mut self, count: usize) {
+        self.lines.truncate(self.lines.len().saturating_sub(count));
+    }
+
+    /// Frees the chunks whose nodes are all empty, and the spare capacity of the chunk table and line table. Freed
+    /// chunks go back to the arena, if any. Writes to the freed nodes allocate again, and may grow the table past its
+    /// new capacity. Subtree marks left by emptied nodes are cleared first.
+    /// Time complexity: O(number of chunks * CHUNK_LEN).
+    pub(crate) fn shrink_to_fit(&mut self) {
+        if self.len > 0 {
+            self.unmark_empty_subtrees(self.cursor(0));
+        }
+        let arena = self.arena.as_ref();
+        shrink_table(&mut self.chunks, |empty_chunks| match arena {
+            Some(arena) => arena.give_back_chunks(empty_chunks),
+            None => empty_chunks.for_each(drop),
+        });
+        shrink_table(&mut self.id_chunks, |empty_chunks| {
+            empty_chunks.for_each(drop)
+        });
+        self.lines.shrink_to_fit();
+    }
+
+    /// Empties every node and changes the number of nodes to `len`, reusing the chunk table's allocation.
+    pub(crate) fn reset(&mut self, len: usize) {
+        let chunk_count = Self::slots(len, self.layout).unwrap().div_ceil(CHUNK_LEN);
+        self.clear();
+        match self.storage {
+            NodeStorage::Inline => resize_table(&mut self.chunks, chunk_count),
+            NodeStorage::Indexed => resize_table(&mut self.id_chunks, chunk_count),
+        }
+        self.set_len(len);
+    }
+
+    /// Iterates over the contents of all nodes in index order, empty ones included.
+    #[cfg(test)]
+    pub(crate) fn iter(&self) -> impl Iterator<Item = Option<Line>> {
+        (0..self.len).map(|node_v_idx| self.get(node_v_idx))
+    }
+}
+
+/// Contents of slot `slot` of a chunk table, or `None` if it is empty.
+#[inline]
+fn read<T: Slot>(chunks: &ChunkTable<T>, slot: usize) -> Option<T> {
+    let chunk = chunks.get(slot / CHUNK_LEN)?.as_ref()?;
+    (chunk.occupied >> (slot % CHUNK_LEN) & 1 == 1).then_some(chunk.slots[slot % CHUNK_LEN])
+}
+
+/// Takes a chunk of lines from the arena, or allocates it.
+fn new_chunk(arena: Option<&Arena>) -> Box<Chunk> {
+    match arena.and_then(Arena::take_chunk) {
+        Some(mut chunk) => {
+            *chunk = Chunk::EMPTY;
+            chunk
+        }
+        None => Box::new(Chunk::EMPTY),
+    }
+}
+
+/// Subtree mark of slot `slot` of a chunk table. Slots of chunks that were never written to are not marked.
+#[inline]
+fn subtree_bit<T>(chunks: &ChunkTable<T>, slot: usize) -> bool {
+    chunks
+        .get(slot / CHUNK_LEN)
+        .and_then(Option::as_ref)
+        .is_some_and(|chunk| chunk.subtree >> (slot % CHUNK_LEN) & 1 == 1)
+}
+
+/// Sets the subtree mark of slot `slot` of a chunk table, taking its chunk from `new_chunk` if it was never written
+/// to, and returns whether it was already set.
+#[inline]
+fn mark<T>(
+    chunks: &mut ChunkTable<T>,
+    slot: usize,
+    new_chunk: impl FnOnce() -> Box<Chunk<T>>,
+) -> bool {
+    let (chunk_idx, bit) = (slot / CHUNK_LEN, 1 << (slot % CHUNK_LEN));
+    if chunk_idx >= chunks.len() {
+        chunks.resize_with(chunk_idx + 1, || None);
+    }
+    let chunk = chunks[chunk_idx].get_or_insert_with(new_chunk);
+    let marked = chunk.subtree & bit != 0;
+    chunk.subtree |= bit;
+    marked
+}
+
+/// Clears the subtree mark of slot `slot`, whose chunk is allocated.
+fn unmark<T>(chunks: &mut ChunkTable<T>, slot: usize) {
+    if let Some(Some(chunk)) = chunks.get_mut(slot / CHUNK_LEN) {
+        chunk.subtree &= !(1 << (slot % CHUNK_LEN));
+    }
+}
+
+/// Contents of slot `slot` of a chunk table, or `Slot::EMPTY` if it is empty.
+#[inline]
+fn read_or_empty<T: Slot>(chunks: &ChunkTable<T>, slot: usize) -> T {
+    match chunks.get(slot / CHUNK_LEN) {
+        Some(Some(chunk)) => chunk.slots[slot % CHUNK_LEN],
+        _ => T::EMPTY,
+    }
+}
+
+/// Writes `value` to slot `slot` of a chunk table, or empties it if `value` is `None`, and returns its previous
+/// contents. Takes the slot's chunk from `new_chunk` if it was never written to, unless it is only emptied.
+#[inline]
+fn write<T: Slot>(
+    chunks: &mut ChunkTable<T>,
+    slot: usize,
+    value: Option<T>,
+    new_chunk: impl FnOnce() -> Box<Chunk<T>>,
+) -> Option<T> {
+    let (chunk_idx, bit) = (slot / CHUNK_LEN, 1 << (slot % CHUNK_LEN));
+    if value.is_none() && !matches!(chunks.get(chunk_idx), Some(Some(_))) {
+        return None;
+    }
+    if chunk_idx >= chunks.len() {
+        chunks.resize(chunk_idx + 1, None);
+    }
+    let chunk = chunks[chunk_idx].get_or_insert_with(new_chunk);
+    let old = (chunk.occupied & bit != 0).then_some(chunk.slots[slot % CHUNK_LEN]);
+    chunk.slots[slot % CHUNK_LEN] = value.unwrap_or(T::EMPTY);
+    match value {
+        Some(_) => chunk.occupied |= bit,
+        None => chunk.occupied &= !bit,
+    }
+    old
+}
+
+/// Drops the chunks past the first `chunk_count`, and reserves room for `chunk_count` chunks.
+fn resize_table<T>(chunks: &mut ChunkTable<T>, chunk_count: usize) {
+    chunks.truncate(chunk_count);
+    chunks.reserve_exact(chunk_count - chunks.len());
+}
+
+fn table_bytes<T>(chunks: &ChunkTable<T>) -> usize {
+    chunks.capacity() * std::mem::size_of::<Option<Box<Chunk<T>>>>()
+        + chunks.iter().flatten().count() * std::mem::size_of::<Chunk<T>>()
+}
+
+fn clear_table<T: Slot>(chunks: &mut ChunkTable<T>) {
+    for chunk in chunks.iter_mut().flatten() {
+        **chunk = Chunk::EMPTY;
+    }
+}
+
+/// Passes the chunks whose slots are all empty to `free`, and frees the chunk table's spare capacity.
+fn shrink_table<T>(
+    chunks: &mut ChunkTable<T>,
+    free: impl FnOnce(&mut dyn Iterator<Item = Box<Chunk<T>>>),
+) {
+    free(
+        &mut chunks
+            .iter_mut()
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .is_some_and(|chunk| chunk.occupied == 0 && chunk.subtree == 0)
+            })
+            .filter_map(Option::take),
+    );
+    let used = chunks
+        .iter()
+        .rposition(Option::is_some)
+        .map_or(0, |chunk_idx| chunk_idx + 1);
+    chunks.truncate(used);
+    chunks.shrink_to_fit();
+}
+
+impl Drop for NodeArray {
+    fn drop(&mut self) {
+        if let Some(arena) = &self.arena {
+            arena.give_back(std::mem::take(&mut self.chunks));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(line: Line) -> Option<Entry> {
+        Some(Entry { line, id: NO_ID })
+    }
+
+    #[test]
+    fn test_chunks_on_first_write() {
+        let mut nodes =
+            NodeArray::try_new(1000, None, NodeLayout::Heap, NodeStorage::Inline).unwrap();
+        assert_eq!(nodes.len(), 1000);
+        assert!(nodes.iter().all(|line| line.is_none()));
+        let empty = nodes.bytes_allocated();
+
+        nodes.set(999, entry(Line::new(1, 2)));
+        nodes.set(998, entry(Line::new(3, 4)));
+        assert_eq!(nodes.get(999), Some(Line::new(1, 2)));
+        assert_eq!(nodes.get(0), None);
+        assert_eq!(nodes.line_or_empty(0), NO_LINE);
+        // The chunk of both nodes, and those of their ancestors 499, 249, 124 and 61 down to the root, which mark
+        // that their subtrees hold lines
+        let written = empty + 5 * std::mem::size_of::<Chunk>();
+        assert_eq!(nodes.bytes_allocated(), written);
+        assert!(nodes.subtree_may_hold(0) && nodes.subtree_may_hold(499));
+        assert!(!nodes.subtree_may_hold(500) && !nodes.subtree_may_hold(1));
+        // Emptying nodes never allocates
+        nodes.set(0, None);
+        assert_eq!(nodes.bytes_allocated(), written);
+
+        nodes.clear();
+        assert!(nodes.iter().all(|line| line.is_none()));
+        assert!(!nodes.subtree_may_hold(0));
+        assert_eq!(nodes.bytes_allocated(), written);
+
+        let capacity = nodes.capacity();
+        assert_eq!(
+            NodeArray::max_bytes(1000, NodeLayout::Heap, NodeStorage::Inline),
+            Some(16 * (1040 + 8))
+        );
+        nodes.reset(10);
+        assert_eq!(nodes.len(), 10);
+        assert_eq!(nodes.capacity(), capacity);
+        nodes.set(9, entry(Line::new(5, 6)));
+        assert_eq!(nodes.iter().flatten().count(), 1);
+    }
+
+    #[test]
+    fn test_occupancy() {
+        let mut nodes =
+            NodeArray::try_new(200, None, NodeLayout::Blocked, NodeStorage::Inline).unwrap();
+        // The line empty nodes hold is stored like any other
+        assert_eq!(nodes.replace(70, entry(NO_LINE)), None);
+        assert_eq!(nodes.get(70), Some(NO_LINE));
+        assert_eq!(nodes.replace(70, entry(Line::new(1, 1))), entry(NO_LINE));
+        assert_eq!(nodes.replace(70, None), entry(Line::new(1, 1)));
+        assert_eq!(nodes.get(70), None);
+        assert_eq!(nodes.line_or_empty(70), NO_LINE);
+
+        nodes.set(3, entry(Line::new(2, 2)));
+        // Node 70 was emptied, but its subtree stays marked until the marks are recomputed
+        assert!(nodes.subtree_may_hold(70) && nodes.subtree_may_hold(34));
+        nodes.shrink_to_fit();
+        assert!(!nodes.subtree_may_hold(70) && !nodes.subtree_may_hold(34));
+        assert!(nodes.subtree_may_hold(3) && nodes.subtree_may_hold(0));
+        assert_eq!(nodes.get(3), Some(Line::new(2, 2)));
+        assert_eq!(nodes.bytes_allocated(), std::mem::size_of::<Chunk>() + 8);
+    }
+
+    #[test]
+    fn test_indexed_storage() {
+        let mut nodes =
+            NodeArray::try_new(1000, None, NodeLayout::Heap, NodeStorage::Indexed).unwrap();
+        assert_eq!(
+            NodeArray::max_bytes(1000, NodeLayout::Heap, NodeStorage::Indexed),
+            Some(16 * (272 + 8))
+        );
+        let first = nodes.push_line(Line::new(1, 2));
+        let second = nodes.push_line(Line::new(3, 4));
+        assert_eq!((first.id, second.id), (0, 1));
+        nodes.set(999, Some(second));
+        nodes.set(998, Some(first));
+        assert_eq!(nodes.entry(999), Some(second));
+        assert_eq!(nodes.get(998), Some(Line::new(1, 2)));
+        assert_eq!(nodes.line_or_empty(0), NO_LINE);
+        assert_eq!(nodes.replace(998, None), Some(first));
+
+        // Equal lines of a batch share the id of the first one
+        let entry_of = nodes.push_batch(&[Line::new(5, 6), Line::new(7, 8), Line::new(5, 6)]);
+        assert_eq!(entry_of(Line::new(5, 6)).id, 2);
+        assert_eq!(entry_of(Line::new(7, 8)).id, 3);
+        assert_eq!(nodes.line_table().len(), 5);
+        nodes.pop_lines(3);
+        assert_eq!(nodes.line_table(), [Line::new(1, 2), Line::new(3, 4)]);
+
+        nodes.clear();
+        assert!(nodes.iter().all(|line| line.is_none()));
+        assert!(nodes.line_table().is_empty());
+        nodes.shrink_to_fit();
+        assert_eq!(nodes.bytes_allocated(), 0);
+    }
+
+    #[test]
+    fn test_blocked_slots() {
+        for levels in 1..=14 {
+            let len = (1 << levels) - 1;
+            let nodes =
+                NodeArray::try_new(len, None, NodeLayout::Blocked, NodeStorage::Inline).unwrap();
+            let slots = NodeArray::slots(len, NodeLayout::Blocked).unwrap();
+            let mut used = vec![false; slots];
+            for node_v_idx in 0..len {
+                let slot = nodes.cursor(node_v_idx).slot;
+                assert!(!used[slot]);
+                used[slot] = true;
+                // Parents and children share a chunk, except across the boundaries between blocks and in the last
+                // level of blocks, which are packed without padding
+                let depth = (node_v_idx + 1).ilog2();
+                if depth % BLOCK_LEVELS != 0 && depth / BLOCK_LEVELS + 1 < nodes.block_levels {
+                    assert_eq!(
+                        slot / CHUNK_LEN,
+                        nodes.cursor((node_v_idx - 1) / 2).slot / CHUNK_LEN
+                    );
+                }
+            }
+            // Only the padding at the end of the blocks above the last level is unused
+            let padding = NodeArray::first_block(nodes.block_levels - 1);
+            assert_eq!(used.iter().filter(|&&used| !used).count(), padding);
+        }
+        assert_eq!(NodeArray::slots(63, NodeLayout::Blocked), Some(63));
+        assert_eq!(NodeArray::slots(127, NodeLayout::Blocked), Some(64 + 64));
+        assert_eq!(
+            NodeArray::max_bytes(usize::MAX, NodeLayout::Blocked, NodeStorage::Inline),
+            None
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_out_of_bounds() {
+        let nodes = NodeArray::try_new(10, None, NodeLayout::Heap, NodeStorage::Inline).unwrap();
+        nodes.get(10);
+    }
+}
@@ -0,0 +1,265 @@
+use crate::Line;
+use crate::frozen::{envelope_order, envelope_pieces_sorted};
+
+/// A line present for queries whose time lies in `[from, until)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimedLine {
+    pub line: Line,
+    pub from: u64,
+    pub until: u64,
+}
+
+/// A query for the minimum at `x_coord` over the lines present at `time`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimedQuery {
+    pub x_coord: i64,
+    pub time: u64,
+}
+
+/// Answers all `queries` at once, returning the minimum y-value for each of them in input order,
+/// or `None` if no line is present at the query's time.
+///
+/// Works by divide and conquer over time: each line is attached to the O(log q) nodes of a segment tree over the
+/// queries (sorted by time) that its lifetime covers exactly, and every node answers its queries from the static
+/// envelope of its lines. Lines are sorted by slope and queries by x once up front, so each node needs only
+/// linear work. Any `i64` coordinates can be used. Values are compared exactly and clamped to the `i64` range.
+/// As in `LiChaoTree`, a minimum of `i64::MAX` is reported as such, not as `None`.
+/// Time complexity: O((n + q) log(q) + n log(n)) for n lines and q queries.
+pub fn solve(lines: &[TimedLine], queries: &[TimedQuery]) -> Vec<Option<i64>> {
+    let mut answers = vec![None; queries.len()];
+    if queries.is_empty() {
+        return answers;
+    }
+
+    // Leaf i of the segment tree is the i-th query in time order
+    let mut by_time: Vec<usize> = (0..queries.len()).collect();
+    by_time.sort_by_key(|&idx| queries[idx].time);
+    let leaf_of_time = |time: u64| by_time.partition_point(|&idx| queries[idx].time < time);
+
+    let mut sorted_lines: Vec<&TimedLine> = lines.iter().collect();
+    sorted_lines.sort_by(|a, b| envelope_order(&a.line, &b.line));
+    let mut node_lines: Vec<Vec<Line>> = vec![Vec::new(); 4 * queries.len()];
+    for timed in sorted_lines {
+        let (first, end) = (leaf_of_time(timed.from), leaf_of_time(timed.until));
+        if first < end {
+            attach(
+                &mut node_lines,
+                timed.line,
+                0,
+                0,
+                queries.len() - 1,
+                first,
+                end - 1,
+            );
+        }
+    }
+
+    // Leaf indices of all queries, sorted by x
+    let mut leaves_by_x: Vec<usize> = (0..queries.len()).collect();
+    leaves_by_x.sort_by_key(|&leaf| queries[by_time[leaf]].x_coord);
+    let mut best: Vec<Option<i128>> = vec![None; queries.len()];
+    let ctx = Context {
+        queries,
+        by_time: &by_time,
+        node_lines: &node_lines,
+    };
+    ctx.answer(&mut best, 0, 0, queries.len() - 1, leaves_by_x);
+
+    for (leaf, val) in best.into_iter().enumerate() {
+        answers[by_time[leaf]] =
+            val.map(|val| val.clamp(i64::MIN as i128, i64::MAX as i128) as i64);
+    }
+    answers
+}
+
+/// Adds `line` to the nodes exactly covering the leaves `[query_l_idx, query_r_idx]`.
+fn attach(
+    node_lines: &mut [Vec<Line>],
+    line: Line,
+    node_v_idx: usize,
+    range_l_idx: usize,
+    range_r_idx: usize,
+    query_l_idx: usize,
+    query_r_idx: usize,
+) {
+    if range_r_idx < query_l_idx || query_r_idx < range_l_idx {
+        return;
+    }
+    if query_l_idx <= range_l_idx && range_r_idx <= query_r_idx {
+        node_lines[node_v_idx].push(line);
+        return;
+    }
+    let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+    attach(
+        node_lines,
+        line,
+        2 * node_v_idx + 1,
+        range_l_idx,
+        range_m_idx,
+        query_l_idx,
+        query_r_idx,
+    );
+    attach(
+        node_lines,
+        line,
+        2 * node_v_idx + 2,
+        range_m_idx + 1,
+        range_r_idx,
+        query_l_idx,
+        query_r_idx,
+    );
+}
+
+struct Context<'a> {
+    queries: &'a [TimedQuery],
+    by_time: &'a [usize],
+    node_lines: &'a [Vec<Line>],
+}
+
+impl Context<'_> {
+    /// Folds every node's envelope into `best` for the leaves in its range. `leaves` holds exactly those leaves,
+    /// sorted by x, so each envelope is walked in a single pass.
+    fn answer(
+        &self,
+        best: &mut [Option<i128>],
+        node_v_idx: usize,
+        range_l_idx: usize,
+        range_r_idx: usize,
+        leaves: Vec<usize>,
+    ) {
+        let x_of = |leaf: usize| self.queries[self.by_time[leaf]].x_coord;
+        let lines = &self.node_lines[node_v_idx];
+        if !lines.is_empty() {
+            let pieces = envelope_pieces_sorted(i64::MIN, i64::MAX, lines);
+            let mut piece = 0;
+            for &leaf in &leaves {
+                let x = x_of(leaf);
+                while piece + 1 < pieces.len() && pieces[piece + 1].0 <= x {
+                    piece += 1;
+                }
+                let val = pieces[piece].1.eval_wide(x);
+                best[leaf] = Some(best[leaf].map_or(val, |best| best.min(val)));
+            }
+        }
+        if range_l_idx == range_r_idx {
+            return;
+        }
+
+        let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+        let (left, right): (Vec<usize>, Vec<usize>) =
+            leaves.into_iter().partition(|&leaf| leaf <= range_m_idx);
+        self.answer(best, 2 * node_v_idx + 1, range_l_idx, range_m_idx, left);
+        self.answer(
+            best,
+            2 * node_v_idx + 2,
+            range_m_idx + 1,
+            range_r_idx,
+            right,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_solve() {
+        let lines = [
+            TimedLine {
+                line: Line::new(1, 0),
+                from: 0,
+                until: 10,
+            },
+            TimedLine {
+                line: Line::new(-1, 0),
+                from: 5,
+                until: 7,
+            },
+        ];
+        let queries = [
+            TimedQuery {
+                x_coord: 3,
+                time: 4,
+            },
+            TimedQuery {
+                x_coord: 3,
+                time: 5,
+            },
+            TimedQuery {
+                x_coord: 3,
+                time: 7,
+            },
+            TimedQuery {
+                x_coord: 3,
+                time: 10,
+            },
+        ];
+        assert_eq!(
+            solve(&lines, &queries),
+            vec![Some(3), Some(-3), Some(3), None]
+        );
+        assert!(solve(&lines, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_max_value_is_reported() {
+        let lines = [TimedLine {
+            line: Line::new(0, i64::MAX),
+            from: 0,
+            until: 1,
+        }];
+        let queries = [
+            TimedQuery {
+                x_coord: 3,
+                time: 0,
+            },
+            TimedQuery {
+                x_coord: 3,
+                time: 1,
+            },
+        ];
+        assert_eq!(solve(&lines, &queries), vec![Some(i64::MAX), None]);
+    }
+
+    #[test]
+    fn test_against_naive() {
+        let mut rng = StdRng::seed_from_u64(260);
+        for _ in 0..50 {
+            let lines: Vec<TimedLine> = (0..rng.random_range(0..60))
+                .map(|_| {
+                    let from = rng.random_range(0..100);
+                    TimedLine {
+                        line: Line::new(
+                            rng.random_range(-1000..=1000),
+                            rng.random_range(-(1 << 50)..=(1 << 50)),
+                        ),
+                        from,
+                        until: rng.random_range(from..=100),
+                    }
+                })
+                .collect();
+            let queries: Vec<TimedQuery> = (0..rng.random_range(1..80))
+                .map(|_| TimedQuery {
+                    x_coord: rng.random_range(-(1 << 40)..=(1 << 40)),
+                    time: rng.random_range(0..100),
+                })
+                .collect();
+            let expected: Vec<Option<i64>> = queries
+                .iter()
+                .map(|query| {
+                    lines
+                        .iter()
+                        .filter(|timed| (timed.from..timed.until).contains(&query.time))
+                        .map(|timed| timed.line.eval(query.x_coord))
+                        .min()
+                })
+                .collect();
+            assert_eq!(solve(&lines, &queries), expected);
+        }
+    }
+}
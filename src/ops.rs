@@ -0,0 +1,213 @@
+use crate::{LiChaoError, LiChaoTree, Line, LogEntry, OutOfRangePolicy};
+
+/// A single tree operation, for callers that drive the tree from serialized or foreign input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Op {
+    /// See [`LiChaoTree::add_line`].
+    AddLine(Line),
+    /// See [`LiChaoTree::add_segment`].
+    AddSegment { line: Line, x_l: i64, x_r: i64 },
+    /// See [`LiChaoTree::assign_range`].
+    AssignRange { x_l: i64, x_r: i64, line: Line },
+    /// See [`LiChaoTree::query`].
+    Query(i64),
+}
+
+impl From<LogEntry> for Op {
+    fn from(entry: LogEntry) -> Self {
+        match entry {
+            LogEntry::Line(line) => Op::AddLine(line),
+            LogEntry::Segment { line, x_l, x_r } => Op::AddSegment { line, x_l, x_r },
+            LogEntry::Assign { line, x_l, x_r } => Op::AssignRange { x_l, x_r, line },
+        }
+    }
+}
+
+/// Result of one [`Op`], in the same position as the operation in the batch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpResult {
+    /// Whether the line was kept, as returned by `add_line`.
+    AddLine(bool),
+    /// Whether the segment was kept, as returned by `add_segment`.
+    AddSegment(bool),
+    AssignRange,
+    Query(Option<i64>),
+}
+
+impl LiChaoTree {
+    /// Applies `ops` in order and returns one result per operation.
+    /// The whole batch is validated first against the tree's policies: if a line cannot be stored, or a range or
+    /// x-coordinate would panic under the [`OutOfRangePolicy`], nothing is applied and the first offending
+    /// operation's error is returned. Otherwise every operation runs as the corresponding method would.
+    /// Query values are not validated, since they depend on the lines added earlier in the batch: under
+    /// `OverflowPolicy::Panic`, a query whose value overflows still panics after the operations before it.
+    pub fn apply_ops(&mut self, ops: &[Op]) -> Result<Vec<OpResult>, LiChaoError> {
+        for op in ops {
+            self.validate_op(op)?;
+        }
+        Ok(ops
+            .iter()
+            .map(|op| match *op {
+                Op::AddLine(line) => OpResult::AddLine(self.add_line(line)),
+                Op::AddSegment { line, x_l, x_r } => {
+                    OpResult::AddSegment(self.add_segment(line, x_l, x_r))
+                }
+                Op::AssignRange { x_l, x_r, line } => {
+                    self.assign_range(x_l, x_r, line);
+                    OpResult::AssignRange
+                }
+                Op::Query(x_coord) => OpResult::Query(self.query(x_coord)),
+            })
+            .collect())
+    }
+
+    /// Returns the error of the method `op` stands for if it would panic, leaving query values aside.
+    fn validate_op(&self, op: &Op) -> Result<(), LiChaoError> {
+        let ignore = self.out_of_range_policy() == OutOfRangePolicy::Ignore;
+        match *op {
+            Op::AddLine(line) => self.check_line(line),
+            Op::AddSegment { line, x_l, x_r } | Op::AssignRange { x_l, x_r, line } => {
+                self.check_line(line)?;
+                if !ignore {
+                    self.check_range(x_l, x_r)
+                } else if x_l > x_r {
+                    Err(LiChaoError::InvalidRange { x_l, x_r })
+                } else {
+                    Ok(())
+                }
+            }
+            Op::Query(x_coord) => {
+                if ignore || self.contains(x_coord) {
+                    Ok(())
+                } else {
+                    Err(LiChaoError::OutOfRange { x_coord })
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OverflowPolicy;
+
+    #[test]
+    fn test_apply_ops() {
+        let mut tree = LiChaoTree::new(0, 10);
+        let results = tree
+            .apply_ops(&[
+                Op::Query(3),
+                Op::AddLine(Line::new(1, 0)),
+                Op::AddLine(Line::new(1, 5)),
+                Op::AddSegment {
+                    line: Line::new(0, 2),
+                    x_l: 8,
+                    x_r: 10,
+                },
+                Op::Query(3),
+                Op::AssignRange {
+                    x_l: 2,
+                    x_r: 4,
+                    line: Line::new(0, 7),
+                },
+                Op::Query(3),
+                Op::Query(5),
+                Op::Query(9),
+            ])
+            .unwrap();
+        assert_eq!(
+            results,
+            vec![
+                OpResult::Query(None),
+                OpResult::AddLine(true),
+                OpResult::AddLine(false),
+                OpResult::AddSegment(true),
+                OpResult::Query(Some(3)),
+                OpResult::AssignRange,
+                OpResult::Query(Some(7)),
+                OpResult::Query(Some(5)),
+                OpResult::Query(Some(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_ops_is_atomic() {
+        let mut tree = LiChaoTree::new(0, 10);
+        tree.add_line(Line::new(0, 5));
+        for (op, err) in [
+            (Op::Query(11), LiChaoError::OutOfRange { x_coord: 11 }),
+            (
+                Op::AddSegment {
+                    line: Line::new(0, 0),
+                    x_l: 3,
+                    x_r: 12,
+                },
+                LiChaoError::OutOfRange { x_coord: 12 },
+            ),
+            (
+                Op::AssignRange {
+                    x_l: 4,
+                    x_r: 3,
+                    line: Line::new(0, 0),
+                },
+                LiChaoError::InvalidRange { x_l: 4, x_r: 3 },
+            ),
+        ] {
+            let ops = [Op::AddLine(Line::new(0, 1)), op];
+            assert_eq!(tree.apply_ops(&ops), Err(err));
+            assert_eq!(tree.query(0), Some(5));
+        }
+    }
+
+    #[test]
+    fn test_apply_ops_follows_policies() {
+        let mut tree = LiChaoTree::new(0, 10);
+        tree.set_out_of_range_policy(OutOfRangePolicy::Ignore);
+        let results = tree
+            .apply_ops(&[
+                Op::AddSegment {
+                    line: Line::new(0, 3),
+                    x_l: -5,
+                    x_r: 4,
+                },
+                Op::AssignRange {
+                    x_l: 8,
+                    x_r: 20,
+                    line: Line::new(0, 6),
+                },
+                Op::Query(2),
+                Op::Query(9),
+                Op::Query(11),
+            ])
+            .unwrap();
+        assert_eq!(
+            results[2..],
+            [
+                OpResult::Query(Some(3)),
+                OpResult::Query(Some(6)),
+                OpResult::Query(None),
+            ]
+        );
+        let reversed = Op::AssignRange {
+            x_l: 4,
+            x_r: 3,
+            line: Line::new(0, 0),
+        };
+        assert_eq!(
+            tree.apply_ops(&[Op::AddLine(Line::new(0, 1)), reversed]),
+            Err(LiChaoError::InvalidRange { x_l: 4, x_r: 3 })
+        );
+        assert_eq!(tree.query(2), Some(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "overflows i64")]
+    fn test_apply_ops_overflowing_query() {
+        let mut tree = LiChaoTree::new(0, 10);
+        tree.set_overflow_policy(OverflowPolicy::Panic);
+        let _ = tree.apply_ops(&[Op::AddLine(Line::new(i64::MIN, 0)), Op::Query(10)]);
+    }
+}
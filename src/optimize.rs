@@ -0,0 +1,163 @@
+/// A candidate solution reported by the oracle of [`min_ratio`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Candidate<T> {
+    pub cost: i64,
+    /// Must be strictly positive.
+    pub weight: i64,
+    /// Caller-defined description of the solution, e.g. the chosen subset or a `Line`.
+    pub witness: T,
+}
+
+/// The optimum found by [`min_ratio`].
+/// The certificate of optimality is the oracle's final answer, kept in `certificate`: at `λ = cost / weight`
+/// no candidate achieves `cost - λ * weight < 0`, so no candidate has a smaller ratio.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MinRatio<T> {
+    pub cost: i64,
+    pub weight: i64,
+    pub witness: T,
+    /// The oracle's minimum at `λ = cost / weight`, scaled by `weight` as in [`min_ratio`]:
+    /// `weight * cost' - cost * weight'` for the candidate `(cost', weight')` it returned.
+    /// It is never negative, and 0 when the oracle is exact, since the optimum itself reaches 0.
+    pub certificate: i128,
+    /// Number of oracle calls made, including the initial one.
+    pub iterations: usize,
+}
+
+impl<T> MinRatio<T> {
+    /// Returns the optimal ratio `cost / weight` as a float.
+    pub fn ratio(&self) -> f64 {
+        self.cost as f64 / self.weight as f64
+    }
+}
+
+/// Minimizes `cost / weight` over a candidate set using Dinkelbach's method.
+///
+/// `oracle(num, den)` is called with `λ = num / den` (`den > 0`) and must return a candidate minimizing
+/// `den * cost - num * weight`, i.e. `min (cost - λ * weight)` scaled by `den` so that it stays integral.
+/// This is typically answered by a Li-Chao tree query. The oracle returns `None` only if there are no candidates.
+///
+/// Each step strictly decreases `λ`, so for a finite candidate set the loop terminates,
+/// typically after a handful of calls. Returns `None` if the oracle has no candidates.
+pub fn min_ratio<T, F>(mut oracle: F) -> Option<MinRatio<T>>
+where
+    F: FnMut(i64, i64) -> Option<Candidate<T>>,
+{
+    let mut best = oracle(0, 1)?;
+    let mut iterations = 1;
+    loop {
+        if best.weight <= 0 {
+            panic!(
+                "min_ratio: candidate weight must be positive, got {}",
+                best.weight
+            );
+        }
+        let next = oracle(best.cost, best.weight)
+            .expect("min_ratio: oracle returned no candidate after previously returning one");
+        iterations += 1;
+        let value =
+            best.weight as i128 * next.cost as i128 - best.cost as i128 * next.weight as i128;
+        if value >= 0 {
+            return Some(MinRatio {
+                cost: best.cost,
+                weight: best.weight,
+                witness: best.witness,
+                certificate: value,
+                iterations,
+            });
+        }
+        best = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LiChaoTree, Line};
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn brute_oracle(items: &[(i64, i64)]) -> impl FnMut(i64, i64) -> Option<Candidate<usize>> + '_ {
+        move |num, den| {
+            items
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, (c, w))| den as i128 * *c as i128 - num as i128 * *w as i128)
+                .map(|(idx, (c, w))| Candidate {
+                    cost: *c,
+                    weight: *w,
+                    witness: idx,
+                })
+        }
+    }
+
+    #[test]
+    fn test_min_ratio_small() {
+        let items = [(10, 2), (9, 3), (20, 4), (7, 1)];
+        let best = min_ratio(brute_oracle(&items)).unwrap();
+        assert_eq!((best.cost, best.weight, best.witness), (9, 3, 1));
+        assert_eq!(best.ratio(), 3.0);
+        assert_eq!(best.certificate, 0);
+    }
+
+    #[test]
+    fn test_certificate_of_inexact_oracle() {
+        // After its first call, this oracle misses the optimum (5, 3) itself, so its minimum at λ = 5 / 3 is positive
+        let items = [(10, 4), (5, 3)];
+        let mut calls = 0;
+        let best = min_ratio(|num, den| {
+            calls += 1;
+            let candidates = if calls == 1 { &items[..] } else { &items[..1] };
+            brute_oracle(candidates)(num, den)
+        })
+        .unwrap();
+        assert_eq!((best.cost, best.weight), (5, 3));
+        assert_eq!(best.certificate, 3 * 10 - 5 * 4);
+    }
+
+    #[test]
+    fn test_min_ratio_empty() {
+        assert!(min_ratio(brute_oracle(&[])).is_none());
+    }
+
+    #[test]
+    fn test_min_ratio_random() {
+        let mut rng = StdRng::seed_from_u64(5);
+        for _ in 0..100 {
+            let items: Vec<(i64, i64)> = (0..rng.random_range(1..50))
+                .map(|_| (rng.random_range(-1000..=1000), rng.random_range(1..=100)))
+                .collect();
+            let best = min_ratio(brute_oracle(&items)).unwrap();
+            assert_eq!(best.certificate, 0);
+            for (c, w) in &items {
+                assert!(best.cost as i128 * *w as i128 <= *c as i128 * best.weight as i128);
+            }
+        }
+    }
+
+    #[test]
+    fn test_min_ratio_with_tree_oracle() {
+        // Candidates with weight 1..=4 and costs fixed per weight; with den = weight of the
+        // current best, `den * cost - num * weight` is a line in `num` for each fixed `den`.
+        let items = [(13, 1), (22, 2), (30, 3), (45, 4)];
+        let best = min_ratio(|num, den| {
+            let mut tree = LiChaoTree::new(num, num);
+            for (c, w) in items {
+                tree.add_line(Line::new(-w, den * c));
+            }
+            let value = tree.query(num)?;
+            items
+                .iter()
+                .find(|(c, w)| den * c - num * w == value)
+                .map(|(c, w)| Candidate {
+                    cost: *c,
+                    weight: *w,
+                    witness: (),
+                })
+        })
+        .unwrap();
+        assert_eq!((best.cost, best.weight), (30, 3));
+    }
+}
@@ -0,0 +1,62 @@
+use rayon::prelude::*;
+
+use crate::LiChaoTree;
+
+/// Number of queries answered by a thread at a time, enough to amortize scheduling over cheap queries.
+const GRAIN: usize = 1024;
+
+impl LiChaoTree {
+    /// Same as `query_many`, but splits the queries across rayon's thread pool. Results are in the same order as
+    /// `x_coords`. Out-of-bounds x-coordinates are handled as in `query`; with `OutOfRangePolicy::Panic`, the panic is
+    /// propagated to the caller.
+    /// Time complexity: O(x_coords.len() * log(domain_size)) work, spread over the pool's threads.
+    pub fn par_query_many(&self, x_coords: &[i64]) -> Vec<Option<i64>> {
+        let mut out = vec![None; x_coords.len()];
+        out.par_chunks_mut(GRAIN)
+            .zip(x_coords.par_chunks(GRAIN))
+            .for_each_init(Vec::new, |buf, (out, x_coords)| {
+                self.query_many_into(x_coords, buf);
+                out.copy_from_slice(buf);
+            });
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EnvelopeMode, LiChaoTree, LiChaoTreeBuilder, Line, OutOfRangePolicy};
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_matches_query_many() {
+        let mut rng = StdRng::seed_from_u64(305);
+        for mode in [EnvelopeMode::Min, EnvelopeMode::Max] {
+            let mut tree = LiChaoTreeBuilder::new(-100_000, 100_000)
+                .mode(mode)
+                .out_of_range_policy(OutOfRangePolicy::Ignore)
+                .build()
+                .unwrap();
+            for _ in 0..500 {
+                tree.add_line(Line::new(
+                    rng.random_range(-1000..=1000),
+                    rng.random_range(-1_000_000..=1_000_000),
+                ));
+            }
+            let x_coords: Vec<i64> = (0..10_000)
+                .map(|_| rng.random_range(-110_000..=110_000))
+                .collect();
+            assert_eq!(tree.par_query_many(&x_coords), tree.query_many(&x_coords));
+        }
+        assert!(LiChaoTree::new(0, 10).par_query_many(&[]).is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_out_of_range_panics() {
+        let tree = LiChaoTree::new(0, 10);
+        tree.par_query_many(&(0..5000).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,428 @@
+use std::collections::HashMap;
+
+use crate::Line;
+
+const NO_NODE: u32 = u32::MAX;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct PersistentNode {
+    line: Line,
+    children: [u32; 2],
+}
+
+/// Handle to one version of a [`PersistentLiChaoTree`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Version(usize);
+
+/// A Li-Chao Tree that keeps every version: `add_line` creates a new version and leaves the old one intact,
+/// and queries can be answered against any version.
+///
+/// Insertions copy only the nodes on their path, so each one costs O(log(domain_size)) time and memory.
+/// Like [`crate::SparseLiChaoTree`], nodes are created on demand and the whole `i64` range can be used as domain.
+/// Versions that are no longer needed can be dropped with [`PersistentLiChaoTree::retain`], which frees the nodes
+/// only they reach. Trees created with [`PersistentLiChaoTree::with_hash_consing`] also share structurally identical
+/// subtrees between versions.
+#[derive(Clone, Debug)]
+pub struct PersistentLiChaoTree {
+    // Children are always pushed before their parents, so they have smaller indices
+    nodes: Vec<PersistentNode>,
+    // Root of each version, `None` once the version is dropped
+    roots: Vec<Option<u32>>,
+    // Index of every node by its contents, for trees that share identical subtrees. Since children are shared first,
+    // equal contents mean equal subtrees.
+    interned: Option<HashMap<PersistentNode, u32>>,
+    x_min_coord: i64,
+    x_max_coord: i64,
+}
+
+impl PersistentLiChaoTree {
+    /// Creates a tree over the x-coordinates `[x_min_coord, x_max_coord]` holding only the empty version,
+    /// see [`PersistentLiChaoTree::empty`].
+    pub fn new(x_min_coord: i64, x_max_coord: i64) -> Self {
+        if x_min_coord > x_max_coord {
+            panic!(
+                "x_min_coord ({}) cannot be greater than x_max_coord ({})",
+                x_min_coord, x_max_coord
+            );
+        }
+        PersistentLiChaoTree {
+            nodes: Vec::new(),
+            roots: vec![Some(NO_NODE)],
+            interned: None,
+            x_min_coord,
+            x_max_coord,
+        }
+    }
+
+    /// Same as `new`, but every node is looked up before it is created, and an existing node with the same line and
+    /// children is shared instead. Versions that repeat the same insertions, or insert lines that change nothing,
+    /// then reuse the existing subtrees. The lookup table takes about as much memory as the nodes themselves, so this
+    /// pays off when histories are repetitive.
+    pub fn with_hash_consing(x_min_coord: i64, x_max_coord: i64) -> Self {
+        PersistentLiChaoTree {
+            interned: Some(HashMap::new()),
+            ..Self::new(x_min_coord, x_max_coord)
+        }
+    }
+
+    /// The version without any lines.
+    pub fn empty(&self) -> Version {
+        Version(0)
+    }
+
+    /// The most recently created version, which may have been dropped by `retain`.
+    pub fn latest(&self) -> Version {
+        Version(self.roots.len() - 1)
+    }
+
+    /// Number of versions created so far, including the empty one and the dropped ones.
+    pub fn version_count(&self) -> usize {
+        self.roots.len()
+    }
+
+    /// Returns whether `version` belongs to this tree and has not been dropped.
+    pub fn contains(&self, version: Version) -> bool {
+        matches!(self.roots.get(version.0), Some(Some(_)))
+    }
+
+    /// Number of nodes held for the versions that have not been dropped.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn root(&self, version: Version) -> u32 {
+        match self.roots.get(version.0) {
+            Some(Some(root)) => *root,
+            Some(None) => panic!("version was dropped"),
+            None => panic!("version does not belong to this tree"),
+        }
+    }
+
+    /// Drops every version for which `keep` returns `false`, except the empty version, and frees the nodes that no
+    /// remaining version reaches. Handles of the remaining versions stay valid, and new versions can still be derived
+    /// from them. Dropped versions cannot be queried or extended anymore, but each still takes a few bytes, so that
+    /// handles never change meaning.
+    /// Time complexity: O(number of nodes before the call).
+    pub fn retain(&mut self, mut keep: impl FnMut(Version) -> bool) {
+        for (idx, root) in self.roots.iter_mut().enumerate().skip(1) {
+            if root.is_some() && !keep(Version(idx)) {
+                *root = None;
+            }
+        }
+
+        // Mark, from parents down to children, which have smaller indices
+        let mut reachable = vec![false; self.nodes.len()];
+        for &root in self.roots.iter().flatten() {
+            if root != NO_NODE {
+                reachable[root as usize] = true;
+            }
+        }
+        for idx in (0..self.nodes.len()).rev() {
+            if reachable[idx] {
+                for child in self.nodes[idx].children {
+                    if child != NO_NODE {
+                        reachable[child as usize] = true;
+                    }
+                }
+            }
+        }
+
+        // Sweep, keeping the order of the nodes so that children stay before their parents
+        let mut new_idx = vec![NO_NODE; self.nodes.len()];
+        let mut kept = 0;
+        for idx in 0..self.nodes.len() {
+            if reachable[idx] {
+                let mut node = self.nodes[idx];
+                for child in &mut node.children {
+                    if *child != NO_NODE {
+                        *child = new_idx[*child as usize];
+                    }
+                }
+                self.nodes[kept] = node;
+                new_idx[idx] = kept as u32;
+                kept += 1;
+            }
+        }
+        self.nodes.truncate(kept);
+        self.nodes.shrink_to_fit();
+        for root in self.roots.iter_mut().flatten() {
+            if *root != NO_NODE {
+                *root = new_idx[*root as usize];
+            }
+        }
+        if let Some(interned) = &mut self.interned {
+            *interned = self
+                .nodes
+                .iter()
+                .enumerate()
+                .map(|(idx, &node)| (node, idx as u32))
+                .collect();
+        }
+    }
+
+    /// Rounds down, so that `[l, m]` and `[m + 1, r]` are both non-empty for `l < r`.
+    #[inline]
+    fn mid(range_l: i64, range_r: i64) -> i64 {
+        (range_l as i128 + range_r as i128).div_euclid(2) as i64
+    }
+
+    fn push_node(&mut self, node: PersistentNode) -> u32 {
+        if let Some(&idx) = self
+            .interned
+            .as_ref()
+            .and_then(|interned| interned.get(&node))
+        {
+            return idx;
+        }
+        let idx = u32::try_from(self.nodes.len())
+            .ok()
+            .filter(|&idx| idx != NO_NODE)
+            .expect("PersistentLiChaoTree: too many nodes");
+        self.nodes.push(node);
+        if let Some(interned) = &mut self.interned {
+            interned.insert(node, idx);
+        }
+        idx
+    }
+
+    /// Creates a new version containing the lines of `version` plus `line`, and returns its handle.
+    /// Panics if `version` belongs to another tree or was dropped.
+    /// Time complexity: O(log(domain_size)).
+    pub fn add_line(&mut self, version: Version, line: Line) -> Version {
+        let root = self.root(version);
+        let new_root = self.add_line_internal(root, line, self.x_min_coord, self.x_max_coord);
+        self.roots.push(Some(new_root));
+        self.latest()
+    }
+
+    /// Returns a copy of `node` with `line_to_add` inserted, leaving `node` itself untouched.
+    fn add_line_internal(
+        &mut self,
+        node: u32,
+        mut line_to_add: Line,
+        range_l: i64,
+        range_r: i64,
+    ) -> u32 {
+        if node == NO_NODE {
+            return self.push_node(PersistentNode {
+                line: line_to_add,
+                children: [NO_NODE; 2],
+            });
+        }
+
+        let mut copy = self.nodes[node as usize];
+        let range_m = Self::mid(range_l, range_r);
+        // Compared exactly, see SparseLiChaoTree
+        if line_to_add.eval_wide(range_m) < copy.line.eval_wide(range_m) {
+            std::mem::swap(&mut copy.line, &mut line_to_add);
+        }
+        if range_l != range_r {
+            if line_to_add.eval_wide(range_l) < copy.line.eval_wide(range_l) {
+                copy.children[0] =
+                    self.add_line_internal(copy.children[0], line_to_add, range_l, range_m);
+            } else if line_to_add.eval_wide(range_r) < copy.line.eval_wide(range_r) {
+                copy.children[1] =
+                    self.add_line_internal(copy.children[1], line_to_add, range_m + 1, range_r);
+            }
+        }
+        self.push_node(copy)
+    }
+
+    /// Queries the minimum y-value at `x_coord` in `version`, or `None` if it has no lines.
+    /// Values are clamped to the `i64` range like in `SparseLiChaoTree`.
+    /// Panics if `x_coord` is outside the tree's bounds, or `version` belongs to another tree or was dropped.
+    /// Time complexity: O(log(domain_size)).
+    pub fn query(&self, version: Version, x_coord: i64) -> Option<i64> {
+        if x_coord < self.x_min_coord || x_coord > self.x_max_coord {
+            panic!("{} does not fit inside the tree's bounds", x_coord);
+        }
+
+        let mut best: Option<i128> = None;
+        let (mut node, mut range_l, mut range_r) =
+            (self.root(version), self.x_min_coord, self.x_max_coord);
+        while node != NO_NODE {
+            let current = &self.nodes[node as usize];
+            let val = current.line.eval_wide(x_coord);
+            best = Some(best.map_or(val, |best| best.min(val)));
+            if range_l == range_r {
+                break;
+            }
+            let range_m = Self::mid(range_l, range_r);
+            node = if x_coord <= range_m {
+                range_r = range_m;
+                current.children[0]
+            } else {
+                range_l = range_m + 1;
+                current.children[1]
+            };
+        }
+        best.map(|best| best.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_versions_are_independent() {
+        let mut tree = PersistentLiChaoTree::new(0, 10);
+        let empty = tree.empty();
+        let rising = tree.add_line(empty, Line::new(1, 0));
+        let both = tree.add_line(rising, Line::new(-1, 10));
+        let falling = tree.add_line(empty, Line::new(-1, 10));
+
+        assert_eq!(tree.query(empty, 0), None);
+        assert_eq!(tree.query(rising, 10), Some(10));
+        assert_eq!(tree.query(both, 10), Some(0));
+        assert_eq!(tree.query(both, 0), Some(0));
+        assert_eq!(tree.query(falling, 0), Some(10));
+        assert_eq!(tree.latest(), falling);
+        assert_eq!(tree.version_count(), 4);
+    }
+
+    #[test]
+    fn test_random_history() {
+        let mut rng = StdRng::seed_from_u64(255);
+        let mut tree = PersistentLiChaoTree::new(-1_000_000_000_000, 1_000_000_000_000);
+        // Lines contained in each version, built as a random tree of versions
+        let mut contents: Vec<Vec<Line>> = vec![Vec::new()];
+        let mut versions = vec![tree.empty()];
+        for _ in 0..300 {
+            let parent = rng.random_range(0..versions.len());
+            let line = Line::new(
+                rng.random_range(-1000..=1000),
+                rng.random_range(-1_000_000..=1_000_000),
+            );
+            versions.push(tree.add_line(versions[parent], line));
+            let mut lines = contents[parent].clone();
+            lines.push(line);
+            contents.push(lines);
+
+            for _ in 0..5 {
+                let k = rng.random_range(0..versions.len());
+                let x = rng.random_range(-1_000_000_000_000..=1_000_000_000_000);
+                let expected = contents[k].iter().map(|line| line.eval(x)).min();
+                assert_eq!(tree.query(versions[k], x), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut rng = StdRng::seed_from_u64(212);
+        let mut tree = PersistentLiChaoTree::new(-1000, 1000);
+        let mut contents: Vec<Vec<Line>> = vec![Vec::new()];
+        let mut versions = vec![tree.empty()];
+        for round in 0..10 {
+            for _ in 0..100 {
+                let parent = rng.random_range(0..versions.len());
+                let line = Line::new(
+                    rng.random_range(-100..=100),
+                    rng.random_range(-10_000..=10_000),
+                );
+                versions.push(tree.add_line(versions[parent], line));
+                let mut lines = contents[parent].clone();
+                lines.push(line);
+                contents.push(lines);
+            }
+
+            // Keep a random half of the versions
+            let before = tree.node_count();
+            let kept: HashSet<Version> = versions
+                .iter()
+                .filter(|_| rng.random_bool(0.5))
+                .copied()
+                .collect();
+            tree.retain(|version| kept.contains(&version));
+            assert!(tree.node_count() <= before);
+            let (mut versions_left, mut contents_left) = (Vec::new(), Vec::new());
+            for (k, version) in versions.iter().enumerate() {
+                assert_eq!(tree.contains(*version), k == 0 || kept.contains(version));
+                if tree.contains(*version) {
+                    versions_left.push(*version);
+                    contents_left.push(contents[k].clone());
+                }
+            }
+            (versions, contents) = (versions_left, contents_left);
+            for (version, lines) in versions.iter().zip(&contents) {
+                for x in [-1000, -round, 0, 17, 1000] {
+                    assert_eq!(
+                        tree.query(*version, x),
+                        lines.iter().map(|line| line.eval(x)).min()
+                    );
+                }
+            }
+        }
+
+        // Dropping everything but the empty version frees every node
+        tree.retain(|_| false);
+        assert_eq!(tree.node_count(), 0);
+        assert_eq!(tree.query(tree.empty(), 0), None);
+    }
+
+    #[test]
+    fn test_hash_consing() {
+        let mut rng = StdRng::seed_from_u64(241);
+        let lines: Vec<Line> = (0..200)
+            .map(|_| {
+                Line::new(
+                    rng.random_range(-100..=100),
+                    rng.random_range(-10_000..=10_000),
+                )
+            })
+            .collect();
+        let mut plain = PersistentLiChaoTree::new(-1000, 1000);
+        let mut shared = PersistentLiChaoTree::with_hash_consing(-1000, 1000);
+        // The same history twice from the empty version, with every line added twice in a row
+        let mut heads = Vec::new();
+        for tree in [&mut plain, &mut shared] {
+            let mut branches = [tree.empty(); 2];
+            for line in &lines {
+                for branch in &mut branches {
+                    *branch = tree.add_line(*branch, *line);
+                    *branch = tree.add_line(*branch, *line);
+                }
+            }
+            heads.push(branches);
+        }
+        assert!(shared.node_count() * 4 <= plain.node_count());
+        for x in -1000..=1000 {
+            let expected = lines.iter().map(|line| line.eval(x)).min();
+            for (tree, branches) in [(&plain, heads[0]), (&shared, heads[1])] {
+                assert_eq!(tree.query(branches[0], x), expected);
+                assert_eq!(tree.query(branches[1], x), expected);
+            }
+        }
+
+        // Sharing continues after nodes are freed
+        let head = heads[1][0];
+        shared.retain(|version| version == head);
+        let count = shared.node_count();
+        let again = shared.add_line(head, lines[0]);
+        assert_eq!(shared.node_count(), count);
+        assert_eq!(shared.query(again, 5), shared.query(head, 5));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_query_dropped_version() {
+        let mut tree = PersistentLiChaoTree::new(0, 10);
+        let version = tree.add_line(tree.empty(), Line::new(1, 0));
+        tree.retain(|_| false);
+        tree.query(version, 0);
+    }
+
+    #[test]
+    fn test_max_value_is_reported() {
+        let mut tree = PersistentLiChaoTree::new(-10, 10);
+        let flat = tree.add_line(tree.empty(), Line::new(0, i64::MAX));
+        assert_eq!(tree.query(flat, 3), Some(i64::MAX));
+        assert_eq!(tree.query(tree.empty(), 3), None);
+    }
+}
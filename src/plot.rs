@@ -0,0 +1,171 @@
+use std::error::Error;
+use std::path::Path;
+
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+use crate::{LiChaoTree, Line, LogEntry};
+
+const STORED_LINE_COLOR: RGBColor = RGBColor(190, 190, 190);
+const ENVELOPE_COLOR: RGBColor = BLUE;
+
+impl LiChaoTree {
+    /// Renders the stored lines and the envelope to an SVG file of `size` pixels, see [`LiChaoTree::plot_on`].
+    pub fn plot_svg(&self, path: impl AsRef<Path>, size: (u32, u32)) -> Result<(), Box<dyn Error>> {
+        let root = SVGBackend::new(path.as_ref(), size).into_drawing_area();
+        self.plot_on(&root)?;
+        root.present()?;
+        Ok(())
+    }
+
+    /// Renders the stored lines and the envelope to a PNG file of `size` pixels, see [`LiChaoTree::plot_on`].
+    pub fn plot_png(&self, path: impl AsRef<Path>, size: (u32, u32)) -> Result<(), Box<dyn Error>> {
+        let root = BitMapBackend::new(path.as_ref(), size).into_drawing_area();
+        self.plot_on(&root)?;
+        root.present()?;
+        Ok(())
+    }
+
+    /// Draws the tree onto a plotters drawing area, for other backends or to embed the plot in a larger figure.
+    ///
+    /// The lines are drawn in gray over the ranges they cover, and the envelope in blue on top of them. With a line
+    /// log, see [`LiChaoTree::set_keep_lines`], these are the lines as they were added; otherwise they are the lines
+    /// stored in the nodes, each over its node's range. The y-axis spans the values of the envelope, so lines far
+    /// above or below it are clipped. An empty envelope only draws the background.
+    /// Time complexity: O(domain_size * log(domain_size)).
+    pub fn plot_on<DB: DrawingBackend>(
+        &self,
+        area: &DrawingArea<DB, Shift>,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        area.fill(&WHITE)?;
+        let pieces = self.to_piecewise();
+        if pieces.is_empty() {
+            return Ok(());
+        }
+
+        let x_min_coord = self.x_min_coord as f64;
+        let x_max_coord = self.get_x_coord_from_idx(self.domain_size - 1) as f64;
+        let values = pieces
+            .iter()
+            .flat_map(|piece| [eval(&piece.line, piece.x_l), eval(&piece.line, piece.x_r)]);
+        let y_lo = values.clone().fold(f64::INFINITY, f64::min);
+        let y_hi = values.fold(f64::NEG_INFINITY, f64::max);
+        // Degenerate ranges would scale everything onto a single pixel
+        let x_pad = if x_max_coord > x_min_coord { 0.0 } else { 0.5 };
+        let y_pad = if y_hi > y_lo {
+            (y_hi - y_lo) * 0.05
+        } else {
+            1.0
+        };
+        let (y_lo, y_hi) = (y_lo - y_pad, y_hi + y_pad);
+        let mut chart = ChartBuilder::on(area)
+            .margin(10)
+            .build_cartesian_2d(x_min_coord - x_pad..x_max_coord + x_pad, y_lo..y_hi)?;
+
+        for entry in self.entries() {
+            let (line, x_l, x_r) = match entry {
+                LogEntry::Line(line) => (line, x_min_coord, x_max_coord),
+                LogEntry::Segment { line, x_l, x_r } | LogEntry::Assign { line, x_l, x_r } => {
+                    (line, x_l as f64, x_r as f64)
+                }
+            };
+            // Part of the range on which the line stays within the y-axis
+            let (m, c) = (line.m as f64, line.c as f64);
+            let (x_l, x_r) = match m {
+                0.0 if c < y_lo || c > y_hi => continue,
+                0.0 => (x_l, x_r),
+                _ => {
+                    let (x_a, x_b) = ((y_lo - c) / m, (y_hi - c) / m);
+                    (x_l.max(x_a.min(x_b)), x_r.min(x_a.max(x_b)))
+                }
+            };
+            if x_l <= x_r {
+                let points = [(x_l, m * x_l + c), (x_r, m * x_r + c)];
+                chart.draw_series(LineSeries::new(points, STORED_LINE_COLOR))?;
+            }
+        }
+
+        // Pieces without a gap between them are joined into one polyline
+        let mut polyline: Vec<(f64, f64)> = Vec::new();
+        for (i, piece) in pieces.iter().enumerate() {
+            polyline.push((piece.x_l as f64, eval(&piece.line, piece.x_l)));
+            if piece.x_r != piece.x_l {
+                polyline.push((piece.x_r as f64, eval(&piece.line, piece.x_r)));
+            }
+            let joined = pieces.get(i + 1).is_some_and(|next| {
+                self.get_idx_from_x_coord(next.x_l) == self.get_idx_from_x_coord(piece.x_r) + 1
+            });
+            if joined {
+                continue;
+            }
+            if polyline.len() == 1 {
+                chart.draw_series([Circle::new(polyline[0], 2, ENVELOPE_COLOR.filled())])?;
+            } else {
+                chart.draw_series(LineSeries::new(
+                    polyline.drain(..),
+                    ENVELOPE_COLOR.stroke_width(2),
+                ))?;
+            }
+            polyline.clear();
+        }
+        Ok(())
+    }
+}
+
+fn eval(line: &Line, x_coord: i64) -> f64 {
+    line.eval_wide(x_coord) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LiChaoTree, Line};
+
+    fn plot(tree: &LiChaoTree, extension: &str) -> Vec<u8> {
+        let path =
+            std::env::temp_dir().join(format!("lichao-plot-{}.{}", std::process::id(), extension));
+        match extension {
+            "svg" => tree.plot_svg(&path, (320, 240)).unwrap(),
+            _ => tree.plot_png(&path, (320, 240)).unwrap(),
+        }
+        let image = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        image
+    }
+
+    #[test]
+    fn test_plot_svg() {
+        let mut tree = LiChaoTree::new(-10, 10);
+        let empty = String::from_utf8(plot(&tree, "svg")).unwrap();
+        assert!(!empty.contains("<polyline"));
+
+        tree.add_line(Line::new(2, 3));
+        tree.add_line(Line::new(-1, 10));
+        let svg = String::from_utf8(plot(&tree, "svg")).unwrap();
+        assert!(svg.starts_with("<svg"));
+        // Both stored lines, and the envelope on top
+        assert_eq!(svg.matches("<polyline").count(), 3);
+
+        // Gaps split the envelope, and single-point pieces are drawn as dots
+        let mut tree = LiChaoTree::new(-10, 10);
+        tree.add_segment(Line::new(2, 3), -10, -5);
+        tree.add_segment(Line::new(0, -5), 5, 5);
+        let svg = String::from_utf8(plot(&tree, "svg")).unwrap();
+        assert_eq!(svg.matches("<polyline").count(), 3);
+        assert_eq!(svg.matches("<circle").count(), 1);
+
+        tree.set_keep_lines(true);
+        tree.add_line(Line::new(0, 0));
+        assert!(
+            String::from_utf8(plot(&tree, "svg"))
+                .unwrap()
+                .contains("<polyline")
+        );
+    }
+
+    #[test]
+    fn test_plot_png() {
+        let mut tree = LiChaoTree::new_with_points(&[-1_000_000, 0, 1_000_000]);
+        tree.add_line(Line::new(1, 0));
+        assert!(plot(&tree, "png").starts_with(b"\x89PNG"));
+    }
+}
@@ -0,0 +1,23 @@
+use crate::Line;
+
+// SAFETY: `Line` is `#[repr(C)]` with two `i64` fields, so it has no padding, and every bit pattern is a valid line.
+unsafe impl bytemuck::Zeroable for Line {}
+unsafe impl bytemuck::Pod for Line {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LiChaoTree, Line};
+
+    #[test]
+    fn test_cast_from_bytes() {
+        let words: [i64; 4] = [2, 3, -1, 10];
+        let bytes: &[u8] = bytemuck::cast_slice(&words);
+        let lines: &[Line] = bytemuck::cast_slice(bytes);
+        assert_eq!(lines, &[Line::new(2, 3), Line::new(-1, 10)]);
+
+        let mut tree = LiChaoTree::new(0, 10);
+        assert_eq!(tree.add_lines(lines).kept, 2);
+        assert_eq!(tree.query(5), Some(5));
+        assert_eq!(bytemuck::bytes_of(&lines[0]), &bytes[..16]);
+    }
+}
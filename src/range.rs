@@ -0,0 +1,162 @@
+use crate::{Line, SparseLiChaoTree};
+
+/// Lines that only apply to a range of positions, each position holding its own lower envelope over a shared
+/// x-domain.
+///
+/// Positions are an index space separate from the x-coordinates: `add_line(l, r, line)` makes `line` available
+/// to every position in `[l, r]`, over the whole x-domain, and `query(pos, x)` takes the minimum at `x` over the
+/// lines available to `pos`. This is an outer segment tree over positions whose nodes hold Li-Chao trees,
+/// created on first use, so memory is O(lines * log(len)) nodes.
+/// Insertions take O(log(len) * log(domain_size)) time, and so do queries.
+#[derive(Clone, Debug)]
+pub struct RangeLiChaoTree {
+    trees: Vec<Option<SparseLiChaoTree>>,
+    len: usize,
+    x_min_coord: i64,
+    x_max_coord: i64,
+}
+
+impl RangeLiChaoTree {
+    /// Creates an empty structure over the positions `[0, len)` and the x-coordinates `[x_min_coord, x_max_coord]`.
+    pub fn new(len: usize, x_min_coord: i64, x_max_coord: i64) -> Self {
+        if x_min_coord > x_max_coord {
+            panic!(
+                "x_min_coord ({}) cannot be greater than x_max_coord ({})",
+                x_min_coord, x_max_coord
+            );
+        }
+        RangeLiChaoTree {
+            trees: vec![None; 4 * len],
+            len,
+            x_min_coord,
+            x_max_coord,
+        }
+    }
+
+    /// Number of positions.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Makes `line` available to the positions `[pos_l, pos_r]`.
+    /// Panics if the range is reversed or not inside `[0, len)`.
+    /// Time complexity: O(log(len) * log(domain_size)).
+    pub fn add_line(&mut self, pos_l: usize, pos_r: usize, line: Line) {
+        if pos_l > pos_r || pos_r >= self.len {
+            panic!(
+                "[{}, {}] is not a valid range of positions in [0, {})",
+                pos_l, pos_r, self.len
+            );
+        }
+        self.add_line_internal(line, 0, 0, self.len - 1, pos_l, pos_r);
+    }
+
+    fn add_line_internal(
+        &mut self,
+        line: Line,
+        node_v_idx: usize,
+        range_l: usize,
+        range_r: usize,
+        query_l: usize,
+        query_r: usize,
+    ) {
+        if query_r < range_l || range_r < query_l {
+            return;
+        }
+        if query_l <= range_l && range_r <= query_r {
+            let (x_min_coord, x_max_coord) = (self.x_min_coord, self.x_max_coord);
+            self.trees[node_v_idx]
+                .get_or_insert_with(|| SparseLiChaoTree::new(x_min_coord, x_max_coord))
+                .add_line(line);
+            return;
+        }
+        let range_m = range_l + (range_r - range_l) / 2;
+        self.add_line_internal(line, 2 * node_v_idx + 1, range_l, range_m, query_l, query_r);
+        self.add_line_internal(
+            line,
+            2 * node_v_idx + 2,
+            range_m + 1,
+            range_r,
+            query_l,
+            query_r,
+        );
+    }
+
+    /// Queries the minimum y-value at `x_coord` over the lines available to `pos`, or `None` if there are none.
+    /// Panics if `pos` is not in `[0, len)` or `x_coord` is outside the x-domain.
+    /// Time complexity: O(log(len) * log(domain_size)).
+    pub fn query(&self, pos: usize, x_coord: i64) -> Option<i64> {
+        if pos >= self.len {
+            panic!("position {} is not in [0, {})", pos, self.len);
+        }
+        if x_coord < self.x_min_coord || x_coord > self.x_max_coord {
+            panic!("{} does not fit inside the tree's bounds", x_coord);
+        }
+        let mut best = None;
+        let (mut node_v_idx, mut range_l, mut range_r) = (0, 0, self.len - 1);
+        loop {
+            if let Some(val) = self.trees[node_v_idx]
+                .as_ref()
+                .and_then(|tree| tree.query(x_coord))
+            {
+                best = Some(best.map_or(val, |best: i64| best.min(val)));
+            }
+            if range_l == range_r {
+                return best;
+            }
+            let range_m = range_l + (range_r - range_l) / 2;
+            if pos <= range_m {
+                (node_v_idx, range_r) = (2 * node_v_idx + 1, range_m);
+            } else {
+                (node_v_idx, range_l) = (2 * node_v_idx + 2, range_m + 1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_matches_naive() {
+        let mut rng = StdRng::seed_from_u64(264);
+        for _ in 0..50 {
+            let len = rng.random_range(1..=20);
+            let mut tree = RangeLiChaoTree::new(len, -30, 30);
+            let mut lines = Vec::new();
+            for _ in 0..30 {
+                let pos_l = rng.random_range(0..len);
+                let pos_r = rng.random_range(pos_l..len);
+                let line = Line::new(rng.random_range(-10..=10), rng.random_range(-100..=100));
+                tree.add_line(pos_l, pos_r, line);
+                lines.push((pos_l, pos_r, line));
+                for pos in 0..len {
+                    for x in -30..=30 {
+                        let expected = lines
+                            .iter()
+                            .filter(|&&(l, r, _)| l <= pos && pos <= r)
+                            .map(|(_, _, line)| line.eval(x))
+                            .min();
+                        assert_eq!(tree.query(pos, x), expected);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_range_out_of_bounds() {
+        let mut tree = RangeLiChaoTree::new(5, 0, 10);
+        tree.add_line(2, 5, Line::new(1, 0));
+    }
+}
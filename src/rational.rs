@@ -0,0 +1,64 @@
+use num_rational::Ratio;
+use num_traits::{CheckedAdd, CheckedMul};
+
+use crate::Scalar;
+
+// Ratios compare exactly, so a GenericLiChaoTree over them never confuses two different values.
+// Arithmetic is checked instead of saturating: a saturated ratio would no longer be exact.
+macro_rules! impl_scalar_ratio {
+    ($($t:ty),*) => {
+        $(
+            impl Scalar for Ratio<$t> {
+                #[inline]
+                fn mul_add(m: Self, x: Self, c: Self) -> Self {
+                    m.checked_mul(&x)
+                        .and_then(|val| val.checked_add(&c))
+                        .unwrap_or_else(|| panic!("{} * {} + {} overflows Ratio<{}>", m, x, c, stringify!($t)))
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar_ratio!(i64, i128);
+
+#[cfg(test)]
+mod tests {
+    use num_rational::Ratio;
+
+    use crate::{GenericLiChaoTree, GenericLine};
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_fractional_coordinates() {
+        let r = |n: i64, d: i64| Ratio::new(n, d);
+        let mut tree = GenericLiChaoTree::new([r(0, 1), r(1, 3), r(1, 2), r(2, 3), r(1, 1)]);
+        tree.add_line(GenericLine::new(r(1, 1), r(0, 1)));
+        tree.add_line(GenericLine::new(r(-1, 1), r(2, 3)));
+        assert_eq!(tree.query(r(1, 3)), Some(r(1, 3)));
+        // Both lines are worth 1/3 here, a float could break the tie either way
+        assert_eq!(tree.query(r(1, 2)), Some(r(1, 6)));
+        assert_eq!(tree.query(r(2, 3)), Some(r(0, 1)));
+    }
+
+    #[test]
+    fn test_matches_naive() {
+        let mut rng = StdRng::seed_from_u64(279);
+        let mut random_ratio =
+            |max: i64| Ratio::new(rng.random_range(-max..=max), rng.random_range(1..=12));
+        let x_coords: Vec<Ratio<i64>> = (0..40).map(|_| random_ratio(50)).collect();
+        let mut tree = GenericLiChaoTree::new(x_coords.iter().copied());
+        let mut lines = Vec::new();
+        for _ in 0..100 {
+            let line = GenericLine::new(random_ratio(20), random_ratio(500));
+            tree.add_line(line);
+            lines.push(line);
+            for &x in &x_coords {
+                assert_eq!(tree.query(x), lines.iter().map(|line| line.eval(x)).min());
+            }
+        }
+    }
+}
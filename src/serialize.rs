@@ -0,0 +1,402 @@
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    EnvelopeMode, LiChaoError, LiChaoTree, LiChaoTreeBuilder, LogEntry, NodeLayout, NodeStorage,
+    Op, OutOfRangePolicy, OverflowPolicy, TieBreak,
+};
+
+/// The x-coordinates of a serialized tree.
+#[derive(Serialize, Deserialize)]
+enum Domain {
+    /// The integers in `[x_min_coord, x_max_coord]`, as for `LiChaoTree::new`.
+    Range { x_min_coord: i64, x_max_coord: i64 },
+    /// Grid points of a tree built with `with_coordinate_map`, in increasing order.
+    Points(Vec<i64>),
+}
+
+/// Serialized form of a tree: its domain and settings, and the insertions that reproduce its envelope.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "LiChaoTree")]
+struct TreeRepr {
+    domain: Domain,
+    mode: EnvelopeMode,
+    tie_break: TieBreak,
+    overflow_policy: OverflowPolicy,
+    out_of_range_policy: OutOfRangePolicy,
+    keep_lines: bool,
+    // Absent from data written before they were serialized, which only held trees with the defaults
+    #[serde(default)]
+    layout: NodeLayout,
+    #[serde(default)]
+    storage: NodeStorage,
+    entries: Vec<LogEntry>,
+}
+
+/// Serializes the domain, the settings and the line log. Trees that keep no log are serialized with the lines
+/// stored in their nodes instead, which reproduces the same envelope.
+/// Coordinate maps are serialized as their list of grid points, and deserialized as a [`crate::SortedCoords`].
+/// The node layout and storage are kept. With [`NodeStorage::Indexed`], lines are numbered by their position in the
+/// log, so `query_argmin` gives the same indices after a round trip only for trees that keep a log.
+impl Serialize for LiChaoTree {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let domain = match self.coord_map {
+            None => Domain::Range {
+                x_min_coord: self.x_min_coord,
+                x_max_coord: self.get_x_coord_from_idx(self.domain_size - 1),
+            },
+            Some(_) => Domain::Points(
+                (0..self.domain_size)
+                    .map(|idx| self.get_x_coord_from_idx(idx))
+                    .collect(),
+            ),
+        };
+        TreeRepr {
+            domain,
+            mode: self.mode,
+            tie_break: self.tie_break,
+            overflow_policy: self.overflow_policy,
+            out_of_range_policy: self.out_of_range_policy,
+            keep_lines: self.line_log.is_some(),
+            layout: self.layout(),
+            storage: self.storage(),
+            entries: self.entries(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Rebuilds the tree by replaying the serialized insertions with `apply_ops`. Invalid data, such as an empty domain
+/// or an insertion that `apply_ops` rejects, is reported as a deserialization error with the corresponding
+/// [`LiChaoError`] message.
+impl<'de> Deserialize<'de> for LiChaoTree {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = TreeRepr::deserialize(deserializer)?;
+        let builder = match repr.domain {
+            Domain::Range {
+                x_min_coord,
+                x_max_coord,
+            } => LiChaoTreeBuilder::new(x_min_coord, x_max_coord),
+            Domain::Points(points) => {
+                if points.is_empty() || points.windows(2).any(|pair| pair[0] >= pair[1]) {
+                    return Err(D::Error::custom(
+                        "grid points must be non-empty and strictly increasing",
+                    ));
+                }
+                LiChaoTreeBuilder::with_points(&points)
+            }
+        };
+        let mut tree = builder
+            .mode(repr.mode)
+            .tie_break(repr.tie_break)
+            .overflow_policy(repr.overflow_policy)
+            .out_of_range_policy(repr.out_of_range_policy)
+            .keep_lines(repr.keep_lines)
+            .layout(repr.layout)
+            .storage(repr.storage)
+            .build()
+            .map_err(D::Error::custom)?;
+        let ops: Vec<Op> = repr.entries.into_iter().map(Op::from).collect();
+        tree.apply_ops(&ops).map_err(D::Error::custom)?;
+        Ok(tree)
+    }
+}
+
+/// The entries appended to a tree's line log since an earlier snapshot, which bring a replica of the tree at that
+/// snapshot up to date without shipping the whole tree, see [`LiChaoTree::delta_since`]. Serialize it with any serde
+/// format to send it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreeDelta {
+    from: usize,
+    entries: Vec<LogEntry>,
+}
+
+impl TreeDelta {
+    /// Snapshot the delta starts from.
+    pub fn from(&self) -> usize {
+        self.from
+    }
+
+    /// Snapshot the delta leads to, to pass to the next `delta_since`.
+    pub fn to(&self) -> usize {
+        self.from + self.entries.len()
+    }
+}
+
+impl LiChaoTree {
+    /// Current snapshot of the tree, to pass to `delta_since` later: the number of entries in its line log, or
+    /// `None` if no log is kept, see `set_keep_lines`.
+    pub fn snapshot_id(&self) -> Option<usize> {
+        self.line_log.as_ref().map(Vec::len)
+    }
+
+    /// Collects the insertions made since `snapshot_id`, as returned by `snapshot_id`, for a replica of the tree at
+    /// that snapshot to catch up with `apply_delta`. Replicas start from a full serialization of a tree that keeps a
+    /// line log. Snapshots only stay valid while the log grows: after `clear`, `rollback`, `map_lines`,
+    /// `extend_domain` or a restart of the log, replicas must be sent the whole tree again.
+    /// Fails with `LiChaoError::LinesNotKept` if no log is kept, and with `LiChaoError::SnapshotMismatch` if
+    /// `snapshot_id` is past the end of the log.
+    /// Time complexity: O(entries since the snapshot).
+    pub fn delta_since(&self, snapshot_id: usize) -> Result<TreeDelta, LiChaoError> {
+        let log = self.line_log.as_ref().ok_or(LiChaoError::LinesNotKept)?;
+        let Some(entries) = log.get(snapshot_id..) else {
+            return Err(LiChaoError::SnapshotMismatch {
+                snapshot_id,
+                log_len: log.len(),
+            });
+        };
+        Ok(TreeDelta {
+            from: snapshot_id,
+            entries: entries.iter().map(|&(entry, _)| entry).collect(),
+        })
+    }
+
+    /// Replays the insertions of `delta` on this replica, which must be at the delta's starting snapshot.
+    /// Fails with `LiChaoError::LinesNotKept` if no log is kept, `LiChaoError::SnapshotMismatch` if the log does not
+    /// end where the delta starts, or with the error of `apply_ops` if an entry does not fit this tree, in which case
+    /// nothing is applied.
+    /// Time complexity: O(k * log^2(domain_size)) for k entries.
+    pub fn apply_delta(&mut self, delta: &TreeDelta) -> Result<(), LiChaoError> {
+        let log_len = self.snapshot_id().ok_or(LiChaoError::LinesNotKept)?;
+        if log_len != delta.from {
+            return Err(LiChaoError::SnapshotMismatch {
+                snapshot_id: delta.from,
+                log_len,
+            });
+        }
+        let ops: Vec<Op> = delta.entries.iter().map(|&entry| Op::from(entry)).collect();
+        self.apply_ops(&ops).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Line;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn assert_same_envelope(tree: &LiChaoTree, restored: &LiChaoTree) {
+        assert!(tree.envelope_eq(restored));
+        assert_eq!(
+            (
+                restored.mode(),
+                restored.tie_break(),
+                restored.overflow_policy()
+            ),
+            (tree.mode(), tree.tie_break(), tree.overflow_policy())
+        );
+        assert_eq!(restored.out_of_range_policy(), tree.out_of_range_policy());
+    }
+
+    #[test]
+    fn test_line_round_trip() {
+        let json = serde_json::to_string(&Line::new(-3, 7)).unwrap();
+        assert_eq!(json, r#"{"m":-3,"c":7}"#);
+        assert_eq!(
+            serde_json::from_str::<Line>(&json).unwrap(),
+            Line::new(-3, 7)
+        );
+    }
+
+    #[test]
+    fn test_tree_round_trip() {
+        let mut rng = StdRng::seed_from_u64(289);
+        for (mode, keep_lines) in [
+            (EnvelopeMode::Min, false),
+            (EnvelopeMode::Min, true),
+            (EnvelopeMode::Max, false),
+            (EnvelopeMode::Max, true),
+        ] {
+            for _ in 0..50 {
+                let mut tree = crate::LiChaoTreeBuilder::new(-30, 30)
+                    .mode(mode)
+                    .tie_break(TieBreak::SmallerSlope)
+                    .overflow_policy(OverflowPolicy::Checked)
+                    .out_of_range_policy(OutOfRangePolicy::Ignore)
+                    .keep_lines(keep_lines)
+                    .build()
+                    .unwrap();
+                for _ in 0..40 {
+                    let line = Line::new(rng.random_range(-20..=20), rng.random_range(-400..=400));
+                    let x_l = rng.random_range(-30..=30);
+                    let x_r = rng.random_range(x_l..=30);
+                    match rng.random_range(0..3) {
+                        0 => {
+                            tree.add_line(line);
+                        }
+                        1 => {
+                            tree.add_segment(line, x_l, x_r);
+                        }
+                        _ => tree.assign_range(x_l, x_r, line),
+                    }
+                }
+                let json = serde_json::to_string(&tree).unwrap();
+                let restored: LiChaoTree = serde_json::from_str(&json).unwrap();
+                assert_same_envelope(&tree, &restored);
+                assert_eq!(restored.keeps_lines(), keep_lines);
+                assert!(restored.lines().eq(tree.lines()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_indexed_round_trip() {
+        let mut rng = StdRng::seed_from_u64(310);
+        for keep_lines in [false, true] {
+            let mut tree = LiChaoTreeBuilder::new(-30, 30)
+                .layout(NodeLayout::Blocked)
+                .storage(NodeStorage::Indexed)
+                .keep_lines(keep_lines)
+                .build()
+                .unwrap();
+            for _ in 0..40 {
+                tree.add_line(Line::new(
+                    rng.random_range(-20..=20),
+                    rng.random_range(-400..=400),
+                ));
+            }
+            let json = serde_json::to_string(&tree).unwrap();
+            let restored: LiChaoTree = serde_json::from_str(&json).unwrap();
+            assert_eq!(restored.layout(), NodeLayout::Blocked);
+            assert_eq!(restored.storage(), NodeStorage::Indexed);
+            assert_same_envelope(&tree, &restored);
+            for x in -30..=30 {
+                let (val, idx) = tree.query_argmin(x).unwrap();
+                let (restored_val, restored_idx) = restored.query_argmin(x).unwrap();
+                assert_eq!(restored_val, val);
+                if keep_lines {
+                    assert_eq!(restored.lines().nth(restored_idx), tree.lines().nth(idx));
+                }
+            }
+        }
+
+        // Data written before the layout and storage were serialized holds trees with the defaults
+        let old = r#"{"domain":{"Range":{"x_min_coord":0,"x_max_coord":10}},"mode":"Min","tie_break":"SmallerSlope","overflow_policy":"WideningI128","out_of_range_policy":"Panic","keep_lines":false,"entries":[]}"#;
+        let restored: LiChaoTree = serde_json::from_str(old).unwrap();
+        assert_eq!(restored.storage(), NodeStorage::Inline);
+        assert_eq!(restored.layout(), NodeLayout::Heap);
+    }
+
+    #[test]
+    fn test_delta_replication() {
+        let mut rng = StdRng::seed_from_u64(223);
+        let mut primary = crate::LiChaoTreeBuilder::new(-30, 30)
+            .keep_lines(true)
+            .build()
+            .unwrap();
+        primary.add_line(Line::new(1, 0));
+        let mut replica: LiChaoTree =
+            serde_json::from_str(&serde_json::to_string(&primary).unwrap()).unwrap();
+        let mut snapshot = primary.snapshot_id().unwrap();
+        for _ in 0..30 {
+            for _ in 0..rng.random_range(0..10) {
+                let line = Line::new(rng.random_range(-20..=20), rng.random_range(-400..=400));
+                let x_l = rng.random_range(-30..=30);
+                let x_r = rng.random_range(x_l..=30);
+                match rng.random_range(0..3) {
+                    0 => {
+                        primary.add_line(line);
+                    }
+                    1 => {
+                        primary.add_segment(line, x_l, x_r);
+                    }
+                    _ => primary.assign_range(x_l, x_r, line),
+                }
+            }
+            let delta = primary.delta_since(snapshot).unwrap();
+            assert_eq!(delta.to(), primary.snapshot_id().unwrap());
+            let delta: TreeDelta =
+                serde_json::from_str(&serde_json::to_string(&delta).unwrap()).unwrap();
+            replica.apply_delta(&delta).unwrap();
+            snapshot = delta.to();
+            assert_same_envelope(&primary, &replica);
+            assert!(replica.lines().eq(primary.lines()));
+        }
+
+        let delta = primary.delta_since(snapshot).unwrap();
+        assert_eq!(
+            primary.delta_since(snapshot + 1),
+            Err(LiChaoError::SnapshotMismatch {
+                snapshot_id: snapshot + 1,
+                log_len: snapshot
+            })
+        );
+        primary.add_line(Line::new(0, -1000));
+        let late = primary.delta_since(snapshot + 1).unwrap();
+        assert!(matches!(
+            replica.apply_delta(&late),
+            Err(LiChaoError::SnapshotMismatch { .. })
+        ));
+        replica.apply_delta(&delta).unwrap();
+        assert_eq!(
+            LiChaoTree::new(0, 1).apply_delta(&delta),
+            Err(LiChaoError::LinesNotKept)
+        );
+
+        // An entry that does not fit leaves the replica untouched
+        let mut small = crate::LiChaoTreeBuilder::new(0, 10)
+            .keep_lines(true)
+            .build()
+            .unwrap();
+        let mut wide = crate::LiChaoTreeBuilder::new(0, 20)
+            .keep_lines(true)
+            .build()
+            .unwrap();
+        wide.add_line(Line::new(1, 1));
+        wide.add_segment(Line::new(0, 0), 5, 15);
+        assert_eq!(
+            small.apply_delta(&wide.delta_since(0).unwrap()),
+            Err(LiChaoError::OutOfRange { x_coord: 15 })
+        );
+        assert_eq!(small.snapshot_id(), Some(0));
+        assert_eq!(small.query(3), None);
+    }
+
+    #[test]
+    fn test_coordinate_map_round_trip() {
+        let mut tree = LiChaoTree::new_with_points(&[-1_000_000, 3, 7, 1_000_000]);
+        tree.add_line(Line::new(2, 1));
+        tree.add_segment(Line::new(0, -5), 3, 7);
+        let json = serde_json::to_string(&tree).unwrap();
+        let restored: LiChaoTree = serde_json::from_str(&json).unwrap();
+        assert_same_envelope(&tree, &restored);
+        assert_eq!(restored.query(7), Some(-5));
+        assert_eq!(restored.query(1_000_000), Some(2_000_001));
+    }
+
+    #[test]
+    fn test_invalid_tree() {
+        let tree = |domain: &str, entries: &str| {
+            let json = format!(
+                concat!(
+                    r#"{{"domain":{},"mode":"Min","tie_break":"KeepExisting","overflow_policy":"WideningI128","#,
+                    r#""out_of_range_policy":"Panic","keep_lines":false,"entries":{}}}"#
+                ),
+                domain, entries
+            );
+            serde_json::from_str::<LiChaoTree>(&json).map_err(|err| err.to_string())
+        };
+        assert!(tree(r#"{"Range":{"x_min_coord":0,"x_max_coord":10}}"#, "[]").is_ok());
+        assert!(
+            tree(r#"{"Range":{"x_min_coord":10,"x_max_coord":0}}"#, "[]")
+                .unwrap_err()
+                .starts_with("x_min_coord (10) cannot be greater than x_max_coord (0)")
+        );
+        assert!(tree(r#"{"Points":[3,1]}"#, "[]").is_err());
+        assert!(tree(r#"{"Points":[]}"#, "[]").is_err());
+        assert!(
+            tree(
+                r#"{"Range":{"x_min_coord":0,"x_max_coord":10}}"#,
+                r#"[{"Segment":{"line":{"m":1,"c":0},"x_l":5,"x_r":11}}]"#
+            )
+            .unwrap_err()
+            .starts_with("11 does not fit inside the tree's bounds")
+        );
+        let flat = format!(r#"[{{"Line":{{"m":0,"c":{}}}}}]"#, i64::MAX);
+        let flat = tree(r#"{"Range":{"x_min_coord":0,"x_max_coord":10}}"#, &flat).unwrap();
+        assert_eq!(flat.query(3), Some(i64::MAX));
+    }
+}
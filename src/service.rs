@@ -0,0 +1,156 @@
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+
+use crate::{LiChaoTree, Line};
+
+/// A thread-safe wrapper for read-mostly workloads.
+///
+/// Queries run against an immutable snapshot of the tree. Insertions are buffered and only become
+/// visible once [`LiChaoService::publish`] applies them to a copy of the snapshot and swaps it in.
+/// Readers never wait for a publish to finish: they only hold a read lock long enough to clone
+/// an `Arc`, and a snapshot they already hold stays valid until they drop it.
+pub struct LiChaoService {
+    snapshot: RwLock<Arc<LiChaoTree>>,
+    pending: Mutex<Vec<Line>>,
+    publishing: Mutex<()>,
+    publish_threshold: Option<usize>,
+}
+
+impl LiChaoService {
+    /// Creates a service serving `tree`. Buffered lines are only published by explicit `publish` calls.
+    pub fn new(tree: LiChaoTree) -> Self {
+        LiChaoService {
+            snapshot: RwLock::new(Arc::new(tree)),
+            pending: Mutex::new(Vec::new()),
+            publishing: Mutex::new(()),
+            publish_threshold: None,
+        }
+    }
+
+    /// Creates a service that also publishes automatically once `threshold` lines are buffered.
+    pub fn with_publish_threshold(tree: LiChaoTree, threshold: usize) -> Self {
+        LiChaoService {
+            publish_threshold: Some(threshold.max(1)),
+            ..LiChaoService::new(tree)
+        }
+    }
+
+    /// Returns the currently published snapshot.
+    pub fn snapshot(&self) -> Arc<LiChaoTree> {
+        self.snapshot
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Queries the currently published snapshot, see [`LiChaoTree::query`].
+    pub fn query(&self, x_coord: i64) -> Option<i64> {
+        self.snapshot().query(x_coord)
+    }
+
+    /// Buffers a line for the next publish. The line is not visible to queries until then.
+    pub fn add_line(&self, line: Line) {
+        let buffered = {
+            let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+            pending.push(line);
+            pending.len()
+        };
+        if self
+            .publish_threshold
+            .is_some_and(|threshold| buffered >= threshold)
+        {
+            self.publish();
+        }
+    }
+
+    /// Returns the number of buffered lines that have not been published yet.
+    pub fn pending_len(&self) -> usize {
+        self.pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Applies all buffered lines to a copy of the current snapshot and atomically publishes it.
+    /// Concurrent publishes are serialized so that no buffered line is lost.
+    /// Returns the number of lines published.
+    pub fn publish(&self) -> usize {
+        let _guard = self
+            .publishing
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let lines =
+            std::mem::take(&mut *self.pending.lock().unwrap_or_else(PoisonError::into_inner));
+        if lines.is_empty() {
+            return 0;
+        }
+
+        let mut next = LiChaoTree::clone(&self.snapshot());
+        for line in &lines {
+            next.add_line(*line);
+        }
+        *self
+            .snapshot
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Arc::new(next);
+        lines.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_visible_after_publish() {
+        let service = LiChaoService::new(LiChaoTree::new(0, 10));
+        service.add_line(Line::new(1, 0));
+        assert_eq!(service.query(5), None);
+        assert_eq!(service.pending_len(), 1);
+
+        let before = service.snapshot();
+        assert_eq!(service.publish(), 1);
+        assert_eq!(service.query(5), Some(5));
+        // Old snapshots are unaffected
+        assert_eq!(before.query(5), None);
+        assert_eq!(service.publish(), 0);
+    }
+
+    #[test]
+    fn test_auto_publish() {
+        let service = LiChaoService::with_publish_threshold(LiChaoTree::new(0, 10), 2);
+        service.add_line(Line::new(0, 3));
+        assert_eq!(service.query(0), None);
+        service.add_line(Line::new(0, 2));
+        assert_eq!(service.query(0), Some(2));
+        assert_eq!(service.pending_len(), 0);
+    }
+
+    #[test]
+    fn test_concurrent_readers_and_writers() {
+        let service = LiChaoService::with_publish_threshold(LiChaoTree::new(0, 1000), 16);
+        std::thread::scope(|scope| {
+            for writer in 0..4 {
+                let service = &service;
+                scope.spawn(move || {
+                    for c in 0..250 {
+                        service.add_line(Line::new(writer, 1000 - 4 * c - writer));
+                    }
+                });
+            }
+            for _ in 0..4 {
+                let service = &service;
+                scope.spawn(move || {
+                    let mut last = i64::MAX;
+                    for _ in 0..1000 {
+                        // Snapshots only ever gain lines, so the value at 0 never increases
+                        let val = service.query(0).unwrap_or(i64::MAX);
+                        assert!(val <= last);
+                        last = val;
+                    }
+                });
+            }
+        });
+        service.publish();
+        assert_eq!(service.query(0), Some(1000 - 4 * 249 - 3));
+    }
+}
@@ -0,0 +1,224 @@
+use crate::{INF_VAL, LiChaoTree, Line, NO_LINE};
+
+/// Number of queries walked down the tree together, one per 64-bit lane of a 256-bit vector.
+const LANES: usize = 4;
+
+impl LiChaoTree {
+    /// Batch version of `query` for trees with the default overflow policy in min mode, used by `query_many_into`.
+    ///
+    /// Queries are walked down the tree in groups of `LANES`, one level at a time, and the lines met at each level are
+    /// evaluated at the group's x-coordinates with AVX2 when the CPU supports it. Vector products are only exact for
+    /// 32-bit factors, so groups with a larger x-coordinate, and levels with a larger slope, are evaluated one by one.
+    pub(crate) fn query_many_simd(&self, x_coords: &[i64], out: &mut Vec<Option<i64>>) {
+        #[cfg(target_arch = "x86_64")]
+        let avx2 = std::arch::is_x86_feature_detected!("avx2");
+        #[cfg(not(target_arch = "x86_64"))]
+        let avx2 = false;
+
+        let mut groups = x_coords.chunks_exact(LANES);
+        for group in &mut groups {
+            let group: [i64; LANES] = group.try_into().unwrap();
+            if group
+                .iter()
+                .all(|&x_coord| self.contains(x_coord) && i32::try_from(x_coord).is_ok())
+            {
+                out.extend(self.query_lanes(group, avx2));
+            } else {
+                out.extend(group.iter().map(|&x_coord| self.query(x_coord)));
+            }
+        }
+        out.extend(
+            groups
+                .remainder()
+                .iter()
+                .map(|&x_coord| self.query(x_coord)),
+        );
+    }
+
+    /// Same as `query` at each of the x-coordinates, which must be inside the domain and fit in `i32`.
+    fn query_lanes(&self, x_coords: [i64; LANES], avx2: bool) -> [Option<i64>; LANES] {
+        let query_idx = x_coords.map(|x_coord| self.get_idx_from_x_coord(x_coord));
+        // Node of each lane and the range of indices it covers, or `None` once the lane's path has ended
+        let mut lanes = [Some((0, 0, self.domain_size - 1)); LANES];
+        let mut best = [INF_VAL; LANES];
+        while lanes.iter().any(Option::is_some) {
+            // Finished lanes evaluate the empty line, which never lowers their minimum
+            let lines = lanes.map(|lane| {
+                lane.map_or(NO_LINE, |(node_v_idx, _, _)| {
+                    self.nodes.line_or_empty(node_v_idx)
+                })
+            });
+            best = min_eval(best, lines, x_coords, avx2);
+
+            for (lane, query_idx) in lanes.iter_mut().zip(query_idx) {
+                *lane = lane.and_then(|(node_v_idx, range_l_idx, range_r_idx)| {
+                    if range_l_idx == range_r_idx || self.children_cleared(node_v_idx) {
+                        return None;
+                    }
+                    let range_m_idx = range_l_idx + (range_r_idx - range_l_idx) / 2;
+                    Some(if query_idx <= range_m_idx {
+                        (2 * node_v_idx + 1, range_l_idx, range_m_idx)
+                    } else {
+                        (2 * node_v_idx + 2, range_m_idx + 1, range_r_idx)
+                    })
+                });
+            }
+        }
+
+        std::array::from_fn(|lane| {
+            if best[lane] == INF_VAL && self.path_is_empty(query_idx[lane]) {
+                return None;
+            }
+            #[cfg(feature = "instrumentation")]
+            self.record_win(query_idx[lane]);
+            Some(best[lane])
+        })
+    }
+}
+
+/// Lane-wise `best.min(line.eval_clamped(x))`, with AVX2 if `avx2` is set and the slopes fit in `i32`.
+#[cfg_attr(not(target_arch = "x86_64"), allow(unused_variables))]
+fn min_eval(
+    best: [i64; LANES],
+    lines: [Line; LANES],
+    x_coords: [i64; LANES],
+    avx2: bool,
+) -> [i64; LANES] {
+    #[cfg(target_arch = "x86_64")]
+    if avx2 && lines.iter().all(|line| i32::try_from(line.m).is_ok()) {
+        // SAFETY: AVX2 support was detected at runtime
+        return unsafe { avx2::min_eval(best, lines, x_coords) };
+    }
+    std::array::from_fn(|lane| best[lane].min(lines[lane].eval_clamped(x_coords[lane])))
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use std::arch::x86_64::*;
+
+    use super::LANES;
+    use crate::Line;
+
+    /// Lane-wise `best.min(line.eval_clamped(x))`. Slopes and x-coordinates must fit in `i32`.
+    #[target_feature(enable = "avx2")]
+    pub(super) fn min_eval(
+        best: [i64; LANES],
+        lines: [Line; LANES],
+        x_coords: [i64; LANES],
+    ) -> [i64; LANES] {
+        let best = load(best);
+        let m = load(lines.map(|line| line.m));
+        let c = load(lines.map(|line| line.c));
+        let x = load(x_coords);
+
+        // Multiplies the low 32 bits of each lane as signed integers, which is exact for 32-bit factors
+        let product = _mm256_mul_epi32(m, x);
+        let sum = _mm256_add_epi64(product, c);
+        // The sum overflowed if it has a different sign than both terms, and then saturates towards their sign
+        let zero = _mm256_setzero_si256();
+        let overflowed = _mm256_cmpgt_epi64(
+            zero,
+            _mm256_and_si256(_mm256_xor_si256(sum, product), _mm256_xor_si256(sum, c)),
+        );
+        let saturated = _mm256_xor_si256(
+            _mm256_set1_epi64x(i64::MAX),
+            _mm256_cmpgt_epi64(zero, product),
+        );
+        let value = _mm256_blendv_epi8(sum, saturated, overflowed);
+
+        let lower = _mm256_cmpgt_epi64(best, value);
+        store(_mm256_blendv_epi8(best, value, lower))
+    }
+
+    #[target_feature(enable = "avx2")]
+    fn load(lanes: [i64; LANES]) -> __m256i {
+        // SAFETY: the array is exactly 256 bits, and unaligned loads have no alignment requirement
+        unsafe { _mm256_loadu_si256(lanes.as_ptr().cast()) }
+    }
+
+    #[target_feature(enable = "avx2")]
+    fn store(vector: __m256i) -> [i64; LANES] {
+        let mut lanes = [0; LANES];
+        // SAFETY: the array is exactly 256 bits, and unaligned stores have no alignment requirement
+        unsafe { _mm256_storeu_si256(lanes.as_mut_ptr().cast(), vector) };
+        lanes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LiChaoTree, Line, OverflowPolicy, SortedCoords};
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_matches_scalar_queries() {
+        let mut rng = StdRng::seed_from_u64(304);
+        for _ in 0..100 {
+            // Grid points on both sides of the 32-bit range, so that some groups are evaluated one by one
+            let points: Vec<i64> = (0..rng.random_range(1..=200))
+                .map(|_| match rng.random_range(0..4) {
+                    0 => rng.random_range(-(1 << 40)..=1 << 40),
+                    _ => rng.random_range(-1000..=1000),
+                })
+                .collect();
+            let coords = SortedCoords::new(points);
+            let points = coords.as_slice().to_vec();
+            let mut tree = LiChaoTree::with_coordinate_map(coords);
+            for _ in 0..rng.random_range(0..30) {
+                // Mostly 32-bit slopes, with some large ones and intercepts near the ends of the i64 range
+                let m = match rng.random_range(0..4) {
+                    0 => rng.random_range(i64::MIN..=i64::MAX),
+                    _ => rng.random_range(i32::MIN as i64..=i32::MAX as i64),
+                };
+                let c = match rng.random_range(0..3) {
+                    0 => rng.random_range(i64::MAX - (1 << 62)..i64::MAX),
+                    1 => rng.random_range(i64::MIN..i64::MIN + (1 << 62)),
+                    _ => rng.random_range(-1000..=1000),
+                };
+                let i = rng.random_range(0..points.len());
+                let (x_l, x_r) = (points[i], points[rng.random_range(i..points.len())]);
+                match rng.random_range(0..3) {
+                    0 => {
+                        tree.add_line(Line::new(m, c));
+                    }
+                    1 => {
+                        tree.add_segment(Line::new(m, c), x_l, x_r);
+                    }
+                    _ => tree.assign_range(x_l, x_r, Line::new(m, c)),
+                }
+            }
+            let x_coords: Vec<i64> = (0..rng.random_range(0..40))
+                .map(|_| points[rng.random_range(0..points.len())])
+                .collect();
+            let expected: Vec<_> = x_coords.iter().map(|&x| tree.query(x)).collect();
+            assert_eq!(tree.query_many(&x_coords), expected);
+        }
+    }
+
+    #[test]
+    fn test_fallbacks() {
+        let mut tree = LiChaoTree::new(-10, 10);
+        tree.add_line(Line::new(2, 3));
+        tree.set_overflow_policy(OverflowPolicy::Checked);
+        assert_eq!(
+            tree.query_many(&[-10, 0, 5, 10, 7]),
+            [Some(-17), Some(3), Some(13), Some(23), Some(17)]
+        );
+
+        let mut tree = LiChaoTree::new_with_points(&[-(1 << 40), -1, 0, 1, 1 << 40]);
+        tree.add_line(Line::new(-3, 1));
+        assert_eq!(
+            tree.query_many(&[-1, 0, 1, 1 << 40, 0]),
+            [
+                Some(4),
+                Some(1),
+                Some(-2),
+                Some(-3 * (1 << 40) + 1),
+                Some(1)
+            ]
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use std::io::{self, Read, Write};
+
+use crate::LiChaoTree;
+
+/// Encoding of the serde representation inside the compressed stream. Fixed-width integers compress better than
+/// varints, since the same byte patterns repeat across lines.
+fn config() -> impl bincode::config::Config {
+    bincode::config::standard().with_fixed_int_encoding()
+}
+
+impl LiChaoTree {
+    /// Writes a binary snapshot of the tree to `writer`, compressed with zstd at `level` (1 to 22, 0 for zstd's
+    /// default). The snapshot holds what `Serialize` does: the domain, the settings, and the line log or else the
+    /// stored lines, so `read_snapshot` rebuilds the same envelope. The tree is encoded straight into the compressor,
+    /// without an uncompressed copy in memory. Fails with the errors of `writer`.
+    /// Time complexity: O(domain_size) without a line log, as for `Serialize`, plus the compression.
+    pub fn write_snapshot<W: Write>(&self, writer: W, level: i32) -> io::Result<()> {
+        let mut encoder = zstd::stream::Encoder::new(writer, level)?;
+        bincode::serde::encode_into_std_write(self, &mut encoder, config())
+            .map_err(io::Error::other)?;
+        encoder.finish()?;
+        Ok(())
+    }
+
+    /// Reads a snapshot written by `write_snapshot` from `reader`, decompressing it as it is decoded. Fails with the
+    /// errors of `reader` and of the decompressor, and with `io::ErrorKind::InvalidData` if the decompressed data is
+    /// not a valid snapshot, including insertions that do not fit the snapshot's domain.
+    /// Time complexity: O(k * log^2(domain_size)) for k serialized insertions, plus the decompression.
+    pub fn read_snapshot<R: Read>(reader: R) -> io::Result<LiChaoTree> {
+        let mut decoder = zstd::stream::Decoder::new(reader)?;
+        bincode::serde::decode_from_std_read(&mut decoder, config()).map_err(|err| match err {
+            bincode::error::DecodeError::Io { inner, .. } => inner,
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LiChaoTree, LiChaoTreeBuilder, Line, NodeStorage};
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut rng = StdRng::seed_from_u64(248);
+        for keep_lines in [false, true] {
+            let storage = if keep_lines {
+                NodeStorage::Indexed
+            } else {
+                NodeStorage::Inline
+            };
+            let mut tree = LiChaoTreeBuilder::new(-500, 500)
+                .keep_lines(keep_lines)
+                .storage(storage)
+                .build()
+                .unwrap();
+            for _ in 0..2000 {
+                let line = Line::new(rng.random_range(-20..=20), rng.random_range(-5000..=5000));
+                let x_l = rng.random_range(-500..=500);
+                match rng.random_range(0..3) {
+                    0 => {
+                        tree.add_line(line);
+                    }
+                    1 => {
+                        tree.add_segment(line, x_l, rng.random_range(x_l..=500));
+                    }
+                    _ => tree.assign_range(x_l, rng.random_range(x_l..=500), line),
+                }
+            }
+            let mut bytes = Vec::new();
+            tree.write_snapshot(&mut bytes, 3).unwrap();
+            let restored = LiChaoTree::read_snapshot(&bytes[..]).unwrap();
+            assert!(restored.envelope_eq(&tree));
+            assert!(restored.lines().eq(tree.lines()));
+            assert_eq!(restored.storage(), storage);
+            if storage == NodeStorage::Indexed {
+                let argmin = |tree: &LiChaoTree| tree.query_argmin(0).map(|(val, _)| val);
+                assert_eq!(argmin(&restored), argmin(&tree));
+            }
+            let uncompressed = bincode::serde::encode_to_vec(&tree, super::config()).unwrap();
+            assert!(bytes.len() < uncompressed.len() / 2);
+        }
+    }
+
+    #[test]
+    fn test_invalid_snapshot() {
+        let mut tree = LiChaoTree::new(0, 10);
+        tree.add_line(Line::new(1, 2));
+        let mut bytes = Vec::new();
+        tree.write_snapshot(&mut bytes, 0).unwrap();
+        let truncated = LiChaoTree::read_snapshot(&bytes[..bytes.len() / 2]).unwrap_err();
+        assert_eq!(truncated.kind(), std::io::ErrorKind::UnexpectedEof);
+        assert!(LiChaoTree::read_snapshot(&b"not a snapshot"[..]).is_err());
+        let garbage = zstd::stream::encode_all(&[0xff; 64][..], 0).unwrap();
+        let garbage = LiChaoTree::read_snapshot(&garbage[..]).unwrap_err();
+        assert_eq!(garbage.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
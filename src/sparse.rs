@@ -0,0 +1,215 @@
+use crate::Line;
+
+/// Index of the root, which is never anyone's child, so it doubles as "no child".
+const NO_CHILD: u32 = 0;
+
+#[derive(Clone, Copy, Debug)]
+struct SparseNode {
+    line: Line,
+    children: [u32; 2],
+}
+
+/// A Li-Chao Tree whose nodes are created on demand, for domains far too large for [`crate::LiChaoTree`].
+///
+/// Nodes live in an arena and only exist once a line has been stored in them. An insertion creates at most one
+/// node, so memory is O(lines) nodes no matter the domain size, and the whole `i64` range can be used.
+/// Operations take O(log(domain_size)) time, i.e. at most 64 steps.
+/// Lines are compared exactly in `i128`, and query results are clamped to the `i64` range.
+/// Nodes only ever hold lines that were added, so any line can be added, and a minimum of `i64::MAX` is returned as
+/// such like in [`crate::LiChaoTree::query_checked`], not as `None`.
+#[derive(Clone, Debug)]
+pub struct SparseLiChaoTree {
+    nodes: Vec<SparseNode>,
+    x_min_coord: i64,
+    x_max_coord: i64,
+}
+
+impl SparseLiChaoTree {
+    /// Creates an empty tree over the x-coordinates `[x_min_coord, x_max_coord]`. Allocates nothing.
+    pub fn new(x_min_coord: i64, x_max_coord: i64) -> Self {
+        if x_min_coord > x_max_coord {
+            panic!(
+                "x_min_coord ({}) cannot be greater than x_max_coord ({})",
+                x_min_coord, x_max_coord
+            );
+        }
+        SparseLiChaoTree {
+            nodes: Vec::new(),
+            x_min_coord,
+            x_max_coord,
+        }
+    }
+
+    /// Number of nodes allocated so far.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns whether `x_coord` lies inside the tree's bounds.
+    #[inline]
+    pub(crate) fn contains(&self, x_coord: i64) -> bool {
+        (self.x_min_coord..=self.x_max_coord).contains(&x_coord)
+    }
+
+    /// Rounds down, so that `[l, m]` and `[m + 1, r]` are both non-empty for `l < r`.
+    #[inline]
+    fn mid(range_l: i64, range_r: i64) -> i64 {
+        (range_l as i128 + range_r as i128).div_euclid(2) as i64
+    }
+
+    fn push_node(&mut self, line: Line) -> u32 {
+        let idx = u32::try_from(self.nodes.len()).expect("SparseLiChaoTree: too many nodes");
+        self.nodes.push(SparseNode {
+            line,
+            children: [NO_CHILD; 2],
+        });
+        idx
+    }
+
+    /// Adds a line to the tree. Returns whether it was kept, see [`crate::LiChaoTree::add_line`].
+    /// Time complexity: O(log(domain_size)).
+    pub fn add_line(&mut self, line: Line) -> bool {
+        if self.nodes.is_empty() {
+            self.push_node(line);
+            return true;
+        }
+
+        let mut line_to_add = line;
+        let mut stored = false;
+        let (mut node, mut range_l, mut range_r) = (0usize, self.x_min_coord, self.x_max_coord);
+        loop {
+            let range_m = Self::mid(range_l, range_r);
+            let current = self.nodes[node].line;
+            // Compare exactly: near the ends of a huge domain, saturated values would tie
+            // and send lines down the wrong side
+            if line_to_add.eval_wide(range_m) < current.eval_wide(range_m) {
+                self.nodes[node].line = line_to_add;
+                line_to_add = current;
+                // The first swap stores the new line, later ones only happen after it was stored
+                stored = true;
+            }
+            if range_l == range_r {
+                return stored;
+            }
+
+            let current = self.nodes[node].line;
+            let side = if line_to_add.eval_wide(range_l) < current.eval_wide(range_l) {
+                range_r = range_m;
+                0
+            } else if line_to_add.eval_wide(range_r) < current.eval_wide(range_r) {
+                range_l = range_m + 1;
+                1
+            } else {
+                return stored;
+            };
+            match self.nodes[node].children[side] {
+                NO_CHILD => {
+                    let child = self.push_node(line_to_add);
+                    self.nodes[node].children[side] = child;
+                    return true;
+                }
+                child => node = child as usize,
+            }
+        }
+    }
+
+    /// Queries the minimum y-value at `x_coord`, or `None` if no line has been added.
+    /// Panics if `x_coord` is outside the tree's bounds.
+    /// Time complexity: O(log(domain_size)).
+    pub fn query(&self, x_coord: i64) -> Option<i64> {
+        if !self.contains(x_coord) {
+            panic!("{} does not fit inside the tree's bounds", x_coord);
+        }
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut best = i128::MAX;
+        let (mut node, mut range_l, mut range_r) = (0usize, self.x_min_coord, self.x_max_coord);
+        loop {
+            best = best.min(self.nodes[node].line.eval_wide(x_coord));
+            if range_l == range_r {
+                break;
+            }
+            let range_m = Self::mid(range_l, range_r);
+            let side = if x_coord <= range_m {
+                range_r = range_m;
+                0
+            } else {
+                range_l = range_m + 1;
+                1
+            };
+            match self.nodes[node].children[side] {
+                NO_CHILD => break,
+                child => node = child as usize,
+            }
+        }
+        // Saturate like Line::eval. Every node holds a real line, so a minimum of i64::MAX is still reported.
+        Some(best.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LiChaoTree;
+
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_matches_dense_tree() {
+        let mut rng = StdRng::seed_from_u64(254);
+        let mut sparse = SparseLiChaoTree::new(-30, 30);
+        let mut dense = LiChaoTree::new(-30, 30);
+        for _ in 0..300 {
+            let line = Line::new(rng.random_range(-20..=20), rng.random_range(-200..=200));
+            assert_eq!(sparse.add_line(line), dense.add_line(line));
+            for x in -30..=30 {
+                assert_eq!(sparse.query(x), dense.query(x));
+            }
+        }
+        assert!(sparse.node_count() <= 300);
+    }
+
+    #[test]
+    fn test_huge_domain() {
+        let mut rng = StdRng::seed_from_u64(255);
+        let mut tree = SparseLiChaoTree::new(i64::MIN, i64::MAX);
+        assert_eq!(tree.query(0), None);
+        let mut lines = Vec::new();
+        // Line::eval saturates the product before adding c, so compute the expected values exactly
+        let naive = |lines: &[Line], x: i64| {
+            lines
+                .iter()
+                .map(|line| line.eval_wide(x))
+                .min()
+                .map(|val| val.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+        };
+        for _ in 0..500 {
+            let line = Line::new(
+                rng.random_range(-1000..=1000),
+                rng.random_range(-(1 << 40)..=(1 << 40)),
+            );
+            tree.add_line(line);
+            lines.push(line);
+            let x = rng.random_range(-1_000_000_000_000_000_000..=1_000_000_000_000_000_000);
+            assert_eq!(tree.query(x), naive(&lines, x));
+        }
+        for x in [i64::MIN, -1, 0, i64::MAX] {
+            assert_eq!(tree.query(x), naive(&lines, x));
+        }
+        assert!(tree.node_count() <= 500);
+    }
+
+    #[test]
+    fn test_max_value_is_reported() {
+        let mut tree = SparseLiChaoTree::new(-10, 10);
+        tree.add_line(Line::new(0, i64::MAX));
+        assert_eq!(tree.query(3), Some(i64::MAX));
+        tree.add_line(Line::new(1, i64::MAX));
+        assert_eq!(tree.query(-10), Some(i64::MAX - 10));
+        assert_eq!(tree.query(10), Some(i64::MAX));
+    }
+}
@@ -0,0 +1,237 @@
+use crate::{EnvelopeMode, LiChaoError, LiChaoTree, SparseLiChaoTree};
+
+/// A structure that [`EnvelopeUnion`] can forward queries to.
+pub trait EnvelopeQuery {
+    /// Which envelope `query` returns. All members of one union must agree on it.
+    fn mode(&self) -> EnvelopeMode;
+
+    /// Returns whether `x_coord` can be queried.
+    fn contains(&self, x_coord: i64) -> bool;
+
+    /// Queries the envelope at `x_coord`, which is contained, or returns `None` if no line covers it.
+    fn query(&self, x_coord: i64) -> Option<i64>;
+}
+
+impl EnvelopeQuery for LiChaoTree {
+    fn mode(&self) -> EnvelopeMode {
+        LiChaoTree::mode(self)
+    }
+
+    fn contains(&self, x_coord: i64) -> bool {
+        LiChaoTree::contains(self, x_coord)
+    }
+
+    fn query(&self, x_coord: i64) -> Option<i64> {
+        LiChaoTree::query(self, x_coord)
+    }
+}
+
+impl EnvelopeQuery for SparseLiChaoTree {
+    fn mode(&self) -> EnvelopeMode {
+        EnvelopeMode::Min
+    }
+
+    fn contains(&self, x_coord: i64) -> bool {
+        SparseLiChaoTree::contains(self, x_coord)
+    }
+
+    fn query(&self, x_coord: i64) -> Option<i64> {
+        SparseLiChaoTree::query(self, x_coord)
+    }
+}
+
+/// A read-only view answering queries as the envelope over several structures, which all maintain the same
+/// [`EnvelopeMode`]: the minimum over lower envelopes, or the maximum over upper ones.
+/// Nothing is merged: each query is forwarded to every member.
+pub struct EnvelopeUnion<'a> {
+    members: Vec<&'a dyn EnvelopeQuery>,
+    // Mode of the first member, `None` while there are none
+    mode: Option<EnvelopeMode>,
+}
+
+impl<'a> EnvelopeUnion<'a> {
+    /// Creates a view over the given members. They may have different domains.
+    /// Panics if their modes differ.
+    pub fn new<T: EnvelopeQuery + 'a>(members: impl IntoIterator<Item = &'a T>) -> Self {
+        Self::try_new(members).unwrap_or_else(|err| panic!("EnvelopeUnion::new: {}", err))
+    }
+
+    /// Same as `new`, but fails with `LiChaoError::ModeMismatch` instead of panicking.
+    pub fn try_new<T: EnvelopeQuery + 'a>(
+        members: impl IntoIterator<Item = &'a T>,
+    ) -> Result<Self, LiChaoError> {
+        let mut union = EnvelopeUnion {
+            members: Vec::new(),
+            mode: None,
+        };
+        for member in members {
+            union.try_push(member)?;
+        }
+        Ok(union)
+    }
+
+    /// Mode shared by all members, or `None` if there are none yet.
+    pub fn mode(&self) -> Option<EnvelopeMode> {
+        self.mode
+    }
+
+    /// Adds another member to the view. It may be of a different type than the others.
+    /// Panics if its mode differs from theirs.
+    pub fn push<T: EnvelopeQuery + 'a>(&mut self, member: &'a T) {
+        if let Err(err) = self.try_push(member) {
+            panic!("EnvelopeUnion::push: {}", err);
+        }
+    }
+
+    /// Same as `push`, but fails with `LiChaoError::ModeMismatch` instead of panicking, leaving the view unchanged.
+    pub fn try_push<T: EnvelopeQuery + 'a>(&mut self, member: &'a T) -> Result<(), LiChaoError> {
+        if self.mode.is_some_and(|mode| mode != member.mode()) {
+            return Err(LiChaoError::ModeMismatch);
+        }
+        self.mode = Some(member.mode());
+        self.members.push(member);
+        Ok(())
+    }
+
+    /// Queries the envelope at `x_coord` over all members whose domain contains it.
+    /// Returns `None` if no member has a line covering `x_coord`, including when no member contains it at all.
+    /// Time complexity: O(k * log(domain_size)) for k members.
+    pub fn query(&self, x_coord: i64) -> Option<i64> {
+        self.try_query(x_coord).ok().flatten()
+    }
+
+    /// Same as `query`, but fails with `LiChaoError::OutOfRange` if no member contains `x_coord`.
+    pub fn try_query(&self, x_coord: i64) -> Result<Option<i64>, LiChaoError> {
+        let mut covered = false;
+        let mut best: Option<i64> = None;
+        for member in self
+            .members
+            .iter()
+            .filter(|member| member.contains(x_coord))
+        {
+            covered = true;
+            if let Some(val) = member.query(x_coord) {
+                best = Some(match (best, self.mode) {
+                    (Some(best), Some(EnvelopeMode::Max)) => best.max(val),
+                    (Some(best), _) => best.min(val),
+                    (None, _) => val,
+                });
+            }
+        }
+        if !covered {
+            return Err(LiChaoError::OutOfRange { x_coord });
+        }
+        Ok(best)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LiChaoTreeBuilder, Line};
+
+    #[test]
+    fn test_union_of_shards() {
+        let mut a = LiChaoTree::new(0, 10);
+        let mut b = LiChaoTree::new(0, 10);
+        a.add_line(Line::new(1, 0));
+        b.add_line(Line::new(-1, 10));
+
+        let union = EnvelopeUnion::new([&a, &b]);
+        assert_eq!(union.query(0), Some(0));
+        assert_eq!(union.query(5), Some(5));
+        assert_eq!(union.query(10), Some(0));
+    }
+
+    #[test]
+    fn test_union_different_domains() {
+        let mut a = LiChaoTree::new(0, 5);
+        let b = LiChaoTree::new(3, 10);
+        a.add_line(Line::new(0, 7));
+
+        let mut union = EnvelopeUnion::new([&a]);
+        union.push(&b);
+        assert_eq!(union.query(4), Some(7));
+        assert_eq!(union.query(8), None);
+    }
+
+    #[test]
+    fn test_union_out_of_bounds() {
+        let a = LiChaoTree::new(0, 5);
+        let union = EnvelopeUnion::new([&a]);
+        assert_eq!(union.query(6), None);
+        assert_eq!(
+            union.try_query(6),
+            Err(LiChaoError::OutOfRange { x_coord: 6 })
+        );
+        assert_eq!(union.try_query(5), Ok(None));
+        assert_eq!(
+            EnvelopeUnion::new::<LiChaoTree>([]).try_query(0),
+            Err(LiChaoError::OutOfRange { x_coord: 0 })
+        );
+    }
+
+    #[test]
+    fn test_union_of_max_trees() {
+        let mut a = LiChaoTreeBuilder::new(0, 10)
+            .mode(EnvelopeMode::Max)
+            .build()
+            .unwrap();
+        let mut b = LiChaoTreeBuilder::new(0, 10)
+            .mode(EnvelopeMode::Max)
+            .build()
+            .unwrap();
+        a.add_line(Line::new(1, 0));
+        b.add_line(Line::new(-1, 10));
+
+        let union = EnvelopeUnion::new([&a, &b]);
+        assert_eq!(union.mode(), Some(EnvelopeMode::Max));
+        assert_eq!(union.query(0), Some(10));
+        assert_eq!(union.query(5), Some(5));
+        assert_eq!(union.query(10), Some(10));
+    }
+
+    #[test]
+    fn test_union_rejects_mixed_modes() {
+        let min = LiChaoTree::new(0, 10);
+        let max = LiChaoTreeBuilder::new(0, 10)
+            .mode(EnvelopeMode::Max)
+            .build()
+            .unwrap();
+        assert_eq!(
+            EnvelopeUnion::try_new([&min, &max]).err(),
+            Some(LiChaoError::ModeMismatch)
+        );
+
+        let mut union = EnvelopeUnion::new([&min]);
+        assert_eq!(union.try_push(&max), Err(LiChaoError::ModeMismatch));
+        let sparse = SparseLiChaoTree::new(0, 10);
+        assert_eq!(union.try_push(&sparse), Ok(()));
+    }
+
+    #[test]
+    #[should_panic(expected = "EnvelopeUnion::push")]
+    fn test_union_push_mixed_modes() {
+        let min = LiChaoTree::new(0, 10);
+        let max = LiChaoTreeBuilder::new(0, 10)
+            .mode(EnvelopeMode::Max)
+            .build()
+            .unwrap();
+        let mut union = EnvelopeUnion::new([&min]);
+        union.push(&max);
+    }
+
+    #[test]
+    fn test_union_of_different_structures() {
+        let mut dense = LiChaoTree::new(0, 100);
+        let mut sparse = SparseLiChaoTree::new(i64::MIN, i64::MAX);
+        dense.add_line(Line::new(1, 0));
+        sparse.add_line(Line::new(0, 50));
+
+        let mut union = EnvelopeUnion::new([&dense]);
+        union.push(&sparse);
+        assert_eq!(union.query(20), Some(20));
+        assert_eq!(union.query(80), Some(50));
+        assert_eq!(union.query(1 << 40), Some(50));
+    }
+}